@@ -0,0 +1,66 @@
+// Registers a parser for an envelope tlparse doesn't know, here a made-up "acme_shard_plan"
+// logged by some organization's own code, and checks that its file shows up in the output:
+//
+//     cargo run --example custom_parser
+use std::path::PathBuf;
+
+use tlparse::{CompileId, Envelope, Metadata, ParseConfig, ParserOutput, ParserResults};
+
+// Writes the payload of every acme_shard_plan, headed by how many shards it has
+struct ShardPlanParser;
+
+impl tlparse::StructuredLogParser for ShardPlanParser {
+    fn name(&self) -> &'static str {
+        "acme_shard_plan"
+    }
+
+    fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
+        e._other.get("acme_shard_plan").map(Metadata::Other)
+    }
+
+    // So that acme_shard_plan isn't reported as an unknown field
+    fn handles_field(&self, field: &str) -> bool {
+        field == "acme_shard_plan"
+    }
+
+    fn parse<'e>(
+        &self,
+        _lineno: usize,
+        metadata: Metadata<'e>,
+        _rank: Option<u32>,
+        compile_id: &Option<CompileId>,
+        payload: &str,
+    ) -> anyhow::Result<ParserResults> {
+        let Metadata::Other(plan) = metadata else {
+            anyhow::bail!("expected acme_shard_plan metadata");
+        };
+        let dir = compile_id
+            .as_ref()
+            .map_or("unknown".to_string(), CompileId::as_directory_name);
+        Ok(vec![ParserOutput::File(
+            PathBuf::from(dir).join("shard_plan.txt"),
+            format!("{} shards\n{payload}", plan["num_shards"]),
+        )])
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let log: &[u8] = include_bytes!("../tests/inputs/custom_envelope.log");
+    let config = ParseConfig {
+        custom_parsers: vec![Box::new(ShardPlanParser)],
+        ..Default::default()
+    };
+    let output = tlparse::parse(log, &config)?;
+    // Custom parsers' files go in a directory named after them, see NamespacedParser
+    let (path, plan) = output
+        .files
+        .iter()
+        .find(|(path, _)| {
+            path.starts_with("0_0_0/acme_shard_plan")
+                && path.extension().is_some_and(|e| e == "txt")
+        })
+        .expect("the shard plan was written");
+    assert!(plan.starts_with("2 shards\nshard 0: layers 0-11"));
+    println!("{}:\n{plan}", path.display());
+    Ok(())
+}
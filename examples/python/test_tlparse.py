@@ -0,0 +1,87 @@
+# Uses the tlparse extension module (the python feature) on the logs tlparse tests itself with:
+#
+#     pip install maturin pytest
+#     maturin develop --release --bindings pyo3 --features python
+#     pytest examples/python
+import pathlib
+import threading
+
+import pytest
+import tlparse
+
+INPUTS = pathlib.Path(__file__).parent.parent.parent / "tests" / "inputs"
+SIMPLE = INPUTS / "simple.log"
+
+
+def test_parse_file():
+    report = tlparse.parse_file(SIMPLE)
+    assert report["stats"]["ok"] == 8
+    [compile] = report["compiles"]
+    assert compile["compile_id"] == {"frame_id": 0, "frame_compile_id": 0, "attempt": 0}
+    graph = compile["files"][0]
+    assert graph["name"] == "0_0_0/dynamo_output_graph_0.txt"
+    assert graph["contents"].startswith(b"class GraphModule(torch.nn.Module):")
+    assert b"<html>" in report["files"]["index.html"]
+    # Nested dicts, down to the compiles of each frame
+    assert report["trie"]["children"]
+
+
+def test_parse_file_options(tmp_path):
+    report = tlparse.parse_file(SIMPLE, output_dir=tmp_path, plain_text=True)
+    graph = report["compiles"][0]["files"][0]
+    assert pathlib.Path(graph["path"]).read_text().startswith("class GraphModule")
+    assert "contents" not in graph
+    assert pathlib.Path(report["files"]["index.html"]) == tmp_path / "index.html"
+    with pytest.raises(TypeError, match="unexpected keyword argument 'no_such_option'"):
+        tlparse.parse_file(SIMPLE, no_such_option=True)
+    with pytest.raises(ValueError, match="no such file"):
+        tlparse.parse_file(tmp_path / "missing.log")
+
+
+def test_parse_file_runs():
+    report = tlparse.parse_file(INPUTS / "two_runs.log")
+    assert [len(run["compiles"]) for run in report["runs"]] == [1, 1]
+    assert "run_1/index.html" in report["files"]
+
+
+def test_parse_file_releases_gil():
+    # Long enough a log that this thread gets to run while the other one parses
+    log = INPUTS / "cache_hit_miss.log"
+    done = threading.Event()
+    ticks = 0
+
+    def parse():
+        tlparse.parse_file(log)
+        done.set()
+
+    thread = threading.Thread(target=parse)
+    thread.start()
+    while not done.wait(0.001):
+        ticks += 1
+    thread.join()
+    assert ticks > 0
+
+
+def test_envelopes():
+    entries = list(tlparse.envelopes(SIMPLE))
+    assert len(entries) == 24
+    first = entries[0]
+    assert (first["line"], first["payload"]) == (1, None)
+    assert "str" in first["envelope"]
+    graph = next(e for e in entries if "dynamo_output_graph" in e["envelope"])
+    assert graph["envelope"]["frame_id"] == 0
+    assert graph["payload"].startswith("class GraphModule(torch.nn.Module):")
+
+
+def test_envelopes_strict(tmp_path):
+    log = tmp_path / "broken.log"
+    lines = SIMPLE.read_text().splitlines()
+    broken = lines[0][: lines[0].index("]") + 2] + "{not json"
+    log.write_text("\n".join([broken] + lines) + "\n")
+    # Skipped like parse_file skips it
+    assert len(list(tlparse.envelopes(log))) == 24
+    entries = tlparse.envelopes(log, strict=True)
+    with pytest.raises(ValueError):
+        next(entries)
+    # And reading goes on after it
+    assert next(entries)["line"] == 2
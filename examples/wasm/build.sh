@@ -0,0 +1,14 @@
+#!/bin/sh
+# Builds the wasm module the demo page loads into examples/wasm/pkg, then serve examples/wasm
+# over http (browsers won't load modules from file://), e.g.
+#
+#     examples/wasm/build.sh && python3 -m http.server -d examples/wasm
+#
+# Needs `rustup target add wasm32-unknown-unknown` and wasm-bindgen-cli of the same version as
+# the wasm-bindgen crate tlparse was built with (`cargo install wasm-bindgen-cli --version ...`).
+set -e
+cd "$(dirname "$0")/../.."
+cargo rustc --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm \
+    --crate-type cdylib
+wasm-bindgen --target web --no-typescript --out-dir examples/wasm/pkg \
+    target/wasm32-unknown-unknown/release/tlparse.wasm
@@ -0,0 +1,316 @@
+// Inline SVG charts for dashboard.html.  Everything is drawn here, with no script or external
+// library, so the charts work from file:// and offline.  The geometry (bar_rects, line_path) is
+// kept apart from the markup so that it can be tested without looking at pictures.
+use html_escape::encode_text;
+
+// Of every chart, in SVG user units; the SVG scales to its container
+pub static CHART_WIDTH: f64 = 600.0;
+pub static CHART_HEIGHT: f64 = 200.0;
+// Room for axis labels: under the plot, and left of it for values (or of bars, for labels)
+static LABEL_HEIGHT: f64 = 20.0;
+static LABEL_WIDTH: f64 = 40.0;
+static BAR_LABEL_WIDTH: f64 = 220.0;
+// Between bars, as a fraction of the room each bar has
+static BAR_GAP: f64 = 0.2;
+// Each bar of a horizontal bar chart
+static ROW_HEIGHT: f64 = 20.0;
+
+static BAR_COLOR: &str = "steelblue";
+
+// A rectangle of a chart: x, y, width and height
+#[derive(Debug, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+// Side by side columns for values, filling width by height with the bottom at height; the
+// biggest value is full height.  Nothing is drawn for values of 0 (or below).
+pub fn bar_rects(values: &[f64], width: f64, height: f64) -> Vec<Rect> {
+    let max = values.iter().copied().fold(0.0, f64::max);
+    let room = width / values.len().max(1) as f64;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| {
+            let h = if max > 0.0 {
+                v.max(0.0) / max * height
+            } else {
+                0.0
+            };
+            Rect {
+                x: i as f64 * room + room * BAR_GAP / 2.0,
+                y: height - h,
+                width: room * (1.0 - BAR_GAP),
+                height: h,
+            }
+        })
+        .collect()
+}
+
+// An SVG path through points (x, y) scaled into width by height, y up: the smallest x is at the
+// left edge and the largest at the right, 0 (or the smallest y, if below 0) at the bottom and
+// the largest y at the top.  Each point is a step, as counts over time are.
+pub fn line_path(points: &[(f64, f64)], width: f64, height: f64) -> String {
+    let Some(&(first_x, _)) = points.first() else {
+        return String::new();
+    };
+    let (min_x, max_x) = points.iter().fold((first_x, first_x), |(lo, hi), &(x, _)| {
+        (lo.min(x), hi.max(x))
+    });
+    let (min_y, max_y) = points
+        .iter()
+        .fold((0.0, 0.0), |(lo, hi): (f64, f64), &(_, y)| {
+            (lo.min(y), hi.max(y))
+        });
+    let scale = |v: f64, lo: f64, hi: f64, extent: f64| {
+        if hi > lo {
+            (v - lo) / (hi - lo) * extent
+        } else {
+            0.0
+        }
+    };
+    let mut path = String::new();
+    let mut last_y = None;
+    for &(x, y) in points {
+        let px = scale(x, min_x, max_x, width);
+        let py = height - scale(y, min_y, max_y, height);
+        match last_y {
+            None => path.push_str(&format!("M{} {}", fmt(px), fmt(py))),
+            Some(last) => path.push_str(&format!(
+                " L{} {} L{} {}",
+                fmt(px),
+                fmt(last),
+                fmt(px),
+                fmt(py)
+            )),
+        }
+        last_y = Some(py);
+    }
+    path
+}
+
+// Coordinates with at most two decimals, and none when they'd be zeros
+fn fmt(v: f64) -> String {
+    let s = format!("{v:.2}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn svg(width: f64, height: f64, body: &str) -> String {
+    format!(
+        "<svg class=\"chart\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">{body}</svg>",
+        fmt(width),
+        fmt(height),
+        fmt(width),
+        fmt(height)
+    )
+}
+
+// A histogram: a column per (label, count), labels underneath
+pub fn column_chart(bars: &[(String, f64)]) -> String {
+    let plot_height = CHART_HEIGHT - LABEL_HEIGHT;
+    let values: Vec<f64> = bars.iter().map(|(_, v)| *v).collect();
+    let mut body = String::new();
+    for ((label, value), r) in bars
+        .iter()
+        .zip(bar_rects(&values, CHART_WIDTH, plot_height))
+    {
+        let label = encode_text(label);
+        body.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{BAR_COLOR}\"><title>{label}: {value}</title></rect>",
+            fmt(r.x),
+            fmt(r.y),
+            fmt(r.width),
+            fmt(r.height)
+        ));
+        let middle = fmt(r.x + r.width / 2.0);
+        body.push_str(&format!(
+            "<text x=\"{middle}\" y=\"{}\" text-anchor=\"middle\" font-size=\"11\">{value}</text>",
+            fmt((r.y - 3.0).max(10.0))
+        ));
+        body.push_str(&format!(
+            "<text x=\"{middle}\" y=\"{}\" text-anchor=\"middle\" font-size=\"11\">{label}</text>",
+            fmt(CHART_HEIGHT - 5.0)
+        ));
+    }
+    svg(CHART_WIDTH, CHART_HEIGHT, &body)
+}
+
+// Ranked horizontal bars, each with its label to the left and its value to the right
+pub fn bar_chart(bars: &[(String, f64)]) -> String {
+    let height = ROW_HEIGHT * bars.len() as f64;
+    let plot_width = CHART_WIDTH - BAR_LABEL_WIDTH - LABEL_WIDTH;
+    let values: Vec<f64> = bars.iter().map(|(_, v)| *v).collect();
+    // Columns as tall as the plot is wide, turned on their side
+    let rects = bar_rects(&values, height, plot_width);
+    let mut body = String::new();
+    for ((label, value), r) in bars.iter().zip(rects) {
+        let (y, thickness, length) = (r.x, r.width, r.height);
+        body.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\" font-size=\"11\">{}</text>",
+            fmt(BAR_LABEL_WIDTH - 5.0),
+            fmt(y + thickness - 2.0),
+            encode_text(&shorten(label, 36))
+        ));
+        let label = encode_text(label);
+        body.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{BAR_COLOR}\"><title>{label}: {value}</title></rect>",
+            fmt(BAR_LABEL_WIDTH),
+            fmt(y),
+            fmt(length),
+            fmt(thickness)
+        ));
+        body.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"11\">{value}</text>",
+            fmt(BAR_LABEL_WIDTH + length + 3.0),
+            fmt(y + thickness - 2.0)
+        ));
+    }
+    svg(CHART_WIDTH, height, &body)
+}
+
+fn shorten(label: &str, chars: usize) -> String {
+    match label.char_indices().nth(chars) {
+        Some((end, _)) => format!("{}…", &label[..end]),
+        None => label.to_string(),
+    }
+}
+
+// A step line through points (x, y), with the range of each axis written at its ends, e.g.
+// "0 s" and "2 min" under it and the biggest y to the left
+pub fn line_chart(points: &[(f64, f64)], x_range: (&str, &str), y_max: &str) -> String {
+    let plot_width = CHART_WIDTH - LABEL_WIDTH;
+    let plot_height = CHART_HEIGHT - LABEL_HEIGHT;
+    let path = line_path(points, plot_width, plot_height);
+    let body = format!(
+        "<line x1=\"{LABEL_WIDTH}\" y1=\"{h}\" x2=\"{CHART_WIDTH}\" y2=\"{h}\" stroke=\"#999\"/>\
+<line x1=\"{LABEL_WIDTH}\" y1=\"0\" x2=\"{LABEL_WIDTH}\" y2=\"{h}\" stroke=\"#999\"/>\
+<path transform=\"translate({LABEL_WIDTH} 0)\" d=\"{path}\" fill=\"none\" stroke=\"{BAR_COLOR}\" stroke-width=\"2\"/>\
+<text x=\"{}\" y=\"11\" text-anchor=\"end\" font-size=\"11\">{}</text>\
+<text x=\"{LABEL_WIDTH}\" y=\"{}\" font-size=\"11\">{}</text>\
+<text x=\"{CHART_WIDTH}\" y=\"{}\" text-anchor=\"end\" font-size=\"11\">{}</text>",
+        fmt(LABEL_WIDTH - 3.0),
+        encode_text(y_max),
+        fmt(CHART_HEIGHT - 5.0),
+        encode_text(x_range.0),
+        fmt(CHART_HEIGHT - 5.0),
+        encode_text(x_range.1),
+        h = fmt(plot_height),
+    );
+    svg(CHART_WIDTH, CHART_HEIGHT, &body)
+}
+
+// One bar split into parts (label, count, color) in proportion, e.g. cache hits and misses,
+// with a legend under it
+pub fn proportion_bar(parts: &[(&str, f64, &str)]) -> String {
+    let total: f64 = parts.iter().map(|(_, v, _)| v.max(0.0)).sum();
+    let bar_height = CHART_HEIGHT / 5.0;
+    let mut body = String::new();
+    let mut x = 0.0;
+    for (i, (label, value, color)) in parts.iter().enumerate() {
+        let width = if total > 0.0 {
+            value.max(0.0) / total * CHART_WIDTH
+        } else {
+            0.0
+        };
+        let label = encode_text(label);
+        body.push_str(&format!(
+            "<rect x=\"{}\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"{color}\"><title>{label}: {value}</title></rect>",
+            fmt(x),
+            fmt(width),
+            fmt(bar_height)
+        ));
+        let legend_x = i as f64 * CHART_WIDTH / parts.len() as f64;
+        body.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{color}\"/><text x=\"{}\" y=\"{}\" font-size=\"11\">{label}: {value}</text>",
+            fmt(legend_x),
+            fmt(bar_height + 8.0),
+            fmt(legend_x + 14.0),
+            fmt(bar_height + 17.0)
+        ));
+        x += width;
+    }
+    svg(CHART_WIDTH, bar_height + 24.0, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bar_rects() {
+        let rects = bar_rects(&[1.0, 4.0, 2.0], 300.0, 100.0);
+        assert_eq!(rects.len(), 3);
+        // Each bar has 100 across, of which the gap takes 20
+        assert_eq!(
+            rects[0],
+            Rect {
+                x: 10.0,
+                y: 75.0,
+                width: 80.0,
+                height: 25.0
+            }
+        );
+        assert_eq!(
+            rects[1],
+            Rect {
+                x: 110.0,
+                y: 0.0,
+                width: 80.0,
+                height: 100.0
+            }
+        );
+        assert_eq!(rects[2].y, 50.0);
+    }
+
+    #[test]
+    fn test_bar_rects_all_zero() {
+        let rects = bar_rects(&[0.0, 0.0], 100.0, 50.0);
+        assert!(rects.iter().all(|r| r.height == 0.0 && r.y == 50.0));
+        assert!(bar_rects(&[], 100.0, 50.0).is_empty());
+    }
+
+    #[test]
+    fn test_line_path() {
+        // Steps up from 0 at the bottom left to the largest y at the top right
+        assert_eq!(
+            line_path(&[(0.0, 0.0), (5.0, 1.0), (10.0, 2.0)], 100.0, 50.0),
+            "M0 50 L50 50 L50 25 L100 25 L100 0"
+        );
+        // Wherever x starts, it starts at the left edge
+        assert_eq!(
+            line_path(&[(10.0, 3.0), (20.0, 3.0)], 100.0, 50.0),
+            "M0 0 L100 0 L100 0"
+        );
+    }
+
+    #[test]
+    fn test_line_path_degenerate() {
+        assert_eq!(line_path(&[], 100.0, 50.0), "");
+        // A single point, or nothing but zeros, sits on the bottom left
+        assert_eq!(line_path(&[(3.0, 0.0)], 100.0, 50.0), "M0 50");
+        assert_eq!(line_path(&[(1.0, 2.5)], 100.0, 50.0), "M0 0");
+        assert_eq!(
+            line_path(&[(0.0, 1.0), (1.0, 2.0)], 30.0, 30.0),
+            "M0 15 L30 15 L30 0"
+        );
+    }
+
+    #[test]
+    fn test_fmt() {
+        assert_eq!(fmt(12.0), "12");
+        assert_eq!(fmt(12.5), "12.5");
+        assert_eq!(fmt(1.0 / 3.0), "0.33");
+        assert_eq!(fmt(0.0), "0");
+    }
+
+    #[test]
+    fn test_proportion_bar() {
+        let svg = proportion_bar(&[("hits", 3.0, "green"), ("misses", 1.0, "red")]);
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"450\" height=\"40\" fill=\"green\">"));
+        assert!(svg.contains("<rect x=\"450\" y=\"0\" width=\"150\" height=\"40\" fill=\"red\">"));
+        assert!(svg.contains("misses: 1"));
+    }
+}
@@ -1,25 +1,49 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use anyhow::{bail, Context};
+use fxhash::FxHashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "serve")]
+use std::{net::TcpListener, sync::atomic::AtomicBool};
 
-use tlparse::{parse_path, ParseConfig};
+#[cfg(feature = "serve")]
+use tlparse::serve_dir;
+use tlparse::{
+    check_input_path, diff_compiles, diff_runs, extract_compile, find_artifact, generate_log,
+    grep_log, parse_path, single_file_report, write_output, write_output_file,
+    write_progress_event, CompileId, DiffRun, DirSink, EnvelopeFieldParser, GenConfig, MemorySink,
+    ParseConfig, StructuredLogParser, TerminalStyle, TrieOrder, DEFAULT_GEN_ARTIFACTS,
+    SINGLE_FILE_MAX_BYTES,
+};
+
+// Written into every output directory we create, so that --overwrite only deletes
+// directories that tlparse made
+static OUTPUT_MARKER: &str = ".tlparse";
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
-    path: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(required = true)]
+    path: Option<PathBuf>,
     /// Parse most recent log
     #[arg(long)]
     latest: bool,
-    /// Output directory, defaults to `tl_out`
+    /// Output directory, defaults to `tl_out`; `-` writes the report to stdout as one HTML
+    /// document instead (raw.log and files over 1 MB left out or cut short) and nothing to disk
     #[arg(short, default_value = "tl_out")]
     out: PathBuf,
     /// Delete out directory if it already exists
     #[arg(long)]
     overwrite: bool,
+    /// Allow --overwrite to delete a directory that wasn't created by tlparse
+    #[arg(long)]
+    force: bool,
     /// Return non-zero exit code if unrecognized log lines are found.  Mostly useful for unit
     /// testing.
     #[arg(long)]
@@ -31,6 +55,11 @@ pub struct Cli {
     /// Don't open browser at the end
     #[arg(long)]
     no_browser: bool,
+    /// Open the report with this shell command instead of the browser, `{}` standing for the
+    /// path of index.html (added at the end if there's no `{}`); $BROWSER is used likewise when
+    /// this isn't given
+    #[arg(long, value_name = "COMMAND", conflicts_with = "no_browser")]
+    open_with: Option<String>,
     /// Some custom HTML to append to the top of report
     #[arg(long, default_value = "")]
     custom_header_html: String,
@@ -41,12 +70,810 @@ pub struct Cli {
     /// Enabiling this option will enforce output as plain text for easier diffing
     #[arg(short, long)]
     plain_text: bool,
+    /// Only render log entries from this rank, instead of detecting it from the log
+    #[arg(long)]
+    rank: Option<u32>,
+    /// Only render this run, when a restarted job appended several runs to the same log
+    #[arg(long)]
+    run: Option<usize>,
+    /// Skip envelopes that exactly repeat a recent one (e.g. a log teed to two handlers)
+    #[arg(long)]
+    dedupe: bool,
+    /// Also accept payload lines indented with this many spaces instead of a tab (default 4, 0
+    /// to only accept tabs)
+    #[arg(long)]
+    payload_indent: Option<usize>,
+    /// Drop everything up to and including this from displayed file names; may be repeated,
+    /// prefix with re: for a regex
+    #[arg(long)]
+    strip_prefix: Vec<String>,
+    /// How many levels of stack trie branches start out unfolded (default 3)
+    #[arg(long)]
+    trie_open_depth: Option<usize>,
+    /// Which compiles to show in the stack trie
+    #[arg(long, value_enum, default_value_t = TrieView::All)]
+    trie: TrieView,
+    /// Only show stacks in the stack trie with a frame whose file or function name matches this
+    /// regex
+    #[arg(long)]
+    trie_filter: Option<String>,
+    /// Show internal frames (inside torch, or under an --internal-frame-prefix) in the stack trie
+    /// instead of collapsing them
+    #[arg(long)]
+    show_internal_frames: bool,
+    /// Also collapse frames from files starting with this (after shortening); may be repeated
+    #[arg(long)]
+    internal_frame_prefix: Vec<String>,
+    /// Cut stacks in the stack trie deeper than this many frames (default 40, 0 for no limit)
+    #[arg(long)]
+    trie_max_depth: Option<usize>,
+    /// Local checkout to read the source of stack trie frames from, shown when hovering a frame
+    #[arg(long)]
+    source_root: Option<PathBuf>,
+    /// How to order the branches of the stack trie
+    #[arg(long, value_enum, default_value_t = TrieSort::Log)]
+    trie_sort: TrieSort,
+    /// Show frames that only differ in their line number as one frame in the stack trie
+    #[arg(long)]
+    trie_merge_lines: bool,
+    /// Also print the stack trie to stdout
+    #[arg(long)]
+    print_trie: bool,
+    /// Split a compile's span (first to last log entry) where its entries are more than this
+    /// many seconds apart (default 60)
+    #[arg(long)]
+    span_gap: Option<f64>,
+    /// Count every overload of an operator as the operator in the ops histogram (aten.add.Tensor
+    /// as aten.add)
+    #[arg(long)]
+    ops_merge_overloads: bool,
+    /// List this many of the compiles that took longest in top.html (default 20), and print them
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+    /// Make each window of timeline.html this many seconds wide (default: a round width that
+    /// splits the log into about 20)
+    #[arg(long, value_name = "SECONDS")]
+    bucket: Option<f64>,
+    /// Flag frames that recompiled at least --loop-min-compiles times in this many seconds at
+    /// the end of the log as stuck in a recompile loop (default 600)
+    #[arg(long, value_name = "SECONDS")]
+    loop_window: Option<f64>,
+    /// How many recompiles in the last --loop-window seconds make a recompile loop (default 4)
+    #[arg(long, value_name = "N")]
+    loop_min_compiles: Option<usize>,
+    /// Expect compiles to have logged these artifacts instead of the default rules, e.g.
+    /// "aot_forward_graph => inductor_output_code unless fx_graph_cache_hit"; may be repeated
+    #[arg(long, value_name = "RULE")]
+    artifact_rule: Vec<String>,
+    /// Don't check what artifacts compiles logged
+    #[arg(long)]
+    no_artifact_rules: bool,
+    /// Show graphs and code bigger than this many bytes without highlighting (default 1 MiB)
+    #[arg(long, value_name = "BYTES")]
+    highlight_max_bytes: Option<usize>,
+    /// Also copy each compile's log entries, payloads cut to a few lines, into raw.log in its
+    /// directory
+    #[arg(long)]
+    keep_raw: bool,
+    /// Leave out the footer saying what log, version and flags every page came from (and its
+    /// block in stats.json and compile_directory.json), so that the output only changes when the
+    /// log does
+    #[arg(long)]
+    no_provenance: bool,
+    /// Split the index's list of compiles by frame into pages (index_000.html, ...) of about
+    /// this many attempts, once there are more than that (default 2000, 0 to never)
+    #[arg(long, value_name = "N")]
+    index_shard_size: Option<usize>,
+    /// Don't show the first lines of every text artifact under its link, for the smallest pages
+    #[arg(long)]
+    no_previews: bool,
+    /// Don't copy the log into the output directory as raw.log
+    #[arg(long)]
+    no_raw_log: bool,
+    /// Write artifacts without run-specific noise (object addresses, temp paths, cache hashes,
+    /// kernel and generated name counters), so that two runs' outputs can be diffed
+    #[arg(long)]
+    normalize: bool,
+    /// Also write JSON artifacts as they were logged, next to the pretty-printed .json
+    #[arg(long)]
+    json_keep_raw: bool,
+    /// Write the payload of every entry with this field, which tlparse doesn't know, to a file
+    /// of its compile under custom_artifact/, as FIELD=FILENAME (e.g. my_stats=my_stats.json);
+    /// may be repeated
+    #[arg(long, value_parser = parse_custom_artifact)]
+    custom_artifact: Vec<(String, String)>,
+    /// Parse the log and list the files that would be written, with their sizes, without
+    /// writing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Instead of progress bars, write progress, warnings and errors to stderr as JSON objects,
+    /// one per line, for programs that run tlparse (see Progress in the library)
+    #[arg(long)]
+    progress_json: bool,
+    /// Run this shell command once the report is written, with the output directory and its
+    /// stats.json as arguments ($1 and $2) and the counts in TLPARSE_* environment variables
+    /// (once for each run_N directory, if the log had several runs); not run if parsing fails
+    #[arg(long, value_name = "COMMAND", conflicts_with = "dry_run")]
+    post_hook: Option<String>,
+    /// Exit with an error when the --post-hook command fails
+    #[arg(long, requires = "post_hook")]
+    fail_on_hook_error: bool,
+}
+
+fn parse_custom_artifact(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((field, filename)) if !field.is_empty() && !filename.is_empty() => {
+            Ok((field.to_string(), filename.to_string()))
+        }
+        _ => Err(format!("expected FIELD=FILENAME, got {s}")),
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Diff the graphs and guards of two compiles of an output directory, e.g. two recompiles
+    /// of a frame: writes diff_<a>_vs_<b>.html into it and prints the diff
+    DiffCompile {
+        /// Output directory of an earlier tlparse run
+        out_dir: PathBuf,
+        /// Compile ids as the report shows them, e.g. 2/0 and 2/1
+        a: String,
+        b: String,
+        /// Diff graphs and guards as logged, without normalizing node names and formatting
+        #[arg(long)]
+        no_normalize: bool,
+    },
+    /// Compare two runs, e.g. before and after a PyTorch upgrade: what compiles were added or
+    /// removed, which frames compiled more often or got slower, and how the artifacts of compiles
+    /// both have differ.  Writes diff.html and diff.json into -o and prints a summary
+    Diff {
+        /// The run before: a log, or the output directory of an earlier tlparse run
+        a: PathBuf,
+        /// The run after, likewise
+        b: PathBuf,
+        /// Output directory, defaults to `tl_diff`; logs are parsed into its a/ and b/
+        #[arg(short, default_value = "tl_diff")]
+        out: PathBuf,
+        /// Delete out directory if it already exists
+        #[arg(long)]
+        overwrite: bool,
+        /// How much longer (in percent) a compile has to take to count as slower
+        #[arg(long, value_name = "PCT", default_value_t = 10.0)]
+        threshold: f64,
+        /// Diff graphs and guards as logged, without normalizing node names and formatting
+        #[arg(long)]
+        no_normalize: bool,
+        /// Return non-zero exit code if b regressed: it has compiles a didn't, a frame compiled
+        /// more often, or something got slower
+        #[arg(long)]
+        fail_on_regression: bool,
+    },
+    /// Print one artifact of a compile to stdout, without writing an output directory, e.g.
+    /// `tlparse cat trace.log 1/0_1 dynamo_output_graph | grep mul`
+    Cat {
+        /// The log to read
+        path: PathBuf,
+        /// The compile, as tlparse prints it: [1/0_1] or 1/0_1
+        compile_id: String,
+        /// The artifact: an entry's kind (dynamo_output_graph, inductor_output_code, ...) or an
+        /// artifact's name; if the compile has no such artifact, the error lists what it has
+        artifact: String,
+    },
+    /// Print the payload lines of a log matching a regex, each prefixed with its compile id,
+    /// artifact and line in the payload, e.g. `[3/0] inductor_output_code:412: tl.dot(...)`
+    Grep {
+        /// Regex to search payloads for
+        pattern: String,
+        /// The log to search
+        path: PathBuf,
+        /// Only search artifacts of this kind or name, as `tlparse cat` takes them
+        #[arg(long, value_name = "NAME")]
+        artifact: Option<String>,
+        /// Only print the ids of compiles with matches, once each
+        #[arg(short = 'l')]
+        list_compiles: bool,
+    },
+    /// Write the pages of one compile and nothing else (no index, stack trie or reports), for
+    /// sharing one frame's graphs and guards without the whole output
+    Extract {
+        /// The log to read
+        path: PathBuf,
+        /// The compile, e.g. 12/3 for all of its attempts or 12/3_1 for one
+        #[arg(long, value_name = "ID")]
+        compile_id: String,
+        /// Only this attempt of the compile
+        #[arg(long)]
+        attempt: Option<u32>,
+        /// Output directory, defaults to `tl_<frame>_<compile>`, e.g. tl_12_3
+        #[arg(short)]
+        out: Option<PathBuf>,
+        /// Delete out directory if it already exists (never a full tlparse output)
+        #[arg(long)]
+        overwrite: bool,
+    },
+    /// Write a synthetic trace log of made-up compiles, for testing and benchmarking tlparse
+    /// without real logs
+    Gen {
+        /// Where to write the log, defaults to stdout
+        #[arg(short)]
+        out: Option<PathBuf>,
+        /// How many frames compile
+        #[arg(long, default_value_t = 3)]
+        frames: usize,
+        /// How many times each frame recompiles
+        #[arg(long, default_value_t = 1)]
+        recompiles: usize,
+        /// What each compile logs: entry kinds, and anything else as an artifact of that name
+        #[arg(long, value_delimiter = ',', default_values_t = DEFAULT_GEN_ARTIFACTS.iter().map(|a| a.to_string()))]
+        artifacts: Vec<String>,
+        /// How many lines each payload has
+        #[arg(long, default_value_t = 10)]
+        payload_lines: usize,
+        /// How many ranks' entries to interleave
+        #[arg(long, default_value_t = 1)]
+        ranks: usize,
+        /// How many payloads get a wrong MD5
+        #[arg(long, default_value_t = 0)]
+        bad_md5: usize,
+        /// How many payloads are missing after their entry
+        #[arg(long, default_value_t = 0)]
+        truncated_payloads: usize,
+        /// How many lines that aren't glog lines to scatter between entries
+        #[arg(long, default_value_t = 0)]
+        junk_lines: usize,
+        /// Which of the possible logs to write
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// When each compile starts, in seconds after the log does, frame by frame; the rest
+        /// start right after the one before
+        #[arg(long, value_delimiter = ',')]
+        compile_times: Vec<f64>,
+        /// How long each compile takes, in seconds
+        #[arg(long, default_value_t = 0.0)]
+        compile_s: f64,
+        /// Cycle the compiles of each frame through this many sets of guards, 0 for new guards
+        /// every compile
+        #[arg(long, default_value_t = 0)]
+        guard_variants: usize,
+        /// Leave out compilation_metrics
+        #[arg(long)]
+        no_metrics: bool,
+        /// How long the log goes on, in seconds, however soon the compiles are done
+        #[arg(long)]
+        log_s: Option<f64>,
+    },
+    /// Serve an output directory on localhost, for what doesn't work from file:// and for
+    /// outputs on another machine (ssh -L 8000:localhost:8000); given a log, parse it first
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Output directory of an earlier tlparse run, or a log to parse into -o
+        path: PathBuf,
+        /// Output directory when parsing a log, defaults to `tl_out`
+        #[arg(short, default_value = "tl_out")]
+        out: PathBuf,
+        /// Delete out directory if it already exists
+        #[arg(long)]
+        overwrite: bool,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+        /// Port to listen on, 0 for any free one
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum TrieView {
+    All,
+    Failed,
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum TrieSort {
+    /// As they appear in the log
+    Log,
+    /// Earliest first compile first
+    Time,
+    /// Most compiles first
+    Compiles,
+    /// Most compile time first
+    CompileTime,
+}
+
+// Make an empty output directory to write into, or empty out one of an earlier run
+fn prepare_out_dir(out_path: &Path, overwrite: bool, force: bool) -> anyhow::Result<()> {
+    if out_path.exists() {
+        if !out_path.is_dir() {
+            bail!(
+                "{} already exists and is not a directory, use -o OUTDIR to write to another location",
+                out_path.display()
+            );
+        }
+        if !overwrite {
+            bail!(
+                "Directory {} already exists, use -o OUTDIR to write to another location or pass --overwrite to overwrite the old contents",
+                out_path.display()
+            );
+        }
+        if !force && !out_path.join(OUTPUT_MARKER).is_file() {
+            bail!(
+                "Directory {} was not created by tlparse (no {} marker), refusing to overwrite it; pass --force if you really want to delete it",
+                out_path.display(),
+                OUTPUT_MARKER
+            );
+        }
+        fs::remove_dir_all(out_path)?;
+    }
+    fs::create_dir(out_path)?;
+    fs::write(out_path.join(OUTPUT_MARKER), "")?;
+    Ok(())
+}
+
+// A run for tlparse diff: an output directory as it is, or a log parsed into out/sub
+fn diff_run(path: &Path, out: &Path, sub: &str) -> anyhow::Result<DiffRun> {
+    let name = path.display().to_string();
+    if path.join("compile_directory.json").is_file() {
+        // Linked to where it is, since it's outside of out
+        let href = format!("{}/", path.canonicalize()?.display());
+        return Ok(DiffRun {
+            dir: path.to_path_buf(),
+            name,
+            href,
+        });
+    }
+    check_input_path(path)?;
+    let dir = out.join(sub);
+    fs::create_dir(&dir)?;
+    let config = ParseConfig {
+        output_dir: Some(dir.clone()),
+        ..Default::default()
+    };
+    write_output(
+        parse_path(&path.to_path_buf(), config)?,
+        &mut DirSink::new(&dir),
+    )?;
+    Ok(DiffRun {
+        dir,
+        name,
+        href: format!("{sub}/"),
+    })
+}
+
+// --top: the compiles that took longest, from the stats.json of every run of the output
+fn print_top_compiles(output: &[(PathBuf, String)], out: &mut dyn Write) -> anyhow::Result<()> {
+    for (path, contents) in output {
+        if path.file_name() != Some(std::ffi::OsStr::new("stats.json")) {
+            continue;
+        }
+        let stats: serde_json::Value = serde_json::from_str(contents)?;
+        let Some(top) = stats["top_compiles"].as_array().filter(|t| !t.is_empty()) else {
+            continue;
+        };
+        match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            Some(run) => writeln!(out, "Top compiles of {}:", run.display())?,
+            None => writeln!(out, "Top compiles:")?,
+        }
+        for c in top {
+            let from_span = if c["from_span"] == true {
+                " (span)"
+            } else {
+                ""
+            };
+            let field = |k: &str| c[k].as_str().unwrap_or_default().to_string();
+            writeln!(
+                out,
+                "  {:>8}{from_span}  {} — {}",
+                field("time"),
+                field("compile_id"),
+                field("frame")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// command for the platform's shell; arguments added to it are the script's $1, $2, ...
+fn shell_command(command: &str, name: &str) -> std::process::Command {
+    if cfg!(windows) {
+        let mut c = std::process::Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    } else {
+        let mut c = std::process::Command::new("sh");
+        // $0 is what the shell calls itself in errors
+        c.arg("-c").arg(command).arg(name);
+        c
+    }
+}
+
+// command with placeholder replaced by path, quoted for the shell, or path appended if it has
+// no placeholder
+fn with_path(command: &str, placeholder: &str, path: &Path) -> String {
+    let path = path.display().to_string();
+    let quoted = if cfg!(windows) {
+        format!("\"{path}\"")
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    };
+    if command.contains(placeholder) {
+        command.replace(placeholder, &quoted)
+    } else {
+        format!("{command} {quoted}")
+    }
+}
+
+// With quiet, what the command prints to stderr goes into its error instead, so that stderr only
+// has the events of --progress-json
+fn run_opener(command: &str, quiet: bool) -> anyhow::Result<()> {
+    let mut command = shell_command(command, "tlparse-open");
+    if !quiet {
+        let status = command.status()?;
+        if !status.success() {
+            bail!("{status}");
+        }
+        return Ok(());
+    }
+    let output = command.stdin(std::process::Stdio::null()).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.trim() {
+            "" => bail!("{}", output.status),
+            stderr => bail!("{} ({stderr})", output.status),
+        }
+    }
+    Ok(())
+}
+
+// What separates the commands of $BROWSER, like the directories of $PATH: `:` would split a
+// Windows path like C:\Program Files\...
+static BROWSER_SEPARATOR: char = if cfg!(windows) { ';' } else { ':' };
+
+// Show the report: with --open-with, else with $BROWSER (a list of commands separated by
+// BROWSER_SEPARATOR, each tried in turn, `%s` standing for the path), else in the system's
+// default browser.  None of it is worth failing over, when the report is there for the taking
+// anyway.
+fn open_report(index: &Path, open_with: Option<&str>, progress_json: bool) {
+    let absolute = fs::canonicalize(index).unwrap_or_else(|_| index.to_path_buf());
+    let browser = std::env::var("BROWSER").unwrap_or_default();
+    let result = if let Some(command) = open_with {
+        run_opener(&with_path(command, "{}", &absolute), progress_json)
+            .with_context(|| format!("--open-with {command} failed"))
+    } else if !browser.is_empty() {
+        // The last one's error, if none of them works
+        let mut result = Err(anyhow::anyhow!("BROWSER has no commands"));
+        for b in browser.split(BROWSER_SEPARATOR).filter(|b| !b.is_empty()) {
+            result = run_opener(&with_path(b, "%s", &absolute), progress_json)
+                .with_context(|| format!("BROWSER command {b} failed"));
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    } else {
+        opener::open(index).context("Couldn't open a browser")
+    };
+    if let Err(err) = result {
+        let message = format!("{err:#}; the report is at {}", absolute.display());
+        if progress_json {
+            write_progress_event("error", serde_json::json!({ "message": message }));
+        } else {
+            eprintln!("{message}");
+        }
+    }
+}
+
+// The reports in an output directory, each with its stats.json: the directory itself, or for a
+// log with several runs in it, each of its run_N directories, and N
+fn written_reports(out_path: &Path) -> anyhow::Result<Vec<(PathBuf, Option<usize>)>> {
+    if out_path.join("stats.json").is_file() {
+        return Ok(vec![(out_path.to_path_buf(), None)]);
+    }
+    let mut runs: Vec<(PathBuf, Option<usize>)> = fs::read_dir(out_path)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let run = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("run_")?
+                .parse()
+                .ok()?;
+            path.join("stats.json")
+                .is_file()
+                .then_some((path, Some(run)))
+        })
+        .collect();
+    runs.sort_by_key(|(_, run)| *run);
+    Ok(runs)
+}
+
+// Run --post-hook on a written report, the one of run if the log had several.  The environment
+// has what scripts usually want out of stats.json, so that a hook needn't parse it:
+// TLPARSE_OUTPUT_DIR, TLPARSE_STATS, TLPARSE_COMPILES, TLPARSE_RECOMPILES,
+// TLPARSE_FAILED_COMPILES, TLPARSE_OK_ENTRIES and TLPARSE_FAILED_ENTRIES (entries that failed to
+// parse, of any kind), and TLPARSE_RUN for a run's report.
+fn run_post_hook(
+    command: &str,
+    out_path: &Path,
+    run: Option<usize>,
+) -> anyhow::Result<std::process::ExitStatus> {
+    let stats_path = out_path.join("stats.json");
+    let stats: serde_json::Value = serde_json::from_slice(
+        &fs::read(&stats_path).with_context(|| format!("reading {}", stats_path.display()))?,
+    )?;
+    let count = |v: &serde_json::Value| v.as_u64().unwrap_or(0);
+    let failed_entries: u64 = stats
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(k, _)| k.starts_with("fail_"))
+        .map(|(_, v)| count(v))
+        .sum();
+
+    let mut hook = shell_command(command, "tlparse-post-hook");
+    hook.arg(out_path)
+        .arg(&stats_path)
+        .env("TLPARSE_OUTPUT_DIR", out_path)
+        .env("TLPARSE_STATS", &stats_path)
+        .env("TLPARSE_OK_ENTRIES", count(&stats["ok"]).to_string())
+        .env("TLPARSE_FAILED_ENTRIES", failed_entries.to_string());
+    if let Some(run) = run {
+        hook.env("TLPARSE_RUN", run.to_string());
+    }
+    for (var, key) in [
+        ("TLPARSE_COMPILES", "total"),
+        ("TLPARSE_RECOMPILES", "recompiles"),
+        ("TLPARSE_FAILED_COMPILES", "failed"),
+    ] {
+        hook.env(var, count(&stats["compiles"][key]).to_string());
+    }
+    hook.status()
+        .with_context(|| format!("running post hook {command}"))
+}
+
+#[cfg(feature = "serve")]
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+// Stop serving on Ctrl-C once the requests in flight are answered, rather than in the middle of
+// one
+#[cfg(all(feature = "serve", unix))]
+fn shutdown_on_ctrl_c() {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        SHUTDOWN.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    // Storing to an atomic is all the handler does, which is safe to do in a signal handler
+    unsafe {
+        libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+    }
+}
+
+#[cfg(all(feature = "serve", not(unix)))]
+fn shutdown_on_ctrl_c() {}
+
+#[cfg(feature = "serve")]
+fn serve(
+    path: PathBuf,
+    out: PathBuf,
+    overwrite: bool,
+    bind: &str,
+    port: u16,
+) -> anyhow::Result<()> {
+    let dir = if path.join("index.html").is_file() {
+        path
+    } else {
+        check_input_path(&path)?;
+        prepare_out_dir(&out, overwrite, false)?;
+        let config = ParseConfig {
+            output_dir: Some(out.clone()),
+            provenance: Some(std::env::args().skip(1).collect()),
+            ..Default::default()
+        };
+        let output = parse_path(&path, config)?;
+        let mut sink = DirSink::new(&out);
+        sink.count_existing()?;
+        write_output(output, &mut sink)?;
+        eprintln!("Wrote {}", sink.totals());
+        out
+    };
+    let listener = TcpListener::bind((bind, port))
+        .with_context(|| format!("Couldn't listen on {bind}:{port}"))?;
+    eprintln!(
+        "Serving {} at http://{}/ (Ctrl-C to stop)",
+        dir.display(),
+        listener.local_addr()?
+    );
+    shutdown_on_ctrl_c();
+    serve_dir(&dir, listener, &SHUTDOWN)?;
+    Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    match cli.command {
+        Some(Command::DiffCompile {
+            out_dir,
+            a,
+            b,
+            no_normalize,
+        }) => {
+            let diff = diff_compiles(&out_dir, &a, &b, !no_normalize)?;
+            write_output_file(&out_dir.join(&diff.filename), diff.html.as_bytes())?;
+            print!("{}", diff.text);
+            eprintln!("Wrote {}", out_dir.join(&diff.filename).display());
+            return Ok(());
+        }
+        Some(Command::Diff {
+            a,
+            b,
+            out,
+            overwrite,
+            threshold,
+            no_normalize,
+            fail_on_regression,
+        }) => {
+            prepare_out_dir(&out, overwrite, false)?;
+            let (a, b) = (diff_run(&a, &out, "a")?, diff_run(&b, &out, "b")?);
+            let report = diff_runs(&a, &b, !no_normalize, threshold)?;
+            write_output_file(&out.join("diff.html"), report.html.as_bytes())?;
+            write_output_file(
+                &out.join("diff.json"),
+                serde_json::to_string_pretty(&report.diff)?.as_bytes(),
+            )?;
+            print!("{}", report.diff.text());
+            eprintln!("Wrote {}", out.join("diff.html").display());
+            if fail_on_regression && report.diff.regressed {
+                bail!("{} regressed: {}", b.name, report.diff.reasons.join("; "));
+            }
+            return Ok(());
+        }
+        Some(Command::Cat {
+            path,
+            compile_id,
+            artifact,
+        }) => {
+            check_input_path(&path)?;
+            let reader = io::BufReader::new(fs::File::open(&path)?);
+            let mut payload = find_artifact(reader, &compile_id, &artifact)?;
+            payload.push('\n');
+            io::stdout().write_all(payload.as_bytes())?;
+            return Ok(());
+        }
+        Some(Command::Grep {
+            pattern,
+            path,
+            artifact,
+            list_compiles,
+        }) => {
+            check_input_path(&path)?;
+            let reader = io::BufReader::new(fs::File::open(&path)?);
+            let mut out = io::stdout().lock();
+            let mut listed = FxHashSet::default();
+            let mut matched = false;
+            let mut written = Ok(());
+            grep_log(reader, &pattern, artifact.as_deref(), |found| {
+                matched = true;
+                written = if !list_compiles {
+                    writeln!(out, "{found}")
+                } else if listed.insert(found.compile_id.cloned()) {
+                    let compile_id = found
+                        .compile_id
+                        .map_or("(unknown)".to_string(), |c| c.to_string());
+                    writeln!(out, "{compile_id}")
+                } else {
+                    Ok(())
+                };
+                written.is_ok()
+            })?;
+            match written {
+                // Like grep, piping into head isn't an error
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                written => written?,
+            }
+            if !matched {
+                bail!("No payload lines match {pattern}");
+            }
+            return Ok(());
+        }
+        Some(Command::Extract {
+            path,
+            compile_id: label,
+            attempt,
+            out,
+            overwrite,
+        }) => {
+            let Some(mut compile_id) = CompileId::parse(&label) else {
+                bail!("{label} is not a compile id, expected e.g. 12/3 or 12/3_1");
+            };
+            let all_attempts = attempt.is_none() && !label.contains('_');
+            compile_id.attempt = attempt.unwrap_or(compile_id.attempt);
+            let out = out.unwrap_or_else(|| {
+                let dir = compile_id.as_directory_name();
+                let dir = if all_attempts {
+                    dir.rsplit_once('_').unwrap().0
+                } else {
+                    &dir
+                };
+                PathBuf::from(format!("tl_{dir}"))
+            });
+            if out.join("index.html").exists() {
+                bail!(
+                    "{} holds a full tlparse output, extract into another directory with -o",
+                    out.display()
+                );
+            }
+            check_input_path(&path)?;
+            let reader = io::BufReader::new(fs::File::open(&path)?);
+            let config = ParseConfig {
+                custom_header_html: cli.custom_header_html,
+                verbose: cli.verbose,
+                ..Default::default()
+            };
+            let output = extract_compile(reader, &compile_id, all_attempts, &config)?;
+            prepare_out_dir(&out, overwrite, false)?;
+            for (file, _) in &output.files {
+                println!("{}", file.display());
+            }
+            write_output(output, &mut DirSink::new(&out))?;
+            eprintln!("Wrote {}", out.display());
+            return Ok(());
+        }
+        Some(Command::Gen {
+            out,
+            frames,
+            recompiles,
+            artifacts,
+            payload_lines,
+            ranks,
+            bad_md5,
+            truncated_payloads,
+            junk_lines,
+            seed,
+            compile_times,
+            compile_s,
+            guard_variants,
+            no_metrics,
+            log_s,
+        }) => {
+            let config = GenConfig {
+                frames,
+                recompiles,
+                // --artifacts '' for none
+                artifacts: artifacts.into_iter().filter(|a| !a.is_empty()).collect(),
+                payload_lines,
+                ranks,
+                bad_md5,
+                truncated_payloads,
+                junk_lines,
+                seed,
+                compile_times,
+                compile_s,
+                guard_variants,
+                metrics: !no_metrics,
+                log_s,
+                ..Default::default()
+            };
+            let mut out: Box<dyn Write> = match out {
+                Some(out) => Box::new(io::BufWriter::new(fs::File::create(out)?)),
+                None => Box::new(io::BufWriter::new(io::stdout().lock())),
+            };
+            generate_log(&config, &mut out)?;
+            out.flush()?;
+            return Ok(());
+        }
+        #[cfg(feature = "serve")]
+        Some(Command::Serve {
+            path,
+            out,
+            overwrite,
+            bind,
+            port,
+        }) => return serve(path, out, overwrite, &bind, port),
+        None => (),
+    }
+    // Required unless there's a subcommand
+    let path = cli.path.unwrap();
     let path = if cli.latest {
-        let input_path = cli.path;
+        let input_path = path;
         // Path should be a directory
         if !input_path.is_dir() {
             bail!(
@@ -66,43 +893,171 @@ fn main() -> anyhow::Result<()> {
         };
         last_modified_file.path()
     } else {
-        cli.path
+        path
     };
 
+    check_input_path(&path)?;
+
     let out_path = cli.out;
+    let to_stdout = out_path == Path::new("-");
+    if to_stdout && cli.post_hook.is_some() {
+        bail!("--post-hook needs an output directory to run on, not -o -");
+    }
+    if to_stdout && cli.print_trie {
+        bail!("--print-trie prints to stdout, which -o - writes the report to");
+    }
 
-    if out_path.exists() {
-        if !cli.overwrite {
-            bail!(
-                "Directory {} already exists, use -o OUTDIR to write to another location or pass --overwrite to overwrite the old contents",
-                out_path.display()
-            );
-        }
-        fs::remove_dir_all(&out_path)?;
+    // A dry run doesn't touch the output directory at all, and -o - has none
+    let to_dir = !cli.dry_run && !to_stdout;
+    if to_dir {
+        prepare_out_dir(&out_path, cli.overwrite, cli.force)?;
     }
-    fs::create_dir(&out_path)?;
 
     let config = ParseConfig {
         strict: cli.strict,
         strict_compile_id: cli.strict_compile_id,
-        custom_parsers: Vec::new(),
+        custom_parsers: cli
+            .custom_artifact
+            .into_iter()
+            .map(|(field, filename)| {
+                Box::new(EnvelopeFieldParser { field, filename }) as Box<dyn StructuredLogParser>
+            })
+            .collect(),
         custom_header_html: cli.custom_header_html,
         verbose: cli.verbose,
         plain_text: cli.plain_text,
+        rank: cli.rank,
+        run: cli.run,
+        dedupe: cli.dedupe,
+        output_dir: to_dir.then(|| out_path.clone()),
+        stream_payload_threshold: None,
+        payload_space_indent: cli.payload_indent,
+        strip_prefixes: cli.strip_prefix,
+        trie_open_depth: cli.trie_open_depth,
+        trie_failed_only: cli.trie == TrieView::Failed,
+        trie_filter: cli.trie_filter,
+        show_internal_frames: cli.show_internal_frames,
+        internal_frame_prefixes: cli.internal_frame_prefix,
+        trie_max_depth: cli.trie_max_depth,
+        source_root: cli.source_root,
+        trie_order: match cli.trie_sort {
+            TrieSort::Log => TrieOrder::Insertion,
+            TrieSort::Time => TrieOrder::FirstSeen,
+            TrieSort::Compiles => TrieOrder::Compiles,
+            TrieSort::CompileTime => TrieOrder::CompileTime,
+        },
+        trie_merge_lines: cli.trie_merge_lines,
+        print_trie: cli.print_trie.then(|| {
+            // Plain and unlimited when piped somewhere, so it can be grepped and diffed
+            let term = console::Term::stdout();
+            let is_tty = term.is_term();
+            TerminalStyle {
+                width: term
+                    .size_checked()
+                    .filter(|_| is_tty)
+                    .map(|(_, cols)| cols as usize),
+                color: is_tty && std::env::var_os("NO_COLOR").is_none(),
+            }
+        }),
+        compile_span_gap_s: cli.span_gap,
+        ops_merge_overloads: cli.ops_merge_overloads,
+        top_compiles: cli.top,
+        timeline_bucket_s: cli.bucket,
+        recompile_loop_window_s: cli.loop_window,
+        recompile_loop_min_compiles: cli.loop_min_compiles,
+        artifact_rules: if cli.no_artifact_rules {
+            Some(Vec::new())
+        } else {
+            (!cli.artifact_rule.is_empty()).then_some(cli.artifact_rule)
+        },
+        highlight_max_bytes: cli.highlight_max_bytes,
+        keep_raw: cli.keep_raw,
+        provenance: (!cli.no_provenance).then(|| std::env::args().skip(1).collect()),
+        index_shard_size: cli.index_shard_size,
+        no_previews: cli.no_previews,
+        no_raw_log: cli.no_raw_log,
+        normalize_artifacts: cli.normalize,
+        json_keep_raw: cli.json_keep_raw,
+        progress_json: cli.progress_json,
     };
 
+    // What's printed after parsing, as events too with --progress-json
+    let say = |message: String| {
+        if cli.progress_json {
+            write_progress_event("info", serde_json::json!({ "message": message }));
+        } else {
+            eprintln!("{message}");
+        }
+    };
     let output = parse_path(&path, config)?;
+    // With -o -, stdout is the report's
+    if cli.top.is_some() && to_stdout {
+        print_top_compiles(&output.files, &mut io::stderr().lock())?;
+    } else if cli.top.is_some() {
+        print_top_compiles(&output.files, &mut io::stdout().lock())?;
+    }
+    // Under the name of its directory when each run has its own
+    let labeled = output.runs.len() > 1;
+    for run in &output.runs {
+        if let Some(text) = &run.trie_text {
+            if labeled {
+                println!("run_{}:", run.run);
+            }
+            print!("{text}");
+        }
+    }
+
+    // Everything but the report itself goes to stderr, and there's no page to open
+    if to_stdout && !cli.dry_run {
+        let mut sink = MemorySink::default();
+        write_output(output, &mut sink)?;
+        let report = single_file_report(&sink.files, SINGLE_FILE_MAX_BYTES);
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(report.as_bytes())?;
+        stdout.flush()?;
+        say(format!("Wrote {} to stdout as one page", sink.totals()));
+        return Ok(());
+    }
+    if cli.dry_run {
+        let mut sink = MemorySink::default();
+        write_output(output, &mut sink)?;
+        for (filename, contents) in &sink.files {
+            println!("{}\t{}", contents.len(), out_path.join(filename).display());
+        }
+        say(format!("Would write {}", sink.totals()));
+        return Ok(());
+    }
+    let mut sink = DirSink::new(&out_path);
+    // What the parse streamed there
+    sink.count_existing()?;
+    write_output(output, &mut sink)?;
+    say(format!("Wrote {}", sink.totals()));
 
-    for (filename, path) in output {
-        let out_file = out_path.join(filename);
-        if let Some(dir) = out_file.parent() {
-            fs::create_dir_all(dir)?;
+    if let Some(command) = &cli.post_hook {
+        // Once for every run, which are each their own report
+        let mut failed = None;
+        for (dir, run) in written_reports(&out_path)? {
+            let status = run_post_hook(command, &dir, run)?;
+            let of_run = run.map_or(String::new(), |run| format!(" for run_{run}"));
+            if status.success() {
+                say(format!("Post hook succeeded{of_run}"));
+            } else if cli.fail_on_hook_error {
+                failed = failed.or(Some(format!("Post hook failed{of_run} ({status})")));
+            } else {
+                say(format!("Post hook failed{of_run} ({status})"));
+            }
+        }
+        if let Some(failed) = failed {
+            bail!(failed);
         }
-        fs::write(out_file, path)?;
     }
 
     if !cli.no_browser {
-        opener::open(out_path.join("index.html"))?;
+        open_report(
+            &out_path.join("index.html"),
+            cli.open_with.as_deref(),
+            cli.progress_json,
+        );
     }
     Ok(())
 }
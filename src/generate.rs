@@ -0,0 +1,400 @@
+// Synthetic trace logs, see `tlparse gen`: made-up compiles in the format PyTorch writes, for
+// tests and benchmarks that would otherwise need real (and huge) logs.  The same GenConfig always
+// gives the same bytes.
+
+use anyhow::bail;
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use fxhash::FxHashSet;
+use md5::{Digest, Md5};
+use serde_json::{json, Value};
+use std::io::Write;
+
+// The artifacts every compile logs unless GenConfig::artifacts says otherwise
+pub static DEFAULT_GEN_ARTIFACTS: &[&str] = &[
+    "dynamo_output_graph",
+    "dynamo_guards",
+    "inductor_output_code",
+];
+
+// The kinds whose payload is an FX graph
+static GRAPH_KINDS: &[&str] = &[
+    "dynamo_output_graph",
+    "aot_forward_graph",
+    "aot_backward_graph",
+    "aot_inference_graph",
+    "aot_joint_graph",
+    "inductor_pre_grad_graph",
+    "inductor_post_grad_graph",
+];
+
+static FILENAMES: &[&str] = &[
+    "/workspace/model.py",
+    "/workspace/torch/_dynamo/eval_frame.py",
+    "/workspace/torch/nn/modules/module.py",
+];
+
+// What to generate.  Corrupted entries are picked at random among the ones with a payload, and
+// junk lines go between entries; how many of each there are is exact, so a test can check the
+// parse's Stats against them.
+pub struct GenConfig {
+    pub frames: usize,
+    // Compiles of each frame after its first
+    pub recompiles: usize,
+    // What every compile logs besides dynamo_start and compilation_metrics: entry kinds
+    // (dynamo_output_graph, dynamo_guards, inductor_output_code, aot_forward_graph, ...), and
+    // anything else as an `artifact` entry of that name
+    pub artifacts: Vec<String>,
+    // How many lines each payload has
+    pub payload_lines: usize,
+    // More than one interleaves the entries of that many ranks, as when they share a log
+    pub ranks: usize,
+    // Entries whose has_payload doesn't match their payload
+    pub bad_md5: usize,
+    // Entries with a has_payload but none of the payload, as when the writer died
+    pub truncated_payloads: usize,
+    // Lines that aren't glog lines at all
+    pub junk_lines: usize,
+    pub seed: u64,
+    // Of the first entry; each one after is a millisecond later, unless the timings below say
+    // otherwise.  Every rank keeps its own time, a microsecond behind the rank before.
+    pub start: NaiveDateTime,
+    // When each compile starts, in seconds after start, frame by frame (all the compiles of frame
+    // 0, then of frame 1, ...), even if that's before the one before is done, as with compiles on
+    // other threads; compiles past the end of the list start right after the one before
+    pub compile_times: Vec<f64>,
+    // How long each compile takes, in seconds from its dynamo_start to its last entry
+    pub compile_s: f64,
+    // More than 0 cycles the compiles of a frame through that many sets of guards, as when an
+    // input keeps flipping between dtypes; 0 gives every compile guards of its own
+    pub guard_variants: usize,
+    // Whether compiles log their compilation_metrics
+    pub metrics: bool,
+    // How long the log goes on, in seconds after start: an entry that late ends it, as when a
+    // job keeps logging after it's done compiling
+    pub log_s: Option<f64>,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        GenConfig {
+            frames: 3,
+            recompiles: 1,
+            artifacts: DEFAULT_GEN_ARTIFACTS
+                .iter()
+                .map(|a| a.to_string())
+                .collect(),
+            payload_lines: 10,
+            ranks: 1,
+            bad_md5: 0,
+            truncated_payloads: 0,
+            junk_lines: 0,
+            seed: 0,
+            start: NaiveDate::from_ymd_opt(2024, 10, 14)
+                .unwrap()
+                .and_hms_opt(9, 0, 0)
+                .unwrap(),
+            compile_times: Vec::new(),
+            compile_s: 0.0,
+            guard_variants: 0,
+            metrics: true,
+            log_s: None,
+        }
+    }
+}
+
+// xorshift64*, so that a seed means the same log everywhere without depending on rand
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as usize % n
+    }
+
+    // k distinct picks out of items, in the order they come
+    fn pick(&mut self, mut items: Vec<usize>, k: usize) -> Vec<usize> {
+        for i in 0..k {
+            let j = i + self.below(items.len() - i);
+            items.swap(i, j);
+        }
+        items.truncate(k);
+        items.sort();
+        items
+    }
+}
+
+struct Entry {
+    // After GenConfig::start
+    at: TimeDelta,
+    logged_from: &'static str,
+    envelope: Value,
+    payload: Option<Vec<String>>,
+}
+
+fn graph_payload(lines: usize, frame: usize, compile: usize) -> Vec<String> {
+    let mut payload = vec![
+        "class GraphModule(torch.nn.Module):".to_string(),
+        "    def forward(self, L_x_: \"f32[8, 8]\"):".to_string(),
+    ];
+    for i in 0..lines.saturating_sub(3) {
+        let input = if i == 0 {
+            "L_x_".to_string()
+        } else {
+            format!("mul_{}", i - 1)
+        };
+        payload.push(format!(
+            "        mul_{i}: \"f32[8, 8]\" = {input} * {}",
+            frame * 10 + compile + i
+        ));
+    }
+    payload.push(format!("        return (mul_{},)", lines.saturating_sub(4)));
+    payload.truncate(lines.max(1));
+    payload
+}
+
+fn guards_payload(lines: usize, variant: usize) -> Vec<String> {
+    let guards = lines.saturating_sub(2).max(1);
+    let mut payload = vec!["[".to_string()];
+    for i in 0..guards {
+        let comma = if i + 1 < guards { "," } else { "" };
+        payload.push(format!(
+            "{{\"code\": \"L['x'].size()[0] == {}\", \"stack\": null, \"user_stack\": null}}{comma}",
+            8 + variant + i
+        ));
+    }
+    payload.push("]".to_string());
+    payload
+}
+
+fn code_payload(lines: usize, frame: usize, compile: usize) -> Vec<String> {
+    let mut payload = vec![format!("# kernel for frame {frame}, compile {compile}")];
+    payload.extend((1..lines).map(|i| format!("buf{i} = empty_strided((8, 8), (8, 1))")));
+    payload
+}
+
+// The time of an entry logged at next, and so of the one after it
+fn tick(next: &mut TimeDelta) -> TimeDelta {
+    let at = *next;
+    *next += TimeDelta::milliseconds(1);
+    at
+}
+
+// The entries of one rank's compiles, in the order it would log them
+fn rank_entries(config: &GenConfig, rank: Option<usize>) -> Vec<Entry> {
+    let with_ids = |mut envelope: Value, frame: usize, compile: usize| {
+        envelope["frame_id"] = json!(frame);
+        envelope["frame_compile_id"] = json!(compile);
+        envelope["attempt"] = json!(0);
+        envelope
+    };
+    let seconds = |s: f64| TimeDelta::microseconds((s * 1e6) as i64);
+    let intern = |filename: &str, i: usize, at: TimeDelta| Entry {
+        at,
+        logged_from: "torch/_logging/structured.py:28",
+        envelope: json!({"str": [filename, i]}),
+        payload: None,
+    };
+    let mut entries: Vec<Entry> = FILENAMES
+        .iter()
+        .enumerate()
+        .map(|(i, f)| intern(f, i, TimeDelta::milliseconds(i as i64)))
+        .collect();
+    // When the next entry is, a millisecond after the one before unless it's the start of a
+    // compile with a time of its own.  Entries go in the order of their times in the end.
+    let interned = TimeDelta::milliseconds(entries.len() as i64);
+    let mut next = interned;
+    let mut compile_times = config.compile_times.iter().map(|&s| seconds(s));
+    for frame in 0..config.frames {
+        for compile in 0..=config.recompiles {
+            // Not before the filenames its stack refers to
+            let compile_start = compile_times.next().map_or(next, |t| t.max(interned));
+            next = compile_start;
+            let stack = json!([
+                {"line": 10, "name": "<module>", "filename": 0},
+                {"line": 574, "name": "_fn", "filename": 1},
+                {"line": 1736, "name": "_call_impl", "filename": 2},
+                {"line": 20 + frame, "name": format!("forward_{frame}"), "filename": 0},
+            ]);
+            entries.push(Entry {
+                at: tick(&mut next),
+                logged_from: "torch/_dynamo/convert_frame.py:1000",
+                envelope: with_ids(json!({"dynamo_start": {"stack": stack}}), frame, compile),
+                payload: None,
+            });
+            for artifact in &config.artifacts {
+                let lines = config.payload_lines;
+                let (envelope, payload) = match artifact.as_str() {
+                    kind if GRAPH_KINDS.contains(&kind) => {
+                        (json!({kind: {}}), graph_payload(lines, frame, compile))
+                    }
+                    "dynamo_guards" => {
+                        let variant = match config.guard_variants {
+                            0 => compile,
+                            n => compile % n,
+                        };
+                        (json!({"dynamo_guards": {}}), guards_payload(lines, variant))
+                    }
+                    "inductor_output_code" => (
+                        json!({"inductor_output_code": {
+                            "filename": format!("/tmp/torchinductor/f{frame}c{compile}.py")
+                        }}),
+                        code_payload(lines, frame, compile),
+                    ),
+                    name => (
+                        json!({"artifact": {"name": name, "encoding": "string"}}),
+                        code_payload(lines, frame, compile),
+                    ),
+                };
+                entries.push(Entry {
+                    at: tick(&mut next),
+                    logged_from: "torch/_dynamo/output_graph.py:1300",
+                    envelope: with_ids(envelope, frame, compile),
+                    payload: Some(payload),
+                });
+            }
+            // The compile's last entry is compile_s after its first, however few there are
+            let end = (compile_start + seconds(config.compile_s)).max(next);
+            if !config.metrics {
+                if let Some(last) = entries.last_mut().filter(|e| e.at > compile_start) {
+                    last.at = end;
+                }
+                next = end + TimeDelta::milliseconds(1);
+                continue;
+            }
+            next = end;
+            // With every field PyTorch logs, like it does
+            let start_time = config.start.and_utc().timestamp() as f64
+                + compile_start.num_microseconds().unwrap() as f64 / 1e6;
+            let metrics = json!({"compilation_metrics": {
+                "co_name": format!("forward_{frame}"),
+                "co_filename": FILENAMES[0],
+                "co_firstlineno": 20 + frame,
+                "cache_size": compile,
+                "accumulated_cache_size": compile,
+                "guard_count": 1 + compile,
+                "shape_env_guard_count": 0,
+                "graph_op_count": config.payload_lines.saturating_sub(3),
+                "graph_node_count": config.payload_lines.saturating_sub(1),
+                "graph_input_count": 1,
+                "start_time": start_time,
+                "entire_frame_compile_time_s": 0.5 + 0.25 * compile as f64,
+                "backend_compile_time_s": 0.25,
+                "inductor_compile_time_s": 0.125,
+                "code_gen_time_s": 0.0625,
+                "fail_type": null,
+                "fail_reason": null,
+                "fail_user_frame_filename": null,
+                "fail_user_frame_lineno": null,
+                "non_compliant_ops": [],
+                "compliant_custom_ops": [],
+                "restart_reasons": [],
+                "dynamo_time_before_restart_s": 0.0,
+            }});
+            entries.push(Entry {
+                at: tick(&mut next),
+                logged_from: "torch/_dynamo/utils.py:1000",
+                envelope: with_ids(metrics, frame, compile),
+                payload: None,
+            });
+        }
+    }
+    if let Some(log_s) = config.log_s {
+        let last = entries.iter().map(|e| e.at).max().unwrap_or_default();
+        entries.push(intern(
+            "/workspace/train.py",
+            FILENAMES.len(),
+            seconds(log_s).max(last),
+        ));
+    }
+    if let Some(rank) = rank {
+        for entry in &mut entries {
+            entry.envelope["rank"] = json!(rank);
+        }
+    }
+    entries
+}
+
+// Write the log config describes to out
+pub fn generate_log(config: &GenConfig, out: &mut dyn Write) -> anyhow::Result<()> {
+    let ranks: Vec<Vec<Entry>> = match config.ranks {
+        0 | 1 => vec![rank_entries(config, None)],
+        n => (0..n).map(|r| rank_entries(config, Some(r))).collect(),
+    };
+    // Ranks take turns, entry by entry, and then everything goes in the order of its time
+    let longest = ranks.iter().map(Vec::len).max().unwrap_or(0);
+    let mut ranks: Vec<_> = ranks.into_iter().map(Vec::into_iter).collect();
+    let mut entries: Vec<(usize, Entry)> = Vec::new();
+    for _ in 0..longest {
+        for (rank, rank_entries) in ranks.iter_mut().enumerate() {
+            entries.extend(rank_entries.next().map(|mut e| {
+                e.at += TimeDelta::microseconds(rank as i64);
+                (rank, e)
+            }));
+        }
+    }
+    entries.sort_by_key(|(_, e)| e.at);
+
+    let with_payload: Vec<usize> = (0..entries.len())
+        .filter(|&i| entries[i].1.payload.is_some())
+        .collect();
+    let corrupted = config.bad_md5 + config.truncated_payloads;
+    if corrupted > with_payload.len() {
+        bail!(
+            "Can't corrupt {corrupted} payloads, the log only has {}",
+            with_payload.len()
+        );
+    }
+    let mut rng = Rng::new(config.seed);
+    let corrupted = rng.pick(with_payload, corrupted);
+    let mut corrupted = corrupted.into_iter();
+    let bad_md5: FxHashSet<usize> = corrupted.by_ref().take(config.bad_md5).collect();
+    let truncated: FxHashSet<usize> = corrupted.collect();
+    let junk_after: FxHashSet<usize> = rng
+        .pick(
+            (0..entries.len()).collect(),
+            config.junk_lines.min(entries.len()),
+        )
+        .into_iter()
+        .collect();
+    // More junk lines than entries pile up at the end
+    let extra_junk = config.junk_lines.saturating_sub(entries.len());
+
+    for (i, (rank, mut entry)) in entries.into_iter().enumerate() {
+        if let Some(payload) = &entry.payload {
+            let mut hasher = Md5::new();
+            hasher.update(payload.join("\n"));
+            if bad_md5.contains(&i) {
+                hasher.update("corrupted");
+            }
+            entry.envelope["has_payload"] = json!(format!("{:x}", hasher.finalize()));
+        }
+        let timestamp = config.start + entry.at;
+        writeln!(
+            out,
+            "V{} {} {} {}] {}",
+            timestamp.format("%m%d"),
+            timestamp.format("%H:%M:%S%.6f"),
+            1000 + rank,
+            entry.logged_from,
+            entry.envelope
+        )?;
+        if !truncated.contains(&i) {
+            for line in entry.payload.iter().flatten() {
+                writeln!(out, "\t{line}")?;
+            }
+        }
+        if junk_after.contains(&i) {
+            writeln!(out, "Traceback (most recent call last):")?;
+        }
+    }
+    for _ in 0..extra_junk {
+        writeln!(out, "Traceback (most recent call last):")?;
+    }
+    Ok(())
+}
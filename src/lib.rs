@@ -3,47 +3,217 @@ use fxhash::{FxHashMap, FxHashSet};
 use md5::{Digest, Md5};
 use std::ffi::{OsStr, OsString};
 
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeDelta};
+#[cfg(feature = "progress")]
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use regex::Regex;
-use std::cell::RefCell;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::cell::{Cell, RefCell};
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt;
 use std::fs::{self, File};
-use std::io::{self, BufRead};
-use std::path::PathBuf;
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use std::time::Instant;
 use tinytemplate::TinyTemplate;
 
-use crate::parsers::default_parsers;
-use crate::parsers::ParserOutput;
-use crate::parsers::StructuredLogParser;
+use crate::parsers::{default_parsers, NamespacedParser};
+use crate::parsers::{
+    fx_graph_calls, highlighted_html, json_tree_html, normalize_fx_graph, normalize_guard,
+};
 use crate::templates::*;
 use crate::types::*;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use no_clock::Instant;
+#[cfg(not(feature = "progress"))]
+use quiet_progress::{MultiProgress, ProgressBar, ProgressStyle};
+mod charts;
+mod generate;
+mod normalize;
 mod parsers;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "serve")]
+mod serve;
+mod single_file;
 mod templates;
 mod types;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+// What custom parsers (ParseConfig::custom_parsers) are written against
+pub use generate::{generate_log, GenConfig, DEFAULT_GEN_ARTIFACTS};
+pub use normalize::normalize_artifact;
+pub use parsers::{EnvelopeFieldParser, ParserOutput, ParserResults, StructuredLogParser};
+#[cfg(feature = "serve")]
+pub use serve::{serve_dir, GZIP_MIN_BYTES};
+pub use single_file::{single_file_report, SINGLE_FILE_MAX_BYTES};
+pub use types::{CompileId, Envelope, FrameSummary, Metadata, StackSummary, StackTrieNode, Stats};
+pub use types::{OutputFile, OutputTotals, ParseOutput, ParsedRun};
+#[cfg(feature = "wasm")]
+pub use wasm::parse_log;
+
+// Everything that changes what a parse does.  The CLI builds one from its flags, and Default is
+// what it does without any, so set only the fields you need:
+// ParseConfig { rank: Some(0), ..Default::default() }
+#[derive(Default)]
 pub struct ParseConfig {
+    // Fail the parse if any line couldn't be parsed or some parser failed
     pub strict: bool,
+    // Fail the parse if some entries weren't part of any compile
     pub strict_compile_id: bool,
-    pub custom_parsers: Vec<Box<dyn crate::parsers::StructuredLogParser>>,
+    // Run on every envelope after the built-in parsers, see StructuredLogParser
+    pub custom_parsers: Vec<Box<dyn StructuredLogParser>>,
+    // Put at the top of every page as is, unescaped
     pub custom_header_html: String,
+    // Say on stderr about every unknown field and partial compile id
     pub verbose: bool,
+    // Write code and the stack trie as plain text instead of highlighted HTML, for diffing
     pub plain_text: bool,
+    // Only render entries of this rank, instead of the one detect_rank picks
+    pub rank: Option<u32>,
+    // Only render this run, when several processes appended to the same file; see RunTracker
+    pub run: Option<usize>,
+    // Skip envelopes that exactly repeat a recent one, e.g. when the log was teed to two handlers
+    pub dedupe: bool,
+    // Where the output is going to be written.  If set, payloads bigger than
+    // stream_payload_threshold (default STREAM_PAYLOAD_THRESHOLD) are streamed straight into it
+    // rather than held in memory, and raw.log is copied there instead of being returned.
+    pub output_dir: Option<PathBuf>,
+    pub stream_payload_threshold: Option<u64>,
+    // Accept payload lines indented with this many spaces instead of a tab (default
+    // DEFAULT_PAYLOAD_SPACE_INDENT, 0 to only accept tabs)
+    pub payload_space_indent: Option<usize>,
+    // Extra rules for shortening displayed file names, tried before the built-in ones (see
+    // StripRules); a "re:" prefix makes the rest a regex, see StripRule::parse
+    pub strip_prefixes: Vec<String>,
+    // How many levels of stack trie branches start out unfolded (default DEFAULT_TRIE_OPEN_DEPTH)
+    pub trie_open_depth: Option<usize>,
+    // Only show compiles that failed in the stack trie
+    pub trie_failed_only: bool,
+    // Only show stacks with a frame whose (simplified) filename or function name matches this
+    // regex in the stack trie
+    pub trie_filter: Option<String>,
+    // Show every frame in the stack trie, instead of collapsing runs of internal frames (see
+    // is_internal_frame)
+    pub show_internal_frames: bool,
+    // Extra (simplified) filename prefixes that count as torch internals, on top of
+    // DEFAULT_INTERNAL_FRAME_PREFIXES
+    pub internal_frame_prefixes: Vec<String>,
+    // Cut stack trie stacks deeper than this many frames (default DEFAULT_TRIE_MAX_DEPTH, 0 for
+    // no limit); stack_trie.json always has everything
+    pub trie_max_depth: Option<usize>,
+    // A local checkout to find the (simplified) filenames of frames in, so that the stack trie
+    // can show the source around each frame's line
+    pub source_root: Option<PathBuf>,
+    // How the children of every stack trie node are ordered
+    pub trie_order: TrieOrder,
+    // Merge stack trie frames that only differ in their line number into one node showing all
+    // of the lines, see StackTrieNode::insert_merging_lines
+    pub trie_merge_lines: bool,
+    // Also render the stack trie as a tree styled for a terminal, into ParsedRun::trie_text for
+    // the caller to print
+    pub print_trie: Option<TerminalStyle>,
+    // Log entries of a compile further apart than this many seconds (default
+    // DEFAULT_COMPILE_SPAN_GAP_S) split its span into segments, see CompileSpan
+    pub compile_span_gap_s: Option<f64>,
+    // Count every overload of an operator as the operator in ops_histogram.html, so that
+    // aten.add.Tensor and aten.add.Scalar are both aten.add
+    pub ops_merge_overloads: bool,
+    // How many compiles top.html lists (default DEFAULT_TOP_COMPILES); if set, they're also
+    // in stats.json, for the CLI to print
+    pub top_compiles: Option<usize>,
+    // How many seconds each window of timeline.html spans; if None, a round width that splits
+    // the log into about 20 windows
+    pub timeline_bucket_s: Option<f64>,
+    // Flag frames with at least recompile_loop_min_compiles (default
+    // DEFAULT_RECOMPILE_LOOP_MIN_COMPILES) recompiles in the last recompile_loop_window_s
+    // (default DEFAULT_RECOMPILE_LOOP_WINDOW_S) seconds of the log, see recompile_loops
+    pub recompile_loop_window_s: Option<f64>,
+    pub recompile_loop_min_compiles: Option<usize>,
+    // What compiles are expected to have logged, instead of DEFAULT_ARTIFACT_RULES; see
+    // ArtifactRule::parse
+    pub artifact_rules: Option<Vec<String>>,
+    // Text artifacts bigger than this many bytes (default DEFAULT_HIGHLIGHT_MAX_BYTES) get their
+    // companion page without highlighting, see highlighted_html
+    pub highlight_max_bytes: Option<usize>,
+    // Also write the envelopes of each compile, payloads cut to RAW_EXCERPT_PAYLOAD_LINES, into
+    // raw.log in its directory
+    pub keep_raw: bool,
+    // Stamp every page, stats.json and compile_directory.json with where they came from (see
+    // Provenance), giving these as the command line's arguments.  None leaves it out, so that
+    // the output only changes when the log does.
+    pub provenance: Option<Vec<String>>,
+    // Split the index's list of compiles into index_NNN.html pages of about this many attempts
+    // once there are more (default DEFAULT_INDEX_SHARD_SIZE, 0 to never)
+    pub index_shard_size: Option<usize>,
+    // Don't show the first lines of each text artifact under its link, see artifact_preview
+    pub no_previews: bool,
+    // Don't copy the log into the output as raw.log
+    pub no_raw_log: bool,
+    // Write payloads as normalize_artifact has them, without addresses, temp paths and the like,
+    // so that the same artifacts of two runs are the same files
+    pub normalize_artifacts: bool,
+    // Also write JSON artifact payloads as they were logged, into <name>_raw.txt next to the
+    // pretty-printed .json
+    pub json_keep_raw: bool,
+    // Report progress and messages as JSON lines on stderr instead of drawing progress bars,
+    // see Progress
+    pub progress_json: bool,
+}
+
+// How to print for a terminal
+#[derive(Default, Clone, Copy, Debug)]
+pub struct TerminalStyle {
+    // Fit lines in this many columns, or don't limit them if None
+    pub width: Option<usize>,
+    // Use ANSI colors
+    pub color: bool,
+}
+
+// Whether a frame is in torch's own machinery, see DEFAULT_INTERNAL_FRAME_PREFIXES
+fn is_internal_frame(
+    frame: &FrameSummary,
+    extra_prefixes: &[String],
+    strip_rules: &StripRules,
+) -> bool {
+    let filename = frame.resolved_filename();
+    let filename = simplify_filename(&filename, strip_rules);
+    DEFAULT_INTERNAL_FRAME_PREFIXES
+        .iter()
+        .copied()
+        .chain(extra_prefixes.iter().map(String::as_str))
+        .any(|prefix| filename.starts_with(prefix))
 }
 
-impl Default for ParseConfig {
-    fn default() -> Self {
-        Self {
-            strict: false,
-            strict_compile_id: false,
-            custom_parsers: Vec::default(),
-            custom_header_html: String::default(),
-            verbose: false,
-            plain_text: false,
+// Whether a frame is in the model's own code: not in torch, not in the standard library (which
+// is under lib/pythonX.Y/, unlike the packages in its site-packages) and not in generated code
+// like <frozen importlib._bootstrap> or <eval_with_key>.
+fn is_user_frame(
+    frame: &FrameSummary,
+    extra_prefixes: &[String],
+    strip_rules: &StripRules,
+    re_stdlib: &Regex,
+) -> bool {
+    let filename = frame.resolved_filename();
+    if filename.starts_with('<') || is_internal_frame(frame, extra_prefixes, strip_rules) {
+        return false;
+    }
+    if simplify_filename(&filename, strip_rules).starts_with("torch/") {
+        return false;
+    }
+    match re_stdlib.find(&filename) {
+        Some(m) => {
+            let rest = &filename[m.end()..];
+            rest.starts_with("site-packages/") || rest.starts_with("dist-packages/")
         }
+        None => true,
     }
 }
 
-fn maybe_remove_convert_frame_suffixes(frames: &mut Vec<FrameSummary>) {
+fn maybe_remove_convert_frame_suffixes(frames: &mut Vec<FrameSummary>, strip_rules: &StripRules) {
     let all_target_frames = [
         [
             ("torch/_dynamo/convert_frame.py", "catch_errors"),
@@ -65,8 +235,8 @@ fn maybe_remove_convert_frame_suffixes(frames: &mut Vec<FrameSummary>) {
                 .iter()
                 .zip(target_frames.iter())
                 .all(|(frame, target)| {
-                    simplify_filename(unintern_str(frame.filename).as_ref()) == target.0
-                        && frame.name == target.1
+                    simplify_filename(&frame.resolved_filename(), strip_rules) == target.0
+                        && *frame.name.resolve() == *target.1
                 })
             {
                 frames.truncate(len - target_frames.len());
@@ -75,20 +245,2434 @@ fn maybe_remove_convert_frame_suffixes(frames: &mut Vec<FrameSummary>) {
     }
 }
 
+// Length of the ANSI CSI sequence (ESC [ params intermediates final) at the start of s
+fn csi_len(s: &str) -> Option<usize> {
+    let b = s.as_bytes();
+    if b.len() < 2 || b[0] != 0x1b || b[1] != b'[' {
+        return None;
+    }
+    let mut i = 2;
+    while i < b.len() && (0x30..=0x3f).contains(&b[i]) {
+        i += 1;
+    }
+    while i < b.len() && (0x20..=0x2f).contains(&b[i]) {
+        i += 1;
+    }
+    if i < b.len() && (0x40..=0x7e).contains(&b[i]) {
+        Some(i + 1)
+    } else {
+        None
+    }
+}
+
+// Returns the line with ANSI color codes removed, or None if there was nothing to strip.
+// Payload continuation lines only lose the escapes in front of their leading tab, so the
+// payload itself (and hence its MD5) is never modified.
+fn strip_ansi_escapes(line: &str) -> Option<String> {
+    // Cheap scan first so clean logs pay nothing
+    if !line.as_bytes().contains(&0x1b) {
+        return None;
+    }
+    let mut rest = line;
+    while let Some(n) = csi_len(rest) {
+        rest = &rest[n..];
+    }
+    if rest.starts_with(['\t', ' ']) {
+        return (rest.len() != line.len()).then(|| rest.to_string());
+    }
+    let mut out = String::with_capacity(line.len());
+    while let Some(pos) = rest.find('\x1b') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        match csi_len(rest) {
+            Some(n) => rest = &rest[n..],
+            None => {
+                out.push('\x1b');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    (out.len() != line.len()).then_some(out)
+}
+
+// Payload continuation lines are indented with a tab, but some log processors convert it to
+// spaces in transit (and some writers use spaces to begin with), so accept `space_indent` spaces
+// too.  Each line is checked on its own, so a block mixing both is fine.
+fn strip_payload_indent(line: &str, space_indent: usize) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix('\t') {
+        return Some(rest);
+    }
+    (space_indent > 0
+        && line
+            .bytes()
+            .take(space_indent)
+            .filter(|&b| b == b' ')
+            .count()
+            == space_indent)
+        .then(|| &line[space_indent..])
+}
+
+// glog headers never start with whitespace, so this is enough to skip payloads when scanning
+fn is_continuation_line(line: &str) -> bool {
+    line.starts_with(['\t', ' '])
+}
+
+// If filename was already written, pick the next free name_1.ext, name_2.ext, ... instead,
+// so that a duplicated artifact doesn't silently clobber the earlier one.  Returns the name
+// to use and whether it had to be renamed.
+fn dedup_filename(filename: PathBuf, written: &mut FxHashSet<PathBuf>) -> (PathBuf, bool) {
+    if written.insert(filename.clone()) {
+        return (filename, false);
+    }
+    let stem = filename.file_stem().unwrap_or_default().to_owned();
+    let mut i = 1;
+    loop {
+        let mut r = stem.clone();
+        r.push(OsStr::new("_"));
+        r.push(i.to_string());
+        if let Some(e) = filename.extension() {
+            r.push(OsStr::new("."));
+            r.push(e);
+        };
+        let candidate = filename.with_file_name(r);
+        if written.insert(candidate.clone()) {
+            return (candidate, true);
+        }
+        i += 1;
+    }
+}
+
+// glog timestamps look like "0401 08:54:21.882000": local time with no year.  This turns them
+// into full datetimes.  Midnight needs no special care since the day is in the timestamp, but the
+// year has to be guessed, and bumped when the log crosses New Year.  Lines from different threads
+// can be slightly out of order, so only a jump backwards of about half a year counts as a
+// rollover.
+pub struct GlogTimestamps {
+    year: i32,
+    // When the file was last modified, which is at or just after its last line
+    mtime: Option<NaiveDateTime>,
+    latest: Option<NaiveDateTime>,
+}
+
+impl GlogTimestamps {
+    // Start out assuming the log begins in `year`
+    pub fn new(year: i32) -> Self {
+        GlogTimestamps {
+            year,
+            mtime: None,
+            latest: None,
+        }
+    }
+
+    // Take the year from the file's mtime, unless that would put the first line in the future
+    // (e.g. a log that started in December and was last written in January)
+    pub fn for_file(path: &Path) -> Self {
+        let mtime = fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|t| DateTime::<Local>::from(t).naive_local());
+        GlogTimestamps {
+            year: mtime.map_or_else(|| Local::now().year(), |t| t.year()),
+            mtime,
+            latest: None,
+        }
+    }
+
+    // Returns None if the fields don't form a valid date, e.g. Feb 30.  Feb 29 of a year that
+    // has none means the year was guessed wrong, so it's taken to be in the nearest leap year
+    // instead, and so is what follows it.
+    pub fn resolve(
+        &mut self,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        microsecond: u32,
+    ) -> Option<NaiveDateTime> {
+        let at = |year| {
+            NaiveDate::from_ymd_opt(year, month, day)?.and_hms_micro_opt(
+                hour,
+                minute,
+                second,
+                microsecond,
+            )
+        };
+        let mut timestamp = match at(self.year) {
+            Some(timestamp) => timestamp,
+            None if (month, day) == (2, 29) => {
+                self.year = nearest_leap_year(self.year);
+                let timestamp = at(self.year)?;
+                // What came before was in the wrong year, so it says nothing about rollovers
+                self.latest = Some(timestamp);
+                return Some(timestamp);
+            }
+            None => return None,
+        };
+        match self.latest {
+            None => {
+                if self
+                    .mtime
+                    .is_some_and(|mtime| timestamp > mtime + TimeDelta::days(1))
+                {
+                    self.year -= 1;
+                    timestamp = at(self.year)?;
+                }
+            }
+            Some(latest) => {
+                if timestamp < latest - TimeDelta::days(183) {
+                    self.year += 1;
+                    timestamp = at(self.year)?;
+                } else if timestamp > latest + TimeDelta::days(183) {
+                    // A straggler from just before the rollover
+                    timestamp = at(self.year - 1)?;
+                }
+            }
+        }
+        self.latest = self.latest.max(Some(timestamp));
+        Some(timestamp)
+    }
+
+    fn resolve_captures(&mut self, caps: &Captures) -> Option<NaiveDateTime> {
+        let field = |name| caps.name(name)?.as_str().parse::<u32>().ok();
+        self.resolve(
+            field("month")?,
+            field("day")?,
+            field("hour")?,
+            field("minute")?,
+            field("second")?,
+            field("millisecond")?,
+        )
+    }
+}
+
+// Ties go to the earlier year, logs being about the past
+fn nearest_leap_year(year: i32) -> i32 {
+    let is_leap = |year| NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+    (0..=4)
+        .flat_map(|d| [year - d, year + d])
+        .find(|&year| is_leap(year))
+        .unwrap_or(year)
+}
+
+// How far apart two log entries of a compile can be before its span starts a new segment, see
+// CompileSpan
+pub static DEFAULT_COMPILE_SPAN_GAP_S: f64 = 60.0;
+
+// Dynamo's default cache_size_limit: a frame recompiled this many times isn't compiled again,
+// and runs in eager from then on
+pub static DEFAULT_CACHE_SIZE_LIMIT: usize = 8;
+
+// How many of the largest payloads largest_artifacts.html lists, and how many of those are
+// printed at the end
+static LARGEST_ARTIFACTS: usize = 50;
+static LARGEST_ARTIFACTS_PRINTED: usize = 5;
+
+// The artifacts that are FX graphs printed by Dynamo or AOTAutograd, whose operators go in
+// ops_histogram.html
+static FX_GRAPH_KINDS: &[&str] = &[
+    "dynamo_output_graph",
+    "aot_forward_graph",
+    "aot_backward_graph",
+    "aot_inference_graph",
+    "aot_joint_graph",
+];
+
+// The name an FX call target is counted under in ops_histogram.html: "aten.add.Tensor" for
+// torch.ops.aten.add.Tensor, or just "aten.add" when merging overloads
+fn op_name(target: &str, merge_overloads: bool) -> &str {
+    let Some(op) = target.strip_prefix("torch.ops.") else {
+        return target;
+    };
+    match op.rsplit_once('.') {
+        Some((name, _)) if merge_overloads && name.contains('.') => name,
+        _ => op,
+    }
+}
+
+// How many levels of branches in the stack trie start out unfolded
+pub static DEFAULT_TRIE_OPEN_DEPTH: usize = 3;
+
+// How deep the stack trie goes before it's cut, see StackTrieNode::truncate
+pub static DEFAULT_TRIE_MAX_DEPTH: usize = 40;
+
+// Frames from files starting with these (after simplify_filename) are collapsed in the stack
+// trie unless show_internal_frames is set
+pub static DEFAULT_INTERNAL_FRAME_PREFIXES: &[&str] =
+    &["torch/_dynamo/", "torch/_inductor/", "torch/nn/modules/"];
+
+// How many spaces can stand in for the tab before a payload line, see strip_payload_indent
+pub static DEFAULT_PAYLOAD_SPACE_INDENT: usize = 4;
+
+// Payloads bigger than this many bytes don't get rendered, see ParseConfig::output_dir
+pub static STREAM_PAYLOAD_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+// Text artifacts bigger than this many bytes aren't highlighted, see
+// ParseConfig::highlight_max_bytes; syntect takes seconds on graphs much bigger than that
+pub static DEFAULT_HIGHLIGHT_MAX_BYTES: usize = 1024 * 1024;
+
+// Past this many attempts, the index's list of compiles is split into pages of about as many,
+// see shard_frames
+pub static DEFAULT_INDEX_SHARD_SIZE: usize = 2000;
+
+// How much of a text artifact its preview shows, see artifact_preview
+static PREVIEW_LINES: usize = 5;
+static PREVIEW_MAX_BYTES: usize = 512;
+
+// The first PREVIEW_LINES lines of an artifact, at most PREVIEW_MAX_BYTES of them; None for
+// pages (a preview of markup says nothing) and anything binary
+fn artifact_preview(filename: &Path, content: &str) -> Option<String> {
+    if filename.extension().is_some_and(|e| e == "html") {
+        return None;
+    }
+    let end = content
+        .match_indices('\n')
+        .nth(PREVIEW_LINES - 1)
+        .map_or(content.len(), |(i, _)| i);
+    let mut end = end.min(PREVIEW_MAX_BYTES);
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    let preview = &content[..end];
+    if preview
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    {
+        return None;
+    }
+    let preview = preview.trim_end();
+    if preview.is_empty() {
+        return None;
+    }
+    Some(if end < content.trim_end().len() {
+        format!("{preview}\n…")
+    } else {
+        preview.to_string()
+    })
+}
+
+// How many recent envelopes --dedupe remembers; a repeat further back than this is kept
+static DEDUPE_WINDOW: usize = 10000;
+
+// What a log is read from: its file, or the reader parse was given.  Either is read once, as it's
+// parsed (see LogLines); only raw.log reads a file again afterwards.
+enum LogInput<'a> {
+    File(&'a PathBuf),
+    Reader,
+}
+
+impl LogInput<'_> {
+    // None for a reader, which can't say how much there is before it's read
+    fn len(&self) -> anyhow::Result<Option<u64>> {
+        Ok(match self {
+            LogInput::File(path) => Some(fs::metadata(path)?.len()),
+            LogInput::Reader => None,
+        })
+    }
+
+    // What messages and the provenance call it; logs that aren't files have no name
+    fn path(&self) -> &Path {
+        match self {
+            LogInput::File(path) => path,
+            LogInput::Reader => Path::new("(in memory)"),
+        }
+    }
+
+    fn timestamps(&self) -> GlogTimestamps {
+        match self {
+            LogInput::File(path) => GlogTimestamps::for_file(path),
+            LogInput::Reader => GlogTimestamps::new(Local::now().year()),
+        }
+    }
+}
+
+fn open_log(path: &Path) -> anyhow::Result<impl BufRead> {
+    Ok(io::BufReader::new(
+        File::open(path).map_err(|err| anyhow!(describe_open_error(path, err)))?,
+    ))
+}
+
+// Reads inner, handing copy everything that was read, for the raw.log of a log that isn't a file
+// to copy afterwards.  A failed write mustn't look like the log ending, so it's kept for finish.
+struct TeeReader<R, W> {
+    inner: R,
+    copy: W,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead, W: Write> TeeReader<R, W> {
+    fn new(inner: R, copy: W) -> Self {
+        TeeReader {
+            inner,
+            copy,
+            error: None,
+        }
+    }
+
+    // Copy what wasn't read, e.g. the runs after ParseConfig::run, and hand back the copy
+    fn finish(mut self) -> io::Result<W> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        io::copy(&mut self.inner, &mut self.copy)?;
+        self.copy.flush()?;
+        Ok(self.copy)
+    }
+}
+
+impl<R: BufRead, W: Write> io::Read for TeeReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead, W: Write> BufRead for TeeReader<R, W> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // What fill_buf returned is still there, and handing it out again doesn't read anything
+        if amt > 0 && self.error.is_none() {
+            let copied = self
+                .inner
+                .fill_buf()
+                .and_then(|buf| self.copy.write_all(&buf[..amt]));
+            self.error = copied.err();
+        }
+        self.inner.consume(amt)
+    }
+}
+
+// Stand-ins for indicatif's progress bars that draw nothing, for builds without the progress
+// feature (e.g. for the browser, where there's no terminal to draw them on)
+#[cfg(not(feature = "progress"))]
+mod quiet_progress {
+    pub struct MultiProgress;
+
+    impl MultiProgress {
+        pub fn new() -> Self {
+            MultiProgress
+        }
+
+        pub fn add(&self, pb: ProgressBar) -> ProgressBar {
+            pb
+        }
+
+        pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+            f()
+        }
+    }
+
+    pub struct ProgressBar;
+
+    impl ProgressBar {
+        pub fn new(_len: u64) -> Self {
+            ProgressBar
+        }
+
+        pub fn new_spinner() -> Self {
+            ProgressBar
+        }
+
+        pub fn set_style(&self, _style: ProgressStyle) {}
+
+        pub fn set_position(&self, _pos: u64) {}
+
+        pub fn set_message(&self, _msg: String) {}
+
+        pub fn finish(&self) {}
+
+        pub fn finish_with_message(&self, _msg: &'static str) {}
+    }
+
+    pub struct ProgressStyle;
+
+    impl ProgressStyle {
+        pub fn default_bar() -> Self {
+            ProgressStyle
+        }
+
+        pub fn template(self, _template: &str) -> Result<Self, std::convert::Infallible> {
+            Ok(self)
+        }
+
+        pub fn progress_chars(self, _chars: &str) -> Self {
+            self
+        }
+    }
+}
+
+// A stand-in for std::time::Instant, which panics in the browser: wasm32-unknown-unknown has no
+// clock, so there no time ever passes
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod no_clock {
+    use std::time::Duration;
+
+    #[derive(Clone, Copy)]
+    pub struct Instant;
+
+    impl Instant {
+        pub fn now() -> Self {
+            Instant
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        pub fn duration_since(&self, _earlier: Instant) -> Duration {
+            Duration::ZERO
+        }
+    }
+}
+
+// Progress bars are redrawn, and progress events written, at most this often
+static PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+// One line of ParseConfig::progress_json output
+pub fn write_progress_event(kind: &str, mut fields: serde_json::Value) {
+    if !fields.is_object() {
+        fields = serde_json::json!({});
+    }
+    fields["type"] = kind.into();
+    let _ = writeln!(io::stderr().lock(), "{fields}");
+}
+
+// How a parse reports how far along it is, and what it says on the way: progress bars, or with
+// ParseConfig::progress_json one JSON object per line on stderr, for programs that run tlparse:
+//   {"type": "start", "total": <bytes of the log, null for a reader>}
+//   {"type": "progress", "bytes": <read so far>, "total": ..., "ok": ..., "fail_json": ...}
+//     with every Stats counter, at most every PROGRESS_INTERVAL
+//   {"type": "error", "message": ...} for each entry that couldn't be parsed or rendered
+//   {"type": "info", "message": ...} for the rest of what would have been printed
+//   {"type": "finish", "bytes": ..., "total": ..., "ok": ...}
+// A log with several runs has a start and a finish per run.
+struct Progress {
+    bars: Option<(MultiProgress, ProgressBar, ProgressBar)>,
+    // None if the size of the log isn't known, see LogInput::len
+    total: Option<u64>,
+    last_update: Cell<Option<Instant>>,
+}
+
+impl Progress {
+    fn new(total: Option<u64>, json: bool) -> anyhow::Result<Self> {
+        if json {
+            write_progress_event("start", serde_json::json!({ "total": total }));
+            return Ok(Progress {
+                bars: None,
+                total,
+                last_update: Cell::new(None),
+            });
+        }
+        let multi = MultiProgress::new();
+        let pb = multi.add(ProgressBar::new(total.unwrap_or(0)));
+        pb.set_style(ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} [{bytes_per_sec}] ({eta})")?
+            .progress_chars("#>-"));
+        let spinner = multi.add(ProgressBar::new_spinner());
+        Ok(Progress {
+            bars: Some((multi, pb, spinner)),
+            total,
+            last_update: Cell::new(None),
+        })
+    }
+
+    fn say(&self, kind: &str, message: fmt::Arguments) {
+        match &self.bars {
+            Some((multi, _, _)) => multi.suspend(|| eprintln!("{message}")),
+            None => {
+                write_progress_event(kind, serde_json::json!({ "message": message.to_string() }))
+            }
+        }
+    }
+
+    fn error(&self, message: fmt::Arguments) {
+        self.say("error", message)
+    }
+
+    fn info(&self, message: fmt::Arguments) {
+        self.say("info", message)
+    }
+
+    fn stats_event(&self, kind: &str, bytes: u64, stats: &Stats) {
+        let mut fields = serde_json::to_value(stats).unwrap_or_default();
+        fields["bytes"] = bytes.into();
+        fields["total"] = self.total.into();
+        write_progress_event(kind, fields);
+    }
+
+    // Called for every line; does nothing unless PROGRESS_INTERVAL has passed since it last
+    // did something
+    fn update(&self, bytes: u64, stats: &Stats) {
+        let now = Instant::now();
+        if self
+            .last_update
+            .get()
+            .is_some_and(|last| now.duration_since(last) < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+        self.last_update.set(Some(now));
+        match &self.bars {
+            Some((_, pb, spinner)) => {
+                pb.set_position(bytes);
+                spinner.set_message(format!("{:?}", stats));
+            }
+            None => self.stats_event("progress", bytes, stats),
+        }
+    }
+
+    // Once the whole run is read, at bytes into the log
+    fn finish(&self, bytes: u64, stats: &Stats) {
+        match &self.bars {
+            Some((_, pb, spinner)) => {
+                pb.set_position(bytes);
+                spinner.set_message(format!("{:?}", stats));
+                pb.finish_with_message("done");
+                spinner.finish();
+            }
+            None => self.stats_event("finish", bytes, stats),
+        }
+    }
+}
+
+// How many envelopes at the start of a run we look at to guess its rank, and how much of the log
+// they may take up at most, since they're read ahead and kept in memory (see LogLines)
+static RANK_DETECTION_WINDOW: usize = 1000;
+static RANK_DETECTION_MAX_BYTES: usize = 4 << 20;
+
+// Pick the most frequent concrete rank among the first few envelopes of the run.  Early
+// initialization lines often carry no rank at all, so those don't get a vote; if nothing in
+// the window has a rank, we return None and lock onto the first rank we see while parsing.
+fn detect_rank<'l>(lines: impl Iterator<Item = &'l str>, re_glog: &Regex) -> Option<u32> {
+    #[derive(serde::Deserialize)]
+    struct RankOnly {
+        rank: Option<u32>,
+    }
+    let mut votes: FxHashMap<u32, usize> = FxHashMap::default();
+    for line in lines {
+        let Some(caps) = re_glog.captures(line) else {
+            continue;
+        };
+        let payload = &line[caps.name("payload").unwrap().start()..];
+        if let Ok(RankOnly { rank: Some(rank) }) = serde_json::from_str(payload) {
+            *votes.entry(rank).or_default() += 1;
+        }
+    }
+    // Ties go to the lowest rank so the choice is deterministic
+    votes
+        .into_iter()
+        .max_by_key(|&(rank, count)| (count, std::cmp::Reverse(rank)))
+        .map(|(rank, _)| rank)
+}
+
+// When a job restarts, the new process appends to the same trace file, so its compile ids
+// start from scratch and would collide with the previous run's.  Every process interns strings
+// from id 0 upwards and logs each one once, before the stacks that use it, so an intern id going
+// backwards is where a new run starts.  The ranks of a job each intern on their own and can share
+// a file, so it's one rank's ids that have to go backwards, not just the last id logged.
+// (Compile ids alone aren't enough: the same dynamo_start is sometimes logged twice by one
+// process.)
+#[derive(Default)]
+struct RunTracker {
+    run: usize,
+    max_intern_ids: FxHashMap<Option<u32>, u32>,
+}
+
+impl RunTracker {
+    // Returns the run the envelope belongs to
+    fn observe(&mut self, e: &Envelope) -> usize {
+        if let Some((_, id)) = e.str {
+            if self
+                .max_intern_ids
+                .get(&e.rank)
+                .is_some_and(|&max| id < max)
+            {
+                self.run += 1;
+                // The other ranks restarted too, and start over from 0 in the new run
+                self.max_intern_ids.clear();
+            }
+            self.max_intern_ids.insert(e.rank, id);
+        }
+        self.run
+    }
+
+    // Like observe, for a line that hasn't been deserialized yet.  Only intern entries can start
+    // a new run, so anything else isn't.
+    fn observe_line(&mut self, line: &str, re_glog: &Regex) -> usize {
+        if is_continuation_line(line) || !line.contains("\"str\"") {
+            return self.run;
+        }
+        let Some(caps) = re_glog.captures(line) else {
+            return self.run;
+        };
+        let payload = &line[caps.name("payload").unwrap().start()..];
+        match serde_json::from_str::<Envelope>(payload) {
+            Ok(e) => self.observe(&e),
+            Err(_) => self.run,
+        }
+    }
+}
+
+// The lines of a log, which is read only once however many runs it has.  What was read ahead, to
+// guess a run's rank or to find where the next run starts, is kept to be handed out again.  Lines
+// that aren't UTF-8 and blank lines are skipped (see parse_run), and ANSI escapes stripped.
+struct LogLines<'r> {
+    lines: io::Lines<Box<dyn BufRead + 'r>>,
+    lineno: usize,
+    ahead: VecDeque<(usize, String)>,
+    ahead_bytes: usize,
+    // With their newlines; near enough, as CRLFs, empty lines and ANSI escapes aren't counted
+    bytes_read: u64,
+    ansi_stripped: u64,
+}
+
+impl<'r> LogLines<'r> {
+    fn new(reader: Box<dyn BufRead + 'r>) -> Self {
+        LogLines {
+            lines: reader.lines(),
+            lineno: 0,
+            ahead: VecDeque::new(),
+            ahead_bytes: 0,
+            bytes_read: 0,
+            ansi_stripped: 0,
+        }
+    }
+
+    fn read(&mut self) -> Option<(usize, String)> {
+        loop {
+            let line = self.lines.next()?;
+            self.lineno += 1;
+            let Ok(line) = line else {
+                continue;
+            };
+            // Lines captured from a colorizing terminal have their escapes stripped here, before
+            // payload continuation lines are recognized by their leading tab
+            let line = match strip_ansi_escapes(&line) {
+                Some(stripped) => {
+                    self.ansi_stripped += 1;
+                    stripped
+                }
+                None => line,
+            };
+            // NB: Sometimes, the log output we get from Logarithm stutters with a blank line.
+            // Filter them out, they're never valid (a blank line in payload will still be \t)
+            if !line.is_empty() {
+                self.bytes_read += line.len() as u64 + 1;
+                // 1-indexed line numbers please
+                return Some((self.lineno, line));
+            }
+        }
+    }
+
+    fn read_ahead(&mut self) -> bool {
+        match self.read() {
+            Some(line) => {
+                self.ahead_bytes += line.1.len();
+                self.ahead.push_back(line);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&(usize, String)> {
+        if self.ahead.is_empty() {
+            self.read_ahead();
+        }
+        self.ahead.front()
+    }
+
+    fn next_if(&mut self, f: impl FnOnce(&(usize, String)) -> bool) -> Option<(usize, String)> {
+        if f(self.peek()?) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    // The next `entries` lines that aren't payload continuation lines, read ahead without taking
+    // them, or fewer if the log ends or they'd take more than max_bytes
+    fn look_ahead(&mut self, entries: usize, max_bytes: usize) -> impl Iterator<Item = &str> {
+        let mut seen = self
+            .ahead
+            .iter()
+            .filter(|(_, l)| !is_continuation_line(l))
+            .count();
+        while seen < entries && self.ahead_bytes < max_bytes && self.read_ahead() {
+            if !is_continuation_line(&self.ahead.back().unwrap().1) {
+                seen += 1;
+            }
+        }
+        self.ahead
+            .iter()
+            .map(|(_, l)| l.as_str())
+            .filter(|l| !is_continuation_line(l))
+            .take(entries)
+    }
+}
+
+impl Iterator for LogLines<'_> {
+    type Item = (usize, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.ahead.pop_front() {
+            Some(line) => {
+                self.ahead_bytes -= line.1.len();
+                Some(line)
+            }
+            None => self.read(),
+        }
+    }
+}
+
+// Go past the rest of one run without parsing it, for ParseConfig::run.  Returns how many of its
+// lines looked like envelopes.
+fn skip_run(lines: &mut LogLines, runs: &mut RunTracker, run: usize, re_glog: &Regex) -> u64 {
+    let mut skipped = 0;
+    while let Some((_, line)) = lines.next_if(|(_, l)| runs.observe_line(l, re_glog) == run) {
+        if !is_continuation_line(&line) && re_glog.is_match(&line) {
+            skipped += 1;
+        }
+    }
+    skipped
+}
+
+// For artifact sizes in templates; a missing size (e.g. for a link) shows nothing
+fn format_bytes(value: &serde_json::Value, out: &mut String) -> tinytemplate::error::Result<()> {
+    if let Some(bytes) = value.as_u64() {
+        out.push_str(&format!(
+            " <span class='artifact-size'>{}</span>",
+            fmt_bytes(bytes)
+        ));
+    }
+    Ok(())
+}
+
+fn glog_regex() -> anyhow::Result<Regex> {
+    Ok(Regex::new(concat!(
+        r"(?<level>[VIWEC])(?<month>\d{2})(?<day>\d{2}) ",
+        r"(?<hour>\d{2}):(?<minute>\d{2}):(?<second>\d{2}).(?<millisecond>\d{6}) ",
+        r"(?<thread>\d+)",
+        r"(?<pathname>[^:]+):(?<line>\d+)\] ",
+        r"(?<payload>.)"
+    ))?)
+}
+
+// How the children of a stack trie node are ordered
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub enum TrieOrder {
+    // The order the stacks were in the log, which is only roughly chronological when several
+    // threads or ranks write to it
+    #[default]
+    Insertion,
+    // Earliest first compile first
+    FirstSeen,
+    // Most compiles first
+    Compiles,
+    // Most compile time first, according to compilation metrics
+    CompileTime,
+}
+
+// How long a compile took: entire_frame_compile_time_s of its compilation metrics, or its span
+// if it has none
+fn measured_compile_time(
+    c: &CompileId,
+    metrics_index: &CompilationMetricsIndex,
+    records: &FxHashMap<CompileId, CompileRecord>,
+) -> Option<(f64, CompileTimeSource)> {
+    metrics_index
+        .get(&Some(c.clone()))
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.entire_frame_compile_time_s)
+        .reduce(|a, b| a + b)
+        .map(|t| (t, CompileTimeSource::Metrics))
+        .or_else(|| {
+            let span = records.get(c)?.span()?;
+            Some((span.duration_s(), CompileTimeSource::Span))
+        })
+}
+
+// The stack of any attempt of a compile, which is logged with its first attempt
+fn compile_stack<'s>(stack_index: &'s StackIndex, c: &CompileId) -> Option<&'s StackSummary> {
+    stack_index.get(&Some(CompileId {
+        attempt: 0,
+        ..c.clone()
+    }))
+}
+
+// What a compile is labeled by everywhere it's listed, and what it counts under in by_file.html:
+// the innermost frame of its stack in user code (see is_user_frame, which the stack trie keeps
+// frames by too)
+fn user_frame<'s>(
+    stack: &'s StackSummary,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+) -> Option<&'s FrameSummary> {
+    stack.iter().rev().find(|f| is_user_frame(f))
+}
+
+static NO_STACK: &str = "(no stack)";
+
+// A compile's user_frame as file:line in function, falling back to the innermost frame of stacks
+// entirely in torch, or NO_STACK for compiles that didn't log one
+fn user_frame_label(
+    stack: Option<&StackSummary>,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+) -> String {
+    let Some(stack) = stack else {
+        return NO_STACK.to_string();
+    };
+    user_frame(stack, is_user_frame)
+        .or(stack.last())
+        .map_or(NO_STACK.to_string(), |frame| {
+            format!(
+                "{}:{} in {}",
+                simplify_filename(&frame.resolved_filename(), strip_rules),
+                frame.line,
+                frame.name
+            )
+        })
+}
+
+// How long every compile took, all of its attempts together: by its compilation metrics if it has
+// any timing in them, otherwise by its span.  None if there are no compiles
+fn summarize_compile_times(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    records: &FxHashMap<CompileId, CompileRecord>,
+) -> Option<CompileTimeSummary> {
+    let mut compiles: FxIndexMap<_, (Option<f64>, Option<f64>)> = FxIndexMap::default();
+    for c in directory.keys().flatten() {
+        let (metrics_time, span_time) = compiles
+            .entry((c.frame_id, c.frame_compile_id))
+            .or_default();
+        let from_metrics = metrics_index
+            .get(&Some(c.clone()))
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m.entire_frame_compile_time_s);
+        for t in from_metrics {
+            *metrics_time = Some(metrics_time.unwrap_or(0.0) + t);
+        }
+        if let Some(span) = records.get(c).and_then(CompileRecord::span) {
+            *span_time = Some(span_time.unwrap_or(0.0) + span.duration_s());
+        }
+    }
+    if compiles.is_empty() {
+        return None;
+    }
+    let times: Vec<(f64, CompileTimeSource)> = compiles
+        .values()
+        .filter_map(|times| match times {
+            (Some(t), _) => Some((*t, CompileTimeSource::Metrics)),
+            (None, Some(t)) => Some((*t, CompileTimeSource::Span)),
+            (None, None) => None,
+        })
+        .collect();
+    Some(CompileTimeSummary::new(compiles.len(), &times))
+}
+
+// Compile time by phase, summed and per compile, linking each compile to its metrics page
+fn phase_breakdown(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    stack_index: &StackIndex,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+) -> Option<PhaseBreakdown> {
+    let mut rows = Vec::new();
+    for (cid, metrics) in metrics_index {
+        let pages = directory
+            .get(cid)
+            .into_iter()
+            .flatten()
+            .filter(|o| o.name.contains("compilation_metrics"));
+        let compile_id = cid
+            .as_ref()
+            .map_or("(unknown)".to_string(), |c| c.to_string());
+        let frame = cid.as_ref().map_or(String::new(), |c| {
+            user_frame_label(compile_stack(stack_index, c), is_user_frame, strip_rules)
+        });
+        for (m, page) in metrics.iter().zip(pages) {
+            rows.push(CompilePhaseTimes {
+                compile_id: compile_id.clone(),
+                frame: frame.clone(),
+                url: page.url.clone(),
+                entire_frame_compile_time_s: m.entire_frame_compile_time_s,
+                phases: m.phase_times(),
+            });
+        }
+    }
+    PhaseBreakdown::new(rows)
+}
+
+// A field of a CSV file, quoted if it has to be
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// ops_histogram.html, and the same numbers as ops_histogram.csv and ops_by_compile.csv
+fn ops_histogram(
+    ops_by_compile: FxIndexMap<Option<CompileId>, FxIndexMap<String, u64>>,
+    (num_graphs, unreadable_graphs): (usize, usize),
+    config: &ParseConfig,
+) -> (OpsHistogramContext, Vec<(PathBuf, String)>) {
+    let mut totals: FxIndexMap<&str, (u64, usize)> = FxIndexMap::default();
+    for ops in ops_by_compile.values() {
+        for (op, count) in ops {
+            let total = totals.entry(op).or_default();
+            total.0 += count;
+            total.1 += 1;
+        }
+    }
+    // Most called first, then by name so that the order doesn't depend on the log's
+    totals.sort_by(|a, (a_count, _), b, (b_count, _)| b_count.cmp(a_count).then(a.cmp(b)));
+    let max = totals.values().map(|(count, _)| *count).max().unwrap_or(0);
+    let ops: Vec<OpCount> = totals
+        .iter()
+        .map(|(op, (count, compiles))| OpCount {
+            op: op.to_string(),
+            count: *count,
+            compiles: *compiles,
+            width: (count * 100).div_ceil(max.max(1)),
+        })
+        .collect();
+    let mut summary_csv = String::from("op,count,compiles\n");
+    for op in &ops {
+        summary_csv.push_str(&format!(
+            "{},{},{}\n",
+            csv_field(&op.op),
+            op.count,
+            op.compiles
+        ));
+    }
+    let mut by_compile_csv = String::from("compile_id,op,count\n");
+    let compiles = ops_by_compile
+        .into_iter()
+        .map(|(c, ops)| {
+            let (compile_id, anchor) = c
+                .map_or(("(unknown)".to_string(), "unknown".to_string()), |c| {
+                    (c.to_string(), c.as_directory_name())
+                });
+            let mut ops: Vec<(String, u64)> = ops.into_iter().collect();
+            ops.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+            for (op, count) in &ops {
+                by_compile_csv.push_str(&format!(
+                    "{},{},{}\n",
+                    csv_field(&compile_id),
+                    csv_field(op),
+                    count
+                ));
+            }
+            CompileOps {
+                compile_id,
+                anchor,
+                ops,
+            }
+        })
+        .collect();
+    let context = OpsHistogramContext {
+        css: CSS,
+        custom_header_html: config.custom_header_html.clone(),
+        num_graphs,
+        unreadable_graphs,
+        merge_overloads: config.ops_merge_overloads,
+        ops,
+        compiles,
+    };
+    (
+        context,
+        vec![
+            (PathBuf::from("ops_histogram.csv"), summary_csv),
+            (PathBuf::from("ops_by_compile.csv"), by_compile_csv),
+        ],
+    )
+}
+
+// How many compiles top.html lists, unless ParseConfig::top_compiles says otherwise
+pub static DEFAULT_TOP_COMPILES: usize = 20;
+
+// For top.html: the most expensive compiles (every attempt on its own, as they have their own
+// artifacts), by measured_compile_time.  Compiles without any time aren't ranked.
+fn top_compiles(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    records: &FxHashMap<CompileId, CompileRecord>,
+    stack_index: &StackIndex,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+    n: usize,
+) -> Vec<TopCompile> {
+    let mut compiles: Vec<(&CompileId, &[OutputFile], f64, CompileTimeSource)> = directory
+        .iter()
+        .filter_map(|(c, files)| {
+            let c = c.as_ref()?;
+            let (t, source) = measured_compile_time(c, metrics_index, records)?;
+            Some((c, &files[..], t, source))
+        })
+        .collect();
+    // Stable, so that compiles that took as long stay in the order they were first seen
+    compiles.sort_by(|a, b| b.2.total_cmp(&a.2));
+    compiles
+        .into_iter()
+        .take(n)
+        .map(|(c, files, t, source)| TopCompile {
+            compile_id: c.to_string(),
+            anchor: c.as_directory_name(),
+            time: fmt_seconds(t),
+            from_span: source == CompileTimeSource::Span,
+            frame: user_frame_label(compile_stack(stack_index, c), is_user_frame, strip_rules),
+            node_count: metrics_index
+                .get(&Some(c.clone()))
+                .into_iter()
+                .flatten()
+                .filter_map(|m| m.graph_node_count)
+                .reduce(|a, b| a + b)
+                .map_or(String::new(), |n| n.to_string()),
+            files: files
+                .iter()
+                .map(|f| (f.url.clone(), f.name.clone()))
+                .collect(),
+        })
+        .collect()
+}
+
+// Stands in for the file and package of compiles whose stack has no frame in user code
+static NO_USER_FRAME: &str = "(no user frame)";
+
+// The top-level package of a simplified filename: its first path component, e.g. "myteam" for
+// myteam/models/encoder.py
+fn source_package(filename: &str) -> &str {
+    let filename = filename.trim_start_matches('/');
+    filename.split('/').next().unwrap_or(filename)
+}
+
+// source_files.html, and the same numbers as source_files.csv and source_packages.csv: compiles
+// by the file of the innermost user frame of their stack, and by that file's package.  Every
+// attempt of a compile counts once, towards the compile.
+fn compiles_by_source(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    stack_index: &StackIndex,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+    config: &ParseConfig,
+) -> (SourceFilesContext, Vec<(PathBuf, String)>) {
+    let mut compiles: FxIndexMap<(Option<u32>, Option<u32>), Vec<&CompileId>> =
+        FxIndexMap::default();
+    for c in directory.keys().flatten() {
+        compiles
+            .entry((c.frame_id, c.frame_compile_id))
+            .or_default()
+            .push(c);
+    }
+    let mut by_file: FxIndexMap<String, SourceGroup> = FxIndexMap::default();
+    let mut frames_by_file: FxHashMap<String, FxHashSet<Option<u32>>> = FxHashMap::default();
+    let mut num_without_user_frame = 0;
+    for ((frame_id, _), attempts) in compiles {
+        let file = attempts
+            .iter()
+            .find_map(|c| {
+                let stack = stack_index.get(&Some((*c).clone()))?;
+                let frame = user_frame(stack, is_user_frame)?;
+                Some(simplify_filename(&frame.resolved_filename(), strip_rules).to_string())
+            })
+            .unwrap_or_else(|| {
+                num_without_user_frame += 1;
+                NO_USER_FRAME.to_string()
+            });
+        let time: f64 = attempts
+            .iter()
+            .filter_map(|c| metrics_index.get(&Some((*c).clone())))
+            .flatten()
+            .filter_map(|m| m.entire_frame_compile_time_s)
+            .sum();
+        frames_by_file
+            .entry(file.clone())
+            .or_default()
+            .insert(frame_id);
+        let group = by_file.entry(file.clone()).or_insert_with(|| SourceGroup {
+            name: file,
+            ..Default::default()
+        });
+        group.compiles += 1;
+        group.compile_time_s += time;
+    }
+    let mut by_package: FxIndexMap<String, SourceGroup> = FxIndexMap::default();
+    let mut frames_by_package: FxHashMap<String, FxHashSet<Option<u32>>> = FxHashMap::default();
+    for group in by_file.values() {
+        let frames = &frames_by_file[&group.name];
+        let package = if group.name == NO_USER_FRAME {
+            NO_USER_FRAME
+        } else {
+            source_package(&group.name)
+        };
+        frames_by_package
+            .entry(package.to_string())
+            .or_default()
+            .extend(frames);
+        let total = by_package
+            .entry(package.to_string())
+            .or_insert_with(|| SourceGroup {
+                name: package.to_string(),
+                ..Default::default()
+            });
+        total.compiles += group.compiles;
+        total.compile_time_s += group.compile_time_s;
+    }
+    // Most compiled first, then by name so that the order doesn't depend on the log's
+    let sorted = |groups: FxIndexMap<String, SourceGroup>,
+                  frames: &FxHashMap<String, FxHashSet<Option<u32>>>| {
+        let mut groups: Vec<SourceGroup> = groups
+            .into_values()
+            .map(|mut g| {
+                g.frames = frames[&g.name].len();
+                // Without compilation metrics there's no time to show
+                if g.compile_time_s > 0.0 {
+                    g.compile_time = fmt_seconds(g.compile_time_s);
+                }
+                g
+            })
+            .collect();
+        groups.sort_by(|a, b| b.compiles.cmp(&a.compiles).then(a.name.cmp(&b.name)));
+        groups
+    };
+    let files = sorted(by_file, &frames_by_file);
+    let packages = sorted(by_package, &frames_by_package);
+    let mut files_csv = String::from("file,package,compiles,frames,compile_time_s\n");
+    for f in &files {
+        let package = if f.name == NO_USER_FRAME {
+            NO_USER_FRAME
+        } else {
+            source_package(&f.name)
+        };
+        files_csv.push_str(&format!(
+            "{},{},{},{},{:.3}\n",
+            csv_field(&f.name),
+            csv_field(package),
+            f.compiles,
+            f.frames,
+            f.compile_time_s
+        ));
+    }
+    let mut packages_csv = String::from("package,compiles,frames,compile_time_s\n");
+    for p in &packages {
+        packages_csv.push_str(&format!(
+            "{},{},{},{:.3}\n",
+            csv_field(&p.name),
+            p.compiles,
+            p.frames,
+            p.compile_time_s
+        ));
+    }
+    let context = SourceFilesContext {
+        css: CSS,
+        custom_header_html: config.custom_header_html.clone(),
+        num_without_user_frame,
+        files,
+        packages,
+    };
+    (
+        context,
+        vec![
+            (PathBuf::from("source_files.csv"), files_csv),
+            (PathBuf::from("source_packages.csv"), packages_csv),
+        ],
+    )
+}
+
+// How many of the most expensive cache misses cache.html lists
+static CACHE_MISSES_LISTED: usize = 20;
+
+// For cache.html: FX graph cache hit rates, overall and per frame, and what the hits saved,
+// estimated from what a miss took.  None if there were no cache lookups.
+fn cache_dashboard(
+    records: &FxHashMap<CompileId, CompileRecord>,
+    metrics_index: &CompilationMetricsIndex,
+    config: &ParseConfig,
+) -> Option<CacheContext> {
+    let mut compiles: Vec<(&CompileId, &CompileRecord)> = records
+        .iter()
+        .filter(|(_, r)| !r.cache_events.is_empty())
+        .collect();
+    if compiles.is_empty() {
+        return None;
+    }
+    compiles.sort_by_key(|(c, _)| (c.frame_id, c.frame_compile_id, c.attempt));
+    let mut attempts: FxHashMap<(Option<u32>, Option<u32>), usize> = FxHashMap::default();
+    for c in records.keys() {
+        *attempts
+            .entry((c.frame_id, c.frame_compile_id))
+            .or_default() += 1;
+    }
+    let mut total = CacheCounts::default();
+    let mut frames: FxIndexMap<Option<u32>, CacheCounts> = FxIndexMap::default();
+    let mut logged_saved: Option<f64> = None;
+    let mut misses: Vec<(Option<f64>, CacheMiss)> = Vec::new();
+    for (c, r) in &compiles {
+        for event in &r.cache_events {
+            total.add(event.state);
+            frames.entry(c.frame_id).or_default().add(event.state);
+            if let (CacheState::Hit, Some(t)) = (event.state, event.time_s) {
+                logged_saved = Some(logged_saved.unwrap_or(0.0) + t);
+            }
+        }
+        if let Some(miss) = r.cache_events.iter().find(|e| e.state == CacheState::Miss) {
+            let t = measured_compile_time(c, metrics_index, records).map(|(t, _)| t);
+            misses.push((
+                t,
+                CacheMiss {
+                    compile_id: c.to_string(),
+                    anchor: c.as_directory_name(),
+                    compile_time: t.map_or(String::new(), fmt_seconds),
+                    attempts: attempts[&(c.frame_id, c.frame_compile_id)],
+                    key: miss.key.clone().unwrap_or_default(),
+                },
+            ));
+        }
+    }
+    let mut miss_times: Vec<f64> = misses.iter().filter_map(|(t, _)| *t).collect();
+    miss_times.sort_by(f64::total_cmp);
+    let median_miss = (!miss_times.is_empty()).then(|| miss_times[(miss_times.len() - 1) / 2]);
+    // Untimed misses last
+    misses.sort_by(|(a, _), (b, _)| b.unwrap_or(-1.0).total_cmp(&a.unwrap_or(-1.0)));
+    Some(CacheContext {
+        css: CSS,
+        custom_header_html: config.custom_header_html.clone(),
+        median_miss: median_miss.map_or(String::new(), fmt_seconds),
+        estimated_saved: median_miss.map_or(String::new(), |m| fmt_seconds(m * total.hits as f64)),
+        logged_saved: logged_saved.map_or(String::new(), fmt_seconds),
+        total,
+        frames: frames
+            .into_iter()
+            .map(|(f, counts)| (f.map_or("(unknown)".to_string(), |f| f.to_string()), counts))
+            .collect(),
+        expensive_misses: misses
+            .into_iter()
+            .take(CACHE_MISSES_LISTED)
+            .map(|(_, m)| m)
+            .collect(),
+    })
+}
+
+// For attempts.html: how every attempt of each compile went, for compiles in the order they
+// were first seen
+fn attempt_outcomes(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    records: &FxHashMap<CompileId, CompileRecord>,
+) -> Vec<AttemptsEntry> {
+    let mut compiles: FxIndexMap<(Option<u32>, Option<u32>), Vec<&CompileId>> =
+        FxIndexMap::default();
+    for c in directory.keys().flatten() {
+        compiles
+            .entry((c.frame_id, c.frame_compile_id))
+            .or_default()
+            .push(c);
+    }
+    compiles
+        .into_values()
+        .map(|mut attempts| {
+            attempts.sort_by_key(|c| c.attempt);
+            let last = attempts.len() - 1;
+            let steps: Vec<AttemptStep> = attempts
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let metrics = metrics_index
+                        .get(&Some((*c).clone()))
+                        .map_or(&[][..], |m| &m[..]);
+                    let outcome = AttemptOutcome::of(
+                        metrics,
+                        records.get(*c).is_some_and(|r| r.failed_elsewhere),
+                        i < last,
+                    );
+                    AttemptStep {
+                        kind: outcome,
+                        attempt: c.attempt,
+                        anchor: c.as_directory_name(),
+                        outcome: outcome.label(),
+                        class: outcome.class(),
+                        diff: None,
+                    }
+                })
+                .collect();
+            AttemptsEntry {
+                compile_id: CompileId {
+                    attempt: 0,
+                    ..attempts[0].clone()
+                }
+                .to_string(),
+                summary: steps
+                    .iter()
+                    .map(|s| format!("{} {}", s.attempt, s.outcome))
+                    .collect::<Vec<_>>()
+                    .join(" → "),
+                steps,
+            }
+        })
+        .collect()
+}
+
+// An artifact's name and text (or size), see diffable_artifacts
+type DiffableArtifact<'a> = (String, Result<&'a str, u64>);
+
+// The (frame id, frame compile id)s of each frame of a run, see diff_runs
+type FrameCompiles = FxIndexMap<String, FxHashSet<(Option<u32>, Option<u32>)>>;
+
+// The artifacts of a compile that attempt_diffs compares, by file name without its number (and
+// with the occurrence, for names that repeat): text from `contents`, or Err(size) if it was
+// streamed to disk rather than kept
+fn diffable_artifacts<'a>(
+    files: &[OutputFile],
+    contents: &FxHashMap<&str, &'a str>,
+) -> Vec<DiffableArtifact<'a>> {
+    let mut seen: FxHashMap<String, usize> = FxHashMap::default();
+    files
+        .iter()
+        .filter_map(|f| {
+            let base = f.name.rsplit('/').next().unwrap_or(&f.name);
+            let (stem, ext) = base.rsplit_once('.')?;
+            if ext != "txt" && ext != "py" {
+                return None;
+            }
+            let stem = stem.strip_suffix(&format!("_{}", f.number)).unwrap_or(stem);
+            let key = format!("{stem}.{ext}");
+            let n = seen.entry(key.clone()).or_default();
+            *n += 1;
+            let name = match *n {
+                1 => key,
+                n => format!("{key} ({n})"),
+            };
+            match contents.get(f.url.as_str()) {
+                Some(text) => Some((name, Ok(*text))),
+                None => f.size.map(|size| (name, Err(size))),
+            }
+        })
+        .collect()
+}
+
+// Every artifact of two compiles (see diffable_artifacts), diffed with the one of the same name
+// in the other: those of a in order, then those only b has
+fn artifact_diffs(
+    a: (&str, &[DiffableArtifact]),
+    b: (&str, &[DiffableArtifact]),
+) -> Vec<ArtifactDiff> {
+    fn find<'a>(artifacts: &[DiffableArtifact<'a>], name: &str) -> Option<Result<&'a str, u64>> {
+        artifacts
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, text)| *text)
+    }
+    let mut names: Vec<&String> = a.1.iter().map(|(n, _)| n).collect();
+    names.extend(
+        b.1.iter()
+            .map(|(n, _)| n)
+            .filter(|n| find(a.1, n).is_none()),
+    );
+    names
+        .into_iter()
+        .map(|name| ArtifactDiff::new(name.clone(), (a.0, find(a.1, name)), (b.0, find(b.1, name))))
+        .collect()
+}
+
+// For each pair of consecutive attempts of a compile with artifacts between them, their
+// diff_<a>_vs_<b>.html: how their text artifacts (see diffable_artifacts) and guards differ.
+// Each later attempt's step in `attempts` gets linked to its page.
+fn attempt_diffs(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    records: &FxHashMap<CompileId, CompileRecord>,
+    attempts: &mut [AttemptsEntry],
+    output: &[(PathBuf, String)],
+    written: &mut FxHashSet<PathBuf>,
+    config: &ParseConfig,
+) -> Vec<(PathBuf, DiffContext)> {
+    let files: FxHashMap<String, (&CompileId, &[OutputFile])> = directory
+        .iter()
+        .filter_map(|(c, files)| Some((c.as_ref()?.as_directory_name(), (c.as_ref()?, &files[..]))))
+        .collect();
+    let contents: FxHashMap<&str, &str> = output
+        .iter()
+        .filter_map(|(p, text)| Some((p.to_str()?, text.as_str())))
+        .collect();
+    let mut pages = Vec::new();
+    for entry in attempts.iter_mut() {
+        for i in 1..entry.steps.len() {
+            let (Some(&(a, a_files)), Some(&(b, b_files))) = (
+                files.get(&entry.steps[i - 1].anchor),
+                files.get(&entry.steps[i].anchor),
+            ) else {
+                continue;
+            };
+            let guards = |c: &CompileId| {
+                records
+                    .get(c)
+                    .and_then(|r| r.guards.as_ref())
+                    .map(|g| g.join("\n"))
+            };
+            let (a_guards, b_guards) = (guards(a), guards(b));
+            let mut a_artifacts = diffable_artifacts(a_files, &contents);
+            let mut b_artifacts = diffable_artifacts(b_files, &contents);
+            a_artifacts.extend(a_guards.as_deref().map(|g| ("guards".to_string(), Ok(g))));
+            b_artifacts.extend(b_guards.as_deref().map(|g| ("guards".to_string(), Ok(g))));
+            if a_artifacts.is_empty() && b_artifacts.is_empty() {
+                continue;
+            }
+            let (a_id, b_id) = (a.to_string(), b.to_string());
+            let artifacts = artifact_diffs((&a_id, &a_artifacts), (&b_id, &b_artifacts));
+            let filename = PathBuf::from(format!(
+                "diff_{}_vs_{}.html",
+                a.as_directory_name(),
+                b.as_directory_name()
+            ));
+            let filename = dedup_filename(filename, written).0;
+            entry.steps[i].diff = Some((a_id.clone(), filename.to_string_lossy().into_owned()));
+            pages.push((
+                filename,
+                DiffContext {
+                    css: CSS,
+                    custom_header_html: config.custom_header_html.clone(),
+                    a: (a_id, a.as_directory_name()),
+                    b: (b_id, b.as_directory_name()),
+                    normalized: false,
+                    artifacts,
+                },
+            ));
+        }
+    }
+    pages
+}
+
+// For recompiles.html: every frame with how many times it was compiled and how many attempts
+// that took, most compiled first
+fn recompiles_per_frame(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    records: &FxHashMap<CompileId, CompileRecord>,
+    stack_index: &StackIndex,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+) -> Vec<RecompileEntry> {
+    let mut frames: FxIndexMap<Option<u32>, Vec<&CompileId>> = FxIndexMap::default();
+    for c in directory.keys().flatten() {
+        frames.entry(c.frame_id).or_default().push(c);
+    }
+    let mut entries: Vec<RecompileEntry> = frames
+        .into_iter()
+        .map(|(frame_id, mut compiles)| {
+            compiles.sort_by_key(|c| (c.frame_compile_id, c.attempt));
+            let num_compiles = compiles
+                .iter()
+                .map(|c| c.frame_compile_id)
+                .collect::<FxHashSet<_>>()
+                .len();
+            // Every compile of a frame has the same stack, near enough
+            let label = user_frame_label(
+                compiles
+                    .iter()
+                    .find_map(|c| stack_index.get(&Some((*c).clone()))),
+                is_user_frame,
+                strip_rules,
+            );
+            RecompileEntry {
+                frame_id: frame_id.map_or(String::new(), |f| f.to_string()),
+                label,
+                num_compiles,
+                num_attempts: compiles.len(),
+                at_limit: num_compiles >= DEFAULT_CACHE_SIZE_LIMIT,
+                compiles: compiles
+                    .iter()
+                    .map(|c| (c.to_string(), c.as_directory_name()))
+                    .collect(),
+                causes: compiles
+                    .iter()
+                    .flat_map(|c| {
+                        records
+                            .get(*c)
+                            .into_iter()
+                            .flat_map(|r| &r.recompile_causes)
+                            .map(|cause| (c.to_string(), c.as_directory_name(), cause.clone()))
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    // Stable, so that equally recompiled frames stay in the order they were first compiled
+    entries.sort_by_key(|e| std::cmp::Reverse((e.num_compiles, e.num_attempts)));
+    entries
+}
+
+// Find the guard a recompile reason of `compile` is about.  Reasons start with the compile
+// whose guards failed ("0/2: ..."), else it's taken to be the last compile of the frame before
+// this one that logged guards, from `guarded` (every compile of the frame that did, in order).
+// The reason is either the code of the guard, or what the guard checks in words, like
+// "tensor 'L['x']' size mismatch at index 0. expected 32, actual 48"; anything else, or a guard
+// that isn't there, leaves only the reason.
+fn attribute_recompile(
+    reason: &str,
+    compile: &CompileId,
+    guarded: &[CompileId],
+    records: &FxHashMap<CompileId, CompileRecord>,
+) -> RecompileCause {
+    thread_local! {
+        static PREFIX: Regex = Regex::new(r"^\s*(?:-\s*)?(\d+)/(\d+):\s*").unwrap();
+        static MISMATCH: Regex = Regex::new(
+            r"^tensor '(.+)' (size|stride) mismatch at index (\d+)\. expected (\S+), actual (\S+)$",
+        )
+        .unwrap();
+        static ACTUAL: Regex = Regex::new(r"\bactual:?\s*([^\s,)]+)").unwrap();
+    }
+    let reason = reason.trim();
+    let (named, body) = match PREFIX.with(|re| re.captures(reason)) {
+        Some(c) => (
+            Some((c[1].parse::<u32>().ok(), c[2].parse::<u32>().ok())),
+            &reason[c.get(0).unwrap().end()..],
+        ),
+        None => (None, reason),
+    };
+    let guarded_by = match named {
+        Some((frame_id, frame_compile_id)) => guarded
+            .iter()
+            .rev()
+            .find(|g| g.frame_id == frame_id && g.frame_compile_id == frame_compile_id),
+        None => guarded
+            .iter()
+            .rev()
+            .find(|g| g.frame_compile_id < compile.frame_compile_id),
+    };
+    let mut candidates = vec![normalize_guard(body)];
+    if let Some(c) = MISMATCH.with(|re| re.captures(body)) {
+        candidates.insert(0, format!("{}.{}()[{}] == {}", &c[1], &c[2], &c[3], &c[4]));
+    }
+    let failed_guard = guarded_by.and_then(|g| {
+        let guards = records.get(g)?.guards.as_ref()?;
+        // The guard itself, else one that checks it along with other things
+        let guard = candidates
+            .iter()
+            .find_map(|c| guards.iter().find(|guard| *guard == c))
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .find_map(|c| guards.iter().find(|guard| guard.contains(c.as_str())))
+            })?;
+        Some(FailedGuard {
+            guard: guard.clone(),
+            compile: (g.to_string(), g.as_directory_name()),
+            actual: ACTUAL
+                .with(|re| re.captures(body).map(|c| c[1].to_string()))
+                .unwrap_or_default(),
+        })
+    });
+    RecompileCause {
+        reason: reason.to_string(),
+        failed_guard,
+    }
+}
+
+// For the index: how many candidate dimensions (see DynamicDims) the compiles kept dynamic and
+// how many they specialized, linking each compile to the specializations on its metrics page.
+// None if no compile created or specialized a symbol.
+fn dynamic_shapes_summary(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    records: &FxHashMap<CompileId, CompileRecord>,
+) -> Option<DynamicShapesSummary> {
+    let mut total = DynamicDims::default();
+    let mut compiles = Vec::new();
+    for (c, files) in directory {
+        let Some(c) = c else { continue };
+        let dims = records.get(c).map(DynamicDims::new).unwrap_or_default();
+        if dims.candidates == 0 {
+            continue;
+        }
+        total.add(dims);
+        let url = files
+            .iter()
+            .find(|f| f.name.contains("compilation_metrics"))
+            .map_or_else(
+                || format!("#{}", c.as_directory_name()),
+                |f| format!("{}#specializations", f.url),
+            );
+        compiles.push((c.to_string(), url, dims));
+    }
+    (total.candidates > 0).then(|| DynamicShapesSummary {
+        specialized_percent: format!(
+            "{:.0}%",
+            total.specialized as f64 * 100.0 / total.candidates as f64
+        ),
+        total,
+        compiles,
+    })
+}
+
+// For eager_fallbacks.html: the frames that run in eager in steady state, judged by the last
+// attempt of their last compile (see EagerFallback::of), in the order they were first compiled
+fn eager_fallbacks(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    records: &FxHashMap<CompileId, CompileRecord>,
+    stack_index: &StackIndex,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+) -> Vec<EagerFallbackEntry> {
+    let mut frames: FxIndexMap<Option<u32>, Vec<&CompileId>> = FxIndexMap::default();
+    for c in directory.keys().flatten() {
+        frames.entry(c.frame_id).or_default().push(c);
+    }
+    frames
+        .into_iter()
+        .filter_map(|(frame_id, compiles)| {
+            let num_compiles = compiles
+                .iter()
+                .map(|c| c.frame_compile_id)
+                .collect::<FxHashSet<_>>()
+                .len();
+            let last = *compiles
+                .iter()
+                .max_by_key(|c| (c.frame_compile_id, c.attempt))?;
+            let metrics = metrics_index
+                .get(&Some(last.clone()))
+                .map_or(&[][..], |m| &m[..]);
+            let at_limit = num_compiles >= DEFAULT_CACHE_SIZE_LIMIT;
+            let fallback = EagerFallback::of(
+                metrics,
+                records.get(last).is_some_and(|r| r.failed_elsewhere),
+                at_limit,
+            )?;
+            let fail_reason = metrics.iter().find_map(|m| {
+                Some(format!(
+                    "{}: {}",
+                    m.fail_type.as_ref()?,
+                    m.fail_reason.as_deref().unwrap_or("")
+                ))
+            });
+            let reason = match fallback {
+                EagerFallback::CacheLimit if at_limit => {
+                    format!(
+                        "compiled {num_compiles} times, the limit is {DEFAULT_CACHE_SIZE_LIMIT}"
+                    )
+                }
+                // A backward compile that failed has no reason in the metrics of the frame
+                _ => fail_reason
+                    .unwrap_or_else(|| "failed outside its compilation metrics".to_string()),
+            };
+            Some(EagerFallbackEntry {
+                frame_id: frame_id.map_or(String::new(), |f| f.to_string()),
+                label: user_frame_label(
+                    compiles
+                        .iter()
+                        .find_map(|c| stack_index.get(&Some((*c).clone()))),
+                    is_user_frame,
+                    strip_rules,
+                ),
+                fallback: fallback.label(),
+                reason,
+                compile: (last.to_string(), last.as_directory_name()),
+            })
+        })
+        .collect()
+}
+
+// How many times each guard was added or removed from one compile of a frame to the next, given
+// the guards of the compiles in order; in the order the guards first appear
+fn guard_flips<'a>(compiles: &[&'a [String]]) -> FxIndexMap<&'a String, usize> {
+    let sets: Vec<FxHashSet<&String>> = compiles.iter().map(|g| g.iter().collect()).collect();
+    let mut flips: FxIndexMap<&String, usize> = FxIndexMap::default();
+    for guard in compiles.iter().flat_map(|g| g.iter()) {
+        flips.entry(guard).or_insert_with(|| {
+            sets.windows(2)
+                .filter(|w| w[0].contains(guard) != w[1].contains(guard))
+                .count()
+        });
+    }
+    flips
+}
+
+// For guard_churn.html: for every frame with several compiles that logged guards, how the guards
+// changed from each of them to the next, with the guards that came and went more than once
+fn guard_churn(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    records: &FxHashMap<CompileId, CompileRecord>,
+) -> Vec<GuardChurnFrame> {
+    let mut frames: FxIndexMap<Option<u32>, Vec<(&CompileId, &[String])>> = FxIndexMap::default();
+    for c in directory.keys().flatten() {
+        if let Some(guards) = records.get(c).and_then(|r| r.guards.as_deref()) {
+            frames.entry(c.frame_id).or_default().push((c, guards));
+        }
+    }
+    frames
+        .into_iter()
+        .filter(|(_, compiles)| compiles.len() > 1)
+        .map(|(frame_id, mut compiles)| {
+            compiles.sort_by_key(|(c, _)| (c.frame_compile_id, c.attempt));
+            let sets: Vec<FxHashSet<&String>> =
+                compiles.iter().map(|(_, g)| g.iter().collect()).collect();
+            let flips = guard_flips(&compiles.iter().map(|(_, g)| *g).collect::<Vec<_>>());
+            let mut churning: Vec<(String, usize)> = flips
+                .iter()
+                .filter(|(_, n)| **n > 1)
+                .map(|(g, n)| ((*g).clone(), *n))
+                .collect();
+            // Stable, so that equally unstable guards stay in the order they first appear
+            churning.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+            let changes = |guards: &[String], other: &FxHashSet<&String>| {
+                guards
+                    .iter()
+                    .filter(|g| !other.contains(g))
+                    .map(|g| (g.clone(), flips[g] > 1))
+                    .collect::<Vec<_>>()
+            };
+            let steps = compiles
+                .windows(2)
+                .zip(sets.windows(2))
+                .map(|(w, s)| GuardStep {
+                    from: (w[0].0.to_string(), w[0].0.as_directory_name()),
+                    to: (w[1].0.to_string(), w[1].0.as_directory_name()),
+                    added: changes(w[1].1, &s[0]),
+                    removed: changes(w[0].1, &s[1]),
+                    num_unchanged: s[0].intersection(&s[1]).count(),
+                })
+                .collect();
+            GuardChurnFrame {
+                frame_id: frame_id.map_or(String::new(), |f| f.to_string()),
+                num_compiles: compiles.len(),
+                churning,
+                steps,
+            }
+        })
+        .collect()
+}
+
+// The defaults of ParseConfig::recompile_loop_window_s and recompile_loop_min_compiles
+pub static DEFAULT_RECOMPILE_LOOP_WINDOW_S: f64 = 600.0;
+pub static DEFAULT_RECOMPILE_LOOP_MIN_COMPILES: usize = 4;
+
+// Frames that look stuck in a recompile loop: at least min_compiles recompiles (new compile ids
+// or new attempts) that started in the last window of the log, all from the same stack but for
+// line numbers, and, if they logged guards, with a guard that came and went more than once
+// among them.  Only logs spanning at least two windows are checked, so that a burst of
+// recompiles while warming up is never mistaken for one.
+fn recompile_loops(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    records: &FxHashMap<CompileId, CompileRecord>,
+    stack_index: &StackIndex,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+    log_span: (NaiveDateTime, NaiveDateTime),
+    config: &ParseConfig,
+) -> Vec<RecompileLoop> {
+    let window_s = config
+        .recompile_loop_window_s
+        .unwrap_or(DEFAULT_RECOMPILE_LOOP_WINDOW_S);
+    let min_compiles = config
+        .recompile_loop_min_compiles
+        .unwrap_or(DEFAULT_RECOMPILE_LOOP_MIN_COMPILES)
+        .max(2);
+    let window = TimeDelta::milliseconds((window_s * 1000.0) as i64);
+    if log_span.1 - log_span.0 < window * 2 {
+        return Vec::new();
+    }
+    let window_start = log_span.1 - window;
+    let mut frames: FxIndexMap<Option<u32>, Vec<&CompileId>> = FxIndexMap::default();
+    for c in directory.keys().flatten() {
+        let is_recompile = c.attempt > 0 || c.frame_compile_id.is_some_and(|n| n > 0);
+        let start = records.get(c).and_then(|r| r.span.start());
+        if is_recompile && start.is_some_and(|t| t >= window_start) {
+            frames.entry(c.frame_id).or_default().push(c);
+        }
+    }
+    frames
+        .into_iter()
+        .filter(|(_, compiles)| compiles.len() >= min_compiles)
+        .filter_map(|(frame_id, mut compiles)| {
+            compiles.sort_by_key(|c| (c.frame_compile_id, c.attempt));
+            let stacks: Vec<&StackSummary> = compiles
+                .iter()
+                .filter_map(|c| compile_stack(stack_index, c))
+                .collect();
+            let shape = |stack: &StackSummary| {
+                stack
+                    .iter()
+                    .map(|f| (f.resolved_filename(), f.name.to_string()))
+                    .collect::<Vec<_>>()
+            };
+            if stacks.windows(2).any(|w| shape(w[0]) != shape(w[1])) {
+                return None;
+            }
+            let guards: Vec<&[String]> = compiles
+                .iter()
+                .filter_map(|c| records.get(c)?.guards.as_deref())
+                .collect();
+            let guard = if guards.len() > 1 {
+                // max_by_key takes the last of equals, so of guards that flipped as often the
+                // first to appear wins
+                let (guard, flips) = guard_flips(&guards)
+                    .into_iter()
+                    .rev()
+                    .max_by_key(|(_, n)| *n)?;
+                if flips < 2 {
+                    return None;
+                }
+                Some((guard.clone(), flips))
+            } else {
+                None
+            };
+            Some(RecompileLoop {
+                frame_id: frame_id.map_or(String::new(), |f| f.to_string()),
+                frame: user_frame_label(stacks.first().copied(), is_user_frame, strip_rules),
+                recompiles: compiles
+                    .iter()
+                    .map(|c| (c.to_string(), c.as_directory_name()))
+                    .collect(),
+                num_recompiles: compiles.len(),
+                guard,
+            })
+        })
+        .collect()
+}
+
+// What each compile that didn't fail is missing by the artifact rules (see ArtifactRule), e.g.
+// "no inductor_output_code recorded": logging was partly disabled, or it died without a word
+fn missing_artifacts(
+    records: &FxHashMap<CompileId, CompileRecord>,
+    metrics_index: &CompilationMetricsIndex,
+    rules: &[ArtifactRule],
+) -> FxHashMap<CompileId, String> {
+    records
+        .iter()
+        .filter(|(c, r)| {
+            !r.failed_elsewhere
+                && !metrics_index
+                    .get(&Some((*c).clone()))
+                    .into_iter()
+                    .flatten()
+                    .any(|m| m.fail_type.is_some())
+        })
+        .filter_map(|(c, r)| {
+            let missing: Vec<String> = rules
+                .iter()
+                .filter(|rule| rule.is_missing(&r.artifacts))
+                .map(|rule| rule.fmt_missing())
+                .collect();
+            (!missing.is_empty()).then(|| (c.clone(), missing.join(", ")))
+        })
+        .collect()
+}
+
+// For ranks.html: every compile, joined on its frame_id and frame_compile_id, with what each
+// rank logged of it.  Ranks that don't compile the same things are a classic cause of hangs:
+// one rank recompiles while the others wait for it in a collective.  None with fewer than two
+// ranks.
+fn compiles_per_rank(
+    mut rank_compiles: FxIndexMap<(Option<u32>, Option<u32>), FxHashMap<u32, RankCompile>>,
+    expected_rank: Option<u32>,
+    config: &ParseConfig,
+) -> Option<RanksContext> {
+    let mut ranks: Vec<u32> = rank_compiles
+        .values()
+        .flat_map(|compiles| compiles.keys().copied())
+        .collect::<FxHashSet<_>>()
+        .into_iter()
+        .collect();
+    if ranks.len() < 2 {
+        return None;
+    }
+    ranks.sort();
+    rank_compiles.sort_keys();
+    let rows: Vec<RankCompileRow> = rank_compiles
+        .into_iter()
+        .map(|((frame_id, frame_compile_id), compiles)| {
+            let c = CompileId {
+                frame_id,
+                frame_compile_id,
+                attempt: 0,
+            };
+            let attempts: FxHashSet<usize> = compiles.values().map(|r| r.attempts.len()).collect();
+            RankCompileRow {
+                compile_id: c.to_string(),
+                anchor: expected_rank
+                    .and_then(|rank| compiles.get(&rank))
+                    .and_then(|r| r.attempts.iter().min())
+                    .map_or(String::new(), |&attempt| {
+                        CompileId { attempt, ..c }.as_directory_name()
+                    }),
+                cells: ranks
+                    .iter()
+                    .map(|rank| compiles.get(rank).map_or(String::new(), RankCompile::fmt))
+                    .collect(),
+                divergent: compiles.len() < ranks.len() || attempts.len() > 1,
+            }
+        })
+        .collect();
+    Some(RanksContext {
+        css: CSS,
+        custom_header_html: config.custom_header_html.clone(),
+        ranks,
+        rendered_rank: expected_rank.map_or(String::new(), |r| r.to_string()),
+        num_divergent: rows.iter().filter(|r| r.divergent).count(),
+        rows,
+    })
+}
+
+// How many bars the graph break and failure charts of dashboard.html have at most, the rest
+// being summed into one
+static DASHBOARD_MAX_BARS: usize = 10;
+
+// The biggest counts first as (label, count) bars, the rest summed into "others" past
+// DASHBOARD_MAX_BARS; ties by label, so the order is stable
+fn ranked_bars(counts: FxHashMap<String, usize>) -> Vec<(String, f64)> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    if counts.len() > DASHBOARD_MAX_BARS {
+        let others: Vec<(String, usize)> = counts.split_off(DASHBOARD_MAX_BARS);
+        counts.push((
+            format!("({} others)", others.len()),
+            others.iter().map(|(_, n)| n).sum(),
+        ));
+    }
+    counts.into_iter().map(|(l, n)| (l, n as f64)).collect()
+}
+
+// dashboard.html: the numbers of the report as charts (see charts)
+#[allow(clippy::too_many_arguments)]
+fn dashboard(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    records: &FxHashMap<CompileId, CompileRecord>,
+    compile_time_summary: Option<&CompileTimeSummary>,
+    log_span: Option<(NaiveDateTime, NaiveDateTime)>,
+    cache: Option<&CacheContext>,
+    failure_groups: &[FailureGroup],
+    config: &ParseConfig,
+) -> DashboardContext {
+    let histogram = compile_time_summary.map_or(&[][..], |s| &s.histogram[..]);
+    // When each compile started, by the first entry of its first attempt
+    let mut starts: Vec<NaiveDateTime> = directory
+        .keys()
+        .flatten()
+        .filter(|c| c.attempt == 0)
+        .filter_map(|c| records.get(c)?.span()?.start())
+        .collect();
+    starts.sort();
+    let compiles_chart = match log_span {
+        Some((start, end)) if !starts.is_empty() => {
+            let seconds =
+                |t: NaiveDateTime| (t - start).num_microseconds().unwrap_or(0) as f64 / 1e6;
+            let mut points = vec![(0.0, 0.0)];
+            points.extend(
+                starts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| (seconds(*t), (i + 1) as f64)),
+            );
+            points.push((seconds(end), starts.len() as f64));
+            charts::line_chart(
+                &points,
+                ("0 s", &fmt_wall_time(seconds(end))),
+                &starts.len().to_string(),
+            )
+        }
+        _ => String::new(),
+    };
+    let mut graph_breaks: FxHashMap<String, usize> = FxHashMap::default();
+    for m in metrics_index.values().flatten() {
+        for reason in m.restart_reasons.iter().flatten() {
+            *graph_breaks.entry(reason.clone()).or_default() += 1;
+        }
+    }
+    let mut failures: FxHashMap<String, usize> = FxHashMap::default();
+    for g in failure_groups {
+        *failures.entry(g.fail_type.clone()).or_default() += g.count;
+    }
+    DashboardContext {
+        css: CSS,
+        custom_header_html: config.custom_header_html.clone(),
+        compile_time_chart: if histogram.is_empty() {
+            String::new()
+        } else {
+            charts::column_chart(
+                &histogram
+                    .iter()
+                    .map(|b| (b.label.clone(), b.count as f64))
+                    .collect::<Vec<_>>(),
+            )
+        },
+        num_compiles: starts.len(),
+        compiles_chart,
+        cache_chart: cache.map_or(String::new(), |c| {
+            charts::proportion_bar(&[
+                ("hits", c.total.hits as f64, "green"),
+                ("misses", c.total.misses as f64, "red"),
+                ("bypasses", c.total.bypasses as f64, "gray"),
+            ])
+        }),
+        cache_hit_rate: cache.map_or(String::new(), |c| c.total.hit_rate.clone()),
+        num_graph_breaks: graph_breaks.values().sum(),
+        graph_breaks_chart: charts::bar_chart(&ranked_bars(graph_breaks)),
+        num_failures: failures.values().sum(),
+        failures_chart: charts::bar_chart(&ranked_bars(failures)),
+    }
+}
+
+// Where the compile.html of each compile with artifacts goes, next to them; picked before any
+// page links to it
+fn compile_page_paths(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    written: &mut FxHashSet<PathBuf>,
+) -> FxHashMap<CompileId, PathBuf> {
+    directory
+        .iter()
+        .filter(|(_, files)| !files.is_empty())
+        .filter_map(|(c, _)| {
+            let c = c.as_ref()?;
+            let filename = PathBuf::from(format!("{}/compile.html", c.as_directory_name()));
+            Some((c.clone(), dedup_filename(filename, written).0))
+        })
+        .collect()
+}
+
+// For each compile with a compile.html (see compile_page_paths), what it says.  attempts gives
+// its status, missing what the artifact rules found missing.
+#[allow(clippy::too_many_arguments)]
+fn compile_pages(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    metrics_index: &CompilationMetricsIndex,
+    records: &FxHashMap<CompileId, CompileRecord>,
+    stack_index: &StackIndex,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+    attempts: &[AttemptsEntry],
+    missing: &FxHashMap<CompileId, String>,
+    page_paths: &FxHashMap<CompileId, PathBuf>,
+    config: &ParseConfig,
+) -> Vec<(CompileId, PathBuf, CompileContext)> {
+    let steps: FxHashMap<&str, &AttemptStep> = attempts
+        .iter()
+        .flat_map(|a| a.steps.iter().map(|s| (s.anchor.as_str(), s)))
+        .collect();
+    let fmt_time = |t: NaiveDateTime| t.format("%m/%d %H:%M:%S%.3f").to_string();
+    directory
+        .iter()
+        .filter_map(|(c, files)| {
+            let c = c.as_ref()?;
+            let filename = page_paths.get(c)?.clone();
+            let anchor = c.as_directory_name();
+            let metrics = metrics_index
+                .get(&Some(c.clone()))
+                .map_or(&[][..], |m| &m[..]);
+            let sum = |count: fn(&CompilationMetricsMetadata) -> Option<u64>| {
+                metrics
+                    .iter()
+                    .filter_map(count)
+                    .reduce(|a, b| a + b)
+                    .map_or(String::new(), |n| n.to_string())
+            };
+            let record = records.get(c);
+            let span = record.and_then(CompileRecord::span);
+            let step = steps.get(anchor.as_str());
+            let compile_time = measured_compile_time(c, metrics_index, records);
+            let context = CompileContext {
+                css: CSS,
+                custom_header_html: config.custom_header_html.clone(),
+                compile_id: c.to_string(),
+                status: step.map_or("", |s| s.outcome),
+                status_class: step.map_or("", |s| s.class),
+                start: span
+                    .and_then(CompileSpan::start)
+                    .map_or(String::new(), fmt_time),
+                end: span
+                    .and_then(CompileSpan::end)
+                    .map_or(String::new(), fmt_time),
+                span: span.map_or(String::new(), CompileSpan::fmt),
+                compile_time: compile_time.map_or(String::new(), |(t, _)| fmt_seconds(t)),
+                compile_time_from_span: compile_time
+                    .is_some_and(|(_, source)| source == CompileTimeSource::Span),
+                frame: user_frame_label(compile_stack(stack_index, c), is_user_frame, strip_rules),
+                guard_count: sum(|m| m.guard_count),
+                node_count: sum(|m| m.graph_node_count),
+                restart_reasons: metrics
+                    .iter()
+                    .flat_map(|m| m.restart_reasons.iter().flatten().cloned())
+                    .collect(),
+                recompile_causes: record.map_or(Vec::new(), |r| r.recompile_causes.clone()),
+                missing: missing.get(c).cloned().unwrap_or_default(),
+                issues: record.map_or(Vec::new(), |r| {
+                    r.issues.iter().map(DataIssue::fmt).collect()
+                }),
+                diff: step
+                    .and_then(|s| s.diff.as_ref())
+                    .map(|(previous, diff)| (previous.clone(), format!("../{diff}"))),
+                raw_log: None,
+                groups: artifact_groups(files),
+                anchor,
+            };
+            Some((c.clone(), filename, context))
+        })
+        .collect()
+}
+
+// What the index's search box matches a compile (or the entries without one) against, see
+// DirectoryEntry::search
+fn compile_search_text(
+    c: Option<&CompileId>,
+    files: &[OutputFile],
+    metrics_index: &CompilationMetricsIndex,
+    stack_index: &StackIndex,
+    is_user_frame: &dyn Fn(&FrameSummary) -> bool,
+    strip_rules: &StripRules,
+) -> String {
+    let mut words = vec![c.map_or("(unknown)".to_string(), CompileId::to_string)];
+    if let Some(stack) = c.and_then(|c| compile_stack(stack_index, c)) {
+        words.push(user_frame_label(Some(stack), is_user_frame, strip_rules));
+    }
+    words.extend(
+        files
+            .iter()
+            .map(|f| f.name.rsplit('/').next().unwrap_or(&f.name).to_string()),
+    );
+    for m in metrics_index.get(&c.cloned()).into_iter().flatten() {
+        words.extend(m.fail_type.clone());
+        words.extend(m.fail_reason.clone());
+    }
+    words.join(" ").to_lowercase()
+}
+
+// The index's compiles by frame, then by frame_compile_id, with their attempts; every level in
+// the order it first appears in the log.  Compiles are labeled by frame_label (see
+// user_frame_label), and frames like the first of their compiles with a stack.
+fn frame_groups(
+    entries: Vec<(CompileId, DirectoryEntry)>,
+    frame_label: &dyn Fn(&CompileId) -> String,
+) -> Vec<FrameGroup> {
+    let mut frames: FxIndexMap<Option<u32>, FxIndexMap<String, Vec<DirectoryEntry>>> =
+        FxIndexMap::default();
+    let mut labels: FxHashMap<Option<u32>, String> = FxHashMap::default();
+    // By CompileId::frame_label, which the compiles are keyed by too
+    let mut compile_labels: FxHashMap<String, String> = FxHashMap::default();
+    for (c, entry) in entries {
+        let compile = frames.entry(c.frame_id).or_default();
+        if !compile.contains_key(&c.frame_label()) {
+            let label = frame_label(&c);
+            let frame = labels.entry(c.frame_id).or_default();
+            if frame.is_empty() || frame == NO_STACK {
+                frame.clone_from(&label);
+            }
+            compile_labels.insert(c.frame_label(), label);
+        }
+        compile.entry(c.frame_label()).or_default().push(entry);
+    }
+    frames
+        .into_iter()
+        .map(|(frame_id, compiles)| {
+            FrameGroup::new(
+                frame_id,
+                labels.remove(&frame_id).unwrap_or_default(),
+                compiles
+                    .into_iter()
+                    .map(|(frame_label, attempts)| {
+                        FrameCompileGroup::new(
+                            format!("[{frame_label}]"),
+                            compile_labels.remove(&frame_label).unwrap_or_default(),
+                            attempts,
+                        )
+                    })
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+// The index's frames split into pages of at most size attempts, keeping every frame whole
+// (frames bigger than that get a page of their own)
+fn shard_frames(frames: Vec<FrameGroup>, size: usize) -> Vec<Vec<FrameGroup>> {
+    let mut shards: Vec<Vec<FrameGroup>> = Vec::new();
+    let mut num_attempts = 0;
+    for frame in frames {
+        match shards.last_mut() {
+            Some(shard) if num_attempts + frame.num_attempts <= size => {
+                num_attempts += frame.num_attempts;
+                shard.push(frame);
+            }
+            _ => {
+                num_attempts = frame.num_attempts;
+                shards.push(vec![frame]);
+            }
+        }
+    }
+    shards
+}
+
+// A compile in the breadcrumb bar: its anchor (and directory) and id, and what links to it go to
+// from the top of the output, its compile.html or else its entry in the index
+struct BreadcrumbCompile {
+    anchor: String,
+    compile_id: String,
+    href: String,
+}
+
+// The compiles in the order they first appear in the log, for the breadcrumb bar's links from one to the next
+fn breadcrumb_compiles(
+    directory: &FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    page_paths: &FxHashMap<CompileId, PathBuf>,
+) -> Vec<BreadcrumbCompile> {
+    directory
+        .keys()
+        .flatten()
+        .map(|c| {
+            let anchor = c.as_directory_name();
+            BreadcrumbCompile {
+                compile_id: c.to_string(),
+                href: page_paths.get(c).map_or_else(
+                    || format!("index.html#{anchor}"),
+                    |p| p.to_string_lossy().into_owned(),
+                ),
+                anchor,
+            }
+        })
+        .collect()
+}
+
+// DARK_MODE_RULES twice over: under prefers-color-scheme unless the switch has chosen light mode,
+// and when it has chosen dark mode.  Then the switch itself, which needs JavaScript, so with none
+// the media query is all there is.
+static DARK_MODE_HTML: Lazy<String> = Lazy::new(|| {
+    let rules = |root: &str| {
+        DARK_MODE_RULES
+            .lines()
+            .filter_map(|rule| rule.split_once(" { "))
+            .map(|(selectors, declarations)| {
+                let selectors = selectors
+                    .split(", ")
+                    .map(|s| match s {
+                        ":root" => root.to_string(),
+                        s => format!("{root} {s}"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{selectors} {{ {declarations}\n")
+            })
+            .collect::<String>()
+    };
+    format!(
+        "<style>\n@media (prefers-color-scheme: dark) {{\n{}}}\n{}</style>{TEMPLATE_DARK_MODE_TOGGLE}",
+        rules(":root:not(.light-mode)"),
+        rules(":root.dark-mode"),
+    )
+});
+
+// Put the breadcrumb bar (TEMPLATE_BREADCRUMBS) at the top of every HTML page of output, right
+// after its <body> (or at the very start, for fragments like highlighted output code).  Pages in
+// a compile's directory also get the compile, and the compiles before and after it in the log.
+// Links are relative to each page, since they can be a directory down.
+fn add_breadcrumbs(
+    output: &mut [(PathBuf, String)],
+    tt: &TinyTemplate,
+    title: &str,
+    compiles: &[BreadcrumbCompile],
+) -> anyhow::Result<()> {
+    for (path, html) in output
+        .iter_mut()
+        .filter(|(p, _)| p.extension().is_some_and(|e| e == "html"))
+    {
+        let root = "../".repeat(path.components().count() - 1);
+        let link = |c: &BreadcrumbCompile| (c.compile_id.clone(), format!("{root}{}", c.href));
+        // Pages of a compile can be a directory further down, see NamespacedParser
+        let position = path
+            .parent()
+            .and_then(|dir| dir.components().next())
+            .and_then(|dir| {
+                compiles
+                    .iter()
+                    .position(|c| dir.as_os_str() == c.anchor.as_str())
+            });
+        let context = BreadcrumbsContext {
+            title: title.to_string(),
+            index: format!("{root}index.html"),
+            compile: position.map(|i| link(&compiles[i])),
+            prev: position
+                .and_then(|i| i.checked_sub(1))
+                .map(|i| link(&compiles[i])),
+            next: position.and_then(|i| compiles.get(i + 1)).map(link),
+            dark_mode: DARK_MODE_HTML.as_str(),
+        };
+        let bar = tt.render("breadcrumbs.html", &context)?;
+        let at = html
+            .find("<body")
+            .and_then(|i| html[i..].find('>').map(|j| i + j + 1))
+            .unwrap_or(0);
+        html.insert_str(at, &bar);
+    }
+    Ok(())
+}
+
+// Put the provenance footer (TEMPLATE_PROVENANCE) at the bottom of every HTML page of output,
+// right before its </body> (or at the very end, for fragments)
+fn add_footer(output: &mut [(PathBuf, String)], footer: &str) {
+    for (_, html) in output
+        .iter_mut()
+        .filter(|(p, _)| p.extension().is_some_and(|e| e == "html"))
+    {
+        let at = html.rfind("</body>").unwrap_or(html.len());
+        html.insert_str(at, footer);
+    }
+}
+
+// A dynamo_start stack, put in the stack trie once the whole log has been read
+struct CompileStack {
+    compile_id: Option<CompileId>,
+    stack: StackSummary,
+    timestamp: Option<NaiveDateTime>,
+}
+
+// A compilation metrics page whose rendering waits until the end of the log
+struct PendingMetrics {
+    lineno: usize,
+    rank: Option<u32>,
+    compile_id: Option<CompileId>,
+    metrics: CompilationMetricsMetadata,
+    output_files: Vec<OutputFile>,
+    compile_id_dir: PathBuf,
+    // Index of the placeholder in the output, and of its entry in the compile's directory
+    output_slot: usize,
+    directory_slot: usize,
+}
+
+// Runs parser on e, adding what it writes to output and compile_directory.  Returns the files it
+// had to give another name, see DataIssue::DuplicateArtifact.
+#[allow(clippy::too_many_arguments)]
 fn run_parser<'t>(
     lineno: usize,
-    parser: &Box<dyn StructuredLogParser + 't>,
+    parser: &(dyn StructuredLogParser + 't),
     e: &Envelope,
     payload: &str,
     output_count: &mut i32,
     output: &mut Vec<(PathBuf, String)>,
+    written: &mut FxHashSet<PathBuf>,
     compile_directory: &mut Vec<OutputFile>,
-    multi: &MultiProgress,
+    progress: &Progress,
     stats: &mut Stats,
-) {
-    if let Some(md) = parser.get_metadata(&e) {
-        let results = parser.parse(lineno, md, e.rank, &e.compile_id, &payload);
-        fn extract_suffix(filename: &String) -> String {
+    highlight_max_bytes: Option<usize>,
+    previews: bool,
+) -> Vec<DataIssue> {
+    let mut duplicates = Vec::new();
+    if let Some(md) = parser.get_metadata(e) {
+        // A parser that panics fails like one that returns an error, rather than taking the
+        // rest of the log down with it
+        let results = panic::catch_unwind(AssertUnwindSafe(|| {
+            parser.parse(lineno, md, e.rank, &e.compile_id, payload)
+        }))
+        .unwrap_or_else(|_| Err(anyhow!("panicked on line {lineno}")));
+        fn extract_suffix(filename: &str) -> String {
             if filename.contains("fx_graph_cache_miss") {
                 "❌".to_string()
             } else if filename.contains("fx_graph_cache_hit") {
@@ -96,7 +2680,34 @@ fn run_parser<'t>(
             } else if filename.contains("fx_graph_cache_bypass") {
                 "❓".to_string()
             } else {
-                "".to_string()
+                "".to_string()
+            }
+        }
+        // Next to a text artifact, the same with its lines numbered and highlighted, or a JSON
+        // one as a tree, unless highlighting is off (max_bytes None); returns where it went
+        fn highlighted_companion(
+            filename: &Path,
+            out: &str,
+            max_bytes: Option<usize>,
+            output: &mut Vec<(PathBuf, String)>,
+            written: &mut FxHashSet<PathBuf>,
+        ) -> Option<String> {
+            let max_bytes = max_bytes?;
+            let title = filename.file_name()?.to_string_lossy();
+            let html = match filename.extension()?.to_str()? {
+                "txt" | "py" => highlighted_html(&title, out, max_bytes),
+                "json" if out.len() <= max_bytes => json_tree_html(&title, out)?,
+                _ => return None,
+            };
+            let (page, _) = dedup_filename(filename.with_extension("html"), written);
+            output.push((page.clone(), html));
+            Some(page.to_string_lossy().into_owned())
+        }
+        fn duplicate_suffix(suffix: String, renamed: bool) -> String {
+            if renamed {
+                format!("{suffix} (duplicate)")
+            } else {
+                suffix
             }
         }
         match results {
@@ -117,35 +2728,86 @@ fn run_parser<'t>(
                             } else {
                                 raw_filename
                             };
+                            let wanted = filename.to_string_lossy().into_owned();
+                            let (filename, renamed) = dedup_filename(filename, written);
+                            if renamed {
+                                stats.duplicate_filename += 1;
+                                duplicates.push(DataIssue::DuplicateArtifact {
+                                    line: lineno,
+                                    wanted,
+                                    written: filename.to_string_lossy().into_owned(),
+                                });
+                            }
+                            let size = out.len() as u64;
+                            let highlighted = highlighted_companion(
+                                &filename,
+                                &out,
+                                highlight_max_bytes,
+                                output,
+                                written,
+                            );
+                            let preview = previews
+                                .then(|| artifact_preview(&filename, &out))
+                                .flatten();
                             output.push((filename.clone(), out));
                             let filename_str = format!("{}", filename.to_string_lossy());
-                            let suffix = extract_suffix(&filename_str);
+                            let suffix = duplicate_suffix(extract_suffix(&filename_str), renamed);
                             compile_directory.push(OutputFile {
                                 url: filename_str.clone(),
                                 name: filename_str,
                                 number: *output_count,
-                                suffix: suffix,
+                                suffix,
+                                size: Some(size),
+                                highlighted,
+                                preview,
                             });
                             *output_count += 1;
                         }
                         ParserOutput::GlobalFile(filename, out) => {
+                            let wanted = filename.to_string_lossy().into_owned();
+                            let (filename, renamed) = dedup_filename(filename, written);
+                            if renamed {
+                                stats.duplicate_filename += 1;
+                                duplicates.push(DataIssue::DuplicateArtifact {
+                                    line: lineno,
+                                    wanted,
+                                    written: filename.to_string_lossy().into_owned(),
+                                });
+                            }
+                            let size = out.len() as u64;
+                            let highlighted = highlighted_companion(
+                                &filename,
+                                &out,
+                                highlight_max_bytes,
+                                output,
+                                written,
+                            );
+                            let preview = previews
+                                .then(|| artifact_preview(&filename, &out))
+                                .flatten();
                             output.push((filename.clone(), out));
                             let filename_str = format!("{}", filename.to_string_lossy());
-                            let suffix = extract_suffix(&filename_str);
+                            let suffix = duplicate_suffix(extract_suffix(&filename_str), renamed);
                             compile_directory.push(OutputFile {
                                 url: filename_str.clone(),
                                 name: filename_str,
                                 number: *output_count,
-                                suffix: suffix,
+                                suffix,
+                                size: Some(size),
+                                highlighted,
+                                preview,
                             });
                             *output_count += 1;
                         }
                         ParserOutput::Link(name, url) => {
                             compile_directory.push(OutputFile {
-                                url: url,
-                                name: name,
+                                url,
+                                name,
                                 number: *output_count,
                                 suffix: "".to_string(),
+                                size: None,
+                                highlighted: None,
+                                preview: None,
                             });
                             *output_count += 1;
                         }
@@ -154,64 +2816,1077 @@ fn run_parser<'t>(
             }
             Err(err) => match parser.name() {
                 "dynamo_guards" => {
-                    multi.suspend(|| eprintln!("Failed to parse guards json: {}", err));
+                    progress.error(format_args!("Failed to parse guards json: {}", err));
                     stats.fail_dynamo_guards_json += 1;
                 }
                 name => {
-                    multi.suspend(|| eprintln!("Parser {name} failed: {err}"));
+                    progress.error(format_args!("Parser {name} failed: {err}"));
                     stats.fail_parser += 1;
                 }
             },
         }
     }
+    duplicates
 }
 
-pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOutput> {
-    let strict = config.strict;
-    if !path.is_file() {
+// Write a file of the output
+pub fn write_output_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    fs::write(path, contents)
+}
+
+// A file of the output that's written as it comes
+fn create_output_file(path: &Path) -> io::Result<impl Write> {
+    Ok(io::BufWriter::new(File::create(path)?))
+}
+
+fn copy_output_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to).map(|_| ())
+}
+
+// Where the pages of a parse go, see write_output.  DirSink writes them into a directory, which
+// is what the CLI does; MemorySink keeps them, for running tlparse somewhere that stores them
+// elsewhere (leave ParseConfig::output_dir unset then, so nothing is streamed to disk either).
+pub trait OutputSink {
+    // path is relative to the output directory
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()>;
+}
+
+pub struct DirSink {
+    dir: PathBuf,
+    created_dirs: FxHashSet<PathBuf>,
+    totals: OutputTotals,
+}
+
+impl DirSink {
+    // dir has to exist already
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DirSink {
+            dir: dir.into(),
+            created_dirs: FxHashSet::default(),
+            totals: OutputTotals::default(),
+        }
+    }
+
+    // Count the files already in the directory as written, e.g. the payloads and raw.log that a
+    // parse with ParseConfig::output_dir wrote there itself.  Dotfiles (like the CLI's marker)
+    // aren't output.
+    pub fn count_existing(&mut self) -> io::Result<()> {
+        let mut dirs = vec![self.dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let metadata = entry.metadata()?;
+                if metadata.is_dir() {
+                    dirs.push(entry.path());
+                } else {
+                    self.totals.files += 1;
+                    self.totals.bytes += metadata.len();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // What's been written through this sink, and what count_existing found
+    pub fn totals(&self) -> OutputTotals {
+        self.totals
+    }
+}
+
+impl OutputSink for DirSink {
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let out_file = self.dir.join(path);
+        // Only create a compile's directory right before its first file is written
+        if let Some(dir) = out_file.parent() {
+            if !self.created_dirs.contains(dir) {
+                fs::create_dir_all(dir)?;
+                self.created_dirs.insert(dir.to_path_buf());
+            }
+        }
+        write_output_file(&out_file, contents)?;
+        self.totals.files += 1;
+        self.totals.bytes += contents.len() as u64;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemorySink {
+    // In the order they were written
+    pub files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl MemorySink {
+    // The rendered index.html, once it's been written
+    pub fn index(&self) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|(path, _)| path == Path::new("index.html"))
+            .and_then(|(_, contents)| std::str::from_utf8(contents).ok())
+    }
+
+    pub fn totals(&self) -> OutputTotals {
+        OutputTotals {
+            files: self.files.len() as u64,
+            bytes: self.files.iter().map(|(_, c)| c.len() as u64).sum(),
+        }
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.files.push((path.to_path_buf(), contents.to_vec()));
+        Ok(())
+    }
+}
+
+// Hand every page of a parse to sink
+pub fn write_output(output: ParseOutput, sink: &mut dyn OutputSink) -> io::Result<()> {
+    for (path, contents) in output.files {
+        sink.write(&path, contents.as_bytes())?;
+    }
+    Ok(())
+}
+
+// Turn the common ways of pointing tlparse at the wrong thing into readable errors,
+// rather than a raw OS error.  The CLI calls this before touching the output directory.
+pub fn check_input_path(path: &Path) -> anyhow::Result<()> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => bail!("{}", describe_open_error(path, err)),
+    };
+    if metadata.is_dir() {
+        let mut candidates: Vec<String> = fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|f| f.path().is_file())
+                    .filter_map(|f| f.file_name().into_string().ok())
+                    .filter(|name| {
+                        name.ends_with(".log") || name.starts_with("dedicated_log_torch_trace")
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        candidates.sort();
+        let mut msg = format!(
+            "{} is a directory; pass a trace file inside it, or use --latest to parse the most recently modified one",
+            path.display()
+        );
+        if !candidates.is_empty() {
+            msg.push_str("\nCandidate trace files:");
+            for name in candidates {
+                msg.push_str(&format!("\n  {}", path.join(name).display()));
+            }
+        }
+        bail!(msg);
+    }
+    if !metadata.is_file() {
         bail!("{} is not a file", path.display())
     }
-    let file = File::open(path)?;
-    let metadata = file.metadata()?;
-    let file_size = metadata.len();
+    // Only opening the file tells us whether we may read it
+    File::open(path).map_err(|err| anyhow!(describe_open_error(path, err)))?;
+    Ok(())
+}
+
+fn describe_open_error(path: &Path, err: io::Error) -> String {
+    match err.kind() {
+        io::ErrorKind::NotFound => format!("{}: no such file", path.display()),
+        io::ErrorKind::PermissionDenied => format!("{}: permission denied", path.display()),
+        _ => format!("{}: {}", path.display(), err),
+    }
+}
+
+// The guards of a dynamo_guards.html page, read back out of its table (TEMPLATE_DYNAMO_GUARDS)
+fn guards_from_page(page: &str) -> Vec<String> {
+    page.lines()
+        .filter(|l| l.starts_with("<tr data-kind="))
+        .filter_map(|row| {
+            // The third cell has the code, whole in its last <code> when it's cut short
+            let cell = row.split("</td> <td>").nth(2)?;
+            let code = &cell[cell.rfind("<code>")? + "<code>".len()..];
+            let code = &code[..code.find("</code>")?];
+            Some(html_escape::decode_html_entities(code).into_owned())
+        })
+        .collect()
+}
+
+// For `tlparse diff-compile`: how two compiles of the output directory of an earlier parse
+// differ, like the attempt diffs but for any two compiles (two recompiles of a frame, say).
+// Artifacts are read back from what was written: the text ones listed in compile_directory.json
+// and the guards in their dynamo_guards.html page.  With `normalize`, graphs and guards are
+// diffed as normalize_fx_graph and normalize_guard have them, and the rest as normalize_artifact
+// has it, so that renumbered nodes, addresses and the like don't drown out what changed.
+pub fn diff_compiles(
+    out_dir: &Path,
+    a: &str,
+    b: &str,
+    normalize: bool,
+) -> anyhow::Result<CompileDiff> {
+    let (listing, entries) = read_compile_directory(out_dir)?;
+    let compile = |label: &str| -> anyhow::Result<(CompileId, &[OutputFile])> {
+        let c = CompileId::parse(label)
+            .ok_or_else(|| anyhow!("{label} isn't a compile id, expected something like 2/0"))?;
+        let entry = entries
+            .iter()
+            .find(|e| e.compile_id.as_ref() == Some(&c))
+            .ok_or_else(|| anyhow!("No compile {c} in {}", listing.display()))?;
+        Ok((c, &entry.files))
+    };
+    let ((a, a_files), (b, b_files)) = (compile(a)?, compile(b)?);
+    let (a_id, b_id) = (a.to_string(), b.to_string());
+    let context = DiffContext {
+        css: CSS,
+        custom_header_html: String::new(),
+        artifacts: diff_written_compiles(
+            (&a_id, out_dir, a_files),
+            (&b_id, out_dir, b_files),
+            normalize,
+        )?,
+        a: (a_id, a.as_directory_name()),
+        b: (b_id, b.as_directory_name()),
+        normalized: normalize,
+    };
+    let mut tt = TinyTemplate::new();
+    tt.add_formatter("format_unescaped", tinytemplate::format_unescaped);
+    tt.add_template("diff.html", TEMPLATE_DIFF)?;
+    Ok(CompileDiff {
+        filename: PathBuf::from(format!(
+            "diff_{}_vs_{}.html",
+            a.as_directory_name(),
+            b.as_directory_name()
+        )),
+        html: tt.render("diff.html", &context)?,
+        text: context.text(),
+    })
+}
+
+// One side of `tlparse diff`: the output directory of a parse, what to call it, and where the
+// report links to it (with a trailing slash)
+pub struct DiffRun {
+    pub dir: PathBuf,
+    pub name: String,
+    pub href: String,
+}
+
+// Compile time changes smaller than this are noise, however large they are relatively
+pub static MIN_COMPILE_TIME_CHANGE_S: f64 = 0.1;
+
+// For `tlparse diff`: how run b of a model compares to run a, say before and after a PyTorch
+// upgrade, from the output directories of their parses (see RunDiff).  Compiles are matched up
+// by compile id and frames by their label, so this is for runs of the same program;
+// `threshold_pct` is how much longer a compile has to take to count as slower.  Artifacts of
+// matching compiles are diffed like diff_compiles does.
+pub fn diff_runs(
+    a: &DiffRun,
+    b: &DiffRun,
+    normalize: bool,
+    threshold_pct: f64,
+) -> anyhow::Result<RunDiffReport> {
+    let (_, a_compiles) = read_compile_directory(&a.dir)?;
+    let (_, b_compiles) = read_compile_directory(&b.dir)?;
+    // Entries outside of any compile don't take part
+    let a_compiles: Vec<ListedCompile> = a_compiles
+        .into_iter()
+        .filter(|c| c.compile_id.is_some())
+        .collect();
+    let b_compiles: Vec<ListedCompile> = b_compiles
+        .into_iter()
+        .filter(|c| c.compile_id.is_some())
+        .collect();
+    let by_id = |compiles: &[ListedCompile]| -> FxHashSet<String> {
+        compiles
+            .iter()
+            .filter_map(|c| Some(c.compile_id.as_ref()?.to_string()))
+            .collect()
+    };
+    let (a_ids, b_ids) = (by_id(&a_compiles), by_id(&b_compiles));
+    let frame = |c: &ListedCompile| c.frame.clone().unwrap_or_else(|| "(unknown)".to_string());
+    let id = |c: &ListedCompile| c.compile_id.as_ref().unwrap().to_string();
+    let page = |run: &DiffRun, c: &ListedCompile| {
+        format!(
+            "{}{}/compile.html",
+            run.href,
+            c.compile_id.as_ref().unwrap().as_directory_name()
+        )
+    };
+    let only_in = |run: &DiffRun, compiles: &[ListedCompile], other: &FxHashSet<String>| {
+        compiles
+            .iter()
+            .filter(|c| !other.contains(&id(c)))
+            .map(|c| RunCompile {
+                compile_id: id(c),
+                frame: frame(c),
+                url: page(run, c),
+            })
+            .collect::<Vec<_>>()
+    };
+    let only_in_a = only_in(a, &a_compiles, &b_ids);
+    let only_in_b = only_in(b, &b_compiles, &a_ids);
+
+    // A frame's compiles are its (frame id, frame compile id)s, however many attempts they took
+    let frame_counts = |compiles: &[ListedCompile]| {
+        let mut counts = FrameCompiles::default();
+        for c in compiles {
+            let cid = c.compile_id.as_ref().unwrap();
+            counts
+                .entry(frame(c))
+                .or_default()
+                .insert((cid.frame_id, cid.frame_compile_id));
+        }
+        counts
+    };
+    let (a_frames, b_frames) = (frame_counts(&a_compiles), frame_counts(&b_compiles));
+    let mut frame_count_changes: Vec<FrameCountChange> = a_frames
+        .keys()
+        .chain(b_frames.keys().filter(|f| !a_frames.contains_key(*f)))
+        .filter_map(|f| {
+            let count = |frames: &FrameCompiles| frames.get(f).map_or(0, |c| c.len());
+            let (a, b) = (count(&a_frames), count(&b_frames));
+            (a != b).then(|| FrameCountChange {
+                frame: f.clone(),
+                a,
+                b,
+            })
+        })
+        .collect();
+    // Biggest increase first
+    frame_count_changes.sort_by_key(|f| std::cmp::Reverse(f.b as i64 - f.a as i64));
+
+    let mut slower = Vec::new();
+    let mut faster = Vec::new();
+    let mut changed_artifacts = Vec::new();
+    for a_compile in &a_compiles {
+        let Some(b_compile) = b_compiles
+            .iter()
+            .find(|c| c.compile_id == a_compile.compile_id)
+        else {
+            continue;
+        };
+        if let (Some(a_s), Some(b_s)) = (a_compile.time_s(), b_compile.time_s()) {
+            if (b_s - a_s).abs() >= MIN_COMPILE_TIME_CHANGE_S {
+                let change = CompileTimeChange::new(id(a_compile), frame(b_compile), a_s, b_s);
+                if b_s > a_s * (1.0 + threshold_pct / 100.0) {
+                    slower.push(change);
+                } else if b_s < a_s * (1.0 - threshold_pct / 100.0) {
+                    faster.push(change);
+                }
+            }
+        }
+        let artifacts: Vec<ArtifactDiff> = diff_written_compiles(
+            (&a.name, &a.dir, &a_compile.files),
+            (&b.name, &b.dir, &b_compile.files),
+            normalize,
+        )?
+        .into_iter()
+        .filter(|d| d.changed)
+        .collect();
+        if !artifacts.is_empty() {
+            changed_artifacts.push(ChangedArtifacts {
+                compile_id: id(a_compile),
+                frame: frame(b_compile),
+                a_url: page(a, a_compile),
+                b_url: page(b, b_compile),
+                artifacts,
+            });
+        }
+    }
+    slower.sort_by(|x, y| y.change_pct.total_cmp(&x.change_pct));
+    faster.sort_by(|x, y| x.change_pct.total_cmp(&y.change_pct));
+
+    let a_summary = RunSummary::new(a.name.clone(), &a_compiles);
+    let b_summary = RunSummary::new(b.name.clone(), &b_compiles);
+    let mut reasons = Vec::new();
+    if !only_in_b.is_empty() {
+        reasons.push(format!("{} compile(s) only in {}", only_in_b.len(), b.name));
+    }
+    let num_more_often = frame_count_changes.iter().filter(|f| f.b > f.a).count();
+    if num_more_often > 0 {
+        reasons.push(format!("{num_more_often} frame(s) compiled more often"));
+    }
+    if !slower.is_empty() {
+        reasons.push(format!(
+            "{} compile(s) more than {threshold_pct}% slower",
+            slower.len()
+        ));
+    }
+    let (a_total, b_total) = (a_summary.compile_time_s, b_summary.compile_time_s);
+    if b_total - a_total >= MIN_COMPILE_TIME_CHANGE_S
+        && b_total > a_total * (1.0 + threshold_pct / 100.0)
+    {
+        reasons.push(format!("compile time went from {a_total} s to {b_total} s"));
+    }
+    let diff = RunDiff {
+        regressed: !reasons.is_empty(),
+        reasons,
+        threshold_pct,
+        normalized: normalize,
+        a: a_summary,
+        b: b_summary,
+        only_in_a,
+        only_in_b,
+        frame_count_changes,
+        slower,
+        faster,
+        changed_artifacts,
+    };
+    let context = RunDiffContext {
+        css: CSS,
+        custom_header_html: String::new(),
+        diff: &diff,
+        artifact_lines: diff
+            .changed_artifacts
+            .iter()
+            .map(|c| ChangedArtifactLines {
+                compile: c,
+                artifacts: &c.artifacts,
+            })
+            .collect(),
+    };
+    let mut tt = TinyTemplate::new();
+    tt.add_formatter("format_unescaped", tinytemplate::format_unescaped);
+    tt.add_template("run_diff.html", TEMPLATE_RUN_DIFF)?;
+    let html = tt.render("run_diff.html", &context)?;
+    Ok(RunDiffReport { diff, html })
+}
+
+// The compile_directory.json of an output directory, and where it is
+fn read_compile_directory(out_dir: &Path) -> anyhow::Result<(PathBuf, Vec<ListedCompile>)> {
+    // Output from before compile_directory.json had its provenance is just the list
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Listing {
+        Compiles { compiles: Vec<ListedCompile> },
+        Entries(Vec<ListedCompile>),
+    }
+    let listing = out_dir.join("compile_directory.json");
+    let entries = match serde_json::from_str(&fs::read_to_string(&listing).map_err(|err| {
+        anyhow!(
+            "{}, is it a tlparse output directory?",
+            describe_open_error(&listing, err)
+        )
+    })?)? {
+        Listing::Compiles { compiles } => compiles,
+        Listing::Entries(entries) => entries,
+    };
+    Ok((listing, entries))
+}
+
+// How two compiles' artifacts differ, each read back from the output directory it was written
+// to: the text ones listed in compile_directory.json, and the guards in their dynamo_guards.html
+// page.  With `normalize`, graphs and guards are diffed as normalize_fx_graph and
+// normalize_guard have them, and the other text artifacts as normalize_artifact has them.
+fn diff_written_compiles(
+    a: (&str, &Path, &[OutputFile]),
+    b: (&str, &Path, &[OutputFile]),
+    normalize: bool,
+) -> anyhow::Result<Vec<ArtifactDiff>> {
+    // Every text artifact of the compile, normalized if it's a graph, and its guards
+    let read = |out_dir: &Path, files: &[OutputFile]| -> anyhow::Result<_> {
+        let mut contents: Vec<(String, String)> = Vec::new();
+        let mut guards = None;
+        for f in files {
+            let base = f.name.rsplit('/').next().unwrap_or(&f.name);
+            let path = out_dir.join(&f.url);
+            if base.ends_with(".txt") || base.ends_with(".py") {
+                let text = fs::read_to_string(&path)?;
+                let text = if !normalize {
+                    text
+                } else if base.contains("graph") && base.ends_with(".txt") {
+                    normalize_fx_graph(&normalize_artifact(&text))
+                } else {
+                    normalize_artifact(&text)
+                };
+                contents.push((f.url.clone(), text));
+            } else if base.starts_with("dynamo_guards") && base.ends_with(".html") {
+                let page = fs::read_to_string(&path)?;
+                let lines: Vec<String> = guards_from_page(&page)
+                    .iter()
+                    .map(|g| {
+                        if normalize {
+                            normalize_guard(g)
+                        } else {
+                            g.clone()
+                        }
+                    })
+                    .collect();
+                guards = Some(lines.join("\n"));
+            }
+        }
+        Ok((contents, guards))
+    };
+    let (a_contents, a_guards) = read(a.1, a.2)?;
+    let (b_contents, b_guards) = read(b.1, b.2)?;
+    fn by_url(contents: &[(String, String)]) -> FxHashMap<&str, &str> {
+        contents
+            .iter()
+            .map(|(url, text)| (url.as_str(), text.as_str()))
+            .collect()
+    }
+    let (a_contents, b_contents) = (by_url(&a_contents), by_url(&b_contents));
+    let mut a_artifacts = diffable_artifacts(a.2, &a_contents);
+    let mut b_artifacts = diffable_artifacts(b.2, &b_contents);
+    a_artifacts.extend(a_guards.as_deref().map(|g| ("guards".to_string(), Ok(g))));
+    b_artifacts.extend(b_guards.as_deref().map(|g| ("guards".to_string(), Ok(g))));
+    Ok(artifact_diffs((a.0, &a_artifacts), (b.0, &b_artifacts)))
+}
+
+pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOutput> {
+    check_input_path(path)?;
+    let input = LogInput::File(path);
+    let mut output = parse_runs(&input, open_log(path)?, &config)?;
+    if !config.no_raw_log {
+        match &config.output_dir {
+            Some(dir) => copy_output_file(path, &dir.join("raw.log"))?,
+            None => output
+                .files
+                .push((PathBuf::from("raw.log"), fs::read_to_string(path)?)),
+        }
+    }
+    Ok(output)
+}
+
+// Like parse_path, for a log that isn't in a file.  It's parsed as it's read, so it needn't fit
+// in memory, unless raw.log is to be returned; nothing is written unless config.output_dir is
+// set.
+pub fn parse(reader: impl BufRead, config: &ParseConfig) -> anyhow::Result<ParseOutput> {
+    if config.no_raw_log {
+        return parse_runs(&LogInput::Reader, reader, config);
+    }
+    match &config.output_dir {
+        Some(dir) => {
+            let mut raw = TeeReader::new(reader, create_output_file(&dir.join("raw.log"))?);
+            let output = parse_runs(&LogInput::Reader, &mut raw, config)?;
+            raw.finish()?;
+            Ok(output)
+        }
+        None => {
+            let mut raw = TeeReader::new(reader, Vec::new());
+            let mut output = parse_runs(&LogInput::Reader, &mut raw, config)?;
+            let raw = raw.finish()?;
+            output.files.push((
+                PathBuf::from("raw.log"),
+                String::from_utf8_lossy(&raw).into_owned(),
+            ));
+            Ok(output)
+        }
+    }
+}
+
+// One entry of a log, as read by envelopes
+#[derive(Debug)]
+pub struct LogEntry {
+    // Of its glog header, 1-indexed
+    pub line: usize,
+    pub envelope: Envelope,
+    // Its continuation lines without their indent, joined by newlines; None if it had none
+    pub payload: Option<String>,
+}
+
+// Read a log entry by entry without rendering anything, for callers that want the envelopes
+// themselves: bindings for other languages, or scripts over logs too big to parse whole.  Lines
+// that aren't entries are skipped like parse skips them, and an entry whose JSON doesn't parse
+// is an Err, after which reading goes on.  Payloads aren't checked against their MD5.
+pub fn envelopes<'r>(reader: impl BufRead + 'r) -> anyhow::Result<Envelopes<'r>> {
+    let lines: Box<dyn Iterator<Item = (usize, String)> + 'r> =
+        Box::new(reader.lines().enumerate().filter_map(|(i, l)| {
+            let l = l.ok()?;
+            let l = strip_ansi_escapes(&l).unwrap_or(l);
+            (!l.is_empty()).then_some((i + 1, l))
+        }));
+    Ok(Envelopes {
+        lines: lines.peekable(),
+        re_glog: glog_regex()?,
+        timestamps: GlogTimestamps::new(Local::now().year()),
+    })
+}
+
+pub struct Envelopes<'r> {
+    lines: std::iter::Peekable<Box<dyn Iterator<Item = (usize, String)> + 'r>>,
+    re_glog: Regex,
+    timestamps: GlogTimestamps,
+}
+
+impl Iterator for Envelopes<'_> {
+    type Item = anyhow::Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (lineno, line) = self.lines.next()?;
+            let Some(caps) = self.re_glog.captures(&line) else {
+                continue;
+            };
+            let mut payload: Option<String> = None;
+            while let Some((_, payload_line)) = self
+                .lines
+                .next_if(|(_, l)| strip_payload_indent(l, DEFAULT_PAYLOAD_SPACE_INDENT).is_some())
+            {
+                let payload_line =
+                    strip_payload_indent(&payload_line, DEFAULT_PAYLOAD_SPACE_INDENT).unwrap();
+                match &mut payload {
+                    Some(payload) => {
+                        payload.push('\n');
+                        payload.push_str(payload_line);
+                    }
+                    None => payload = Some(payload_line.to_string()),
+                }
+            }
+            let json = &line[caps.name("payload").unwrap().start()..];
+            return Some(match serde_json::from_str::<Envelope>(json) {
+                Ok(mut envelope) => {
+                    envelope.timestamp = self.timestamps.resolve_captures(&caps);
+                    Ok(LogEntry {
+                        line: lineno,
+                        envelope,
+                        payload,
+                    })
+                }
+                Err(err) => Err(anyhow!("line {lineno}: {err}")),
+            });
+        }
+    }
+}
+
+// What `tlparse cat` calls an entry: the name of an artifact, graph dump or dump_file, and the
+// kind of anything else
+fn artifact_name(envelope: &Envelope) -> String {
+    if let Some(artifact) = &envelope.artifact {
+        artifact.name.clone()
+    } else if let Some(graph_dump) = &envelope.graph_dump {
+        graph_dump.name.clone()
+    } else if let Some(dump_file) = &envelope.dump_file {
+        dump_file.name.clone()
+    } else {
+        envelope
+            .kinds()
+            .first()
+            .copied()
+            .unwrap_or("unknown")
+            .to_string()
+    }
+}
+
+// The payload of one artifact of a compile, reading the log only until it's found.  compile_id
+// is as tlparse prints it, with or without the brackets, and name as artifact_name has it, with
+// or without a file extension; a name the compile logged more than once is "name (2)" from its
+// second on, like attempt diffs number them.  If there's no such artifact the error lists what
+// the compile (or, for an unknown compile id, the log) has, which takes reading all of it.
+pub fn find_artifact(reader: impl BufRead, compile_id: &str, name: &str) -> anyhow::Result<String> {
+    let compile_id = compile_id.trim_start_matches('[').trim_end_matches(']');
+    let requested = |listed: &str| {
+        listed == name
+            || name
+                .rsplit_once('.')
+                .is_some_and(|(stem, _)| stem == listed)
+    };
+    let mut compiles: FxIndexMap<String, Vec<String>> = FxIndexMap::default();
+    let mut seen: FxHashMap<String, usize> = FxHashMap::default();
+    for entry in envelopes(reader)? {
+        // One broken entry shouldn't hide the rest
+        let Ok(entry) = entry else { continue };
+        let (Some(cid), Some(payload)) = (&entry.envelope.compile_id, entry.payload) else {
+            continue;
+        };
+        let cid = cid.to_string();
+        let artifacts = compiles.entry(cid.clone()).or_default();
+        if cid[1..cid.len() - 1] != *compile_id {
+            continue;
+        }
+        let base = artifact_name(&entry.envelope);
+        let n = seen.entry(base.clone()).or_default();
+        *n += 1;
+        let listed = match *n {
+            1 => base,
+            n => format!("{base} ({n})"),
+        };
+        if requested(&listed) {
+            return Ok(payload);
+        }
+        artifacts.push(listed);
+    }
+    match compiles.get(&format!("[{compile_id}]")) {
+        Some(artifacts) => bail!(
+            "[{compile_id}] has no artifact {name}; it has: {}",
+            artifacts.join(", ")
+        ),
+        None if compiles.is_empty() => bail!("No compile [{compile_id}]: the log has no artifacts"),
+        None => bail!(
+            "No compile [{compile_id}]; the log has artifacts for: {}",
+            compiles.keys().cloned().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+// The pages of one compile (every attempt, unless all_attempts is false and then only
+// compile_id's) and nothing else: its directories and the diffs between its attempts.  The log
+// is gone over once to keep only that compile's entries, and the interned strings its stacks
+// use, and then just those are parsed, so it takes about as long as reading the log.  Artifact
+// file names are numbered as if the log were only those entries.
+pub fn extract_compile(
+    mut reader: impl BufRead,
+    compile_id: &CompileId,
+    all_attempts: bool,
+    config: &ParseConfig,
+) -> anyhow::Result<ParseOutput> {
+    let wanted = |c: &CompileId| {
+        c.frame_id == compile_id.frame_id
+            && c.frame_compile_id == compile_id.frame_compile_id
+            && (all_attempts || c.attempt == compile_id.attempt)
+    };
+    let re_glog = glog_regex()?;
+    let mut kept = Vec::new();
+    // Whether the entry whose payload lines come next was kept
+    let mut keeping = false;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+        let stripped = strip_ansi_escapes(line);
+        let line = stripped.as_deref().unwrap_or(line);
+        let payload_space_indent = config
+            .payload_space_indent
+            .unwrap_or(DEFAULT_PAYLOAD_SPACE_INDENT);
+        if strip_payload_indent(line, payload_space_indent).is_none() {
+            keeping = re_glog.captures(line).is_some_and(|caps| {
+                let json = &line[caps.name("payload").unwrap().start()..];
+                serde_json::from_str::<Envelope>(json).is_ok_and(|e| match &e.compile_id {
+                    Some(c) => wanted(c),
+                    None => e.str.is_some(),
+                })
+            });
+        }
+        if keeping {
+            kept.extend_from_slice(&buf);
+        }
+    }
+    let ours = |dir: &str| {
+        CompileId::parse(&dir.replacen('_', "/", 1))
+            .is_some_and(|c| wanted(&c) && c.as_directory_name() == dir)
+    };
+    let in_dir = |path: &Path| {
+        path.components().count() > 1
+            && path
+                .iter()
+                .next()
+                .and_then(|d| d.to_str())
+                .is_some_and(ours)
+    };
+    let mut output = parse_runs(&LogInput::Reader, &kept[..], config)?;
+    if !output.files.iter().any(|(path, _)| in_dir(path)) {
+        bail!("No compile {compile_id} in the log");
+    }
+    output.files.retain(|(path, _)| {
+        let attempt_diff = path
+            .to_str()
+            .and_then(|p| {
+                p.strip_prefix("diff_")?
+                    .strip_suffix(".html")?
+                    .split_once("_vs_")
+            })
+            .is_some_and(|(a, b)| ours(a) && ours(b));
+        in_dir(path) || attempt_diff
+    });
+    for run in &mut output.runs {
+        run.directory.retain(|c, _| c.as_ref().is_some_and(wanted));
+    }
+    Ok(output)
+}
+
+// A payload line that `tlparse grep`'s pattern matched
+#[derive(Debug)]
+pub struct GrepMatch<'a> {
+    pub compile_id: Option<&'a CompileId>,
+    // As artifact_name has it
+    pub artifact: &'a str,
+    // Of the line within its payload, 1-indexed
+    pub line: usize,
+    pub text: &'a str,
+}
+
+impl fmt::Display for GrepMatch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let compile_id = self
+            .compile_id
+            .map_or("(unknown)".to_string(), |c| c.to_string());
+        write!(
+            f,
+            "{compile_id} {}:{}: {}",
+            self.artifact, self.line, self.text
+        )
+    }
+}
+
+// Call on_match with every payload line matching pattern, of the artifacts named artifact (all
+// of them if None), in log order.  The log is gone over a line at a time and payloads are never
+// kept whole, so huge ones cost no more than their longest line; lines that aren't UTF-8 are
+// matched with their bad bytes replaced.  on_match returns whether to go on.
+pub fn grep_log(
+    mut reader: impl BufRead,
+    pattern: &str,
+    artifact: Option<&str>,
+    mut on_match: impl FnMut(&GrepMatch) -> bool,
+) -> anyhow::Result<()> {
+    let pattern = Regex::new(pattern)?;
+    let re_glog = glog_regex()?;
+    // The searched entry whose payload lines come next, and how many of them there were so far
+    let mut current: Option<(Option<CompileId>, String)> = None;
+    let mut payload_line = 0;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+        let stripped = strip_ansi_escapes(line);
+        let line = stripped.as_deref().unwrap_or(line);
+        if let Some(text) = strip_payload_indent(line, DEFAULT_PAYLOAD_SPACE_INDENT) {
+            if let Some((compile_id, name)) = &current {
+                payload_line += 1;
+                if pattern.is_match(text) {
+                    let found = GrepMatch {
+                        compile_id: compile_id.as_ref(),
+                        artifact: name,
+                        line: payload_line,
+                        text,
+                    };
+                    if !on_match(&found) {
+                        return Ok(());
+                    }
+                }
+            }
+            continue;
+        }
+        current = None;
+        let Some(caps) = re_glog.captures(line) else {
+            continue;
+        };
+        let json = &line[caps.name("payload").unwrap().start()..];
+        let Ok(envelope) = serde_json::from_str::<Envelope>(json) else {
+            continue;
+        };
+        let name = artifact_name(&envelope);
+        if artifact.is_none_or(|a| a == name) {
+            current = Some((envelope.compile_id, name));
+            payload_line = 0;
+        }
+    }
+}
+
+// Parse every run of the log, or only config.run, without copying it into raw.log
+fn parse_runs(
+    input: &LogInput,
+    reader: impl BufRead,
+    config: &ParseConfig,
+) -> anyhow::Result<ParseOutput> {
+    let re_glog = glog_regex()?;
+    // The runs follow one another, so each is parsed as it's read, and only once it's over is it
+    // clear whether there are more
+    let mut lines = LogLines::new(Box::new(reader));
+    let mut runs = RunTracker::default();
+    match config.run {
+        Some(wanted) => {
+            let mut skipped = 0;
+            for run in 0..wanted {
+                skipped += skip_run(&mut lines, &mut runs, run, &re_glog);
+                if lines.peek().is_none() {
+                    bail!(
+                        "There is no run {} in {}, it has {} run(s)",
+                        wanted,
+                        input.path().display(),
+                        run + 1
+                    )
+                }
+            }
+            parse_run(input, &mut lines, &mut runs, config, wanted, skipped)
+        }
+        None => {
+            let first = parse_run(input, &mut lines, &mut runs, config, 0, 0)?;
+            if lines.peek().is_none() {
+                Ok(first)
+            } else {
+                // Each run gets its own subtree, so that their compile ids don't collide
+                let message =
+                    "Found several runs appended to the same log, writing each to its own run_N directory";
+                if config.progress_json {
+                    write_progress_event("info", serde_json::json!({ "message": message }));
+                } else {
+                    eprintln!("{message}");
+                }
+                let mut parsed = vec![first];
+                while lines.peek().is_some() {
+                    parsed.push(parse_run(
+                        input,
+                        &mut lines,
+                        &mut runs,
+                        config,
+                        parsed.len(),
+                        0,
+                    )?);
+                }
+                let num_runs = parsed.len();
+                let mut output = ParseOutput {
+                    files: Vec::new(),
+                    runs: Vec::new(),
+                };
+                for (run, parsed) in parsed.into_iter().enumerate() {
+                    let run_dir = PathBuf::from(format!("run_{run}"));
+                    output.files.extend(
+                        parsed
+                            .files
+                            .into_iter()
+                            .map(|(filename, content)| (run_dir.join(filename), content)),
+                    );
+                    output.runs.extend(parsed.runs);
+                }
+                let mut tt: TinyTemplate = TinyTemplate::new();
+                tt.add_formatter("format_unescaped", tinytemplate::format_unescaped);
+                tt.add_template("runs_index.html", TEMPLATE_RUNS_INDEX)?;
+                let runs_context = RunsIndexContext {
+                    css: CSS,
+                    custom_header_html: config.custom_header_html.clone(),
+                    runs: (0..num_runs).collect(),
+                };
+                let mut index = vec![(
+                    PathBuf::from("index.html"),
+                    tt.render("runs_index.html", &runs_context)?,
+                )];
+                if let Some(flags) = &config.provenance {
+                    tt.add_template("provenance.html", TEMPLATE_PROVENANCE)?;
+                    let footer = tt.render(
+                        "provenance.html",
+                        &Provenance::new(input.path(), flags, None, None),
+                    )?;
+                    add_footer(&mut index, &footer);
+                }
+                output.files.extend(index);
+                Ok(output)
+            }
+        }
+    }
+}
+
+// Parse the envelopes of run up to where the next run starts, see RunTracker.  other_run is how
+// many envelopes of the runs before it were skipped.
+fn parse_run(
+    input: &LogInput,
+    lines: &mut LogLines,
+    runs: &mut RunTracker,
+    config: &ParseConfig,
+    run: usize,
+    other_run: u64,
+) -> anyhow::Result<ParseOutput> {
+    let strict = config.strict;
+    let stream_payload_threshold = config
+        .stream_payload_threshold
+        .unwrap_or(STREAM_PAYLOAD_THRESHOLD);
+    // Plain text output has no HTML to highlight for
+    let highlight_max_bytes = (!config.plain_text).then(|| {
+        config
+            .highlight_max_bytes
+            .unwrap_or(DEFAULT_HIGHLIGHT_MAX_BYTES)
+    });
+    let trie_open_depth = config.trie_open_depth.unwrap_or(DEFAULT_TRIE_OPEN_DEPTH);
+    let trie_filter = config.trie_filter.as_deref().map(Regex::new).transpose()?;
+    let space_indent = config
+        .payload_space_indent
+        .unwrap_or(DEFAULT_PAYLOAD_SPACE_INDENT);
+    let file_size = input.len()?;
 
     // TODO: abstract out this spinner to not be part of the library
     // Instead, add a callback trait for CLIs to implement
-    let multi = MultiProgress::new();
-    let pb = multi.add(ProgressBar::new(file_size));
-    pb.set_style(ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} [{bytes_per_sec}] ({eta})")?
-        .progress_chars("#>-"));
-    let spinner = multi.add(ProgressBar::new_spinner());
+    let progress = Progress::new(file_size, config.progress_json)?;
+    let start_ansi_stripped = lines.ansi_stripped;
 
-    let reader = io::BufReader::new(file);
+    // Until a second run turns up, the first is written out as if it were the only one
+    let stream_dir = match config.run {
+        None if run > 0 => config
+            .output_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("run_{run}"))),
+        _ => config.output_dir.clone(),
+    };
+    // The payloads written to stream_dir as they were read
+    let mut streamed: Vec<PathBuf> = Vec::new();
 
-    let re_glog = Regex::new(concat!(
-        r"(?<level>[VIWEC])(?<month>\d{2})(?<day>\d{2}) ",
-        r"(?<hour>\d{2}):(?<minute>\d{2}):(?<second>\d{2}).(?<millisecond>\d{6}) ",
-        r"(?<thread>\d+)",
-        r"(?<pathname>[^:]+):(?<line>\d+)\] ",
-        r"(?<payload>.)"
-    ))?;
+    let re_glog = glog_regex()?;
 
     let mut stack_trie = StackTrieNode::default();
     let mut unknown_stack_trie = StackTrieNode::default();
+    let insert_stack = |trie: &mut StackTrieNode,
+                        stack: StackSummary,
+                        compile_id: Option<CompileId>,
+                        timestamp: Option<NaiveDateTime>| {
+        if config.trie_merge_lines {
+            trie.insert_merging_lines(stack, compile_id, timestamp);
+        } else {
+            trie.insert(stack, compile_id, timestamp);
+        }
+    };
 
-    let mut stats = Stats::default();
+    let mut stats = Stats {
+        other_run,
+        ..Default::default()
+    };
     let _mod_count: FxHashMap<String, i32> = FxHashMap::default();
 
-    let mut bytes_read: u64 = 0;
-
     // Some stuff for profiling
     let mut fastest_time = std::time::Duration::MAX;
     let mut slowest_time = std::time::Duration::ZERO;
 
-    let mut expected_rank: Option<Option<u32>> = None;
+    // Envelopes without a rank are always kept, see detect_rank
+    let mut expected_rank: Option<u32> = match config.rank {
+        Some(rank) => Some(rank),
+        None => detect_rank(
+            lines.look_ahead(RANK_DETECTION_WINDOW, RANK_DETECTION_MAX_BYTES),
+            &re_glog,
+        ),
+    };
+    let rank_detected = config.rank.is_none() && expected_rank.is_some();
+    if let Some(rank) = expected_rank.filter(|_| rank_detected) {
+        progress.info(format_args!("Detected rank: {rank}"));
+    }
+    let mut rank_counts: FxIndexMap<Option<u32>, u64> = FxIndexMap::default();
 
     // Each entry is a compile id => (link, rendered name, output number)
     // For files, link and rendered name are the same
     // For links, you can specify a custom name for the link
+    // Compiles are kept in the order they were first seen in the log, and their files in the
+    // order they were written, so the index is the same on every run over the same log
     let mut directory: FxIndexMap<Option<CompileId>, Vec<OutputFile>> = FxIndexMap::default();
+    // What every compile's envelopes say about it, for the reports joining them
+    let mut records: FxHashMap<CompileId, CompileRecord> = FxHashMap::default();
+    // The compiles of each frame that logged guards, in the order they did, so that a recompile
+    // can be tied to the guard that failed
+    let mut guarded_compiles: FxHashMap<Option<u32>, Vec<CompileId>> = FxHashMap::default();
+    // The first and last timestamp of the entries that were rendered
+    let mut log_span: Option<(NaiveDateTime, NaiveDateTime)> = None;
+    // When every graph break and recompile happened, for the timeline
+    let mut timeline_events: Vec<(NaiveDateTime, TimelineEvent)> = Vec::new();
+    // The LARGEST_ARTIFACTS largest payloads so far, smallest on top to be the next one out
+    let mut largest_artifacts: BinaryHeap<std::cmp::Reverse<LargeArtifact>> = BinaryHeap::new();
+    let mut payloads_total: (usize, u64) = (0, 0);
+    // How often each operator is called in the FX graphs of every compile
+    let mut ops_by_compile: FxIndexMap<Option<CompileId>, FxIndexMap<String, u64>> =
+        FxIndexMap::default();
+    // (graphs read, graphs that didn't look like a printed FX graph)
+    let mut ops_graphs: (usize, usize) = (0, 0);
+    // The compiles whose Dynamo graph normalizes to the same thing, by the MD5 of that
+    let mut graphs_by_hash: FxIndexMap<String, Vec<CompileId>> = FxIndexMap::default();
+    let span_gap = TimeDelta::milliseconds(
+        (config
+            .compile_span_gap_s
+            .unwrap_or(DEFAULT_COMPILE_SPAN_GAP_S)
+            * 1000.0) as i64,
+    );
 
     let mut metrics_index: CompilationMetricsIndex = FxIndexMap::default();
     let stack_index: RefCell<StackIndex> = RefCell::new(FxHashMap::default());
@@ -221,11 +3896,35 @@ pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOu
 
     // Store results in an output Vec<PathBuf, String>
     let mut output: Vec<(PathBuf, String)> = Vec::new();
+    let mut written: FxHashSet<PathBuf> = FxHashSet::default();
 
     let mut tt: TinyTemplate = TinyTemplate::new();
     tt.add_formatter("format_unescaped", tinytemplate::format_unescaped);
+    tt.add_formatter("format_bytes", format_bytes);
     tt.add_template("index.html", TEMPLATE_INDEX)?;
+    tt.add_template("directory_entry.html", TEMPLATE_DIRECTORY_ENTRY)?;
+    tt.add_template("compile_list.html", TEMPLATE_COMPILE_LIST)?;
+    tt.add_template("index_shard.html", TEMPLATE_INDEX_SHARD)?;
     tt.add_template("failures_and_restarts.html", TEMPLATE_FAILURES_AND_RESTARTS)?;
+    tt.add_template("stack_trie_condensed.html", TEMPLATE_CONDENSED_STACK_TRIE)?;
+    tt.add_template("by_file.html", TEMPLATE_BY_FILE)?;
+    tt.add_template("recompiles.html", TEMPLATE_RECOMPILES)?;
+    tt.add_template("guard_churn.html", TEMPLATE_GUARD_CHURN)?;
+    tt.add_template("attempts.html", TEMPLATE_ATTEMPTS)?;
+    tt.add_template("diff.html", TEMPLATE_DIFF)?;
+    tt.add_template("cache.html", TEMPLATE_CACHE)?;
+    tt.add_template("dashboard.html", TEMPLATE_DASHBOARD)?;
+    tt.add_template("source_files.html", TEMPLATE_SOURCE_FILES)?;
+    tt.add_template("top.html", TEMPLATE_TOP_COMPILES)?;
+    tt.add_template("timeline.html", TEMPLATE_TIMELINE)?;
+    tt.add_template("ranks.html", TEMPLATE_RANKS)?;
+    tt.add_template("compile.html", TEMPLATE_COMPILE)?;
+    tt.add_template("breadcrumbs.html", TEMPLATE_BREADCRUMBS)?;
+    tt.add_template("provenance.html", TEMPLATE_PROVENANCE)?;
+    tt.add_template("eager_fallbacks.html", TEMPLATE_EAGER_FALLBACKS)?;
+    tt.add_template("ops_histogram.html", TEMPLATE_OPS_HISTOGRAM)?;
+    tt.add_template("duplicate_graphs.html", TEMPLATE_DUPLICATE_GRAPHS)?;
+    tt.add_template("largest_artifacts.html", TEMPLATE_LARGEST_ARTIFACTS)?;
     tt.add_template("dynamo_guards.html", TEMPLATE_DYNAMO_GUARDS)?;
     tt.add_template("compilation_metrics.html", TEMPLATE_COMPILATION_METRICS)?;
     tt.add_template(
@@ -238,40 +3937,95 @@ pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOu
     )?;
 
     let mut unknown_fields: FxHashSet<String> = FxHashSet::default();
+    // Every envelope that could be read, by its kind, for EnvelopeKinds
+    let mut kind_counts: FxHashMap<&'static str, u64> = FxHashMap::default();
+    let mut unknown_kind: (u64, FxHashMap<String, u64>) = (0, FxHashMap::default());
+    let mut compile_stacks: Vec<CompileStack> = Vec::new();
+    let mut other_rank_stacks: FxIndexMap<u32, Vec<CompileStack>> = FxIndexMap::default();
+    // Every process interns its filenames on its own, so the same id can be a different file on
+    // each rank; other ranks' stacks are resolved against what their rank logged
+    let mut other_rank_intern_tables: FxHashMap<u32, FxHashMap<u32, String>> = FxHashMap::default();
+    // What every rank logged of every compile, for ranks.html
+    let mut rank_compiles: FxIndexMap<(Option<u32>, Option<u32>), FxHashMap<u32, RankCompile>> =
+        FxIndexMap::default();
+    let mut pending_metrics: Vec<PendingMetrics> = Vec::new();
+    let mut intern_conflicts: Vec<(u32, String, String)> = Vec::new();
+    INTERN_TABLE.with_borrow_mut(|intern_table| intern_table.clear());
+    clear_frame_strings();
+    let strip_rules = StripRules::new(&config.strip_prefixes)?;
+    let artifact_rules = match &config.artifact_rules {
+        Some(rules) => rules.iter().map(String::as_str).collect(),
+        None => DEFAULT_ARTIFACT_RULES.to_vec(),
+    }
+    .into_iter()
+    .map(ArtifactRule::parse)
+    .collect::<anyhow::Result<Vec<_>>>()?;
+    set_source_root(config.source_root.clone());
 
     let mut output_count = 0;
 
+    // (fail type, normalized reason) -> (count, first compile id, when it failed)
+    let mut failure_groups: FxIndexMap<(String, String), (usize, String, Option<NaiveDateTime>)> =
+        FxIndexMap::default();
     let mut breaks = RestartsAndFailuresContext {
         css: TEMPLATE_FAILURES_CSS,
         failures: Vec::new(),
+        failure_groups: Vec::new(),
         qps: TEMPLATE_QUERY_PARAM_SCRIPT,
     };
 
-    // NB: Sometimes, the log output we get from Logarithm stutters with a blank line.
-    // Filter them out, they're never valid (a blank line in payload will still be \t)
-    let mut iter = reader
-        .lines()
-        .enumerate()
-        .filter_map(|(i, l)| match l {
-            // 1-indexed line numbers please
-            Ok(l) if !l.is_empty() => Some((i + 1, l)),
-            _ => None,
-        })
-        .peekable();
-
-    let mut all_parsers = default_parsers(&tt, &config);
-    all_parsers.extend(config.custom_parsers);
+    let default_parsers = default_parsers(&tt, config, &strip_rules);
+    let custom_parsers: Vec<NamespacedParser> = config
+        .custom_parsers
+        .iter()
+        .map(|p| NamespacedParser(p.as_ref()))
+        .collect();
+    let all_parsers: Vec<&dyn StructuredLogParser> = default_parsers
+        .iter()
+        .map(Box::as_ref)
+        .chain(custom_parsers.iter().map(|p| p as &dyn StructuredLogParser))
+        .collect();
     let mut chromium_events: Vec<serde_json::Value> = Vec::new();
 
-    while let Some((lineno, line)) = iter.next() {
-        bytes_read += line.len() as u64;
-        pb.set_position(bytes_read);
-        spinner.set_message(format!("{:?}", stats));
+    let mut timestamps = input.timestamps();
+
+    // Hashes of the last DEDUPE_WINDOW envelope headers, for --dedupe
+    let mut recent_envelopes: FxHashSet<u64> = FxHashSet::default();
+    let mut recent_envelope_order: VecDeque<u64> = VecDeque::with_capacity(DEDUPE_WINDOW);
+
+    while let Some((lineno, line)) = lines.next_if(|(_, l)| runs.observe_line(l, &re_glog) == run) {
+        stats.ansi_stripped = lines.ansi_stripped - start_ansi_stripped;
+        progress.update(lines.bytes_read, &stats);
         //spinner.set_message(format!("{:?} {:?}", slowest_time, fastest_time));
         let start = Instant::now();
 
+        // The header carries the payload's MD5, so a repeated header is a repeat of the whole
+        // envelope, and we can drop the duplicate's payload lines without reading them into memory
+        if config.dedupe && strip_payload_indent(&line, space_indent).is_none() {
+            let hash = fxhash::hash64(&line);
+            if recent_envelopes.contains(&hash) {
+                while lines
+                    .next_if(|(_, l)| strip_payload_indent(l, space_indent).is_some())
+                    .is_some()
+                {}
+                progress.update(lines.bytes_read, &stats);
+                stats.duplicate_envelope += 1;
+                continue;
+            }
+            if recent_envelope_order.len() == DEDUPE_WINDOW {
+                if let Some(oldest) = recent_envelope_order.pop_front() {
+                    recent_envelopes.remove(&oldest);
+                }
+            }
+            recent_envelopes.insert(hash);
+            recent_envelope_order.push_back(hash);
+        }
+
         let Some(caps) = re_glog.captures(&line) else {
-            multi.suspend(|| eprintln!("Failed to parse glog prefix on line {}", lineno));
+            progress.error(format_args!(
+                "Failed to parse glog prefix on line {}",
+                lineno
+            ));
             stats.fail_glog += 1;
             continue;
         };
@@ -285,140 +4039,555 @@ pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOu
         }
         let payload = &line[caps.name("payload").unwrap().start()..];
 
-        let e = match serde_json::from_str::<Envelope>(payload) {
+        let mut e = match serde_json::from_str::<Envelope>(payload) {
             Ok(r) => r,
             Err(err) => {
-                multi.suspend(|| {
-                    eprintln!("Failed to parse metadata JSON: {}\n{:?}", payload, err);
-                });
+                progress.error(format_args!(
+                    "Failed to parse metadata JSON: {}\n{:?}",
+                    payload, err
+                ));
                 stats.fail_json += 1;
                 continue;
             }
         };
 
-        stats.unknown += e._other.len() as u64;
+        e.timestamp = timestamps.resolve_captures(&caps);
+
+        // Fields some parser handles aren't unknown, see StructuredLogParser::handles_field
+        let unknown_fields_of_e: Vec<String> = e
+            ._other
+            .keys()
+            .filter(|k| !all_parsers.iter().any(|p| p.handles_field(k)))
+            .cloned()
+            .collect();
+        stats.unknown += unknown_fields_of_e.len() as u64;
+
+        let kinds = e.kinds();
+        if kinds.len() > 1 {
+            progress.info(format_args!(
+                "Multiple artifact fields {:?} on line {}, only handling {}",
+                kinds, lineno, kinds[0]
+            ));
+            stats.multiple_kinds += 1;
+            e.retain_kind(kinds[0]);
+        } else if kinds.is_empty() && e._other.is_empty() {
+            stats.empty_envelope += 1;
+        }
+        match kinds.first() {
+            Some(kind) => *kind_counts.entry(*kind).or_default() += 1,
+            None if !unknown_fields_of_e.is_empty() => {
+                unknown_kind.0 += 1;
+                for k in &unknown_fields_of_e {
+                    *unknown_kind.1.entry(k.clone()).or_default() += 1;
+                }
+            }
+            None => (),
+        }
+
+        if e.compile_id.as_ref().is_some_and(CompileId::is_partial) {
+            if config.verbose {
+                progress.info(format_args!("Partial compile id on line {}", lineno));
+            }
+            stats.partial_compile_id += 1;
+        }
 
-        for k in e._other.keys() {
+        for k in &unknown_fields_of_e {
             unknown_fields.insert(k.clone());
             if config.verbose {
-                multi.suspend(|| eprintln!("Unknown field {}", k))
+                progress.info(format_args!("Unknown field {}", k))
             }
         }
 
         if let Some((s, i)) = e.str {
-            let mut intern_table = INTERN_TABLE.lock().unwrap();
-            intern_table.insert(i, s);
+            if let Some(rank) = e.rank.filter(|r| expected_rank.is_some_and(|x| x != *r)) {
+                other_rank_intern_tables
+                    .entry(rank)
+                    .or_default()
+                    .entry(i)
+                    .or_insert(s);
+                continue;
+            }
+            INTERN_TABLE.with_borrow_mut(|intern_table| match intern_table.get(&i) {
+                // Two different strings for one id usually means two processes were writing
+                // into the same file.  Keep the first rather than relabeling earlier frames.
+                Some(old) if *old != s => {
+                    progress.info(format_args!(
+                        "Conflicting intern table entry on line {}: {} is {:?}, ignoring {:?}",
+                        lineno, i, old, s
+                    ));
+                    stats.intern_conflicts += 1;
+                    intern_conflicts.push((i, old.clone(), s));
+                }
+                Some(_) => {}
+                None => {
+                    intern_table.insert(i, s);
+                }
+            });
             continue;
         };
 
         let mut payload = String::new();
+        // Also counts what was streamed out instead of kept in payload
+        let mut payload_bytes: u64 = 0;
+        let mut missing_payload = false;
+        // For the compile's record, see DataIssue
+        let mut issues: Vec<DataIssue> = Vec::new();
+        let issue_kind = || {
+            e.artifact.as_ref().map_or_else(
+                || kinds.first().copied().unwrap_or("entry").to_string(),
+                |a| a.name.clone(),
+            )
+        };
+        // Where a payload too big to hold in memory was written, relative to the output directory
+        let mut streamed_payload: Option<PathBuf> = None;
+        // For --keep-raw: the first lines of the payload as they were in the log, and how many
+        // there were
+        let mut raw_payload: Vec<String> = Vec::new();
+        let mut payload_lines = 0;
         if let Some(ref expect) = e.has_payload {
             let mut first = true;
+            let mut hasher = Md5::new();
+            let mut sink: Option<Box<dyn Write>> = None;
             while let Some((_payload_lineno, payload_line)) =
-                iter.next_if(|(_, l)| l.starts_with('\t'))
+                lines.next_if(|(_, l)| strip_payload_indent(l, space_indent).is_some())
             {
+                if config.keep_raw {
+                    payload_lines += 1;
+                    if payload_lines <= RAW_EXCERPT_PAYLOAD_LINES {
+                        raw_payload.push(payload_line.clone());
+                    }
+                }
+                let payload_line = strip_payload_indent(&payload_line, space_indent).unwrap();
                 // Careful! Distinguish between missing EOL and not
                 if !first {
-                    payload.push('\n');
+                    hasher.update("\n");
+                }
+                hasher.update(payload_line);
+                payload_bytes += payload_line.len() as u64 + !first as u64;
+                match sink {
+                    Some(ref mut sink) => {
+                        if !first {
+                            sink.write_all(b"\n")?;
+                        }
+                        sink.write_all(payload_line.as_bytes())?;
+                    }
+                    None => {
+                        if !first {
+                            payload.push('\n');
+                        }
+                        payload.push_str(payload_line);
+                    }
+                }
+                first = false;
+                // Past the threshold, stop collecting the payload and write it out as it comes
+                // instead.  No parser gets to see it then, so it's only listed, as unknown_<n>.txt
+                // if it's of no kind we recognize.
+                if let (None, Some(dir)) = (&sink, &stream_dir) {
+                    if payload.len() as u64 > stream_payload_threshold {
+                        let kind = kinds.first().copied().unwrap_or("unknown");
+                        let filename = PathBuf::from(compile_directory_name(&e.compile_id, lineno))
+                            .join(format!("{}_{}.txt", kind, output_count));
+                        let (filename, _) = dedup_filename(filename, &mut written);
+                        let out_file = dir.join(&filename);
+                        if let Some(parent) = out_file.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        streamed_payload = Some(filename);
+                        let mut writer: Box<dyn Write> = Box::new(create_output_file(&out_file)?);
+                        writer.write_all(payload.as_bytes())?;
+                        payload = String::new();
+                        sink = Some(writer);
+                    }
                 }
-                first = false;
-                payload.push_str(&payload_line[1..]);
             }
-            let mut hasher = Md5::new();
-            hasher.update(&payload);
+            if let Some(mut sink) = sink {
+                sink.flush()?;
+            }
             let hash = hasher.finalize();
             let mut expect_buf = [0u8; 16];
-            if base16ct::lower::decode(expect, &mut expect_buf).is_ok() {
-                if expect_buf != hash[..] {
-                    // TODO: error log
-                    stats.fail_payload_md5 += 1;
-                }
-            } else {
+            // No continuation lines is only fine if the payload really was empty; otherwise
+            // the writer probably died before getting the payload out
+            if first && expect != EMPTY_PAYLOAD_MD5 {
+                progress.error(format_args!(
+                    "Expected payload on line {} but got none",
+                    lineno
+                ));
+                stats.fail_missing_payload += 1;
+                missing_payload = true;
+                issues.push(DataIssue::TruncatedPayload {
+                    line: lineno,
+                    kind: issue_kind(),
+                    expected: expect.clone(),
+                });
+            } else if base16ct::lower::decode(expect, &mut expect_buf)
+                .map_or(true, |decoded| decoded != &hash[..])
+            {
+                // TODO: error log
                 stats.fail_payload_md5 += 1;
+                issues.push(DataIssue::Md5Mismatch {
+                    line: lineno,
+                    kind: issue_kind(),
+                    expected: expect.clone(),
+                    actual: format!("{hash:x}"),
+                });
             }
         }
 
-        match expected_rank {
-            Some(rank) => {
-                if rank != e.rank {
-                    stats.other_rank += 1;
-                    continue;
-                }
-            }
-            None => {
-                multi.suspend(|| {
-                    eprintln!("Detected rank: {:?}", e.rank);
-                });
-                expected_rank = Some(e.rank);
+        // A streamed payload was written before we knew whether we'd keep the envelope
+        let discard_streamed = |streamed: &Option<PathBuf>| {
+            if let (Some(filename), Some(dir)) = (streamed, &stream_dir) {
+                let _ = fs::remove_file(dir.join(filename));
             }
         };
 
+        *rank_counts.entry(e.rank).or_default() += 1;
+        if let (Some(rank), Some(c)) = (e.rank, &e.compile_id) {
+            let compile = rank_compiles
+                .entry((c.frame_id, c.frame_compile_id))
+                .or_default()
+                .entry(rank)
+                .or_default();
+            compile.attempts.insert(c.attempt);
+            if let Some(t) = e
+                .compilation_metrics
+                .as_ref()
+                .and_then(|m| m.entire_frame_compile_time_s)
+            {
+                *compile.time_s.get_or_insert(0.0) += t;
+            }
+        }
+        if let Some(rank) = e.rank {
+            match expected_rank {
+                Some(expected) => {
+                    if rank != expected {
+                        stats.other_rank += 1;
+                        // Nothing else from other ranks is rendered, but their stacks still go
+                        // in their own stack trie
+                        if let Some(stack) = e.dynamo_start.and_then(|m| m.stack) {
+                            other_rank_stacks
+                                .entry(rank)
+                                .or_default()
+                                .push(CompileStack {
+                                    compile_id: e.compile_id.clone(),
+                                    stack,
+                                    timestamp: e.timestamp,
+                                });
+                        }
+                        discard_streamed(&streamed_payload);
+                        continue;
+                    }
+                }
+                None => {
+                    progress.info(format_args!("Detected rank: {rank}"));
+                    expected_rank = Some(rank);
+                }
+            }
+        }
+
         stats.ok += 1;
 
+        if let (Some(c), Some(t)) = (&e.compile_id, e.timestamp) {
+            records.entry(c.clone()).or_default().span.add(t, span_gap);
+        }
+        if let (Some(c), true) = (&e.compile_id, config.keep_raw) {
+            records.entry(c.clone()).or_default().raw_excerpt.add(
+                lineno,
+                &line,
+                &raw_payload,
+                payload_lines,
+            );
+        }
+        if !unknown_fields_of_e.is_empty() {
+            let mut fields = unknown_fields_of_e;
+            fields.sort();
+            issues.push(DataIssue::UnknownFields {
+                line: lineno,
+                kind: issue_kind(),
+                fields,
+            });
+        }
+        if let (Some(c), false) = (&e.compile_id, issues.is_empty()) {
+            records
+                .entry(c.clone())
+                .or_default()
+                .issues
+                .append(&mut issues);
+        }
+        if let (Some(c), Some(kind)) = (&e.compile_id, kinds.first()) {
+            let artifacts = &mut records.entry(c.clone()).or_default().artifacts;
+            artifacts.insert(kind.to_string());
+            if let Some(a) = &e.artifact {
+                artifacts.insert(a.name.clone());
+            }
+            if let Some(Ok(serde_json::Value::Object(fields))) =
+                e.compilation_metrics.as_ref().map(serde_json::to_value)
+            {
+                artifacts.extend(
+                    fields
+                        .into_iter()
+                        .filter(|(_, v)| !v.is_null())
+                        .map(|(k, _)| k),
+                );
+            }
+        }
+        if let Some(t) = e.timestamp {
+            log_span = Some(log_span.map_or((t, t), |(start, end)| (start.min(t), end.max(t))));
+            if let Some(restarts) = e
+                .compilation_metrics
+                .as_ref()
+                .and_then(|m| m.restart_reasons.as_ref())
+            {
+                timeline_events.extend(restarts.iter().map(|_| (t, TimelineEvent::GraphBreak)));
+            }
+            if e.dynamo_start.is_some()
+                && e.compile_id
+                    .as_ref()
+                    .is_some_and(|c| c.attempt == 0 && c.frame_compile_id.is_some_and(|n| n > 0))
+            {
+                timeline_events.push((t, TimelineEvent::Recompile));
+            }
+        }
+
         // lol this clone, probably shouldn't use entry
         // TODO: output should be able to generate this without explicitly creating
         let compile_directory = directory.entry(e.compile_id.clone()).or_default();
 
-        for parser in &all_parsers {
-            run_parser(
+        // Linked to the file it ends up in, once that has been written
+        let mut large_artifact = (e.has_payload.is_some() && !missing_payload).then(|| {
+            payloads_total.0 += 1;
+            payloads_total.1 += payload_bytes;
+            LargeArtifact {
+                bytes: payload_bytes,
+                size: fmt_bytes(payload_bytes),
                 lineno,
-                parser,
-                &e,
-                &payload,
-                &mut output_count,
-                &mut output,
-                compile_directory,
-                &multi,
-                &mut stats,
-            )
+                kind: kinds.first().unwrap_or(&"unknown").to_string(),
+                compile_id: e
+                    .compile_id
+                    .as_ref()
+                    .map_or("(unknown)".to_string(), |c| c.to_string()),
+                anchor: e
+                    .compile_id
+                    .as_ref()
+                    .map_or("unknown".to_string(), |c| c.as_directory_name()),
+                url: None,
+            }
+        });
+        let mut keep_largest = |artifact: Option<LargeArtifact>| {
+            if let Some(artifact) = artifact {
+                largest_artifacts.push(std::cmp::Reverse(artifact));
+                if largest_artifacts.len() > LARGEST_ARTIFACTS {
+                    largest_artifacts.pop();
+                }
+            }
+        };
+
+        // Don't write an empty artifact that looks like real output, leave a stub saying what
+        // happened instead
+        if missing_payload {
+            let filename = compile_directory_name(&e.compile_id, lineno);
+            let filename = PathBuf::from(filename).join(format!(
+                "{}_missing_payload_{}.txt",
+                kinds.first().unwrap_or(&"unknown"),
+                output_count
+            ));
+            written.insert(filename.clone());
+            let stub = format!(
+                "Line {} declared a payload with MD5 {} but no payload lines followed it.\n\
+                 The process writing the log may have crashed before writing it.\n",
+                lineno,
+                e.has_payload.as_deref().unwrap_or("")
+            );
+            let filename_str = format!("{}", filename.to_string_lossy());
+            compile_directory.push(OutputFile {
+                url: filename_str.clone(),
+                name: filename_str,
+                number: output_count,
+                suffix: "⚠️".to_string(),
+                size: Some(stub.len() as u64),
+                highlighted: None,
+                preview: None,
+            });
+            output.push((filename, stub));
+            output_count += 1;
+            continue;
         }
 
-        if let Some(ref m) = e.compilation_metrics {
-            let copied_directory = compile_directory.clone();
-            let compile_id_dir: PathBuf = e
-                .compile_id
-                .as_ref()
-                .map_or(
-                    format!("unknown_{lineno}"),
-                    |CompileId {
-                         frame_id,
-                         frame_compile_id,
-                         attempt,
-                     }| { format!("{frame_id}_{frame_compile_id}_{attempt}") },
-                )
-                .into();
-            let parser: Box<dyn StructuredLogParser> =
-                Box::new(crate::parsers::CompilationMetricsParser {
-                    tt: &tt,
-                    stack_index: &stack_index,
-                    symbolic_shape_specialization_index: &symbolic_shape_specialization_index,
-                    output_files: &copied_directory,
-                    compile_id_dir: &compile_id_dir,
-                });
-            run_parser(
+        if let Some(filename) = streamed_payload {
+            let filename_str = format!("{}", filename.to_string_lossy());
+            compile_directory.push(OutputFile {
+                url: filename_str.clone(),
+                name: filename_str,
+                number: output_count,
+                suffix: "(too large to render)".to_string(),
+                size: Some(payload_bytes),
+                highlighted: None,
+                preview: None,
+            });
+            output_count += 1;
+            stats.streamed_payload += 1;
+            streamed.push(filename.clone());
+            if let Some(a) = large_artifact.as_mut() {
+                a.url = Some(filename.to_string_lossy().to_string());
+            }
+            keep_largest(large_artifact);
+            continue;
+        }
+
+        // Only now, since its MD5 is of what was logged
+        if config.normalize_artifacts {
+            payload = normalize_artifact(&payload);
+        }
+        let first_output = compile_directory.len();
+        for parser in &all_parsers {
+            let duplicates = run_parser(
                 lineno,
-                &parser,
+                *parser,
                 &e,
                 &payload,
                 &mut output_count,
                 &mut output,
+                &mut written,
                 compile_directory,
-                &multi,
+                &progress,
                 &mut stats,
+                highlight_max_bytes,
+                !config.no_previews,
             );
+            if let (Some(c), false) = (&e.compile_id, duplicates.is_empty()) {
+                records
+                    .entry(c.clone())
+                    .or_default()
+                    .issues
+                    .extend(duplicates);
+            }
+        }
+        if let Some(a) = large_artifact.as_mut() {
+            a.url = compile_directory.get(first_output).map(|o| o.url.clone());
+        }
+        keep_largest(large_artifact);
+
+        if kinds.first().is_some_and(|k| FX_GRAPH_KINDS.contains(k)) {
+            match fx_graph_calls(&payload) {
+                Some(calls) => {
+                    ops_graphs.0 += 1;
+                    let ops = ops_by_compile.entry(e.compile_id.clone()).or_default();
+                    for target in calls {
+                        *ops.entry(op_name(&target, config.ops_merge_overloads).to_string())
+                            .or_default() += 1;
+                    }
+                }
+                None => ops_graphs.1 += 1,
+            }
+        }
+
+        if let Some(c) = &e.compile_id {
+            if e.dynamo_output_graph.is_some() {
+                let graph = normalize_fx_graph(&normalize_artifact(&payload));
+                let hash = format!("{:x}", Md5::digest(graph));
+                let compiles = graphs_by_hash.entry(hash).or_default();
+                if !compiles.contains(c) {
+                    compiles.push(c.clone());
+                }
+            }
+            if e.dynamo_guards.is_some() {
+                // Unparseable guards were already counted by their parser
+                if let Ok(guards) = serde_json::from_str::<Vec<DynamoGuard>>(&payload) {
+                    let mut seen: FxHashSet<String> = FxHashSet::default();
+                    records.entry(c.clone()).or_default().guards = Some(
+                        guards
+                            .iter()
+                            .map(|g| normalize_guard(&g.code))
+                            .filter(|g| seen.insert(g.clone()))
+                            .collect(),
+                    );
+                    let guarded = guarded_compiles.entry(c.frame_id).or_default();
+                    if !guarded.contains(c) {
+                        guarded.push(c.clone());
+                    }
+                }
+            }
+            if e.artifact
+                .as_ref()
+                .is_some_and(|a| a.name == "recompile_reasons")
+            {
+                // A list of reasons, or just the one
+                let reasons = match serde_json::from_str::<serde_json::Value>(&payload) {
+                    Ok(serde_json::Value::Array(reasons)) => reasons
+                        .iter()
+                        .filter_map(|r| r.as_str().map(str::to_string))
+                        .collect(),
+                    Ok(serde_json::Value::String(reason)) => vec![reason],
+                    _ => payload.lines().map(str::to_string).collect(),
+                };
+                let guarded = guarded_compiles
+                    .get(&c.frame_id)
+                    .map_or(&[][..], |g| &g[..]);
+                let causes: Vec<RecompileCause> = reasons
+                    .iter()
+                    .filter(|r| !r.trim().is_empty())
+                    .map(|r| attribute_recompile(r, c, guarded, &records))
+                    .collect();
+                records
+                    .entry(c.clone())
+                    .or_default()
+                    .recompile_causes
+                    .extend(causes);
+            }
+            if let Some(event) = CacheEvent::parse(&e, &payload) {
+                records
+                    .entry(c.clone())
+                    .or_default()
+                    .cache_events
+                    .push(event);
+            }
+        }
+
+        let failed_backward = e
+            .bwd_compilation_metrics
+            .as_ref()
+            .is_some_and(|m| m.fail_type.is_some())
+            || e.aot_autograd_backward_compilation_metrics
+                .as_ref()
+                .is_some_and(|m| m.fail_type.is_some());
+        if let (true, Some(c)) = (failed_backward, &e.compile_id) {
+            records.entry(c.clone()).or_default().failed_elsewhere = true;
+        }
 
-            // compilation metrics is always the last output, since it just ran
-            let metrics_filename = format!(
-                "compilation_metrics_{}.html",
-                (output_count - 1).to_string(),
+        if let Some(ref m) = e.compilation_metrics {
+            let compile_id_dir: PathBuf = compile_directory_name(&e.compile_id, lineno).into();
+            // The metrics page embeds rendered stacks, so it's only rendered once the whole
+            // log has been read and every intern string is known.  Until then, hold its place
+            // in the output and in the compile directory.
+            let (metrics_filename, _) = dedup_filename(
+                compile_id_dir.join(format!("compilation_metrics_{}.html", output_count)),
+                &mut written,
             );
+            pending_metrics.push(PendingMetrics {
+                lineno,
+                rank: e.rank,
+                compile_id: e.compile_id.clone(),
+                metrics: m.clone(),
+                output_files: compile_directory.clone(),
+                compile_id_dir,
+                output_slot: output.len(),
+                directory_slot: compile_directory.len(),
+            });
+            output.push((metrics_filename.clone(), String::new()));
+            let metrics_filename = format!("{}", metrics_filename.to_string_lossy());
+            compile_directory.push(OutputFile {
+                url: metrics_filename.clone(),
+                name: metrics_filename.clone(),
+                number: output_count,
+                suffix: "".to_string(),
+                // Filled in once it has been rendered
+                size: None,
+                highlighted: None,
+                preview: None,
+            });
+            output_count += 1;
+
             let id = e.compile_id.clone().map_or("(unknown) ".to_string(), |c| {
-                format!(
-                    "<a href='{}/{}'>{cid}</a> ",
-                    compile_id_dir.display(),
-                    metrics_filename,
-                    cid = c,
-                )
+                format!("<a href='{}'>{cid}</a> ", metrics_filename, cid = c)
             });
             if let Some(rr) = m.restart_reasons.as_ref() {
                 for restart in rr {
@@ -442,27 +4611,52 @@ pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOu
                     f.clone(),
                     reason.clone(),
                     user_frame_filename.clone(),
-                    user_frame_lineno.clone(),
+                    user_frame_lineno,
                 ));
                 breaks
                     .failures
                     .push((id.clone(), format!("{failure_reason}")));
+                let group = failure_groups
+                    .entry((f.clone(), normalize_fail_reason(&reason)))
+                    .or_insert_with(|| (0, id.clone(), e.timestamp));
+                group.0 += 1;
             }
-            let mut cid = e.compile_id.clone();
-            if let Some(c) = cid.as_mut() {
-                c.attempt = 0;
-            }
-            metrics_index.entry(cid).or_default().push(m.clone());
+            metrics_index
+                .entry(e.compile_id.clone())
+                .or_default()
+                .push(m.clone());
         }
 
         if let Some(stack) = e.stack {
-            unknown_stack_trie.insert(stack.clone(), None);
+            insert_stack(&mut unknown_stack_trie, stack.clone(), None, e.timestamp);
         }
 
-        if let Some(_) = e.chromium_event {
+        if e.chromium_event.is_some() {
             chromium_events.push(serde_json::from_str(&payload)?);
         }
 
+        if let (Some(c), Some(m)) = (&e.compile_id, &e.create_symbol) {
+            if let Some(symbol) = m.symbol.clone().or(m.source.clone()) {
+                records
+                    .entry(c.clone())
+                    .or_default()
+                    .created_symbols
+                    .push(symbol);
+            }
+        }
+        if let (Some(c), Some(spec)) = (&e.compile_id, &e.symbolic_shape_specialization) {
+            let symbol = spec
+                .symbol
+                .clone()
+                .or_else(|| spec.sources.as_ref()?.first().cloned());
+            if let Some(symbol) = symbol {
+                records
+                    .entry(c.clone())
+                    .or_default()
+                    .specialized_symbols
+                    .push(symbol);
+            }
+        }
         if let Some(specialization) = e.symbolic_shape_specialization {
             symbolic_shape_specialization_index
                 .borrow_mut()
@@ -472,58 +4666,927 @@ pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOu
         }
 
         if let Some(m) = e.dynamo_start {
-            if let Some(mut stack) = m.stack {
-                maybe_remove_convert_frame_suffixes(&mut stack);
-                stack_index
-                    .borrow_mut()
-                    .insert(e.compile_id.clone(), stack.clone());
-                stack_trie.insert(stack, e.compile_id.clone());
+            if let Some(stack) = m.stack {
+                compile_stacks.push(CompileStack {
+                    compile_id: e.compile_id.clone(),
+                    stack,
+                    timestamp: e.timestamp,
+                });
             };
         };
     }
+    stats.ansi_stripped = lines.ansi_stripped - start_ansi_stripped;
+    if lines.bytes_read == 0 {
+        progress.info(format_args!(
+            "Warning: no log lines were read from {}, is it the right file?",
+            input.path().display()
+        ));
+    }
+    let multiple_runs = run > 0 || lines.peek().is_some();
+    if let (None, 0, true, Some(dir)) = (config.run, run, multiple_runs, &stream_dir) {
+        let run_dir = dir.join("run_0");
+        for filename in &streamed {
+            let to = run_dir.join(filename);
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(dir.join(filename), to)?;
+            // Unless something else is in there still, its compile's directory is gone now
+            if let Some(parent) = filename.parent().filter(|p| !p.as_os_str().is_empty()) {
+                let _ = fs::remove_dir(dir.join(parent));
+            }
+        }
+    }
+
+    // Everything from here on resolves intern ids, which is only reliable now that the whole
+    // log has been read: intern entries can be flushed after the stacks that use them
+    // For by_file.html: the compiles whose stack goes through each (simplified) file
+    let mut compiles_by_file: FxIndexMap<String, Vec<CompileId>> = FxIndexMap::default();
+    for CompileStack {
+        compile_id,
+        mut stack,
+        timestamp,
+    } in compile_stacks
+    {
+        maybe_remove_convert_frame_suffixes(&mut stack, &strip_rules);
+        if let Some(c) = &compile_id {
+            for frame in &stack {
+                let filename = frame.resolved_filename();
+                let compiles = compiles_by_file
+                    .entry(simplify_filename(&filename, &strip_rules).to_string())
+                    .or_default();
+                // A file can be in a stack more than once
+                if compiles.last() != Some(c) {
+                    compiles.push(c.clone());
+                }
+            }
+        }
+        stack_index
+            .borrow_mut()
+            .insert(compile_id.clone(), stack.clone());
+        insert_stack(&mut stack_trie, stack, compile_id, timestamp);
+    }
+    // Only this rank's compilation metrics are kept, so other ranks' compiles are untimed
+    stack_trie.set_compile_times(&metrics_index);
+    // The rendered rank's trie goes under its own rank too, so that every rank is in here
+    let mut stack_tries: FxIndexMap<Option<u32>, StackTrieNode> = FxIndexMap::default();
+    stack_tries.insert(expected_rank, stack_trie);
+    for (rank, stacks) in other_rank_stacks {
+        let intern_table = other_rank_intern_tables.remove(&rank).unwrap_or_default();
+        let trie = stack_tries.entry(Some(rank)).or_default();
+        for mut s in stacks {
+            for frame in &mut s.stack {
+                frame.resolve_with(&intern_table);
+            }
+            maybe_remove_convert_frame_suffixes(&mut s.stack, &strip_rules);
+            insert_stack(trie, s.stack, s.compile_id, s.timestamp);
+        }
+    }
+    stack_tries.sort_keys();
+    for trie in stack_tries.values_mut() {
+        trie.update_counts();
+    }
+    stats.unresolved_frames =
+        stack_tries[&expected_rank].unresolved_frames() + unknown_stack_trie.unresolved_frames();
+
+    for pending in pending_metrics {
+        let parser = crate::parsers::CompilationMetricsParser {
+            tt: &tt,
+            stack_index: &stack_index,
+            symbolic_shape_specialization_index: &symbolic_shape_specialization_index,
+            output_files: &pending.output_files,
+            compile_id_dir: &pending.compile_id_dir,
+            strip_rules: &strip_rules,
+            dynamic_dims: pending
+                .compile_id
+                .as_ref()
+                .and_then(|c| records.get(c))
+                .map(DynamicDims::new)
+                .filter(|d| d.candidates > 0),
+        };
+        let results = parser.parse(
+            pending.lineno,
+            Metadata::CompilationMetrics(&pending.metrics),
+            pending.rank,
+            &pending.compile_id,
+            "",
+        );
+        match results {
+            Ok(results) => {
+                for result in results {
+                    if let ParserOutput::File(_, out) = result {
+                        if let Some(files) = directory.get_mut(&pending.compile_id) {
+                            files[pending.directory_slot].size = Some(out.len() as u64);
+                        }
+                        output[pending.output_slot].1 = out;
+                    }
+                }
+            }
+            Err(err) => {
+                progress.error(format_args!("Parser {} failed: {err}", parser.name()));
+                stats.fail_parser += 1;
+            }
+        }
+    }
+
+    breaks.failure_groups = failure_groups
+        .into_iter()
+        .map(
+            |((fail_type, reason), (count, example, first_seen))| FailureGroup {
+                fail_type,
+                reason,
+                count,
+                example,
+                first_seen: first_seen
+                    .map_or(String::new(), |t| t.format("%m/%d %H:%M:%S").to_string()),
+            },
+        )
+        .collect();
+    // Stable, so that equally common failures stay in the order they first happened
+    breaks
+        .failure_groups
+        .sort_by_key(|g| std::cmp::Reverse(g.count));
     output.push((
         PathBuf::from("failures_and_restarts.html"),
         tt.render("failures_and_restarts.html", &breaks)?,
     ));
-    pb.finish_with_message("done");
-    spinner.finish();
+    // lines.bytes_read is only about right, but the end of a file is known exactly
+    let finished_at = match (lines.peek(), file_size) {
+        (None, Some(size)) => size,
+        _ => lines.bytes_read,
+    };
+    progress.finish(finished_at, &stats);
 
     output.push((
         PathBuf::from("chromium_events.json"),
         serde_json::to_string_pretty(&chromium_events).unwrap(),
     ));
+    // Every page from here on links to the compile pages, so where they go is settled first
+    let page_paths = compile_page_paths(&directory, &mut written);
+    let breadcrumbs = breadcrumb_compiles(&directory, &page_paths);
+    let report_title = if multiple_runs {
+        format!("tlparse report, run {run}")
+    } else {
+        "tlparse report".to_string()
+    };
+    // The artifacts get their bar before their sizes are used for anything; the pages of the
+    // report get theirs once they have been rendered, at the end
+    add_breadcrumbs(&mut output, &tt, &report_title, &breadcrumbs)?;
+    let provenance = config.provenance.as_ref().map(|flags| {
+        let rank_source = if config.rank.is_some() {
+            "selected"
+        } else if rank_detected {
+            "detected"
+        } else {
+            "first seen"
+        };
+        Provenance::new(
+            input.path(),
+            flags,
+            expected_rank,
+            expected_rank.map(|_| rank_source),
+        )
+    });
+    let footer = provenance
+        .as_ref()
+        .map(|p| tt.render("provenance.html", p))
+        .transpose()?;
+    if let Some(footer) = &footer {
+        add_footer(&mut output, footer);
+    }
+    let num_artifact_outputs = output.len();
+    let html_sizes: FxHashMap<String, u64> = output
+        .iter()
+        .filter(|(p, _)| p.extension().is_some_and(|e| e == "html"))
+        .map(|(p, html)| (p.to_string_lossy().into_owned(), html.len() as u64))
+        .collect();
+    for file in directory.values_mut().flatten() {
+        if let Some(size) = html_sizes.get(&file.url) {
+            file.size = Some(*size);
+        }
+    }
+    let re_stdlib = Regex::new(r"/lib/python[0-9.]*/")?;
+    let frame_label = |c: &CompileId| {
+        user_frame_label(
+            compile_stack(&stack_index.borrow(), c),
+            &|frame| {
+                is_user_frame(
+                    frame,
+                    &config.internal_frame_prefixes,
+                    &strip_rules,
+                    &re_stdlib,
+                )
+            },
+            &strip_rules,
+        )
+    };
+    // Every compile's artifacts as listed in the index, sizes included
+    let directory_json = CompileDirectoryJson {
+        provenance: provenance.as_ref(),
+        compiles: directory
+            .iter()
+            .map(|(c, files)| {
+                CompileDirectoryEntry::new(
+                    c.as_ref(),
+                    files,
+                    c.as_ref().and_then(|c| records.get(c)),
+                    c.as_ref().map(frame_label),
+                    metrics_index
+                        .get(c)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|m| m.entire_frame_compile_time_s)
+                        .reduce(|a, b| a + b),
+                )
+            })
+            .collect(),
+    };
+    output.push((
+        PathBuf::from("compile_directory.json"),
+        serde_json::to_string_pretty(&directory_json)?,
+    ));
+    // The whole trie, before any of the options that only change what the index shows.  Not
+    // pretty-printed, since indenting every level of a deep trie takes more space than the data.
+    output.push((
+        PathBuf::from("stack_trie.json"),
+        serde_json::to_string(&stack_tries[&expected_rank].serializable(&strip_rules))?,
+    ));
 
-    eprintln!("{:?}", stats);
-    if unknown_fields.len() > 0 {
-        eprintln!(
+    let (num_unknown_kind, unknown_kind_fields) = unknown_kind;
+    let envelope_kinds = EnvelopeKinds::new(&kind_counts, num_unknown_kind, unknown_kind_fields);
+    let wall_clock =
+        log_span.map(|span| WallClockTime::new(span, records.values().map(|r| &r.span)));
+    let timeline =
+        log_span.map(|span| Timeline::new(span, &timeline_events, config.timeline_bucket_s));
+    let compile_counts = CompileCounts::new(directory.keys().flatten(), &metrics_index, &records);
+    let top = top_compiles(
+        &directory,
+        &metrics_index,
+        &records,
+        &stack_index.borrow(),
+        &|frame| {
+            is_user_frame(
+                frame,
+                &config.internal_frame_prefixes,
+                &strip_rules,
+                &re_stdlib,
+            )
+        },
+        &strip_rules,
+        config.top_compiles.unwrap_or(DEFAULT_TOP_COMPILES),
+    );
+    output.push((
+        PathBuf::from("stats.json"),
+        serde_json::to_string_pretty(&StatsJson {
+            stats: &stats,
+            compiles: &compile_counts,
+            top_compiles: config.top_compiles.map(|_| top.as_slice()),
+            wall_clock: wall_clock.as_ref(),
+            envelope_kinds: &envelope_kinds,
+            timeline: timeline.as_ref(),
+            provenance: provenance.as_ref(),
+        })?,
+    ));
+    // The finish event has them
+    if !config.progress_json {
+        eprintln!("{:?}", stats);
+    }
+    // Largest first
+    let largest_artifacts: Vec<LargeArtifact> = largest_artifacts
+        .into_sorted_vec()
+        .into_iter()
+        .map(|a| a.0)
+        .collect();
+    if !largest_artifacts.is_empty() {
+        let mut largest = "Largest payloads:".to_string();
+        for a in largest_artifacts.iter().take(LARGEST_ARTIFACTS_PRINTED) {
+            largest += &format!(
+                "\n  {:>9}  {} {} (line {})",
+                a.size, a.compile_id, a.kind, a.lineno
+            );
+        }
+        progress.info(format_args!("{largest}"));
+        let context = LargestArtifactsContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            num_payloads: payloads_total.0,
+            total_size: fmt_bytes(payloads_total.1),
+            artifacts: &largest_artifacts,
+        };
+        output.push((
+            PathBuf::from("largest_artifacts.html"),
+            tt.render("largest_artifacts.html", &context)?,
+        ));
+    }
+    if rank_counts.len() > 1 {
+        rank_counts.sort_keys();
+        progress.info(format_args!(
+            "Envelopes per rank: {:?} (only rank {:?} was rendered)",
+            rank_counts, expected_rank
+        ));
+    }
+    if !unknown_fields.is_empty() {
+        let mut unknown_fields: Vec<_> = unknown_fields.into_iter().collect();
+        unknown_fields.sort();
+        progress.info(format_args!(
             "Unknown fields: {:?} (consider updating tlparse to render these)",
             unknown_fields
-        );
+        ));
     }
 
     let has_unknown_compile_id = directory.contains_key(&None);
 
+    // The index only shows what the options ask for, in every rank's trie
+    let statuses = CompileStatuses::new(&metrics_index);
+    for trie in stack_tries.values_mut() {
+        if config.trie_failed_only {
+            *trie =
+                trie.retain_terminals(&|compile_id| statuses.compile(compile_id) == "status-error");
+        }
+        if let Some(re) = &trie_filter {
+            *trie = trie.retain_paths(&|frame| {
+                re.is_match(simplify_filename(&frame.resolved_filename(), &strip_rules))
+                    || re.is_match(&frame.name.resolve())
+            });
+        }
+    }
+    // Model owners mostly care about their own code, so the condensed view only has that
+    let mut condensed_stack_trie = stack_tries[&expected_rank].retain_frames(&|frame| {
+        is_user_frame(
+            frame,
+            &config.internal_frame_prefixes,
+            &strip_rules,
+            &re_stdlib,
+        )
+    });
+    if !config.show_internal_frames {
+        let is_internal = |frame: &FrameSummary| {
+            is_internal_frame(frame, &config.internal_frame_prefixes, &strip_rules)
+        };
+        for trie in stack_tries.values_mut() {
+            *trie = trie.collapse_internal(&is_internal);
+        }
+        unknown_stack_trie = unknown_stack_trie.collapse_internal(&is_internal);
+    }
+    let trie_max_depth = config.trie_max_depth.unwrap_or(DEFAULT_TRIE_MAX_DEPTH);
+    if trie_max_depth > 0 {
+        for trie in stack_tries.values_mut() {
+            *trie = trie.truncate(trie_max_depth);
+        }
+        unknown_stack_trie = unknown_stack_trie.truncate(trie_max_depth);
+    }
+    if config.trie_order != TrieOrder::Insertion {
+        for trie in stack_tries.values_mut() {
+            trie.sort_children(config.trie_order);
+        }
+        unknown_stack_trie.sort_children(config.trie_order);
+        condensed_stack_trie.sort_children(config.trie_order);
+    }
+    // Compiles on other ranks have no index entry of their own, so they aren't linked
+    let render_trie = |trie: &StackTrieNode, metrics_index: Option<&CompilationMetricsIndex>| {
+        if config.plain_text {
+            trie.fmt_text(&strip_rules)
+        } else {
+            trie.fmt(metrics_index, trie_open_depth, &strip_rules)
+        }
+    };
+    let trie_text = config.print_trie.map(|style| {
+        stack_tries[&expected_rank].fmt_ansi(
+            Some(&metrics_index),
+            style.width,
+            style.color,
+            &strip_rules,
+        )
+    });
+    let mut other_rank_stack_tries = Vec::new();
+    for (rank, trie) in stack_tries.iter().filter(|(r, _)| **r != expected_rank) {
+        if let Some(rank) = rank {
+            other_rank_stack_tries.push((*rank, render_trie(trie, None)?));
+        }
+    }
+    let compile_time_summary = summarize_compile_times(&directory, &metrics_index, &records);
+    let phase_breakdown = phase_breakdown(
+        &directory,
+        &metrics_index,
+        &stack_index.borrow(),
+        &|frame| {
+            is_user_frame(
+                frame,
+                &config.internal_frame_prefixes,
+                &strip_rules,
+                &re_stdlib,
+            )
+        },
+        &strip_rules,
+    );
+    let recompiles = recompiles_per_frame(
+        &directory,
+        &records,
+        &stack_index.borrow(),
+        &|frame| {
+            is_user_frame(
+                frame,
+                &config.internal_frame_prefixes,
+                &strip_rules,
+                &re_stdlib,
+            )
+        },
+        &strip_rules,
+    );
+    let frames_at_recompile_limit = recompiles.iter().filter(|r| r.at_limit).count();
+    let dynamic_shapes = dynamic_shapes_summary(&directory, &records);
+    let fallbacks = eager_fallbacks(
+        &directory,
+        &metrics_index,
+        &records,
+        &stack_index.borrow(),
+        &|frame| {
+            is_user_frame(
+                frame,
+                &config.internal_frame_prefixes,
+                &strip_rules,
+                &re_stdlib,
+            )
+        },
+        &strip_rules,
+    );
+    let source_files = directory.keys().any(Option::is_some).then(|| {
+        compiles_by_source(
+            &directory,
+            &metrics_index,
+            &stack_index.borrow(),
+            &|frame| {
+                is_user_frame(
+                    frame,
+                    &config.internal_frame_prefixes,
+                    &strip_rules,
+                    &re_stdlib,
+                )
+            },
+            &strip_rules,
+            config,
+        )
+    });
+    let mut attempts = attempt_outcomes(&directory, &metrics_index, &records);
+    let diffs = attempt_diffs(
+        &directory,
+        &records,
+        &mut attempts,
+        &output,
+        &mut written,
+        config,
+    );
+    for (filename, context) in diffs {
+        let page = tt.render("diff.html", &context)?;
+        output.push((filename, page));
+    }
+    let cache = cache_dashboard(&records, &metrics_index, config);
+    let guard_churn = guard_churn(&directory, &records);
+    let loops = log_span.map_or_else(Vec::new, |span| {
+        recompile_loops(
+            &directory,
+            &records,
+            &stack_index.borrow(),
+            &|frame| {
+                is_user_frame(
+                    frame,
+                    &config.internal_frame_prefixes,
+                    &strip_rules,
+                    &re_stdlib,
+                )
+            },
+            &strip_rules,
+            span,
+            config,
+        )
+    });
+    for l in &loops {
+        progress.info(format_args!("Warning: frame {} looks stuck in a recompile loop ({} recompiles near the end of the log)",
+                l.frame_id, l.num_recompiles));
+    }
+    let mut duplicate_graphs: Vec<DuplicateGraphGroup> = graphs_by_hash
+        .into_values()
+        .filter(|compiles| compiles.len() > 1)
+        .map(|compiles| DuplicateGraphGroup::new(&compiles, &records))
+        .collect();
+    // Biggest first; stable, so groups the same size stay in the order they were first seen
+    duplicate_graphs.sort_by_key(|g| std::cmp::Reverse(g.compiles.len()));
+    // Only compiles that were attempted more than once get their outcomes in the index
+    let attempt_summaries: FxHashMap<&str, &str> = attempts
+        .iter()
+        .filter(|a| a.steps.len() > 1)
+        .flat_map(|a| {
+            a.steps
+                .iter()
+                .map(move |s| (s.anchor.as_str(), a.summary.as_str()))
+        })
+        .collect();
+    let no_record = CompileRecord::default();
+    let attempt_kinds: FxHashMap<&str, AttemptOutcome> = attempts
+        .iter()
+        .flat_map(|a| a.steps.iter().map(|s| (s.anchor.as_str(), s.kind)))
+        .collect();
+    let mut missing = missing_artifacts(&records, &metrics_index, &artifact_rules);
+    let ranks = compiles_per_rank(rank_compiles, expected_rank, config);
+    let num_missing_artifacts = missing.len();
+    let pages = compile_pages(
+        &directory,
+        &metrics_index,
+        &records,
+        &stack_index.borrow(),
+        &|frame| {
+            is_user_frame(
+                frame,
+                &config.internal_frame_prefixes,
+                &strip_rules,
+                &re_stdlib,
+            )
+        },
+        &strip_rules,
+        &attempts,
+        &missing,
+        &page_paths,
+        config,
+    );
+    // With --keep-raw, each compile's envelopes go next to its artifacts, linked from its page
+    let mut raw_logs: FxHashMap<CompileId, (String, String)> = FxHashMap::default();
+    for c in directory.keys().flatten() {
+        let Some(excerpt) = records
+            .get_mut(c)
+            .map(|r| std::mem::take(&mut r.raw_excerpt))
+        else {
+            continue;
+        };
+        let Some((first, last)) = excerpt.lines else {
+            continue;
+        };
+        let filename = PathBuf::from(format!("{}/raw.log", c.as_directory_name()));
+        let filename = dedup_filename(filename, &mut written).0;
+        raw_logs.insert(
+            c.clone(),
+            (
+                filename
+                    .file_name()
+                    .map_or(String::new(), |f| f.to_string_lossy().into_owned()),
+                format!(
+                    "{} entries, lines {first} to {last} of the log",
+                    excerpt.entries
+                ),
+            ),
+        );
+        output.push((filename, excerpt.text));
+    }
+    let mut page_urls: FxHashMap<CompileId, String> = FxHashMap::default();
+    for (c, filename, mut context) in pages {
+        context.raw_log = raw_logs.remove(&c);
+        let page = tt.render("compile.html", &context)?;
+        page_urls.insert(c, filename.to_string_lossy().into_owned());
+        output.push((filename, page));
+    }
+    let dashboard_context = dashboard(
+        &directory,
+        &metrics_index,
+        &records,
+        compile_time_summary.as_ref(),
+        log_span,
+        cache.as_ref(),
+        &breaks.failure_groups,
+        config,
+    );
+    output.push((
+        PathBuf::from("dashboard.html"),
+        tt.render("dashboard.html", &dashboard_context)?,
+    ));
+    let mut directory_entries: Vec<(CompileId, DirectoryEntry)> = Vec::new();
+    let mut unknown_compile = None;
+    let parsed_directory = directory.clone();
+    for (x, y) in directory.drain(..) {
+        let size = fmt_directory_size(&y);
+        let search = compile_search_text(
+            x.as_ref(),
+            &y,
+            &metrics_index,
+            &stack_index.borrow(),
+            &|frame| {
+                is_user_frame(
+                    frame,
+                    &config.internal_frame_prefixes,
+                    &strip_rules,
+                    &re_stdlib,
+                )
+            },
+            &strip_rules,
+        );
+        match x {
+            Some(c) => {
+                let span = records
+                    .get(&c)
+                    .and_then(CompileRecord::span)
+                    .map_or(String::new(), CompileSpan::fmt);
+                let anchor = c.as_directory_name();
+                let outcomes = attempt_summaries
+                    .get(anchor.as_str())
+                    .map_or(String::new(), |s| s.to_string());
+                let badges = records.get(&c).unwrap_or(&no_record).badges(
+                    attempt_kinds.get(anchor.as_str()).copied(),
+                    &missing.remove(&c).unwrap_or_default(),
+                );
+                let entry = DirectoryEntry {
+                    compile_id: c.to_string(),
+                    page: page_urls.remove(&c).unwrap_or_default(),
+                    severity: if badges.iter().any(Badge::is_failure) {
+                        "failure"
+                    } else if !badges.is_empty() {
+                        "warning"
+                    } else {
+                        ""
+                    },
+                    badges: badges.iter().map(|b| (b.label(), b.class())).collect(),
+                    anchor,
+                    files: Vec::new(),
+                    size,
+                    span,
+                    outcomes,
+                    search,
+                };
+                directory_entries.push((c, entry));
+            }
+            None => {
+                unknown_compile = Some(DirectoryEntry {
+                    compile_id: "(unknown)".to_string(),
+                    anchor: "unknown".to_string(),
+                    page: String::new(),
+                    files: y,
+                    size,
+                    span: String::new(),
+                    outcomes: String::new(),
+                    search,
+                    badges: Vec::new(),
+                    severity: "",
+                })
+            }
+        }
+    }
+    let frames = frame_groups(directory_entries, &frame_label);
+    let index_shard_size = config.index_shard_size.unwrap_or(DEFAULT_INDEX_SHARD_SIZE);
+    let num_listed: usize = frames.iter().map(|f| f.num_attempts).sum();
+    let mut index_shards = Vec::new();
+    let frames = if index_shard_size > 0 && num_listed > index_shard_size {
+        let shards = shard_frames(frames, index_shard_size);
+        let url = |i: usize| format!("index_{i:03}.html");
+        let num_shards = shards.len();
+        for (i, frames) in shards.into_iter().enumerate() {
+            let shard = IndexShard::new(url(i), &frames);
+            let context = IndexShardContext {
+                css: CSS,
+                custom_header_html: config.custom_header_html.clone(),
+                label: shard.label.clone(),
+                prev: i.checked_sub(1).map(url),
+                next: (i + 1 < num_shards).then(|| url(i + 1)),
+                compile_search: TEMPLATE_COMPILE_SEARCH,
+                compiles: CompileList {
+                    frames,
+                    unknown_compile: None,
+                },
+            };
+            output.push((
+                PathBuf::from(&shard.url),
+                tt.render("index_shard.html", &context)?,
+            ));
+            index_shards.push(shard);
+        }
+        Vec::new()
+    } else {
+        frames
+    };
     let index_context = IndexContext {
         css: CSS,
-        javascript: JAVASCRIPT,
-        custom_header_html: config.custom_header_html,
-        directory: directory
-            .drain(..)
-            .map(|(x, y)| (x.map_or("(unknown)".to_string(), |e| e.to_string()), y))
-            .collect(),
-        stack_trie_html: stack_trie.fmt(Some(&metrics_index)).unwrap(),
-        unknown_stack_trie_html: unknown_stack_trie.fmt(Some(&metrics_index)).unwrap(),
+        custom_header_html: config.custom_header_html.clone(),
+        compiles: CompileList {
+            frames,
+            unknown_compile,
+        },
+        index_shards,
+        index_shard_redirect: TEMPLATE_INDEX_SHARD_REDIRECT,
+        stack_trie_html: render_trie(&stack_tries[&expected_rank], Some(&metrics_index))?,
+        stack_trie_rank: expected_rank.map_or(String::new(), |r| r.to_string()),
+        other_rank_stack_tries,
+        unknown_stack_trie_html: render_trie(&unknown_stack_trie, Some(&metrics_index))?,
+        has_condensed_stack_trie: !condensed_stack_trie.is_empty(),
+        has_compiles_by_file: !compiles_by_file.is_empty(),
         has_unknown_stack_trie: !unknown_stack_trie.is_empty(),
         num_breaks: breaks.failures.len(),
         has_chromium_events: !chromium_events.is_empty(),
+        intern_conflicts,
+        multiple_runs,
+        num_ranks: ranks.as_ref().map_or(0, |r| r.ranks.len()),
+        num_divergent_rank_compiles: ranks.as_ref().map_or(0, |r| r.num_divergent),
+        trie_failed_only: config.trie_failed_only,
+        trie_filter: config.trie_filter.clone().unwrap_or_default(),
+        internal_frames_hidden: !config.show_internal_frames,
+        run,
+        compile_time_summary,
+        phase_breakdown,
+        wall_clock: wall_clock.as_ref().map(WallClockTime::fmt),
+        timeline_sparkline: timeline
+            .as_ref()
+            .filter(|t| t.num_events() > 0)
+            .map_or(String::new(), Timeline::sparkline),
+        timeline_late_events: timeline.as_ref().map_or(0, |t| t.late_events),
+        recompile_loops: loops,
+        num_missing_artifacts,
+        recompile_loop_window: fmt_wall_time(
+            config
+                .recompile_loop_window_s
+                .unwrap_or(DEFAULT_RECOMPILE_LOOP_WINDOW_S),
+        ),
+        envelope_kinds,
+        dynamic_shapes,
+        has_recompiles: !recompiles.is_empty(),
+        has_largest_artifacts: !largest_artifacts.is_empty(),
+        has_attempts: !attempts.is_empty(),
+        cache_hit_rate: cache
+            .as_ref()
+            .map_or(String::new(), |c| c.total.hit_rate.clone()),
+        has_ops_histogram: ops_graphs != (0, 0),
+        has_source_files: source_files.is_some(),
+        has_top_compiles: !top.is_empty(),
+        num_eager_fallbacks: fallbacks.len(),
+        num_duplicate_graphs: duplicate_graphs.len(),
+        has_guard_churn: !guard_churn.is_empty(),
+        num_churning_guards: guard_churn.iter().map(|f| f.churning.len()).sum(),
+        frames_at_recompile_limit,
+        cache_size_limit: DEFAULT_CACHE_SIZE_LIMIT,
+        trie_search: TEMPLATE_TRIE_SEARCH,
+        compile_search: TEMPLATE_COMPILE_SEARCH,
         qps: TEMPLATE_QUERY_PARAM_SCRIPT,
     };
     output.push((
         PathBuf::from("index.html"),
         tt.render("index.html", &index_context)?,
     ));
+    if !compiles_by_file.is_empty() {
+        let mut files: Vec<ByFileEntry> = compiles_by_file
+            .into_iter()
+            .map(|(filename, compiles)| ByFileEntry {
+                filename,
+                // Restarts don't count as more compiles
+                num_compiles: compiles
+                    .iter()
+                    .map(|c| (c.frame_id, c.frame_compile_id))
+                    .collect::<FxHashSet<_>>()
+                    .len(),
+                compiles: compiles
+                    .iter()
+                    .map(|c| (c.to_string(), c.as_directory_name()))
+                    .collect(),
+            })
+            .collect();
+        files.sort_by(|a, b| {
+            b.num_compiles
+                .cmp(&a.num_compiles)
+                .then_with(|| a.filename.cmp(&b.filename))
+        });
+        let context = ByFileContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            files,
+        };
+        output.push((
+            PathBuf::from("by_file.html"),
+            tt.render("by_file.html", &context)?,
+        ));
+    }
+    if !duplicate_graphs.is_empty() {
+        let context = DuplicateGraphsContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            groups: duplicate_graphs,
+        };
+        output.push((
+            PathBuf::from("duplicate_graphs.html"),
+            tt.render("duplicate_graphs.html", &context)?,
+        ));
+    }
+    if ops_graphs != (0, 0) {
+        let (context, csvs) = ops_histogram(ops_by_compile, ops_graphs, config);
+        output.push((
+            PathBuf::from("ops_histogram.html"),
+            tt.render("ops_histogram.html", &context)?,
+        ));
+        output.extend(csvs);
+    }
+    if !fallbacks.is_empty() {
+        let context = EagerFallbacksContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            cache_size_limit: DEFAULT_CACHE_SIZE_LIMIT,
+            frames: fallbacks,
+        };
+        output.push((
+            PathBuf::from("eager_fallbacks.html"),
+            tt.render("eager_fallbacks.html", &context)?,
+        ));
+    }
+    if !top.is_empty() {
+        let context = TopCompilesContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            compiles: top,
+        };
+        output.push((PathBuf::from("top.html"), tt.render("top.html", &context)?));
+    }
+    if let Some(context) = ranks {
+        output.push((
+            PathBuf::from("ranks.html"),
+            tt.render("ranks.html", &context)?,
+        ));
+    }
+    if let Some(timeline) = timeline.as_ref().filter(|t| t.num_events() > 0) {
+        let context = TimelineContext::new(timeline, CSS, config.custom_header_html.clone());
+        output.push((
+            PathBuf::from("timeline.html"),
+            tt.render("timeline.html", &context)?,
+        ));
+    }
+    if let Some((context, csvs)) = source_files {
+        output.push((
+            PathBuf::from("source_files.html"),
+            tt.render("source_files.html", &context)?,
+        ));
+        output.extend(csvs);
+    }
+    if let Some(context) = cache {
+        output.push((
+            PathBuf::from("cache.html"),
+            tt.render("cache.html", &context)?,
+        ));
+    }
+    if !attempts.is_empty() {
+        let context = AttemptsContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            compiles: &attempts,
+        };
+        output.push((
+            PathBuf::from("attempts.html"),
+            tt.render("attempts.html", &context)?,
+        ));
+    }
+    if !guard_churn.is_empty() {
+        let context = GuardChurnContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            frames: guard_churn,
+        };
+        output.push((
+            PathBuf::from("guard_churn.html"),
+            tt.render("guard_churn.html", &context)?,
+        ));
+    }
+    if !recompiles.is_empty() {
+        let context = RecompilesContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            cache_size_limit: DEFAULT_CACHE_SIZE_LIMIT,
+            frames: recompiles,
+        };
+        output.push((
+            PathBuf::from("recompiles.html"),
+            tt.render("recompiles.html", &context)?,
+        ));
+    }
+    if !condensed_stack_trie.is_empty() {
+        let stack_trie_html = if config.plain_text {
+            condensed_stack_trie.fmt_text(&strip_rules)?
+        } else {
+            condensed_stack_trie.fmt_linking_to(
+                Some(&metrics_index),
+                trie_open_depth,
+                "index.html",
+                &strip_rules,
+            )?
+        };
+        let context = CondensedStackTrieContext {
+            css: CSS,
+            custom_header_html: config.custom_header_html.clone(),
+            stack_trie_html,
+        };
+        output.push((
+            PathBuf::from("stack_trie_condensed.html"),
+            tt.render("stack_trie_condensed.html", &context)?,
+        ));
+    }
 
-    output.push((PathBuf::from("raw.log"), fs::read_to_string(path)?));
+    add_breadcrumbs(
+        &mut output[num_artifact_outputs..],
+        &tt,
+        &report_title,
+        &breadcrumbs,
+    )?;
+    if let Some(footer) = &footer {
+        add_footer(&mut output[num_artifact_outputs..], footer);
+    }
 
     // other_rank is included here because you should only have logs from one rank when
     // configured properly
@@ -534,6 +5597,9 @@ pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOu
             + stats.other_rank
             + stats.fail_dynamo_guards_json
             + stats.fail_parser
+            + stats.multiple_kinds
+            + stats.fail_missing_payload
+            + stats.intern_conflicts
             > 0)
     {
         // Report something went wrong
@@ -544,5 +5610,78 @@ pub fn parse_path(path: &PathBuf, config: ParseConfig) -> anyhow::Result<ParseOu
         return Err(anyhow!("Some log entries did not have compile id"));
     }
 
-    Ok(output)
+    Ok(ParseOutput {
+        files: output,
+        runs: vec![ParsedRun {
+            run,
+            stats,
+            directory: parsed_directory,
+            stack_trie: stack_tries.swap_remove(&expected_rank).unwrap_or_default(),
+            trie_text,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(filename: &str, line: i32, name: &str) -> FrameSummary {
+        FrameSummary {
+            filename: FrameFilename::Uninterned(FrameStr::new(filename)),
+            line,
+            name: FrameStr::new(name),
+        }
+    }
+
+    fn label(stack: Option<&StackSummary>) -> String {
+        let re_stdlib = Regex::new(r"/lib/python[0-9.]*/").unwrap();
+        let strip_rules = StripRules::default();
+        user_frame_label(
+            stack,
+            &|f| is_user_frame(f, &[], &strip_rules, &re_stdlib),
+            &strip_rules,
+        )
+    }
+
+    #[test]
+    fn test_user_frame_label() {
+        let stack = vec![
+            frame("/home/me/train.py", 12, "main"),
+            frame("/home/me/model/transformer.py", 288, "attention_forward"),
+            frame(
+                "/usr/lib/python3.10/site-packages/torch/_dynamo/eval_frame.py",
+                600,
+                "_fn",
+            ),
+        ];
+        assert_eq!(
+            label(Some(&stack)),
+            "/home/me/model/transformer.py:288 in attention_forward"
+        );
+        assert_eq!(label(None), "(no stack)");
+        assert_eq!(label(Some(&Vec::new())), "(no stack)");
+    }
+
+    #[test]
+    fn test_user_frame_label_all_internal() {
+        // Stacks entirely in torch are labeled by their innermost frame, and count under no file
+        let stack = vec![
+            frame("/usr/lib/python3.10/contextlib.py", 79, "inner"),
+            frame(
+                "/usr/lib/python3.10/site-packages/torch/_dynamo/eval_frame.py",
+                600,
+                "_fn",
+            ),
+            frame(
+                "/usr/lib/python3.10/site-packages/torch/_inductor/graph.py",
+                30,
+                "run",
+            ),
+        ];
+        let re_stdlib = Regex::new(r"/lib/python[0-9.]*/").unwrap();
+        let strip_rules = StripRules::default();
+        assert!(user_frame(&stack, &|f| is_user_frame(f, &[], &strip_rules, &re_stdlib)).is_none());
+        assert_eq!(label(Some(&stack)), "torch/_inductor/graph.py:30 in run");
+    }
 }
@@ -0,0 +1,203 @@
+// Run-specific noise in graphs and output code, canonicalized so that the same artifact from two
+// runs is the same text: object addresses, temp paths, cache hashes and names with a global
+// counter in them.  The rules only touch what has the shape PyTorch gives these (an address is
+// in a repr, a kernel counter ends a kernel name), so that constants and paths in user code are
+// left alone.
+
+use fxhash::FxHashMap;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+enum Rewrite {
+    Replace(&'static str),
+    // Group 1 is a name and group 2 its counter, which becomes 0, 1, ... for that name in order
+    // of appearance, so that the third and fifth kernels of a run are the first and second of
+    // another
+    Renumber,
+}
+
+struct NormalizeRule {
+    // For the tests, which pick rules by it
+    #[allow(dead_code)]
+    name: &'static str,
+    pattern: Regex,
+    rewrite: Rewrite,
+}
+
+// In the order they're applied
+static NORMALIZE_RULES: Lazy<Vec<NormalizeRule>> = Lazy::new(|| {
+    let rule = |name, pattern, rewrite| NormalizeRule {
+        name,
+        pattern: Regex::new(pattern).unwrap(),
+        rewrite,
+    };
+    vec![
+        // Inductor's cache and kernel files, /tmp/torchinductor_user/xy/cxy....py; macOS keeps
+        // them under /var/folders/../T.  Only from where a path starts, so that ~/tmp isn't one.
+        rule(
+            "temp_path",
+            r#"(^|[\s'"(=,])(?:/tmp|/var/tmp|(?:/private)?/var/folders/[^/\s]+/[^/\s]+/T)/[^\s'"(),:;]+"#,
+            Rewrite::Replace("${1}/tmp/…"),
+        ),
+        // Addresses in reprs, <function f at 0x7f3a...> or <Stream cuda_stream=0x55d1...>
+        rule(
+            "object_address",
+            r"(<[^<>\n]*?(?:\bat |=))0x[0-9a-fA-F]{6,}",
+            Rewrite::Replace("${1}0x…"),
+        ),
+        // FX graph cache keys and inductor's code hashes are 52 characters of base32
+        rule("cache_hash", r"\b[a-z0-9]{52}\b", Rewrite::Replace("…")),
+        // triton_poi_fused_add_mul_3: the counter is global to the process
+        rule(
+            "kernel_counter",
+            r"\b(triton_(?:poi|per|red|tem|for|unk)_\w*?_)(\d+)\b",
+            Rewrite::Renumber,
+        ),
+        // Names dynamo generates with a global counter
+        rule(
+            "generated_name_counter",
+            r"\b(__compiled_fn_|__resume_at_\d+_|__import_)(\d+)\b",
+            Rewrite::Renumber,
+        ),
+    ]
+});
+
+fn apply_rule(rule: &NormalizeRule, text: &str) -> String {
+    match rule.rewrite {
+        Rewrite::Replace(with) => rule.pattern.replace_all(text, with).into_owned(),
+        Rewrite::Renumber => {
+            let mut numbers: FxHashMap<(String, String), usize> = FxHashMap::default();
+            let mut next: FxHashMap<String, usize> = FxHashMap::default();
+            rule.pattern
+                .replace_all(text, |c: &Captures| {
+                    let key = (c[1].to_string(), c[2].to_string());
+                    let n = *numbers.entry(key).or_insert_with(|| {
+                        let n = next.entry(c[1].to_string()).or_default();
+                        *n += 1;
+                        *n - 1
+                    });
+                    format!("{}{n}", &c[1])
+                })
+                .into_owned()
+        }
+    }
+}
+
+// An artifact's text with every rule applied, see ParseConfig::normalize_artifacts
+pub fn normalize_artifact(text: &str) -> String {
+    NORMALIZE_RULES
+        .iter()
+        .fold(text.to_string(), |text, rule| apply_rule(rule, &text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(name: &str, text: &str) -> String {
+        let rule = NORMALIZE_RULES.iter().find(|r| r.name == name).unwrap();
+        apply_rule(rule, text)
+    }
+
+    #[test]
+    fn test_temp_path() {
+        assert_eq!(
+            apply(
+                "temp_path",
+                "# kernel path: /tmp/torchinductor_alice/ab/cabk2.py"
+            ),
+            "# kernel path: /tmp/…"
+        );
+        assert_eq!(
+            apply("temp_path", "open('/var/folders/x1/y2/T/t.py')"),
+            "open('/tmp/…')"
+        );
+        // Paths elsewhere, and names that merely start with tmp
+        for kept in ["/home/alice/tmp/model.py", "/tmpfs/x", "tmp/x.py"] {
+            assert_eq!(apply("temp_path", kept), kept);
+        }
+    }
+
+    #[test]
+    fn test_object_address() {
+        assert_eq!(
+            apply(
+                "object_address",
+                "fn = <function forward at 0x7f3a2b4c5d60>"
+            ),
+            "fn = <function forward at 0x…>"
+        );
+        assert_eq!(
+            apply(
+                "object_address",
+                "<torch.cuda.Stream cuda_stream=0x55d1c0ffee00>"
+            ),
+            "<torch.cuda.Stream cuda_stream=0x…>"
+        );
+        // Hex constants in code are values, not addresses
+        for kept in [
+            "mask = x & 0xFFFFFFFFFFFF",
+            "seed = 0x7f3a2b4c5d60",
+            "<0xdeadbeef>",
+        ] {
+            assert_eq!(apply("object_address", kept), kept);
+        }
+    }
+
+    #[test]
+    fn test_cache_hash() {
+        let key = "fqs7wetfpsz3bcezsaewltuhffaagjclmgcxsqxejpbqpnrjgzmz";
+        assert_eq!(key.len(), 52);
+        assert_eq!(apply("cache_hash", &format!("key = '{key}'")), "key = '…'");
+        assert_eq!(apply("cache_hash", &key[1..]), &key[1..]);
+        assert_eq!(apply("cache_hash", &format!("{key}x")), format!("{key}x"));
+    }
+
+    #[test]
+    fn test_kernel_counter() {
+        assert_eq!(
+            apply(
+                "kernel_counter",
+                "triton_poi_fused_add_7.run(buf0)\ntriton_red_fused_sum_9.run(buf1)\ntriton_poi_fused_add_7.run(buf2)\ntriton_poi_fused_mul_12.run(buf3)"
+            ),
+            "triton_poi_fused_add_0.run(buf0)\ntriton_red_fused_sum_0.run(buf1)\ntriton_poi_fused_add_0.run(buf2)\ntriton_poi_fused_mul_0.run(buf3)"
+        );
+        for kept in [
+            "my_triton_kernel_3",
+            "triton_helpers.maximum",
+            "buf12 = empty(...)",
+        ] {
+            assert_eq!(apply("kernel_counter", kept), kept);
+        }
+    }
+
+    #[test]
+    fn test_generated_name_counter() {
+        assert_eq!(
+            apply(
+                "generated_name_counter",
+                "__compiled_fn_11(x); __resume_at_30_12(y); __compiled_fn_13(z); __compiled_fn_11(x)"
+            ),
+            "__compiled_fn_0(x); __resume_at_30_0(y); __compiled_fn_1(z); __compiled_fn_0(x)"
+        );
+        assert_eq!(apply("generated_name_counter", "layer_11"), "layer_11");
+    }
+
+    #[test]
+    fn test_normalize_artifact() {
+        // Two runs' output code, differing only in noise
+        let code = |n: usize, dir: &str| {
+            format!(
+                "# kernel path: /tmp/torchinductor_{dir}/x/c.py\ntriton_poi_fused_add_{n}.run(arg0_1, 0xFF)\nstream = <Stream cuda_stream=0x55d1c0ffee{n:02}>"
+            )
+        };
+        assert_eq!(
+            normalize_artifact(&code(3, "a")),
+            normalize_artifact(&code(17, "b"))
+        );
+        assert_eq!(
+            normalize_artifact(&code(3, "a")),
+            "# kernel path: /tmp/…\ntriton_poi_fused_add_0.run(arg0_1, 0xFF)\nstream = <Stream cuda_stream=0x…>"
+        );
+    }
+}
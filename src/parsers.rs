@@ -1,16 +1,27 @@
-use crate::templates::TEMPLATE_QUERY_PARAM_SCRIPT;
+use crate::templates::{TEMPLATE_GUARDS_TABLE_SCRIPT, TEMPLATE_QUERY_PARAM_SCRIPT};
 use crate::{types::*, ParseConfig};
+use fxhash::{FxHashMap, FxHashSet};
 use html_escape::encode_text;
+use regex::Regex;
 use std::cell::RefCell;
 use std::ffi::{OsStr, OsString};
+use std::fmt::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use tinytemplate::TinyTemplate;
 
 use serde_json::Value;
 
+use once_cell::sync::Lazy;
+use syntect::easy::HighlightLines;
 use syntect::highlighting::ThemeSet;
+use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
 use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+// Loading these takes a while, so it's done once for every artifact that gets highlighted
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
 
 pub enum ParserOutput {
     File(PathBuf, String),       // File to be saved on disk
@@ -45,6 +56,92 @@ pub trait StructuredLogParser {
 
     // Name of the parser, for error logging
     fn name(&self) -> &'static str;
+
+    // Whether this parser handles an envelope field tlparse doesn't know (see Metadata::Other),
+    // which then isn't reported as unknown
+    fn handles_field(&self, _field: &str) -> bool {
+        false
+    }
+}
+
+// A custom parser (ParseConfig::custom_parsers), with its files put in a directory named after
+// it, so that they can't collide with tlparse's own or another parser's
+pub struct NamespacedParser<'p>(pub &'p dyn StructuredLogParser);
+
+impl StructuredLogParser for NamespacedParser<'_> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+    fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
+        self.0.get_metadata(e)
+    }
+    fn handles_field(&self, field: &str) -> bool {
+        self.0.handles_field(field)
+    }
+    fn parse<'e>(
+        &self,
+        lineno: usize,
+        metadata: Metadata<'e>,
+        rank: Option<u32>,
+        compile_id: &Option<CompileId>,
+        payload: &str,
+    ) -> anyhow::Result<ParserResults> {
+        let namespace = self.name();
+        let results = self.0.parse(lineno, metadata, rank, compile_id, payload)?;
+        Ok(results
+            .into_iter()
+            .map(|result| match result {
+                ParserOutput::File(f, out) => {
+                    let dir = f.parent().unwrap_or(Path::new("")).join(namespace);
+                    ParserOutput::File(dir.join(f.file_name().unwrap_or_default()), out)
+                }
+                ParserOutput::GlobalFile(f, out) => {
+                    ParserOutput::GlobalFile(Path::new(namespace).join(f), out)
+                }
+                link => link,
+            })
+            .collect())
+    }
+}
+
+/**
+ * Writes the payload of every envelope with a field tlparse doesn't know to a file of its
+ * compile, for organization-specific log entries (--custom-artifact FIELD=FILENAME).  Envelopes
+ * without a payload get the field's value, as JSON.
+ */
+pub struct EnvelopeFieldParser {
+    pub field: String,
+    pub filename: String,
+}
+
+impl StructuredLogParser for EnvelopeFieldParser {
+    fn name(&self) -> &'static str {
+        "custom_artifact"
+    }
+    fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
+        e._other.get(&self.field).map(Metadata::Other)
+    }
+    fn handles_field(&self, field: &str) -> bool {
+        field == self.field
+    }
+    fn parse<'e>(
+        &self,
+        lineno: usize,
+        metadata: Metadata<'e>,
+        _rank: Option<u32>,
+        compile_id: &Option<CompileId>,
+        payload: &str,
+    ) -> anyhow::Result<ParserResults> {
+        match (metadata, payload) {
+            (Metadata::Other(value), "") => simple_file_output(
+                &self.filename,
+                lineno,
+                compile_id,
+                &serde_json::to_string_pretty(value)?,
+            ),
+            _ => simple_file_output(&self.filename, lineno, compile_id, payload),
+        }
+    }
 }
 
 // Takes a filename and a payload and writes that payload into a the file
@@ -54,19 +151,8 @@ fn simple_file_output(
     compile_id: &Option<CompileId>,
     payload: &str,
 ) -> anyhow::Result<ParserResults> {
-    let compile_id_dir: PathBuf = compile_id
-        .as_ref()
-        .map_or(
-            format!("unknown_{lineno}"),
-            |CompileId {
-                 frame_id,
-                 frame_compile_id,
-                 attempt,
-             }| { format!("{frame_id}_{frame_compile_id}_{attempt}") },
-        )
-        .into();
-    let subdir = PathBuf::from(compile_id_dir);
-    let f = subdir.join(filename);
+    let compile_id_dir: PathBuf = compile_directory_name(compile_id, lineno).into();
+    let f = compile_id_dir.join(filename);
     Ok(Vec::from([ParserOutput::File(f, String::from(payload))]))
 }
 
@@ -93,7 +179,7 @@ impl StructuredLogParser for SentinelFileParser {
         self.filename
     }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
-        (self.get_sentinel)(e).map(|m| Metadata::Empty(m))
+        (self.get_sentinel)(e).map(Metadata::Empty)
     }
     fn parse<'e>(
         &self,
@@ -121,7 +207,7 @@ impl StructuredLogParser for GraphDumpParser {
         "graph_dump" // ToDO: more specific?
     }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
-        e.graph_dump.as_ref().map(|m| Metadata::GraphDump(m))
+        e.graph_dump.as_ref().map(Metadata::GraphDump)
     }
     fn parse<'e>(
         &self,
@@ -153,7 +239,7 @@ impl StructuredLogParser for DynamoOutputGraphParser {
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.dynamo_output_graph
             .as_ref()
-            .map(|m| Metadata::DynamoOutputGraph(m))
+            .map(Metadata::DynamoOutputGraph)
     }
     fn parse<'e>(
         &self,
@@ -169,13 +255,14 @@ impl StructuredLogParser for DynamoOutputGraphParser {
 
 pub struct DynamoGuardParser<'t> {
     tt: &'t TinyTemplate<'t>,
+    strip_rules: &'t StripRules,
 }
 impl StructuredLogParser for DynamoGuardParser<'_> {
     fn name(&self) -> &'static str {
         "dynamo_guards"
     }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
-        e.dynamo_guards.as_ref().map(|m| Metadata::Empty(m))
+        e.dynamo_guards.as_ref().map(Metadata::Empty)
     }
     fn parse<'e>(
         &self,
@@ -186,9 +273,43 @@ impl StructuredLogParser for DynamoGuardParser<'_> {
         payload: &str,
     ) -> anyhow::Result<ParserResults> {
         let filename = format!("{}.html", self.name());
-        let guards = serde_json::from_str::<Vec<DynamoGuard>>(payload)?;
+        let guards: Vec<GuardRow> = serde_json::from_str::<Vec<DynamoGuard>>(payload)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, g)| GuardRow {
+                number: i + 1,
+                kind: guard_kind(&g.code),
+                preview: match g.code.char_indices().nth(GUARD_PREVIEW_CHARS) {
+                    Some((end, _)) => format!("{}…", &g.code[..end]),
+                    None => String::new(),
+                },
+                source: g
+                    .user_stack
+                    .as_ref()
+                    .or(g.stack.as_ref())
+                    .and_then(|stack| stack.last())
+                    .map_or(String::new(), |frame| {
+                        format!(
+                            "{}:{} in {}",
+                            simplify_filename(&frame.resolved_filename(), self.strip_rules),
+                            frame.line,
+                            frame.name
+                        )
+                    }),
+                code: g.code,
+            })
+            .collect();
+        let mut kinds: FxHashMap<&'static str, usize> = FxHashMap::default();
+        for g in &guards {
+            *kinds.entry(g.kind).or_default() += 1;
+        }
+        let mut kinds: Vec<(&'static str, usize)> = kinds.into_iter().collect();
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
         let guards_context = DynamoGuardsContext {
+            num_guards: guards.len(),
             guards,
+            kinds,
+            guards_table: TEMPLATE_GUARDS_TABLE_SCRIPT,
             qps: TEMPLATE_QUERY_PARAM_SCRIPT,
         };
         let output = self.tt.render(&filename, &guards_context)?;
@@ -199,12 +320,17 @@ impl StructuredLogParser for DynamoGuardParser<'_> {
 pub struct InductorOutputCodeParser {
     // If true we output the code as plain text, otherwise we output it as rendered html
     plain_text: bool,
+    // See highlighted_html
+    highlight_max_bytes: usize,
 }
 
 impl InductorOutputCodeParser {
     pub fn new(config: &ParseConfig) -> Self {
         InductorOutputCodeParser {
             plain_text: config.plain_text,
+            highlight_max_bytes: config
+                .highlight_max_bytes
+                .unwrap_or(crate::DEFAULT_HIGHLIGHT_MAX_BYTES),
         }
     }
 }
@@ -216,7 +342,7 @@ impl StructuredLogParser for InductorOutputCodeParser {
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.inductor_output_code
             .as_ref()
-            .map(|m| Metadata::InductorOutputCode(m))
+            .map(Metadata::InductorOutputCode)
     }
 
     fn parse<'e>(
@@ -254,12 +380,11 @@ impl StructuredLogParser for InductorOutputCodeParser {
             let output_content = if self.plain_text {
                 payload.to_string()
             } else {
-                match generate_html_output(payload) {
-                    Ok(html) => html,
-                    Err(_e) => {
-                        return Err(anyhow::anyhow!("Failed to parse inductor code to html"))
-                    }
-                }
+                highlighted_html(
+                    &filename.to_string_lossy(),
+                    payload,
+                    self.highlight_max_bytes,
+                )
             };
 
             simple_file_output(
@@ -274,17 +399,155 @@ impl StructuredLogParser for InductorOutputCodeParser {
     }
 }
 
-fn generate_html_output(payload: &str) -> Result<String, anyhow::Error> {
-    let syntax_set = SyntaxSet::load_defaults_newlines();
-    let theme_set = ThemeSet::load_defaults();
-    let syntax = syntax_set.find_syntax_by_extension("py").unwrap();
-    let html = syntect::html::highlighted_html_for_string(
-        &payload,
-        &syntax_set,
-        &syntax,
-        &theme_set.themes["InspiredGitHub"],
-    );
-    Ok(html?)
+// The id of line n (from 1) of every page showing an artifact's lines, highlighted_html and
+// anchor_source: line 482 is #L482, and its number in the gutter links there.  Anything linking
+// to a line of one of those pages (the stack trie, for one) should go through here, so that
+// links stay good as the pages change.
+pub fn line_anchor(n: usize) -> String {
+    format!("L{n}")
+}
+
+// Every line of code as a <span class="line"> with its line_anchor, inside a <pre>, its number in
+// the gutter linking to it.  Lines are highlighted until the highlighter (if any) gives up on one;
+// the rest is plain (escaped) text.
+fn numbered_lines_page(
+    title: &str,
+    code: &str,
+    mut highlighter: Option<HighlightLines>,
+    script: &str,
+) -> String {
+    let mut lines = String::with_capacity(code.len() * 2);
+    for (i, line) in LinesWithEndings::from(code).enumerate() {
+        let html = highlighter.as_mut().and_then(|h| {
+            let ranges = h.highlight_line(line, &SYNTAX_SET).ok()?;
+            styled_line_to_highlighted_html(&ranges, IncludeBackground::No).ok()
+        });
+        if html.is_none() {
+            highlighter = None;
+        }
+        let n = i + 1;
+        let anchor = line_anchor(n);
+        let _ = write!(
+            lines,
+            "<span class=\"line\" id=\"{anchor}\"><a class=\"lineno\" href=\"#{anchor}\">{n}</a>"
+        );
+        match html {
+            Some(html) => lines.push_str(&html),
+            None => lines.push_str(&encode_text(line)),
+        }
+        lines.push_str("</span>");
+    }
+    format!(
+        r#"<html>
+<head>
+<meta charset="UTF-8">
+<title>{}</title>
+<style>
+pre {{ font-size: 13px; }}
+.lineno {{ display: inline-block; width: 6ch; margin-right: 2ch; text-align: right; color: #999; text-decoration: none; user-select: none; }}
+.line:target {{ background-color: #fff3b0; }}
+</style>
+</head>
+<body>
+<pre>{lines}</pre>
+{script}</body>
+</html>
+"#,
+        encode_text(title)
+    )
+}
+
+// A page showing a text artifact (FX graphs and output code are all Python) with every line
+// numbered and anchored, see numbered_lines_page.  Highlighting is skipped for code bigger than
+// max_bytes.
+pub fn highlighted_html(title: &str, code: &str, max_bytes: usize) -> String {
+    let syntax = SYNTAX_SET.find_syntax_by_extension("py").unwrap();
+    let highlighter = (code.len() <= max_bytes)
+        .then(|| HighlightLines::new(syntax, &THEME_SET.themes["InspiredGitHub"]));
+    numbered_lines_page(title, code, highlighter, "")
+}
+
+// How many levels of a JSON tree start out unfolded
+static JSON_TREE_OPEN_DEPTH: usize = 2;
+
+fn json_tree_node(key: Option<&str>, value: &Value, depth: usize, out: &mut String) {
+    let key = key.map_or(String::new(), |k| {
+        format!("<span class=\"json-key\">{}</span>: ", encode_text(k))
+    });
+    let open = if depth < JSON_TREE_OPEN_DEPTH {
+        " open"
+    } else {
+        ""
+    };
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let count = match value {
+        Value::Object(map) => format!("{{ {} key(s) }}", map.len()),
+        Value::Array(items) => format!("[ {} item(s) ]", items.len()),
+        _ => String::new(),
+    };
+    if children.is_empty() {
+        let class = match value {
+            Value::String(_) => "json-string",
+            Value::Number(_) => "json-number",
+            Value::Bool(_) | Value::Null => "json-literal",
+            // Empty
+            _ => "json-count",
+        };
+        out.push_str(&format!(
+            "<li>{key}<span class=\"{class}\">{}</span></li>",
+            encode_text(&value.to_string())
+        ));
+        return;
+    }
+    out.push_str(&format!(
+        "<li><details{open}><summary>{key}<span class=\"json-count\">{count}</span></summary><ul>"
+    ));
+    for (k, v) in children {
+        json_tree_node(Some(&k), v, depth + 1, out);
+    }
+    out.push_str("</ul></details></li>");
+}
+
+// A page showing a JSON artifact as a tree of folding nodes, the first JSON_TREE_OPEN_DEPTH
+// levels unfolded.  Every key is text on the page, so the browser's search finds (and unfolds)
+// them.  None unless it's an object or array.
+pub fn json_tree_html(title: &str, json: &str) -> Option<String> {
+    let value = serde_json::from_str::<Value>(json)
+        .ok()
+        .filter(|v| v.is_object() || v.is_array())?;
+    let mut tree = String::new();
+    json_tree_node(None, &value, 0, &mut tree);
+    Some(format!(
+        r#"<html>
+<head>
+<meta charset="UTF-8">
+<title>{}</title>
+<style>
+.json-tree, .json-tree ul {{ list-style-type: none; font-family: monospace; font-size: 13px; }}
+.json-tree ul {{ padding-left: 2ch; }}
+.json-tree summary {{ cursor: pointer; }}
+.json-key {{ color: #a31515; }}
+.json-string {{ color: #080; }}
+.json-number {{ color: #00c; }}
+.json-literal {{ color: #a0a; }}
+.json-count {{ color: #999; }}
+</style>
+</head>
+<body>
+<ul class="json-tree">{tree}</ul>
+</body>
+</html>
+"#,
+        encode_text(title)
+    ))
 }
 
 pub struct OptimizeDdpSplitChildParser;
@@ -295,7 +558,7 @@ impl StructuredLogParser for OptimizeDdpSplitChildParser {
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.optimize_ddp_split_child
             .as_ref()
-            .map(|m| Metadata::OptimizeDdpSplitChild(m))
+            .map(Metadata::OptimizeDdpSplitChild)
     }
 
     fn parse<'e>(
@@ -321,7 +584,7 @@ impl StructuredLogParser for LinkParser {
         "link_parser"
     }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
-        e.link.as_ref().map(|m| Metadata::Link(m))
+        e.link.as_ref().map(Metadata::Link)
     }
 
     fn parse<'e>(
@@ -343,10 +606,11 @@ impl StructuredLogParser for LinkParser {
     }
 }
 
-fn format_stack(stack: &StackSummary) -> String {
+fn format_stack(stack: &StackSummary, strip_rules: &StripRules) -> String {
     let mut trie = StackTrieNode::default();
     trie.insert_no_terminal(stack.to_vec());
-    trie.fmt(None).unwrap()
+    // A single stack has no branch points to fold
+    trie.fmt(None, 0, strip_rules).unwrap()
 }
 
 pub struct CompilationMetricsParser<'t> {
@@ -355,6 +619,9 @@ pub struct CompilationMetricsParser<'t> {
     pub symbolic_shape_specialization_index: &'t RefCell<SymbolicShapeSpecializationIndex>,
     pub output_files: &'t Vec<OutputFile>,
     pub compile_id_dir: &'t PathBuf,
+    pub strip_rules: &'t StripRules,
+    // None if the compile created and specialized no symbols
+    pub dynamic_dims: Option<DynamicDims>,
 }
 impl StructuredLogParser for CompilationMetricsParser<'_> {
     fn name(&self) -> &'static str {
@@ -363,7 +630,7 @@ impl StructuredLogParser for CompilationMetricsParser<'_> {
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.compilation_metrics
             .as_ref()
-            .map(|m| Metadata::CompilationMetrics(m))
+            .map(Metadata::CompilationMetrics)
     }
     fn parse<'e>(
         &self,
@@ -386,16 +653,16 @@ impl StructuredLogParser for CompilationMetricsParser<'_> {
                 .stack_index
                 .borrow()
                 .get(&cid)
-                .map_or("".to_string(), format_stack);
+                .map_or("".to_string(), |stack| {
+                    format_stack(stack, self.strip_rules)
+                });
             let mini_stack_html = if let (Some(name), Some(filename), Some(line)) =
                 (&m.co_name, &m.co_filename, m.co_firstlineno)
             {
-                format_stack(&Vec::from([FrameSummary {
-                    uninterned_filename: Some(filename.clone()),
-                    filename: u32::MAX,
-                    line: line,
-                    name: name.clone(),
-                }]))
+                format_stack(
+                    &Vec::from([FrameSummary::uninterned(filename, line, name)]),
+                    self.strip_rules,
+                )
             } else {
                 "".to_string()
             };
@@ -403,14 +670,17 @@ impl StructuredLogParser for CompilationMetricsParser<'_> {
                 .symbolic_shape_specialization_index
                 .borrow_mut()
                 .remove(&cid)
-                .unwrap_or(Vec::new())
+                .unwrap_or_default()
                 .drain(..)
                 .map(|spec| SymbolicShapeSpecializationContext {
                     symbol: spec.symbol.unwrap_or("".to_string()),
                     sources: spec.sources.unwrap_or(Vec::new()),
                     value: spec.value.unwrap_or("".to_string()),
-                    user_stack_html: format_stack(&spec.user_stack.unwrap_or(Vec::new())),
-                    stack_html: format_stack(&spec.stack.unwrap_or(Vec::new())),
+                    user_stack_html: format_stack(
+                        &spec.user_stack.unwrap_or(Vec::new()),
+                        self.strip_rules,
+                    ),
+                    stack_html: format_stack(&spec.stack.unwrap_or(Vec::new()), self.strip_rules),
                 })
                 .collect();
             let remove_prefix = |x: &String| -> String {
@@ -426,19 +696,28 @@ impl StructuredLogParser for CompilationMetricsParser<'_> {
                 .map(|o| OutputFile {
                     url: remove_prefix(&o.url),
                     name: remove_prefix(&o.name),
-                    number: o.number.clone(),
+                    number: o.number,
                     suffix: o.suffix.clone(),
+                    size: o.size,
+                    highlighted: o.highlighted.as_ref().map(remove_prefix),
+                    preview: None,
                 })
                 .collect();
             let context = CompilationMetricsContext {
                 css: crate::CSS,
-                m: &m,
+                m,
                 compile_id: id,
-                stack_html: stack_html,
-                mini_stack_html: mini_stack_html,
+                stack_html,
+                mini_stack_html,
+                phases: m
+                    .phase_times()
+                    .into_iter()
+                    .map(|(p, t)| (p, fmt_seconds(t)))
+                    .collect(),
                 symbolic_shape_specializations: specializations,
+                dynamic_dims: self.dynamic_dims,
                 output_files: &output_files,
-                compile_id_dir: &self.compile_id_dir,
+                compile_id_dir: self.compile_id_dir,
                 qps: TEMPLATE_QUERY_PARAM_SCRIPT,
             };
             let output = self.tt.render(&filename, &context)?;
@@ -459,7 +738,7 @@ impl StructuredLogParser for AOTAutogradBackwardCompilationMetricsParser<'_> {
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.aot_autograd_backward_compilation_metrics
             .as_ref()
-            .map(|m| Metadata::AOTAutogradBackwardCompilationMetrics(m))
+            .map(Metadata::AOTAutogradBackwardCompilationMetrics)
     }
     fn parse<'e>(
         &self,
@@ -476,7 +755,7 @@ impl StructuredLogParser for AOTAutogradBackwardCompilationMetricsParser<'_> {
                 .map_or("(unknown) ".to_string(), |c| format!("{cid} ", cid = c));
             let context = AOTAutogradBackwardCompilationMetricsContext {
                 css: crate::CSS,
-                m: &m,
+                m,
                 compile_id: id,
                 qps: TEMPLATE_QUERY_PARAM_SCRIPT,
             };
@@ -500,7 +779,7 @@ impl StructuredLogParser for BwdCompilationMetricsParser<'_> {
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
         e.bwd_compilation_metrics
             .as_ref()
-            .map(|m| Metadata::BwdCompilationMetrics(m))
+            .map(Metadata::BwdCompilationMetrics)
     }
     fn parse<'e>(
         &self,
@@ -517,7 +796,7 @@ impl StructuredLogParser for BwdCompilationMetricsParser<'_> {
                 .map_or("(unknown) ".to_string(), |c| format!("{cid} ", cid = c));
             let context = BwdCompilationMetricsContext {
                 css: crate::CSS,
-                m: &m,
+                m,
                 compile_id: id,
                 qps: TEMPLATE_QUERY_PARAM_SCRIPT,
             };
@@ -535,7 +814,7 @@ impl StructuredLogParser for DumpFileParser {
         "dump_file"
     }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
-        e.dump_file.as_ref().map(|m| Metadata::DumpFile(m))
+        e.dump_file.as_ref().map(Metadata::DumpFile)
     }
     fn parse<'e>(
         &self,
@@ -565,60 +844,228 @@ impl StructuredLogParser for DumpFileParser {
 }
 
 pub fn anchor_source(text: &str) -> String {
-    let lines: Vec<&str> = text.lines().collect();
-    let mut html = String::from(
-        r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Source Code</title>
-    <style>
-        pre {
-            counter-reset: line;
+    numbered_lines_page("Source Code", text, None, TEMPLATE_QUERY_PARAM_SCRIPT)
+}
+
+// The call_function and call_method targets of an FX graph as print_readable prints it, one per
+// node in graph order: "torch.ops.aten.add.Tensor", "operator.getitem", or "Tensor.sin" for a
+// method.  Calls of submodules (self.foo(x)) are call_module and aren't included.  None if the
+// payload doesn't look like a printed graph at all.
+pub fn fx_graph_calls(payload: &str) -> Option<Vec<String>> {
+    thread_local! {
+        // `x = target(` or, with the node's type, `x: "f32[3]" = target(`; the target is missing
+        // for placeholders and operators written inline, like `add = x + y`
+        static NODE: Regex = Regex::new(
+            r#"^\s*([A-Za-z_]\w*)(?:\s*:\s*(?:"[^"]*"|[\w.]+))?\s*=\s*(?:([A-Za-z_][\w.]*)\()?"#,
+        )
+        .unwrap();
+        static PARAM: Regex = Regex::new(r"(?:\(|,)\s*([A-Za-z_]\w*)").unwrap();
+    }
+    let mut calls = Vec::new();
+    // Names bound in the graph so far: a call on one of them is a method call
+    let mut locals: FxHashSet<&str> = FxHashSet::default();
+    let mut is_graph = false;
+    for line in payload.lines() {
+        let trimmed = line.trim_start();
+        if let Some(params) = trimmed.strip_prefix("def forward") {
+            is_graph = true;
+            PARAM.with(|re| {
+                locals.extend(re.captures_iter(params).map(|c| c.get(1).unwrap().as_str()))
+            });
+            continue;
         }
-        pre span {
-            display: block;
+        if trimmed.starts_with('#') {
+            continue;
         }
-        pre span:before {
-            counter-increment: line;
-            content: counter(line);
-            display: inline-block;
-            padding: 0 .5em;
-            margin-right: .5em;
-            color: #888;
+        let Some((name, target)) = NODE.with(|re| {
+            re.captures(line)
+                .map(|c| (c.get(1).unwrap().as_str(), c.get(2).map(|t| t.as_str())))
+        }) else {
+            continue;
+        };
+        if let Some(target) = target {
+            let (root, method) = target.split_once('.').unwrap_or((target, ""));
+            if root == "self" {
+                // call_module
+            } else if locals.contains(root) && !method.is_empty() {
+                calls.push(format!("Tensor.{}", method.rsplit('.').next().unwrap()));
+            } else {
+                calls.push(target.to_string());
+            }
         }
-        pre span:target {
-            background-color: #ffff00;
+        locals.insert(name);
+    }
+    is_graph.then_some(calls)
+}
+
+// An FX graph as print_readable prints it, with what differs between graphs that compute the
+// same thing taken out: comments (which carry file names and lines), names of nodes and
+// placeholders (renumbered n0, n1, ... in order of appearance, so that add_3 and add_1 are the
+// same) and addresses.  Two graphs that normalize the same are the same graph.
+pub fn normalize_fx_graph(payload: &str) -> String {
+    thread_local! {
+        // With the dot before an attribute, so that torch.ops.aten.add isn't renamed along with
+        // a node named add
+        static WORD: Regex = Regex::new(r"\.?[A-Za-z_]\w*").unwrap();
+        static BOUND: Regex =
+            Regex::new(r"^\s*([A-Za-z_]\w*)(?:\s*:\s*(?:\x22[^\x22]*\x22|[\w.]+))?\s*=").unwrap();
+        static PARAM: Regex = Regex::new(r"(?:\(|,)\s*([A-Za-z_]\w*)").unwrap();
+        static ADDRESS: Regex = Regex::new(r"0x[0-9a-fA-F]+").unwrap();
+    }
+    let lines: Vec<&str> = payload
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.is_empty() && !l.trim_start().starts_with('#'))
+        .collect();
+    // Every name the graph binds, numbered in the order they're bound
+    let mut names: FxHashMap<&str, usize> = FxHashMap::default();
+    for line in &lines {
+        let trimmed = line.trim_start();
+        let bound: Vec<&str> = if let Some(params) = trimmed.strip_prefix("def forward") {
+            PARAM.with(|re| {
+                re.captures_iter(params)
+                    .map(|c| c.get(1).unwrap().as_str())
+                    .filter(|p| *p != "self")
+                    .collect()
+            })
+        } else {
+            BOUND.with(|re| {
+                re.captures(line)
+                    .map(|c| c.get(1).unwrap().as_str())
+                    .into_iter()
+                    .collect()
+            })
+        };
+        for name in bound {
+            let next = names.len();
+            names.entry(name).or_insert(next);
         }
-    </style>
-</head>
-<body>
-    <pre>"#,
-    );
-
-    for (i, line) in lines.iter().enumerate() {
-        let line_number = i + 1;
-        html.push_str(&format!(
-            r#"<span id="L{}">{}</span>"#,
-            line_number,
-            encode_text(line)
-        ));
     }
+    let mut normalized = String::new();
+    for line in lines {
+        let line = ADDRESS.with(|re| re.replace_all(line, "0x…").into_owned());
+        let line = WORD.with(|re| {
+            re.replace_all(&line, |c: &regex::Captures| match names.get(&c[0]) {
+                Some(i) => format!("n{i}"),
+                // Attributes start with the dot, so they're never found
+                None => c[0].to_string(),
+            })
+            .into_owned()
+        });
+        normalized.push_str(&line);
+        normalized.push('\n');
+    }
+    normalized
+}
 
-    html.push_str(&format!(
-        "</pre>{TEMPLATE_QUERY_PARAM_SCRIPT}</body></html>"
-    ));
-    html
+// Guards longer than this many characters are cut short in dynamo_guards.html, until clicked
+static GUARD_PREVIEW_CHARS: usize = 120;
+
+// What a guard checks, after the names of PyTorch's guard builders, by how its code starts: the
+// log only has the code.  Symbolic shape guards are SHAPE_ENV, anything unrecognized OTHER.
+pub fn guard_kind(code: &str) -> &'static str {
+    let code = code.trim_start();
+    let code = code.strip_prefix("not ").unwrap_or(code);
+    static PREFIXES: [(&str, &str); 12] = [
+        ("check_tensor(", "TENSOR_MATCH"),
+        ("___check_tensors", "TENSOR_MATCH"),
+        ("___check_obj_id(", "ID_MATCH"),
+        ("___check_type_id(", "TYPE_MATCH"),
+        ("___check_current_backend(", "BACKEND_MATCH"),
+        ("___check_global_state(", "GLOBAL_STATE"),
+        (
+            "___check_torch_function_mode_stack(",
+            "TORCH_FUNCTION_STATE",
+        ),
+        ("___dict_contains(", "DICT_CONTAINS"),
+        ("___dict_version(", "DICT_VERSION"),
+        ("hasattr(", "HASATTR"),
+        ("len(", "LENGTH_CHECK"),
+        ("utils_device.CURRENT_DEVICE", "DEFAULT_DEVICE"),
+    ];
+    if let Some((_, kind)) = PREFIXES.iter().find(|(p, _)| code.starts_with(p)) {
+        kind
+    } else if code.contains(".size()") || code.contains(".stride()") || code.contains("Eq(") {
+        "SHAPE_ENV"
+    } else if code.contains(" is None") || code.contains(" is not None") {
+        "NONE_MATCH"
+    } else if code.contains("==") {
+        "EQUALS_MATCH"
+    } else {
+        "OTHER"
+    }
+}
+
+// The code of a guard, written so that two guards checking the same thing compare equal:
+// whitespace collapsed, a trailing comment dropped and string literals single quoted (so
+// L["x"] is L['x']).  Inside string literals nothing is touched.
+pub fn normalize_guard(code: &str) -> String {
+    let mut normalized = String::with_capacity(code.len());
+    let mut chars = code.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let mut literal = String::new();
+                let mut closed = false;
+                while let Some(s) = chars.next() {
+                    if s == '\\' {
+                        literal.push(s);
+                        literal.extend(chars.next());
+                    } else if s == c {
+                        closed = true;
+                        break;
+                    } else {
+                        literal.push(s);
+                    }
+                }
+                let quote = if literal.contains('\'') { c } else { '\'' };
+                normalized.push(quote);
+                normalized.push_str(&literal);
+                if closed {
+                    normalized.push(quote);
+                }
+            }
+            '#' => break,
+            c if c.is_whitespace() => {
+                while chars.next_if(|s| s.is_whitespace()).is_some() {}
+                normalized.push(' ');
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized.trim_end().to_string()
+}
+
+// A "string" payload that is a JSON object or array; None for anything else, e.g. text that only
+// starts like JSON
+fn json_payload(payload: &str) -> Option<Value> {
+    if !payload.trim_start().starts_with(['{', '[']) {
+        return None;
+    }
+    serde_json::from_str::<Value>(payload)
+        .ok()
+        .filter(|v| v.is_object() || v.is_array())
+}
+
+pub struct ArtifactParser {
+    // Also write JSON payloads as they were logged, see ParseConfig::json_keep_raw
+    keep_raw: bool,
+}
+
+impl ArtifactParser {
+    pub fn new(config: &ParseConfig) -> Self {
+        ArtifactParser {
+            keep_raw: config.json_keep_raw,
+        }
+    }
 }
 
-pub struct ArtifactParser;
 impl StructuredLogParser for ArtifactParser {
     fn name(&self) -> &'static str {
         "artifact"
     }
     fn get_metadata<'e>(&self, e: &'e Envelope) -> Option<Metadata<'e>> {
-        e.artifact.as_ref().map(|m| Metadata::Artifact(m))
+        e.artifact.as_ref().map(Metadata::Artifact)
     }
     fn parse<'e>(
         &self,
@@ -629,22 +1076,35 @@ impl StructuredLogParser for ArtifactParser {
         payload: &str,
     ) -> anyhow::Result<ParserResults> {
         if let Metadata::Artifact(metadata) = metadata {
-            match metadata.encoding.as_str() {
-                "string" => {
-                    let filename = format!("{}.txt", metadata.name);
-                    simple_file_output(&filename, lineno, compile_id, &payload)
+            // JSON is pretty-printed (and gets a tree page, see json_tree_html); anything that
+            // doesn't parse, even if it says it's JSON, is written as it is
+            let value = match metadata.encoding.as_str() {
+                "string" => json_payload(payload),
+                "json" => serde_json::from_str::<Value>(payload).ok(),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Unsupported encoding: {}",
+                        metadata.encoding
+                    ))
                 }
-                "json" => {
-                    let filename = format!("{}.json", metadata.name);
-                    let value: Value = serde_json::from_str(&payload).unwrap();
-                    let pretty = serde_json::to_string_pretty(&value).unwrap();
-                    simple_file_output(&filename, lineno, compile_id, &pretty)
-                }
-                _ => Err(anyhow::anyhow!(
-                    "Unsupported encoding: {}",
-                    metadata.encoding
-                )),
+            };
+            let Some(value) = value else {
+                let filename = format!("{}.txt", metadata.name);
+                return simple_file_output(&filename, lineno, compile_id, payload);
+            };
+            let compile_id_dir: PathBuf = compile_directory_name(compile_id, lineno).into();
+            let pretty = serde_json::to_string_pretty(&value)?;
+            let mut results = vec![ParserOutput::File(
+                compile_id_dir.join(format!("{}.json", metadata.name)),
+                pretty,
+            )];
+            if self.keep_raw {
+                results.push(ParserOutput::File(
+                    compile_id_dir.join(format!("{}_raw.txt", metadata.name)),
+                    payload.to_string(),
+                ));
             }
+            Ok(results)
         } else {
             Err(anyhow::anyhow!("Expected Artifact metadata"))
         }
@@ -655,6 +1115,7 @@ impl StructuredLogParser for ArtifactParser {
 pub fn default_parsers<'t>(
     tt: &'t TinyTemplate<'t>,
     parser_config: &ParseConfig,
+    strip_rules: &'t StripRules,
 ) -> Vec<Box<dyn StructuredLogParser + 't>> {
     // We need to use Box wrappers here because vecs in Rust need to have known size
     let result: Vec<Box<dyn StructuredLogParser>> = vec![
@@ -687,13 +1148,13 @@ pub fn default_parsers<'t>(
         })),
         Box::new(GraphDumpParser),
         Box::new(DynamoOutputGraphParser),
-        Box::new(DynamoGuardParser { tt }),
+        Box::new(DynamoGuardParser { tt, strip_rules }),
         Box::new(InductorOutputCodeParser::new(parser_config)),
         Box::new(OptimizeDdpSplitChildParser),
         Box::new(AOTAutogradBackwardCompilationMetricsParser { tt }), // TODO: use own tt instances
         Box::new(BwdCompilationMetricsParser { tt }),                 // TODO: use own tt instances
         Box::new(LinkParser),
-        Box::new(ArtifactParser),
+        Box::new(ArtifactParser::new(parser_config)),
         Box::new(DumpFileParser),
     ];
 
@@ -0,0 +1,283 @@
+// The tlparse extension module for Python (the python feature; see examples/python).  Parsing
+// doesn't hold the GIL, so other Python threads keep running through a big log.
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::{envelopes, parse_path, DirSink, LogEntry, OutputSink, ParseConfig};
+
+// How many entries envelopes() reads ahead of the Python code iterating over them
+static ENVELOPES_READ_AHEAD: usize = 256;
+
+// The keyword arguments of parse_file, named like the ParseConfig fields they set.  They're read
+// while holding the GIL, since a ParseConfig can't be sent to the thread parsing without it
+// (custom parsers needn't be Send).
+#[derive(Default)]
+struct ParseOptions {
+    strict: bool,
+    strict_compile_id: bool,
+    rank: Option<u32>,
+    run: Option<usize>,
+    dedupe: bool,
+    output_dir: Option<PathBuf>,
+    plain_text: bool,
+    highlight_max_bytes: Option<usize>,
+    strip_prefixes: Vec<String>,
+    normalize_artifacts: bool,
+    keep_raw: bool,
+    no_previews: bool,
+}
+
+impl ParseOptions {
+    fn extract(options: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let mut o = ParseOptions::default();
+        for (key, value) in options.into_iter().flatten() {
+            let key: String = key.extract()?;
+            match key.as_str() {
+                "strict" => o.strict = value.extract()?,
+                "strict_compile_id" => o.strict_compile_id = value.extract()?,
+                "rank" => o.rank = value.extract()?,
+                "run" => o.run = value.extract()?,
+                "dedupe" => o.dedupe = value.extract()?,
+                "output_dir" => o.output_dir = value.extract()?,
+                "plain_text" => o.plain_text = value.extract()?,
+                "highlight_max_bytes" => o.highlight_max_bytes = value.extract()?,
+                "strip_prefixes" => o.strip_prefixes = value.extract()?,
+                "normalize_artifacts" => o.normalize_artifacts = value.extract()?,
+                "keep_raw" => o.keep_raw = value.extract()?,
+                "no_previews" => o.no_previews = value.extract()?,
+                _ => {
+                    return Err(PyTypeError::new_err(format!(
+                        "parse_file() got an unexpected keyword argument '{key}'"
+                    )))
+                }
+            }
+        }
+        Ok(o)
+    }
+
+    fn config(self) -> ParseConfig {
+        ParseConfig {
+            strict: self.strict,
+            strict_compile_id: self.strict_compile_id,
+            rank: self.rank,
+            run: self.run,
+            dedupe: self.dedupe,
+            output_dir: self.output_dir,
+            plain_text: self.plain_text,
+            highlight_max_bytes: self.highlight_max_bytes,
+            strip_prefixes: self.strip_prefixes,
+            normalize_artifacts: self.normalize_artifacts,
+            keep_raw: self.keep_raw,
+            no_previews: self.no_previews,
+            ..Default::default()
+        }
+    }
+}
+
+// A report file's path as the pages link to it, with / between directories
+fn report_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn json_loads<'py>(py: Python<'py>, json: &str) -> PyResult<Bound<'py, PyAny>> {
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+// The part of a report under prefix ("" or "run_<n>/"): its stats, its compiles and the stack
+// trie, from the JSON files the report has of them.  Each file of a compile gets the file's
+// contents from files, or the path it was written to in output_dir.
+fn run_report<'py>(
+    py: Python<'py>,
+    output: &[(String, String)],
+    files: &Bound<'py, PyDict>,
+    output_dir: Option<&Path>,
+    prefix: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let report = PyDict::new(py);
+    let file = |name: &str| {
+        output
+            .iter()
+            .find(|(path, _)| *path == format!("{prefix}{name}"))
+            .map(|(_, contents)| contents.as_str())
+    };
+    if let Some(stats) = file("stats.json") {
+        report.set_item("stats", json_loads(py, stats)?)?;
+    }
+    if let Some(trie) = file("stack_trie.json") {
+        report.set_item("trie", json_loads(py, trie)?)?;
+    }
+    let compiles = match file("compile_directory.json") {
+        Some(directory) => json_loads(py, directory)?.get_item("compiles")?,
+        None => PyList::empty(py).into_any(),
+    };
+    for compile in compiles.try_iter()? {
+        for compile_file in compile?.get_item("files")?.try_iter()? {
+            let compile_file = compile_file?;
+            let url: String = compile_file.get_item("url")?.extract()?;
+            let path = format!("{prefix}{url}");
+            match output_dir {
+                // Streamed payloads too, which aren't in files
+                Some(dir) => compile_file.set_item("path", dir.join(&path))?,
+                None => {
+                    if let Some(contents) = files.get_item(&path)? {
+                        compile_file.set_item("contents", contents)?;
+                    }
+                }
+            }
+        }
+    }
+    report.set_item("compiles", compiles)?;
+    Ok(report)
+}
+
+// Parse the log at path into a dict of what the report has: "stats" (as in stats.json),
+// "compiles" (as in compile_directory.json, each file with its "contents" as bytes), "trie" (the
+// stack trie, as nested dicts) and "files", every file of the report by its path.  With
+// output_dir, the report is written there instead, and files and the compiles' files have the
+// "path" each was written to.  A log with several runs has a "runs" list of one such report each.
+// Keyword arguments are ParseConfig's fields of the same names.
+#[pyfunction]
+#[pyo3(signature = (path, **options))]
+fn parse_file<'py>(
+    py: Python<'py>,
+    path: PathBuf,
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let options = ParseOptions::extract(options)?;
+    let output_dir = options.output_dir.clone();
+    let output = py
+        .allow_threads(|| {
+            if let Some(dir) = &output_dir {
+                fs::create_dir_all(dir)?;
+            }
+            let output = parse_path(&path, options.config())?;
+            let output: Vec<(String, String)> = output
+                .files
+                .into_iter()
+                .map(|(path, contents)| (report_path(&path), contents))
+                .collect();
+            if let Some(dir) = &output_dir {
+                let mut sink = DirSink::new(dir);
+                for (path, contents) in &output {
+                    sink.write(Path::new(path), contents.as_bytes())?;
+                }
+            }
+            anyhow::Ok(output)
+        })
+        .map_err(|err| PyValueError::new_err(format!("{err:#}")))?;
+
+    let files = PyDict::new(py);
+    for (path, contents) in &output {
+        match &output_dir {
+            Some(dir) => files.set_item(path, dir.join(path))?,
+            None => files.set_item(path, PyBytes::new(py, contents.as_bytes()))?,
+        }
+    }
+    let output_dir = output_dir.as_deref();
+    let report = run_report(py, &output, &files, output_dir, "")?;
+    // run_<n>/stats.json for each run
+    let mut runs: Vec<usize> = output
+        .iter()
+        .filter_map(|(path, _)| {
+            let run = path.strip_prefix("run_")?.strip_suffix("/stats.json")?;
+            run.parse().ok()
+        })
+        .collect();
+    runs.sort();
+    if !runs.is_empty() {
+        let reports = PyList::empty(py);
+        for run in runs {
+            let prefix = format!("run_{run}/");
+            reports.append(run_report(py, &output, &files, output_dir, &prefix)?)?;
+        }
+        report.set_item("runs", reports)?;
+    }
+    report.set_item("files", files)?;
+    Ok(report)
+}
+
+// The entries of a log, read on a thread of their own a little ahead of the Python code taking
+// them, so that it only waits (without the GIL) when it's caught up
+#[pyclass]
+struct Envelopes {
+    // Locked so that another thread can wait on it while this one runs Python
+    entries: Mutex<Receiver<anyhow::Result<LogEntry>>>,
+    strict: bool,
+}
+
+#[pymethods]
+impl Envelopes {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        loop {
+            let entry = match py.allow_threads(|| self.entries.lock().unwrap().recv()) {
+                Ok(Ok(entry)) => entry,
+                Ok(Err(err)) if self.strict => {
+                    return Err(PyValueError::new_err(format!("{err:#}")))
+                }
+                Ok(Err(_)) => continue,
+                // The log is read to its end
+                Err(_) => return Ok(None),
+            };
+            let envelope = serde_json::to_string(&entry.envelope)
+                .map_err(|err| PyValueError::new_err(err.to_string()))?;
+            let dict = PyDict::new(py);
+            dict.set_item("line", entry.line)?;
+            dict.set_item("envelope", json_loads(py, &envelope)?)?;
+            dict.set_item("payload", entry.payload)?;
+            return Ok(Some(dict));
+        }
+    }
+}
+
+// Iterate over the entries of the log at path without rendering anything, each a dict of its
+// "line" number, its "envelope" as logged, and its "payload" (or None).  Entries whose JSON
+// doesn't parse are skipped, or with strict=True raise ValueError, after which iterating goes on.
+#[pyfunction]
+#[pyo3(name = "envelopes", signature = (path, strict = false))]
+fn py_envelopes(path: PathBuf, strict: bool) -> PyResult<Envelopes> {
+    let file = File::open(&path)
+        .map_err(|err| PyValueError::new_err(format!("{}: {err}", path.display())))?;
+    let (send, entries) = sync_channel(ENVELOPES_READ_AHEAD);
+    thread::spawn(move || {
+        let entries = match envelopes(BufReader::new(file)) {
+            Ok(entries) => entries,
+            Err(err) => {
+                let _ = send.send(Err(err));
+                return;
+            }
+        };
+        for entry in entries {
+            // Nobody's iterating any more
+            if send.send(entry).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(Envelopes {
+        entries: Mutex::new(entries),
+        strict,
+    })
+}
+
+#[pymodule]
+fn tlparse(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse_file, m)?)?;
+    m.add_function(wrap_pyfunction!(py_envelopes, m)?)?;
+    m.add_class::<Envelopes>()?;
+    Ok(())
+}
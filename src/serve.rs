@@ -0,0 +1,247 @@
+// A small HTTP server for browsing an output directory, see `tlparse serve`.  Opening the pages
+// over file:// breaks the things that fetch other files of the output (the compile search, for
+// one), and on a remote machine the output is easiest to reach through `ssh -L`.  It only ever
+// serves files under its directory, one thread per connection (up to MAX_CONNECTIONS at once),
+// and closes every connection after its response.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// Text files at least this big are gzipped for clients that accept it
+pub static GZIP_MIN_BYTES: u64 = 16 * 1024;
+
+// How often the accept loop checks whether it should shut down
+static SHUTDOWN_POLL: Duration = Duration::from_millis(50);
+
+// Requests with headers longer than this are refused
+static MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+// Connections answered at once; more wait in the listener's backlog until one finishes
+static MAX_CONNECTIONS: usize = 64;
+
+// Serve the files under dir on listener until shutdown is set.  Requests being answered then are
+// finished before this returns.
+pub fn serve_dir(dir: &Path, listener: TcpListener, shutdown: &AtomicBool) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let mut handlers: Vec<thread::JoinHandle<()>> = Vec::new();
+    while !shutdown.load(Ordering::Relaxed) {
+        handlers.retain(|h| !h.is_finished());
+        if handlers.len() >= MAX_CONNECTIONS {
+            thread::sleep(SHUTDOWN_POLL);
+            continue;
+        }
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let dir = dir.to_path_buf();
+                handlers.push(thread::spawn(move || {
+                    // The client going away mid-response is its business
+                    let _ = handle_connection(&dir, stream);
+                }));
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(SHUTDOWN_POLL),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+    for handler in handlers {
+        let _ = handler.join();
+    }
+    Ok(())
+}
+
+struct Request {
+    method: String,
+    path: String,
+    accepts_gzip: bool,
+}
+
+fn read_request(stream: &TcpStream) -> io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream).take(MAX_REQUEST_HEAD as u64);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let mut accepts_gzip = false;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            // Ran out of request, or over MAX_REQUEST_HEAD
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("accept-encoding") {
+                accepts_gzip = value
+                    .split(',')
+                    .any(|e| e.split(';').next().unwrap_or_default().trim() == "gzip");
+            }
+        }
+    }
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: percent_decode(path),
+        accepts_gzip,
+    }))
+}
+
+fn handle_connection(dir: &Path, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let Some(request) = read_request(&stream)? else {
+        return respond_error(&mut stream, "400 Bad Request");
+    };
+    if request.method != "GET" && request.method != "HEAD" {
+        return respond_error(&mut stream, "405 Method Not Allowed");
+    }
+    let Some(path) = resolve(dir, &request.path) else {
+        return respond_error(&mut stream, "404 Not Found");
+    };
+    respond_file(&mut stream, &request, &path)
+}
+
+// The file at path, streamed rather than read whole.  Failing to open it is a 500; failing
+// partway through, after the head went out, can only cut the response short.
+fn respond_file(stream: &mut TcpStream, request: &Request, path: &Path) -> io::Result<()> {
+    let opened = File::open(path).and_then(|file| {
+        let len = file.metadata()?.len();
+        Ok((file, len))
+    });
+    let Ok((mut file, len)) = opened else {
+        return respond_error(stream, "500 Internal Server Error");
+    };
+    let content_type = content_type(path);
+    let gzip = request.accepts_gzip
+        && len >= GZIP_MIN_BYTES
+        && (content_type.starts_with("text/") || content_type.starts_with("application/json"));
+    // A gzipped body's length isn't known until it's written, so it ends where the connection does
+    let mut head = format!("HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\n");
+    if gzip {
+        head.push_str("Content-Encoding: gzip\r\nVary: Accept-Encoding\r\n");
+    } else {
+        head.push_str(&format!("Content-Length: {len}\r\n"));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    stream.write_all(head.as_bytes())?;
+    if request.method == "GET" {
+        if gzip {
+            let mut encoder = GzEncoder::new(&mut *stream, Compression::default());
+            io::copy(&mut file, &mut encoder)?;
+            encoder.finish()?;
+        } else {
+            io::copy(&mut file, stream)?;
+        }
+    }
+    stream.flush()
+}
+
+fn respond_error(stream: &mut TcpStream, status: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{status}",
+        status.len()
+    )?;
+    stream.flush()
+}
+
+// The file a request path names: "/" and directories mean their index.html, and anything that
+// would leave dir (.., absolute paths) names nothing
+fn resolve(dir: &Path, request_path: &str) -> Option<PathBuf> {
+    let mut file = dir.to_path_buf();
+    for component in Path::new(request_path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => file.push(part),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+    if file.is_dir() {
+        file.push("index.html");
+    }
+    file.is_file().then_some(file)
+}
+
+fn content_type(file: &Path) -> &'static str {
+    match file
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+    {
+        "html" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" | "log" | "py" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+// %XX escapes in a request path; a malformed escape is kept as it is
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/0_0_0/a%20b.txt"), "/0_0_0/a b.txt");
+        assert_eq!(percent_decode("/%2e%2e/x"), "/../x");
+        assert_eq!(percent_decode("/100%"), "/100%");
+        assert_eq!(percent_decode("/%zz"), "/%zz");
+    }
+
+    #[test]
+    fn test_respond_file_unreadable() {
+        // A file gone between resolving and opening it is the server's failure, not silence
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let request = Request {
+            method: "GET".to_string(),
+            path: "/gone.html".to_string(),
+            accepts_gzip: false,
+        };
+        let gone = std::env::temp_dir().join(format!("tlparse_serve_gone_{}", std::process::id()));
+        respond_file(&mut stream, &request, &gone).unwrap();
+        drop(stream);
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+    }
+}
@@ -0,0 +1,176 @@
+// A whole report as one HTML document, for `-o -`: every page goes in a JSON blob, and a small
+// viewer shows one at a time in an iframe, following links between them in place.  The pages
+// link each other by relative path, so the viewer resolves those against the page showing and
+// leaves links out of the report (and anchors within a page) to the browser.
+
+use html_escape::encode_text;
+use std::path::{Path, PathBuf};
+
+use crate::types::fmt_bytes;
+
+// Bigger files are cut to their first this many bytes, rendered as text
+pub static SINGLE_FILE_MAX_BYTES: usize = 1_000_000;
+
+static VIEWER: &str = r#"<script>
+const files = JSON.parse(document.getElementById('tlparse-files').textContent);
+const page = document.getElementById('tlparse-page');
+let current = 'index.html';
+const escapeText = (s) => s.replace(/&/g, '&amp;').replace(/</g, '&lt;');
+function show(path, hash) {
+    const file = files[path];
+    current = path;
+    page.onload = () => {
+        page.contentDocument.addEventListener('click', follow);
+        const target = hash && page.contentDocument.getElementById(decodeURIComponent(hash.slice(1)));
+        if (target) {
+            target.scrollIntoView();
+        }
+    };
+    if (file === undefined) {
+        page.srcdoc = '<p>' + escapeText(path) + ' is not in this report</p>';
+    } else if (/\.html?$/.test(path)) {
+        page.srcdoc = file;
+    } else {
+        page.srcdoc = '<pre>' + escapeText(file) + '</pre>';
+    }
+    document.title = path;
+}
+function follow(event) {
+    const link = event.target.closest('a[href]');
+    const href = link && link.getAttribute('href');
+    if (!href || href.startsWith('#') || /^[a-z][a-z0-9+.-]*:/i.test(href)) {
+        return;
+    }
+    event.preventDefault();
+    const url = new URL(href, 'http://report/' + current);
+    show(decodeURIComponent(url.pathname.slice(1)), url.hash);
+}
+show('index.html', '');
+</script>"#;
+
+// Which of the files a single-file report can't hold, and why
+fn left_out(path: &Path, contents: &[u8]) -> Option<&'static str> {
+    if path == Path::new("raw.log") {
+        Some("is the log itself")
+    } else if std::str::from_utf8(contents).is_err() {
+        Some("isn't text")
+    } else {
+        None
+    }
+}
+
+// What the viewer shows for a file: as written when it fits in max_bytes, otherwise its start
+// with a note saying so
+fn report_entry(path: &str, contents: &[u8], max_bytes: usize) -> String {
+    let contents = String::from_utf8_lossy(contents);
+    if contents.len() <= max_bytes {
+        return contents.into_owned();
+    }
+    let mut end = max_bytes;
+    while !contents.is_char_boundary(end) {
+        end -= 1;
+    }
+    let note = format!(
+        "{path} is {}, more than a single-file report holds; this is its first {}. Write the report to a directory with -o for all of it.",
+        fmt_bytes(contents.len() as u64),
+        fmt_bytes(end as u64)
+    );
+    if path.ends_with(".html") {
+        format!(
+            "<p>{}</p><pre>{}</pre>",
+            encode_text(&note),
+            encode_text(&contents[..end])
+        )
+    } else {
+        format!("{note}\n\n{}", &contents[..end])
+    }
+}
+
+// The report of files (as an OutputSink got them) in one self-contained document.  raw.log and
+// files that aren't UTF-8 are left out, with a note in their place, and files bigger than
+// max_bytes are cut short.
+pub fn single_file_report(files: &[(PathBuf, Vec<u8>)], max_bytes: usize) -> String {
+    let mut entries = serde_json::Map::new();
+    for (path, contents) in files {
+        // Links between pages always use /
+        let name = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let entry = match left_out(path, contents) {
+            Some(why) => format!(
+                "{name} {why}, so it's left out of single-file reports. Write the report to a directory with -o for it."
+            ),
+            None => report_entry(&name, contents, max_bytes),
+        };
+        entries.insert(name, entry.into());
+    }
+    // A </script> (or <!--) in a page mustn't end the script holding it
+    let json = serde_json::Value::Object(entries)
+        .to_string()
+        .replace('<', "\\u003c");
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>tlparse</title>
+<style>
+body {{ margin: 0; }}
+#tlparse-page {{ display: block; border: 0; width: 100%; height: 100vh; }}
+</style>
+</head>
+<body>
+<iframe id="tlparse-page"></iframe>
+<script id="tlparse-files" type="application/json">{json}</script>
+{VIEWER}
+</body>
+</html>
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(report: &str) -> serde_json::Value {
+        let start = report.find("application/json\">").unwrap() + "application/json\">".len();
+        let end = start + report[start..].find("</script>").unwrap();
+        serde_json::from_str(&report[start..end]).unwrap()
+    }
+
+    #[test]
+    fn test_single_file_report() {
+        let files = vec![
+            (
+                PathBuf::from("index.html"),
+                b"<script>x()</script>".to_vec(),
+            ),
+            (PathBuf::from("0_0_0/big.txt"), "é".repeat(20).into_bytes()),
+            (PathBuf::from("0_0_0/big.html"), b"<b>".repeat(10)),
+            (PathBuf::from("raw.log"), b"V1014 ...".to_vec()),
+            (PathBuf::from("chart.bin"), vec![0xff, 0xfe]),
+        ];
+        let report = single_file_report(&files, 21);
+        // The page's own </script> is escaped, so only the report's two scripts end
+        assert_eq!(report.matches("</script>").count(), 2);
+        let entries = entries(&report);
+        assert_eq!(entries["index.html"], "<script>x()</script>");
+        // Cut at a character boundary, past the note
+        let big = entries["0_0_0/big.txt"].as_str().unwrap();
+        assert!(big.starts_with("0_0_0/big.txt is 40 B"), "{big}");
+        assert!(big.ends_with(&format!("\n\n{}", "é".repeat(10))), "{big}");
+        let big = entries["0_0_0/big.html"].as_str().unwrap();
+        assert!(big.ends_with(
+            "<pre>&lt;b&gt;&lt;b&gt;&lt;b&gt;&lt;b&gt;&lt;b&gt;&lt;b&gt;&lt;b&gt;</pre>"
+        ));
+        for left_out in ["raw.log", "chart.bin"] {
+            assert!(entries[left_out]
+                .as_str()
+                .unwrap()
+                .contains("left out of single-file reports"));
+        }
+    }
+}
@@ -4,52 +4,1011 @@ table td { vertical-align: top; }
 .stack-trie { white-space: nowrap; font-family: monospace; }
 .stack-trie ul { padding-left: 1ch;  }
 .stack-trie li { margin-left: 1ch; list-style-type: none;  }
-.stack-trie .marker {
-  cursor: pointer;
-}
-.stack-trie .marker.collapsed::before {
-  content: "+ ";
-}
-.stack-trie .marker:not(.collapsed)::before {
-  content: "- ";
-}
+.stack-trie summary { cursor: pointer; }
+.stack-trie .source { text-decoration: underline dotted; }
+.internal-frames, .truncated-frames { color: #999; }
+.stack-trie .first-seen { color: #999; font-size: smaller; }
 .stack-trie a { text-decoration: none; }
 .stack-trie a:hover { text-decoration: underline; }
+.artifact-size, .compile-span, .attempt-outcomes, .compile-group-counts { color: #999; }
+.compile-list li.compile-group { list-style-type: none; }
+.compile-list summary { cursor: pointer; }
+.artifact-preview { color: #999; font-size: smaller; }
+.artifact-preview summary { cursor: pointer; }
+.artifact-preview pre { margin: 0 0 0.5em 2ch; }
+.guard-added { color: #080; margin: 0; }
+.guard-removed { color: #c00; margin: 0; }
+.guard-churning { background-color: #ff0; }
+.rank-divergent { background-color: #fdd; }
+.kind-missing { color: #999; }
+.histogram td:last-child { width: 20em; }
+.histogram-bar { background-color: steelblue; height: 1em; }
+.sparkline { font-family: monospace; text-decoration: none; }
+.stack-trie li.trie-match:not(:has(> details)), .stack-trie li.trie-match > details > summary { background-color: yellow; }
 .status-missing { background-color: purple; color: white; }
 .status-error { background-color: red; color: white; }
 .status-empty { background-color: white; color: black; }
 .status-ok { background-color: green; color: white; }
 .status-break { background-color: lime; color: black; }
+.badge { display: inline-block; padding: 0 0.5em; border-radius: 0.7em; font-family: sans-serif; font-size: 0.8em; line-height: 1.5; vertical-align: middle; }
+.badge-warning { background-color: orange; color: black; }
+.data-issues { background-color: #fff3b0; color: black; border: 1px solid #e0c000; padding: 0 1em; }
+.compile-filter label { margin-right: 1em; }
+.diff { border-collapse: collapse; font-family: monospace; }
+.diff td { padding: 0 0.5em; }
+.diff pre { margin: 0; }
+.diff .lineno { color: #999; text-align: right; }
+.diff-added { background-color: #dfd; }
+.diff-removed { background-color: #fdd; }
+.diff-skip { color: #999; font-style: italic; }
+.chart { max-width: 100%; height: auto; display: block; margin-bottom: 1em; }
+"#;
+
+pub static TEMPLATE_DYNAMO_GUARDS: &str = r#"
+<html>
+<head>
+<meta charset="UTF-8">
+</head>
+<body>
+<h2>Guards</h2>
+<p class="guard-filter" hidden>
+Show <select>
+<option value="">all {num_guards} guards</option>
+{{ for k in kinds }}<option value="{k.0}">{k.0} ({k.1})</option>
+{{ endfor }}</select>
+<input type="search" placeholder="and only expressions containing" size="40">
+<span class="guard-filter-count"></span>
+</p>
+<table class="guards">
+<thead>
+<tr> <th data-numeric="1">#</th> <th>Type</th> <th>Expression</th> <th>Source</th> </tr>
+</thead>
+<tbody>
+{{ for guard in guards }}
+<tr data-kind="{guard.kind}"> <td>{guard.number}</td> <td>{guard.kind}</td> <td>{{ if guard.preview }}<details><summary><code>{guard.preview}</code></summary><code>{guard.code}</code></details>{{ else }}<code>{guard.code}</code>{{ endif }}</td> <td>{{ if guard.source }}<code>{guard.source}</code>{{ endif }}</td> </tr>
+{{ endfor }}
+</tbody>
+</table>
+{guards_table | format_unescaped}
+{qps | format_unescaped}
+</body>
+</html>
+"#;
+
+// Sorting by any column of the guards table (by clicking its header) and filtering it by type and
+// expression.  The filter only shows up once the script runs; without JS the table is all there.
+pub static TEMPLATE_GUARDS_TABLE_SCRIPT: &str = r#"
+<style>
+.guards td, .guards th { text-align: left; vertical-align: top; padding: 2px 8px; }
+.guards th { cursor: pointer; user-select: none; }
+.guards code { white-space: pre-wrap; word-break: break-all; }
+.guards summary { cursor: pointer; }
+.guards details[open] > summary { display: none; }
+</style>
+<script>
+document.addEventListener('DOMContentLoaded', function() {
+    const table = document.querySelector('.guards');
+    const rows = Array.from(table.tBodies[0].rows);
+    const box = document.querySelector('.guard-filter');
+    const kind = box.querySelector('select');
+    const input = box.querySelector('input');
+    const count = box.querySelector('.guard-filter-count');
+    box.hidden = false;
+
+    function update() {
+        const query = input.value.trim().toLowerCase();
+        let found = 0;
+        for (const row of rows) {
+            const match = (kind.value === '' || row.dataset.kind === kind.value)
+                && (query === '' || row.cells[2].textContent.toLowerCase().includes(query));
+            row.hidden = !match;
+            if (match) found++;
+        }
+        count.textContent = kind.value === '' && query === '' ? '' : `${found} of ${rows.length}`;
+    }
+    kind.addEventListener('change', update);
+    input.addEventListener('input', update);
+
+    // Clicking a header again reverses the order
+    table.querySelectorAll('th').forEach((th, column) => {
+        th.addEventListener('click', function() {
+            const numeric = th.dataset.numeric === '1';
+            const descending = th.dataset.sorted === 'asc';
+            table.querySelectorAll('th').forEach((other) => { delete other.dataset.sorted; });
+            th.dataset.sorted = descending ? 'desc' : 'asc';
+            const key = (row) => numeric ? Number(row.cells[column].textContent)
+                : row.cells[column].textContent;
+            rows.sort((a, b) => {
+                const [x, y] = [key(a), key(b)];
+                const order = numeric ? x - y : x.localeCompare(y);
+                return descending ? -order : order;
+            });
+            for (const row of rows) table.tBodies[0].appendChild(row);
+        });
+    });
+});
+</script>
+"#;
+
+pub static TEMPLATE_INDEX: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<p class="dashboard-link"><strong><a href="dashboard.html">Dashboard</a></strong>: compile times, compiles over time, cache hits, graph breaks and failures as charts.</p>
+{{ if multiple_runs }}
+<p>
+This log contains several runs (a restarted job appended to the same file), this is
+<strong>run {run}</strong>.  See <a href="../index.html">all runs</a>.
+</p>
+{{ endif }}
+{{ if num_ranks }}
+<p>
+This log has <strong>{num_ranks} ranks</strong>, only rank {stack_trie_rank} is shown here.  See
+<a href="ranks.html">compiles per rank</a> for what each of them compiled{{ if num_divergent_rank_compiles }};
+<span class="status-error">the ranks disagree on {num_divergent_rank_compiles} compile(s)</span>{{ endif }}.
+</p>
+{{ endif }}
+{{ if intern_conflicts }}
+<h2>Intern table conflicts</h2>
+<p>
+The same intern id was assigned different strings in this log, which usually means several
+processes were writing to the same file.  The first assignment was kept, so file names in stacks
+below may be wrong.
+</p>
+<table>
+<tr> <th>Id</th> <th>Kept</th> <th>Ignored</th> </tr>
+{{ for conflict in intern_conflicts }}
+<tr> <td>{conflict.0}</td> <td><code>{conflict.1}</code></td> <td><code>{conflict.2}</code></td> </tr>
+{{ endfor }}
+</table>
+{{ endif }}
+{{ if recompile_loops }}
+<h2>Recompile loops</h2>
+<p>
+These frames kept recompiling from the same stack in the last {recompile_loop_window} of the log,
+long after warming up, and are unlikely to ever stop.  A guard that keeps coming and going is
+usually the cause{{ if has_guard_churn }}; see <a href="guard_churn.html">guard churn</a>{{ endif }}.
+</p>
+<ul>
+{{ for l in recompile_loops }}
+<li><span class="status-error">⚠ Frame {l.frame_id}</span> <code>{l.frame}</code>: {l.num_recompiles} recompiles ({{ for c in l.recompiles }}<a href='#{c.1}'>{c.0}</a>{{ if not @last }} {{ endif }}{{ endfor }}){{ if l.guard }}, <code>{l.guard.0}</code> came and went {l.guard.1} times{{ endif }}</li>
+{{ endfor }}
+</ul>
+{{ endif }}
+{{ if compile_time_summary }}
+<div class="summary">
+<h2>Summary</h2>
+<p>
+Compiles: <strong>{compile_time_summary.num_compiles}</strong>.
+{{ if compile_time_summary.num_timed }}
+Compile time: total <strong>{compile_time_summary.total}</strong>,
+mean {compile_time_summary.mean}, p50 {compile_time_summary.p50}, p90 {compile_time_summary.p90},
+max {compile_time_summary.max}
+(from {compile_time_summary.source}{{ if compile_time_summary.untimed }}; {compile_time_summary.untimed} compiles have no time{{ endif }}).
+</p>
+<table class="histogram">
+{{ for bucket in compile_time_summary.histogram }}
+<tr> <td>{bucket.label}</td> <td>{bucket.count}</td> <td><div class="histogram-bar" style="width: {bucket.width}%"></div></td> </tr>
+{{ endfor }}
+</table>
+{{ else }}
+None of them have a compile time, from compilation metrics or from log timestamps.
+</p>
+{{ endif }}
+{{ if wall_clock }}
+<p>Wall clock: {wall_clock} <abbr title="From log timestamps: the union of every compile's span, against the first to the last entry of the log">[?]</abbr>.</p>
+{{ endif }}
+{{ if timeline_sparkline }}
+<p>Graph breaks and recompiles over time: <a href="timeline.html" class="sparkline">{timeline_sparkline}</a>{{ if timeline_late_events }}; <span class="status-error">{timeline_late_events} happened in the second half of the log</span>, so compilation may not be converging{{ endif }}.</p>
+{{ endif }}
+{{ if dynamic_shapes }}
+<p>Dynamic shapes: {dynamic_shapes.total.specialized} of {dynamic_shapes.total.candidates} candidate dimensions were specialized ({dynamic_shapes.specialized_percent}), {dynamic_shapes.total.dynamic} stayed dynamic <abbr title="Every symbol a compile created for an input, or specialized to a constant, is a candidate">[?]</abbr>.</p>
+<details>
+<summary>By compile</summary>
+<table>
+<tr> <th>Compile</th> <th>Dynamic</th> <th>Specialized</th> </tr>
+{{ for c in dynamic_shapes.compiles }}
+<tr> <td><a href="{c.1}">{c.0}</a></td> <td>{c.2.dynamic}</td> <td>{c.2.specialized}</td> </tr>
+{{ endfor }}
+</table>
+</details>
+{{ endif }}
+<details>
+<summary>Log entries by kind</summary>
+<p>A kind with no entries usually wasn't enabled when the log was captured.</p>
+<table class="envelope-kinds">
+{{ for k in envelope_kinds.known }}
+<tr{{ if not k.count }} class="kind-missing"{{ endif }}> <td><code>{k.kind}</code></td> <td>{k.count}</td> </tr>
+{{ endfor }}
+</table>
+{{ if envelope_kinds.unknown }}
+<p>{envelope_kinds.unknown} entries were of no known kind, with the fields {{ for f in envelope_kinds.unknown_fields }}<code>{f.kind}</code> ({f.count}){{ if not @last }}, {{ endif }}{{ endfor }}.</p>
+{{ endif }}
+</details>
+{{ if phase_breakdown }}
+<p>
+Compile time by phase: total <strong>{phase_breakdown.total}</strong> —{{ for p in phase_breakdown.phases }} {p.0} {p.1}{{ if not @last }},{{ endif }}{{ endfor }}
+<abbr title="Phases nest (backend compile includes aot autograd, which includes inductor), so they don't add up to the total">[?]</abbr>
+</p>
+<details>
+<summary>By compile</summary>
+<table class="phases">
+<tr> <th>Compile</th> <th>Total</th> {{ for c in phase_breakdown.columns }}<th>{c}</th> {{ endfor }}</tr>
+{{ for row in phase_breakdown.rows }}
+<tr> <td><a href="{row.url}">{row.compile_id}</a>{{ if row.frame }} — <code>{row.frame}</code>{{ endif }}</td> <td>{row.total}</td> {{ for t in row.phases }}<td>{t}</td> {{ endfor }}</tr>
+{{ endfor }}
+</table>
+</details>
+{{ endif }}
+{{ if cache_hit_rate }}
+<p>{cache_hit_rate} of FX graph cache lookups hit; see the <a href="cache.html">cache dashboard</a>.</p>
+{{ endif }}
+{{ if has_attempts }}
+<p>For how each attempt of every compile went, see <a href="attempts.html">attempts per compile</a>.</p>
+{{ endif }}
+{{ if num_duplicate_graphs }}
+<p>{num_duplicate_graphs} groups of compiles captured the same graph; see <a href="duplicate_graphs.html">duplicate graphs</a>.</p>
+{{ endif }}
+{{ if num_eager_fallbacks }}
+<p><span class="status-error">{num_eager_fallbacks} frame(s) run in eager</span> after failing, being skipped or reaching the recompile limit; see <a href="eager_fallbacks.html">eager fallbacks</a>.</p>
+{{ endif }}
+{{ if num_missing_artifacts }}
+<p><span class="status-missing">{num_missing_artifacts} compile(s) are missing artifacts</span> that other compiles of the log have, so logging was partly disabled or they died without a word; they're marked below.</p>
+{{ endif }}
+{{ if has_top_compiles }}
+<p>For the compiles that took longest, see <a href="top.html">top compiles</a>.</p>
+{{ endif }}
+{{ if has_source_files }}
+<p>For which files and packages cause the most compiles, see <a href="source_files.html">compiles by source</a>.</p>
+{{ endif }}
+{{ if has_ops_histogram }}
+<p>For which operators the captured graphs call, see the <a href="ops_histogram.html">operator histogram</a>.</p>
+{{ endif }}
+{{ if has_largest_artifacts }}
+<p>For what takes up the most space in the log, see <a href="largest_artifacts.html">largest payloads</a>.</p>
+{{ endif }}
+{{ if frames_at_recompile_limit }}
+<p><span class="status-error">⚠ {frames_at_recompile_limit} frame(s) reached the recompile limit</span> ({cache_size_limit} compiles) and run in eager from then on; see <a href="recompiles.html">recompiles per frame</a>.</p>
+{{ else }}{{ if has_recompiles }}
+<p>For how many times each frame was compiled, see <a href="recompiles.html">recompiles per frame</a>.</p>
+{{ endif }}{{ endif }}
+{{ if has_guard_churn }}
+<p>For how the guards changed from one compile of a frame to the next, see <a href="guard_churn.html">guard churn</a>{{ if num_churning_guards }}; <span class="guard-churning">{num_churning_guards} guard(s) kept appearing and disappearing</span>{{ endif }}.</p>
+{{ endif }}
+</div>
+{{ endif }}
+<h2>Stack trie</h2>
+<p>
+The <strong>stack trie</strong> is a way of getting a quick orientation on where all the
+compilations in a model take place, esp., if you are compiling a codebase you are unfamiliar with.
+It is a tree of stack frames, for all stacks that triggered PT2 compilation.  If only a single
+stack is in the tree, you will simply see a plain list of frames (most recent call last).  With
+multiple stacks, at every point where two stacks diverge from having a common prefix, we increase
+the indentation of the list and have a separate sub-list per sub-tree.
+</p>
+<p>
+Links to particular compilation are color coded by status:
+<span class="status-ok">[Success]</span>,
+<span class="status-break">[Success with restart (e.g., graph break)]</span>,
+<span class="status-empty">[Empty graph]</span>,
+<span class="status-error">[✗ Error]</span>,
+<span class="status-missing">[Metrics were missing]</span>
+</p>
+{{ if trie_failed_only }}
+<p>Only compiles that failed are shown.</p>
+{{ endif }}
+{{ if trie_filter }}
+<p>Only stacks with a frame matching <code>{trie_filter}</code> are shown.</p>
+{{ endif }}
+{{ if has_compiles_by_file }}
+<p>To find the compiles whose stack goes through a particular source file, see <a href="by_file.html">compiles by file</a>.</p>
+{{ endif }}
+{{ if has_condensed_stack_trie }}
+<p>To see just the call structure of your own code, without frames in torch or the standard library, see the <a href="stack_trie_condensed.html">condensed view</a>.</p>
+{{ endif }}
+{{ if internal_frames_hidden }}
+<p>Runs of internal frames (inside torch, or under an <code>--internal-frame-prefix</code>) are collapsed; pass <code>--show-internal-frames</code> to see them.</p>
+{{ endif }}
+{{ if other_rank_stack_tries }}
+<p>This log has several ranks; the trie below is for rank {stack_trie_rank}, the other ranks' tries follow it.</p>
+{{ endif }}
+{trie_search | format_unescaped}
+{stack_trie_html | format_unescaped}
+{{ for t in other_rank_stack_tries }}
+<details>
+<summary>Rank {t.0}</summary>
+{t.1 | format_unescaped}
+</details>
+{{ endfor }}
+</div>
+<div>
+{{ if num_breaks }}
+<h2> Failures and Restarts </h2>
+<p>
+Various issues may cause Dynamo to restart its analysis or give up on compilation entirely, causing graph breaks and fallbacks to eager mode.
+This run had <strong><a href="failures_and_restarts.html">{num_breaks} restart(s) and/or compilation failure(s)</a></strong>.
+</p>
+{{ endif }}
+<h2>IR dumps</h2>
+<p>
+The <strong>IR dumps</strong> collected dumped intermediate products from various points of the PT2
+compilation process.  The products are organized by compile id, and then sorted in chronological
+order.
+</p>
+<p>
+A <strong>compile id</strong> uniquely identifies are particular compilation inside a PT2
+program.  It is traditionally written as <code>[x/y]</code>, where the <strong>frame id</strong> x
+identifies the particular Python frame which we are compiling, and <strong>frame compile
+id</strong> y identifies how many times we've recompiled this same frame.  For example,
+<code>[0/0]</code> refers to the very first frame compiled by PT2; <code>[0/1]</code> refers to the
+first recompilation of this frame, while <code>[1/0]</code> refers to a different frame, within
+distinct code cache, which we are compiling next (perhaps because of a graph break).  Although
+Dynamo treats distinct frames as completely unrelated, a frame compilation could overlap with another
+frame; for example, if you graph break in an inlined function, Dynamo will typically try to compile
+the nested frame again on an inner frame.  You can identify the hierarchical relationship between
+frames by looking at the stack trie above.
+</p>
+<p>
+In some situations, the compile id will have an extra signifier <code>[x/y_z]</code>, where z is the
+<strong>attempt</strong> for this particular (re)compilation.  Certain conditions will cause Dynamo to
+restart analysis, when Dynamo discovers that it needs to undo a decision it previously made.  The most
+common cause of recompilation is a graph break in an inlined function call, which forces to restart
+and avoid inlining the function in the first place.
+</p>
+<p>
+Here is a high level description of PT2's compilation phases, and the intermediate products each
+phase generates:
+</p>
+<ol>
+<li><em>Optional:</em> If compiled autograd is enabled, and we are processing a backward call, compiled autograd will trace the autograd graph from the autograd engine, and produce an FX graph <code>compiled_autograd_graph</code> that will be Dynamo traced.  Otherwise, Dynamo will directly trace user's bytecode.</li>
+<li>Dynamo symbolically evaluates the Python bytecode of a program, producing <code>dynamo_output_graph</code></li>
+<li><em>Optional:</em> If <code>optimize_ddp</code> is enabled, the DDPOptimizer will split the Dynamo output graph to improve pipelining communications.  Each split subgraph is <code>optimize_ddp_split_child_submod</code>, and the high level graph that plumbs the graphs together is <code>optimize_ddp_split_graph</code>.  If there are multiple splits, each subsequent build product will be produced multiple times, one for each split.</li>
+<li>AOTAutograd traces the (possibly split) Dynamo output graph, producing a <code>aot_joint_graph</code> if backwards is enabled.  It then partitions the graph into <code>aot_forward_graph</code> and <code>aot_backward_graph</code>.  If training is not needed, there may only be an <code>aot_inference_graph</code>.</li>
+<li>Inductor will apply some post grad FX passes, producing <code>inductor_post_grad_graph</code></li>
+<li>Inductor will perform code generation, producing the final <code>inductor_output_code</code> which will be executed at runtime.  This output is a valid Python program and can be directly run.</li>
+</ol>
+
+{{ if has_chromium_events }}
+<h2> Chromium Events </h2>
+PT2 generates <a href='chromium_events.json'>Chromium Trace Events</a> in JSON on specific events during compilation.
+You can download and view them in a tool like <a href='https://ui.perfetto.dev/'>Perfetto</a>.
+{{ endif  }}
+<p>
+Build products below; each compile links to its own page, with what is known about it and its artifacts:
+</p>
+{{ if index_shards }}
+<p>There are too many to list on one page, so they are split by frame:</p>
+<ul class="index-shards">
+{{ for s in index_shards }}
+    <li><a href="{s.url}" data-frames="{s.frames}">{s.label}</a> <span class="compile-group-counts">({s.num_frames} frame(s), {s.num_compiles} compile(s), {s.num_attempts} attempt(s){{ if s.num_failed }}, {s.num_failed} failed{{ endif }})</span></li>
+{{ endfor }}
+</ul>
+{index_shard_redirect | format_unescaped}
+{{ else }}
+{compile_search | format_unescaped}
+{{ endif }}
+{{ call compile_list.html with compiles }}
+</div>
+
+{{ if has_unknown_stack_trie }}
+<div>
+<h2>Unknown stacks</h2>
+<p>
+  Sometimes, logs are made without a compile id.  This makes it difficult to correlate related
+  logs.  This stack trie shows all places where log entries occurred without compile context; to
+  fix, look an appropriate place in the stack where compile id should have been specified.
+</p>
+{unknown_stack_trie_html | format_unescaped}
+</div>
+{{ endif }}
+{qps | format_unescaped}
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_CONDENSED_STACK_TRIE: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Stack trie (condensed)</h2>
+<p>
+This is the <a href="index.html">stack trie</a> with only the frames in your own code: frames in
+torch and the standard library have been taken out, and stacks that only differed in those frames
+have been merged.  Compiles are listed at the last of your frames before they started, and
+compiles whose stack was entirely in torch don't appear at all.
+</p>
+{stack_trie_html | format_unescaped}
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_BY_FILE: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Compiles by file</h2>
+<p>
+Every source file that appears in the stack of a compile, with the compiles whose stack goes
+through it, most compiles first.  Restarts of a compile are listed but not counted again.
+</p>
+<table>
+<tr> <th>File</th> <th>Compiles</th> <th>Compile ids</th> </tr>
+{{ for file in files }}
+<tr> <td><code>{file.filename}</code></td> <td>{file.num_compiles}</td> <td>{{ for c in file.compiles }}<a href="index.html#{c.1}">{c.0}</a> {{ endfor }}</td> </tr>
+{{ endfor }}
+</table>
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_RECOMPILES: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Recompiles per frame</h2>
+<p>
+Every frame Dynamo compiled, with how many times it was compiled (its recompiles, the y of
+<code>[x/y]</code>) and how many attempts that took in all, most compiled first.  A frame compiled
+{cache_size_limit} times has reached Dynamo's default <code>cache_size_limit</code>
+and is marked ⚠: it won't be compiled again, and silently runs in eager from then on.
+</p>
+<table>
+<tr> <th>Frame</th> <th>Where</th> <th>Compiles</th> <th>Attempts</th> <th>Compile ids</th> </tr>
+{{ for frame in frames }}
+<tr> <td>{frame.frame_id}</td> <td><code>{frame.label}</code></td> <td>{{ if frame.at_limit }}<span class="status-error">⚠ {frame.num_compiles}</span>{{ else }}{frame.num_compiles}{{ endif }}</td> <td>{frame.num_attempts}</td> <td>{{ for c in frame.compiles }}<a href="index.html#{c.1}">{c.0}</a> {{ endfor }}</td> </tr>
+{{ endfor }}
+</table>
+{{ for frame in frames }}{{ if frame.causes }}
+<h3>Why frame {frame.frame_id} recompiled</h3>
+<ul>
+{{ for c in frame.causes }}<li>
+{{ if c.2.failed_guard }}compile <a href="index.html#{c.1}">{c.0}</a> happened because guard <code>{c.2.failed_guard.guard}</code> from <a href="index.html#{c.2.failed_guard.compile.1}">{c.2.failed_guard.compile.0}</a> failed{{ if c.2.failed_guard.actual }} (actual: {c.2.failed_guard.actual}){{ endif }}
+{{ else }}compile <a href="index.html#{c.1}">{c.0}</a> happened because: <code>{c.2.reason}</code>
+{{ endif }}</li>
+{{ endfor }}</ul>
+{{ endif }}{{ endfor }}
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_EAGER_FALLBACKS: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Eager fallbacks</h2>
+<p>
+The frames that torch.compile isn't compiling in steady state, going by how the last attempt
+of their last compile went.  A frame that reached the recompile limit ({cache_size_limit}
+compiles) isn't compiled again; a <em>skipped</em> frame is one Dynamo chose not to compile; a
+<em>failed</em> one it tried to compile and gave up on, and runs in eager if errors are
+suppressed.  When several apply, they're taken in that order.
+</p>
+<table>
+<tr> <th>Frame</th> <th>Where</th> <th>Why</th> <th>Last compile</th> <th>Reason</th> </tr>
+{{ for frame in frames }}
+<tr> <td>{frame.frame_id}</td> <td><code>{frame.label}</code></td> <td>{frame.fallback}</td> <td><a href="index.html#{frame.compile.1}">{frame.compile.0}</a></td> <td><pre>{frame.reason}</pre></td> </tr>
+{{ endfor }}
+</table>
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_TOP_COMPILES: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Top compiles</h2>
+<p>
+The compiles that took longest, by <code>entire_frame_compile_time_s</code> of their compilation
+metrics.  Compiles without metrics are timed by their span in the log instead (marked
+<em>span</em>), which can include time spent running rather than compiling.  Every attempt is
+listed on its own.
+</p>
+<table>
+<tr> <th>Compile</th> <th>Time</th> <th>Nodes</th> <th>Artifacts</th> </tr>
+{{ for c in compiles }}
+<tr> <td><a href="index.html#{c.anchor}">{c.compile_id}</a> — <code>{c.frame}</code></td> <td>{c.time}{{ if c.from_span }} <em>span</em>{{ endif }}</td> <td>{c.node_count}</td> <td>{{ for f in c.files }}<a href="{f.0}">{f.1}</a> {{ endfor }}</td> </tr>
+{{ endfor }}
+</table>
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_RANKS: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Compiles per rank</h2>
+<p>
+Every compile of any rank, with how many attempts each rank made of it and how long they took
+(<code>entire_frame_compile_time_s</code> of their compilation metrics).  Highlighted rows are
+compiles the ranks disagree on: some rank didn't compile it, or made a different number of
+attempts.  A rank that recompiles while the others wait for it in a collective is a classic
+cause of distributed hangs.  Links go to rank {rendered_rank}, the one the rest of the report is
+about.
+</p>
+<table>
+<tr> <th>Compile</th> {{ for r in ranks }}<th>Rank {r}</th> {{ endfor }}</tr>
+{{ for row in rows }}
+<tr{{ if row.divergent }} class="rank-divergent"{{ endif }}> <td>{{ if row.anchor }}<a href="index.html#{row.anchor}">{row.compile_id}</a>{{ else }}{row.compile_id}{{ endif }}</td> {{ for c in row.cells }}<td>{c}</td> {{ endfor }}</tr>
+{{ endfor }}
+</table>
+</div>
+</body>
+</html>
+"#;
+
+// The bar at the top of every page, see add_breadcrumbs; styled inline, as not every page has CSS
+pub static TEMPLATE_BREADCRUMBS: &str = r#"
+<div class="breadcrumbs" style="font-family: sans-serif; font-size: 14px; padding: 4px 0; margin-bottom: 8px; border-bottom: 1px solid #ddd;">
+<a href="{index}">{title}</a>{{ if compile }} › <a href="{compile.1}">{compile.0}</a>{{ endif }}
+{{ if prev }}<span style="margin-left: 2em;"><a href="{prev.1}">← {prev.0}</a></span>{{ endif }}
+{{ if next }}<span style="margin-left: 1em;"><a href="{next.1}">{next.0} →</a></span>{{ endif }}
+{dark_mode | format_unescaped}
+</div>
+"#;
+
+pub static TEMPLATE_PROVENANCE: &str = r#"
+<div class="provenance" style="font-family: sans-serif; font-size: 12px; color: #999; padding: 4px 0; margin-top: 16px; border-top: 1px solid #ddd;">
+Generated by tlparse {tlparse_version} on {generated} from <code>{input}</code>{{ if input_modified }} ({input_size} bytes, modified {input_modified}){{ endif }}{{ if rank_source }}, rank {rank} ({rank_source}){{ endif }}{{ if flags }}, with <code>{{ for flag in flags }}{{ if not @first }} {{ endif }}{flag}{{ endfor }}</code>{{ endif }}
+</div>
+"#;
+
+// The dark palette, one rule per line, for every page of output (pages have stylesheets of their
+// own, so this overrides them all rather than being part of CSS).  Each selector gets the root
+// prefixed, see dark_mode_html in lib.rs; a selector of just :root is the root itself.
+pub static DARK_MODE_RULES: &str = r#"
+:root { color-scheme: dark; }
+body { background-color: #1e1e1e; color: #ddd; }
+a { color: #8ab4f8; }
+a:visited { color: #c58af9; }
+.breadcrumbs { border-bottom-color: #444 !important; }
+.provenance { border-top-color: #444 !important; }
+th { background-color: #333; }
+table, th, td { border-color: #555; }
+tr:nth-child(odd):not([class]) { background-color: #262626; }
+.guard-added { color: #6c6; }
+.guard-removed { color: #f66; }
+.guard-churning { background-color: #5c5200; }
+.rank-divergent, .diff-removed { background-color: #4a2323; }
+.diff-added { background-color: #1f3d1f; }
+.stack-trie li.trie-match:not(:has(> details)), .stack-trie li.trie-match > details > summary { background-color: #5c5200; }
+.status-empty { background-color: #333; color: #ddd; }
+.chart text { fill: #ddd; }
+.json-key { color: #9cdcfe; }
+.json-string { color: #8c8; }
+.json-number { color: #8ab4f8; }
+.json-literal { color: #d7a; }
+pre:has(> .line) { background-color: #f8f8f8; color: #323232; }
+"#;
+
+// The manual switch in the breadcrumb bar, overriding prefers-color-scheme for every page once
+// ticked or unticked (kept in localStorage).  Hidden until the script shows it, so without
+// JavaScript a page only follows the system setting.
+pub static TEMPLATE_DARK_MODE_TOGGLE: &str = r#"
+<label class="dark-mode-toggle" style="float: right;" hidden><input type="checkbox"> Dark mode</label>
+<script>
+(function() {
+    const root = document.documentElement;
+    const label = document.currentScript.parentElement.querySelector(".dark-mode-toggle");
+    const checkbox = label.querySelector("input");
+    const apply = (dark) => {
+        root.classList.toggle("dark-mode", dark);
+        root.classList.toggle("light-mode", !dark);
+        checkbox.checked = dark;
+    };
+    let saved = null;
+    try { saved = localStorage.getItem("tlparse-dark-mode"); } catch (e) {}
+    if (saved === null) {
+        checkbox.checked = window.matchMedia("(prefers-color-scheme: dark)").matches;
+    } else {
+        apply(saved === "on");
+    }
+    checkbox.addEventListener("change", () => {
+        apply(checkbox.checked);
+        try { localStorage.setItem("tlparse-dark-mode", checkbox.checked ? "on" : "off"); } catch (e) {}
+    });
+    label.hidden = false;
+})();
+</script>
+"#;
+
+pub static TEMPLATE_COMPILE: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Compile {compile_id}</h2>
+<p><a href='../index.html#{anchor}'>Back to the index</a></p>
+{{ if issues }}
+<div class="data-issues">
+<p>⚠ Some of this compile's log entries weren't logged as expected, so its artifacts may be
+incomplete or wrong.
+This is probably a bug in logging, worth reporting with these details:</p>
+<ul>
+{{ for issue in issues }}<li>{issue}</li>
+{{ endfor }}</ul>
+</div>
+{{ endif }}
+<table>
+{{ if status }}<tr> <th>Status</th> <td><span class="{status_class}">{status}</span></td> </tr>{{ endif }}
+{{ if start }}<tr> <th>First log entry</th> <td>{start}</td> </tr>
+<tr> <th>Last log entry</th> <td>{end} ({span})</td> </tr>{{ endif }}
+{{ if compile_time }}<tr> <th>Compile time</th> <td>{compile_time}{{ if compile_time_from_span }} (from its span, it has no compilation metrics timing){{ endif }}</td> </tr>{{ endif }}
+{{ if diff }}<tr> <th>Previous attempt</th> <td><a href='{diff.1}'>what changed since {diff.0}</a></td> </tr>{{ endif }}
+{{ if raw_log }}<tr> <th>Raw log</th> <td><a href='{raw_log.0}'>raw.log</a> ({raw_log.1})</td> </tr>{{ endif }}
+{{ if frame }}<tr> <th>Frame</th> <td><code>{frame}</code></td> </tr>{{ endif }}
+{{ if guard_count }}<tr> <th>Guards</th> <td>{guard_count}</td> </tr>{{ endif }}
+{{ if node_count }}<tr> <th>Graph nodes</th> <td>{node_count}</td> </tr>{{ endif }}
+</table>
+{{ if missing }}
+<p><span class="status-missing">{missing}</span></p>
+{{ endif }}
+{{ if restart_reasons }}
+<h3>Restart reasons</h3>
+<ul>
+{{ for r in restart_reasons }}<li><code>{r}</code></li>
+{{ endfor }}</ul>
+{{ endif }}
+{{ if recompile_causes }}
+<h3>Why it recompiled</h3>
+<ul>
+{{ for c in recompile_causes }}<li>
+{{ if c.failed_guard }}guard <code>{c.failed_guard.guard}</code> from <a href="../index.html#{c.failed_guard.compile.1}">{c.failed_guard.compile.0}</a> failed{{ if c.failed_guard.actual }} (actual: {c.failed_guard.actual}){{ endif }}
+{{ else }}<code>{c.reason}</code>
+{{ endif }}</li>
+{{ endfor }}</ul>
+{{ endif }}
+<h3>Artifacts</h3>
+{{ for g in groups }}
+<h4>{g.category}</h4>
+<ul>
+{{ for a in g.artifacts }}<li><a href="{a.url}">{a.name}</a>{a.size | format_bytes} {a.suffix} ({a.number}){{ if a.highlighted }} <a href="{a.highlighted}">highlighted</a>{{ endif }}
+{{ if a.preview }}<details class="artifact-preview"><summary>preview</summary><pre>{a.preview}</pre></details>{{ endif }}</li>
+{{ endfor }}</ul>
+{{ endfor }}
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_TIMELINE: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Graph breaks and recompiles over time</h2>
+<p>
+Graph breaks (restart reasons of compilation metrics) and recompiles (a frame compiled again) in
+windows of {bucket}, from the first entry of the log.  They normally all happen at startup; if
+they keep happening while the model runs, something like data-dependent branching or changing
+shapes keeps it compiling.
+</p>
+<table class="histogram">
+<tr> <th>From</th> <th>Graph breaks</th> <th>Recompiles</th> <th></th> </tr>
+{{ for b in rows }}
+<tr> <td>{b.start}</td> <td>{b.graph_breaks}</td> <td>{b.recompiles}</td> <td><div class="histogram-bar" style="width: {b.width}%"></div></td> </tr>
+{{ endfor }}
+</table>
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_SOURCE_FILES: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Compiles by source</h2>
+<p>
+Every compile counted towards the file of the innermost frame of its stack that is in user code
+(not in torch or the standard library), and towards that file's package: the first component
+of its path once shortened (pass <code>--strip-prefix</code> if paths are still absolute).  The
+attempts of a compile count once.
+{{ if num_without_user_frame }}
+{num_without_user_frame} compile(s) had no frame in user code, or no stack, and count as
+<code>(no user frame)</code>.
+{{ endif }}
+Also in <a href="source_files.csv">source_files.csv</a> and <a href="source_packages.csv">source_packages.csv</a>.
+</p>
+<h3>By package</h3>
+<table>
+<tr> <th>Package</th> <th>Compiles</th> <th>Frames</th> <th>Compile time</th> </tr>
+{{ for p in packages }}
+<tr> <td><code>{p.name}</code></td> <td>{p.compiles}</td> <td>{p.frames}</td> <td>{p.compile_time}</td> </tr>
+{{ endfor }}
+</table>
+<h3>By file</h3>
+<table>
+<tr> <th>File</th> <th>Compiles</th> <th>Frames</th> <th>Compile time</th> </tr>
+{{ for f in files }}
+<tr> <td><code>{f.name}</code></td> <td>{f.compiles}</td> <td>{f.frames}</td> <td>{f.compile_time}</td> </tr>
+{{ endfor }}
+</table>
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_CACHE: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>FX graph cache</h2>
+<p>
+Inductor looks up every graph it compiles in the FX graph cache; a hit skips most of the
+compile.  {total.hits} hits, {total.misses} misses and {total.bypasses} bypasses make a hit rate
+of {total.hit_rate}.
+{{ if median_miss }}
+A compile that missed took {median_miss} (median), so the hits saved about {estimated_saved}.
+{{ endif }}
+{{ if logged_saved }}
+The hits themselves logged saving {logged_saved}.
+{{ endif }}
+</p>
+<h3>Per frame</h3>
+<table>
+<tr> <th>Frame</th> <th>Hits</th> <th>Misses</th> <th>Bypasses</th> <th>Hit rate</th> </tr>
+{{ for f in frames }}
+<tr> <td>{f.0}</td> <td>{f.1.hits}</td> <td>{f.1.misses}</td> <td>{f.1.bypasses}</td> <td>{f.1.hit_rate}</td> </tr>
+{{ endfor }}
+</table>
+{{ if expensive_misses }}
+<h3>Most expensive misses</h3>
+<table>
+<tr> <th>Compile</th> <th>Compile time</th> <th>Attempts</th> <th>Cache key</th> </tr>
+{{ for m in expensive_misses }}
+<tr> <td><a href="index.html#{m.anchor}">{m.compile_id}</a></td> <td>{m.compile_time}</td> <td>{m.attempts}</td> <td><code>{m.key}</code></td> </tr>
+{{ endfor }}
+</table>
+{{ endif }}
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_ATTEMPTS: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Attempts per compile</h2>
+<p>
+When Dynamo restarts analysis of a frame (after a graph break, say) it tries again as the next
+attempt of the same compile: <code>[0/0_1]</code> is the second attempt at <code>[0/0]</code>.
+Here is how every attempt went.  An attempt followed by another was <em>restarted</em>; a failure
+anywhere, backward compiles included, counts as
+<span class="status-error">failed</span> even if the metrics otherwise look fine.
+</p>
+<table>
+<tr> <th>Compile</th> <th>Attempts</th> </tr>
+{{ for c in compiles }}
+<tr> <td>{c.compile_id}</td> <td>{{ for s in c.steps }}{{ if not @first }} → {{ endif }}{{ if s.diff }}(<a href="{s.diff.1}">diff</a>) {{ endif }}<a href="index.html#{s.anchor}" class="{s.class}">{s.attempt} {s.outcome}</a>{{ endfor }}</td> </tr>
+{{ endfor }}
+</table>
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_DIFF: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>What changed from <a href='index.html#{a.1}'>{a.0}</a> to <a href='index.html#{b.1}'>{b.0}</a></h2>
+<p>
+The artifacts the two compiles have in common, line by line: lines only {a.0} had are
+<span class="diff-removed">removed</span>, lines only {b.0} has are
+<span class="diff-added">added</span>.  Artifacts only one of them has are wholly one or the other.
+{{ if normalized }}Graphs and guards are normalized first, so that node names, comments and
+formatting don't show up as changes.{{ endif }}
+</p>
+<ul>
+{{ for d in artifacts }}<li>{{ if d.lines }}<a href='#diff-{@index}'>{d.name}</a>{{ else }}{d.name}{{ endif }}: {d.status}{{ if d.lines }} (+{d.added} −{d.removed}){{ endif }}</li>
+{{ endfor }}</ul>
+{{ for d in artifacts }}{{ if d.lines }}
+<h3 id="diff-{@index}">{d.name}</h3>
+<table class="diff">
+{{ for l in d.lines }}<tr class="{l.class}"><td class="lineno">{l.old}</td><td class="lineno">{l.new}</td><td><pre>{l.text}</pre></td></tr>
+{{ endfor }}</table>
+{{ endif }}{{ endfor }}
+</div>
+</body>
+</html>
 "#;
 
-pub static JAVASCRIPT: &str = r#"
-  function toggleList(toggleItem) {
-    const listItem = toggleItem.parentNode;
-    const nestedList = listItem.querySelector('ul');
-    if (nestedList) {
-      nestedList.style.display = nestedList.style.display === 'none' ? 'block' : 'none';
-
-      // Toggle the collapse/expand indicator
-      toggleItem.classList.toggle('collapsed');
-    }
-  }
+pub static TEMPLATE_RUN_DIFF: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>What changed from {a.name} to {b.name}</h2>
+{{ if regressed }}<p><strong>{b.name} regressed</strong>: {{ for r in reasons }}{r}{{ if not @last }}; {{ endif }}{{ endfor }}.</p>
+{{ else }}<p>No regressions: {b.name} has no compiles {a.name} didn't, no frame compiled more often, and nothing compiled more than {threshold_pct}% slower.</p>
+{{ endif }}
+<table>
+<tr><th>Run</th><th>Compiles</th><th>Frames</th><th>Compile time (s)</th></tr>
+<tr><td>{a.name}</td><td>{a.num_compiles}</td><td>{a.num_frames}</td><td>{a.compile_time_s}</td></tr>
+<tr><td>{b.name}</td><td>{b.num_compiles}</td><td>{b.num_frames}</td><td>{b.compile_time_s}</td></tr>
+</table>
+<h3>Compiles only one run has</h3>
+<p>Compiles are matched up by compile id.</p>
+{{ if only_in_b }}<p>Only in {b.name}:</p>
+<ul>
+{{ for c in only_in_b }}<li><a href='{c.url}'>{c.compile_id}</a> — <code>{c.frame}</code></li>
+{{ endfor }}</ul>
+{{ endif }}{{ if only_in_a }}<p>Only in {a.name}:</p>
+<ul>
+{{ for c in only_in_a }}<li><a href='{c.url}'>{c.compile_id}</a> — <code>{c.frame}</code></li>
+{{ endfor }}</ul>
+{{ endif }}{{ if not only_in_a }}{{ if not only_in_b }}<p>None, both have the same compiles.</p>
+{{ endif }}{{ endif }}
+<h3>Frames compiled more or less often</h3>
+{{ if frame_count_changes }}<table>
+<tr><th>Frame</th><th>{a.name}</th><th>{b.name}</th></tr>
+{{ for f in frame_count_changes }}<tr><td><code>{f.frame}</code></td><td>{f.a}</td><td>{f.b}</td></tr>
+{{ endfor }}</table>
+{{ else }}<p>None, every frame compiled as many times.</p>
+{{ endif }}
+<h3>Compile time</h3>
+<p>Compiles whose time changed by more than {threshold_pct}%, by their compilation metrics (or their span in the log without them).</p>
+{{ if slower }}<table>
+<tr><th>Slower</th><th>{a.name} (s)</th><th>{b.name} (s)</th><th>Change</th></tr>
+{{ for c in slower }}<tr><td>{c.compile_id} — <code>{c.frame}</code></td><td>{c.a_s}</td><td>{c.b_s}</td><td>+{c.change_pct}%</td></tr>
+{{ endfor }}</table>
+{{ endif }}{{ if faster }}<table>
+<tr><th>Faster</th><th>{a.name} (s)</th><th>{b.name} (s)</th><th>Change</th></tr>
+{{ for c in faster }}<tr><td>{c.compile_id} — <code>{c.frame}</code></td><td>{c.a_s}</td><td>{c.b_s}</td><td>{c.change_pct}%</td></tr>
+{{ endfor }}</table>
+{{ endif }}{{ if not slower }}{{ if not faster }}<p>None.</p>
+{{ endif }}{{ endif }}
+<h3>Artifacts</h3>
+<p>The artifacts of compiles both runs have that aren't the same{{ if normalized }}, with graphs and guards normalized first so that node names, comments and formatting don't show up as changes{{ endif }}.</p>
+{{ for c in artifact_lines }}<h4><a href='{c.compile.a_url}'>{c.compile.compile_id}</a> (<a href='{c.compile.b_url}'>in {b.name}</a>) — <code>{c.compile.frame}</code></h4>
+{{ for d in c.artifacts }}<details><summary>{d.name}: {d.status}{{ if d.lines }} (+{d.added} −{d.removed}){{ endif }}</summary>
+{{ if d.lines }}<table class="diff">
+{{ for l in d.lines }}<tr class="{l.class}"><td class="lineno">{l.old}</td><td class="lineno">{l.new}</td><td><pre>{l.text}</pre></td></tr>
+{{ endfor }}</table>
+{{ endif }}</details>
+{{ endfor }}{{ endfor }}{{ if not changed_artifacts }}<p>None changed.</p>
+{{ endif }}
+</div>
+</body>
+</html>
 "#;
 
-pub static TEMPLATE_DYNAMO_GUARDS: &str = r#"
+pub static TEMPLATE_DASHBOARD: &str = r#"
 <html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
 <body>
-<h2>Guards</h2>
-<ul>
-{{ for guard in guards }}
-    <li><code>{guard.code}</code></li>
-{{ endfor }}
-</ul>
-{qps | format_unescaped}
+<div>
+{custom_header_html | format_unescaped}
+<h2>Dashboard</h2>
+<p>The numbers of this report at a glance.  Hover over a bar for its count; <a href="index.html">the index</a> has the details.</p>
+<h3>Compile time</h3>
+{{ if compile_time_chart }}{compile_time_chart | format_unescaped}{{ else }}<p>No compile has a time.</p>{{ endif }}
+<h3>Compiles over time</h3>
+{{ if compiles_chart }}<p>How many of the {num_compiles} compile(s) had started, from the start of the log to its end.</p>
+{compiles_chart | format_unescaped}{{ else }}<p>The log has no timestamps of compiles.</p>{{ endif }}
+<h3>Cache hit rate</h3>
+{{ if cache_chart }}<p>{cache_hit_rate} of FX graph cache lookups hit, see the <a href="cache.html">cache dashboard</a>.</p>
+{cache_chart | format_unescaped}{{ else }}<p>No compile looked up the FX graph cache.</p>{{ endif }}
+<h3>Graph breaks</h3>
+{{ if num_graph_breaks }}<p>{num_graph_breaks} graph break(s), by reason, see <a href="failures_and_restarts.html">failures and restarts</a>.</p>
+{graph_breaks_chart | format_unescaped}{{ else }}<p>No graph breaks.</p>{{ endif }}
+<h3>Failures</h3>
+{{ if num_failures }}<p>{num_failures} failure(s), by type, see <a href="failures_and_restarts.html">failures and restarts</a>.</p>
+{failures_chart | format_unescaped}{{ else }}<p>No failures.</p>{{ endif }}
+</div>
 </body>
 </html>
 "#;
 
-pub static TEMPLATE_INDEX: &str = r#"
+pub static TEMPLATE_GUARD_CHURN: &str = r#"
 <html>
 <head>
   <meta charset="UTF-8">
@@ -57,111 +1016,173 @@ pub static TEMPLATE_INDEX: &str = r#"
 <style>
 {css | format_unescaped}
 </style>
-<script>
-{javascript | format_unescaped}
-</script>
 <body>
 <div>
 {custom_header_html | format_unescaped}
-<h2>Stack trie</h2>
-<p>
-The <strong>stack trie</strong> is a way of getting a quick orientation on where all the
-compilations in a model take place, esp., if you are compiling a codebase you are unfamiliar with.
-It is a tree of stack frames, for all stacks that triggered PT2 compilation.  If only a single
-stack is in the tree, you will simply see a plain list of frames (most recent call last).  With
-multiple stacks, at every point where two stacks diverge from having a common prefix, we increase
-the indentation of the list and have a separate sub-list per sub-tree.
-</p>
+<h2>Guard churn</h2>
 <p>
-Links to particular compilation are color coded by status:
-<span class="status-ok">[Success]</span>,
-<span class="status-break">[Success with restart (e.g., graph break)]</span>,
-<span class="status-empty">[Empty graph]</span>,
-<span class="status-error">[Error]</span>,
-<span class="status-missing">[Metrics were missing]</span>
+A frame is compiled again when a guard of every earlier compile fails, so the guards that
+changed between two compiles are what made the second one.  Guards are compared after
+normalizing their code, in whatever order they were logged.  A guard that was added and then
+removed again (or the other way around) is <span class="guard-churning">churning</span>: something
+about it keeps flipping between calls, and it's usually the one to look at first.
 </p>
-{stack_trie_html | format_unescaped}
+{{ for frame in frames }}
+<h3>Frame {frame.frame_id} ({frame.num_compiles} compiles)</h3>
+{{ if frame.churning }}
+<p>Churning guards:</p>
+<ul>
+{{ for g in frame.churning }}<li><code class="guard-churning">{g.0}</code> (changed {g.1} times)</li>
+{{ endfor }}</ul>
+{{ endif }}
+{{ for step in frame.steps }}
+<p><a href="index.html#{step.from.1}">{step.from.0}</a> → <a href="index.html#{step.to.1}">{step.to.0}</a>: {step.num_unchanged} unchanged{{ if not step.added }}{{ if not step.removed }}, the same guards{{ endif }}{{ endif }}</p>
+{{ if step.added }}{{ for g in step.added }}<pre class="guard-added{{ if g.1 }} guard-churning{{ endif }}">+ {g.0}</pre>
+{{ endfor }}{{ endif }}
+{{ if step.removed }}{{ for g in step.removed }}<pre class="guard-removed{{ if g.1 }} guard-churning{{ endif }}">- {g.0}</pre>
+{{ endfor }}{{ endif }}
+{{ endfor }}
+{{ endfor }}
 </div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_DUPLICATE_GRAPHS: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
 <div>
-{{ if num_breaks }}
-<h2> Failures and Restarts </h2>
+{custom_header_html | format_unescaped}
+<h2>Duplicate graphs</h2>
 <p>
-Various issues may cause Dynamo to restart its analysis or give up on compilation entirely, causing graph breaks and fallbacks to eager mode.
-This run had <strong><a href="failures_and_restarts.html">{num_breaks} restart(s) and/or compilation failure(s)</a></strong>.
+These compiles captured the same Dynamo graph (up to the names of nodes, comments and
+addresses), so recompiling them gained nothing: usually a guard that keeps failing on something
+that doesn't change the graph.  That's worth a Dynamo bug report, or marking the input dynamic.
+Where guards were logged, the difference between the guards of two of them is shown.
 </p>
+{{ for group in groups }}
+<h3>{group.num_compiles} compiles</h3>
+<p>{{ for c in group.compiles }}<a href="index.html#{c.1}">{c.0}</a> {{ endfor }}</p>
+{{ if group.guard_diff }}
+<p>{group.guard_diff.num_shared} guards are the same in {group.guard_diff.a} and {group.guard_diff.b}.</p>
+{{ if group.guard_diff.only_a }}
+<p>Only in {group.guard_diff.a}:</p>
+<ul>
+{{ for g in group.guard_diff.only_a }}<li><code>{g}</code></li>
+{{ endfor }}</ul>
 {{ endif }}
-<h2>IR dumps</h2>
-<p>
-The <strong>IR dumps</strong> collected dumped intermediate products from various points of the PT2
-compilation process.  The products are organized by compile id, and then sorted in chronological
-order.
-</p>
-<p>
-A <strong>compile id</strong> uniquely identifies are particular compilation inside a PT2
-program.  It is traditionally written as <code>[x/y]</code>, where the <strong>frame id</strong> x
-identifies the particular Python frame which we are compiling, and <strong>frame compile
-id</strong> y identifies how many times we've recompiled this same frame.  For example,
-<code>[0/0]</code> refers to the very first frame compiled by PT2; <code>[0/1]</code> refers to the
-first recompilation of this frame, while <code>[1/0]</code> refers to a different frame, within
-distinct code cache, which we are compiling next (perhaps because of a graph break).  Although
-Dynamo treats distinct frames as completely unrelated, a frame compilation could overlap with another
-frame; for example, if you graph break in an inlined function, Dynamo will typically try to compile
-the nested frame again on an inner frame.  You can identify the hierarchical relationship between
-frames by looking at the stack trie above.
-</p>
-<p>
-In some situations, the compile id will have an extra signifier <code>[x/y_z]</code>, where z is the
-<strong>attempt</strong> for this particular (re)compilation.  Certain conditions will cause Dynamo to
-restart analysis, when Dynamo discovers that it needs to undo a decision it previously made.  The most
-common cause of recompilation is a graph break in an inlined function call, which forces to restart
-and avoid inlining the function in the first place.
-</p>
+{{ if group.guard_diff.only_b }}
+<p>Only in {group.guard_diff.b}:</p>
+<ul>
+{{ for g in group.guard_diff.only_b }}<li><code>{g}</code></li>
+{{ endfor }}</ul>
+{{ endif }}
+{{ else }}
+<p>Fewer than two of them logged their guards.</p>
+{{ endif }}
+{{ endfor }}
+</div>
+</body>
+</html>
+"#;
+
+pub static TEMPLATE_OPS_HISTOGRAM: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Operators</h2>
 <p>
-Here is a high level description of PT2's compilation phases, and the intermediate products each
-phase generates:
+How often each operator is called in the {num_graphs} FX graphs Dynamo and AOTAutograd captured
+(<code>call_function</code> and <code>call_method</code> nodes; Dynamo's graphs call torch
+functions, AOTAutograd's call aten ops; Python operators written inline, like <code>x + y</code>,
+aren't counted).
+{{ if merge_overloads }}Overloads are counted as their operator.{{ else }}Every overload is counted on its own; pass <code>--ops-merge-overloads</code> to count them as their operator.{{ endif }}
+{{ if unreadable_graphs }}{unreadable_graphs} graphs couldn't be read and aren't counted.{{ endif }}
+Also in <a href="ops_histogram.csv">ops_histogram.csv</a> and, per compile, <a href="ops_by_compile.csv">ops_by_compile.csv</a>.
 </p>
-<ol>
-<li><em>Optional:</em> If compiled autograd is enabled, and we are processing a backward call, compiled autograd will trace the autograd graph from the autograd engine, and produce an FX graph <code>compiled_autograd_graph</code> that will be Dynamo traced.  Otherwise, Dynamo will directly trace user's bytecode.</li>
-<li>Dynamo symbolically evaluates the Python bytecode of a program, producing <code>dynamo_output_graph</code></li>
-<li><em>Optional:</em> If <code>optimize_ddp</code> is enabled, the DDPOptimizer will split the Dynamo output graph to improve pipelining communications.  Each split subgraph is <code>optimize_ddp_split_child_submod</code>, and the high level graph that plumbs the graphs together is <code>optimize_ddp_split_graph</code>.  If there are multiple splits, each subsequent build product will be produced multiple times, one for each split.</li>
-<li>AOTAutograd traces the (possibly split) Dynamo output graph, producing a <code>aot_joint_graph</code> if backwards is enabled.  It then partitions the graph into <code>aot_forward_graph</code> and <code>aot_backward_graph</code>.  If training is not needed, there may only be an <code>aot_inference_graph</code>.</li>
-<li>Inductor will apply some post grad FX passes, producing <code>inductor_post_grad_graph</code></li>
-<li>Inductor will perform code generation, producing the final <code>inductor_output_code</code> which will be executed at runtime.  This output is a valid Python program and can be directly run.</li>
-</ol>
+<table class="histogram">
+<tr> <th>Operator</th> <th>Calls</th> <th>Compiles</th> <th></th> </tr>
+{{ for op in ops }}
+<tr> <td><code>{op.op}</code></td> <td>{op.count}</td> <td>{op.compiles}</td> <td><div class="histogram-bar" style="width: {op.width}%"></div></td> </tr>
+{{ endfor }}
+</table>
+<h2>By compile</h2>
+{{ for c in compiles }}
+<details>
+<summary><a href="index.html#{c.anchor}">{c.compile_id}</a></summary>
+<table>
+{{ for op in c.ops }}
+<tr> <td><code>{op.0}</code></td> <td>{op.1}</td> </tr>
+{{ endfor }}
+</table>
+</details>
+{{ endfor }}
+</div>
+</body>
+</html>
+"#;
 
-{{ if has_chromium_events }}
-<h2> Chromium Events </h2>
-PT2 generates <a href='chromium_events.json'>Chromium Trace Events</a> in JSON on specific events during compilation.
-You can download and view them in a tool like <a href='https://ui.perfetto.dev/'>Perfetto</a>.
-{{ endif  }}
+pub static TEMPLATE_LARGEST_ARTIFACTS: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Largest payloads</h2>
 <p>
-Build products below:
+The log has {num_payloads} payloads, {total_size} in all; these are the largest of them.
 </p>
-<ul>
-{{ for compile_directory in directory }}
-    <li><a id="{compile_directory.0}">{compile_directory.0}</a>
-    <ul>
-        {{ for path_idx in compile_directory.1 }}
-            <li><a href="{path_idx.url}">{path_idx.name}</a> {path_idx.suffix} ({path_idx.number})</li>
-        {{ endfor }}
-    </ul>
-    </li>
+<table>
+<tr> <th>Size</th> <th>Artifact</th> <th>Compile</th> <th>Line</th> </tr>
+{{ for a in artifacts }}
+<tr> <td>{a.size}</td> <td>{{ if a.url }}<a href="{a.url}">{a.kind}</a>{{ else }}{a.kind}{{ endif }}</td> <td><a href="index.html#{a.anchor}">{a.compile_id}</a></td> <td>{a.lineno}</td> </tr>
 {{ endfor }}
-</ul>
+</table>
 </div>
+</body>
+</html>
+"#;
 
-{{ if has_unknown_stack_trie }}
+pub static TEMPLATE_RUNS_INDEX: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
 <div>
-<h2>Unknown stacks</h2>
+{custom_header_html | format_unescaped}
+<h2>Runs</h2>
 <p>
-  Sometimes, logs are made without a compile id.  This makes it difficult to correlate related
-  logs.  This stack trie shows all places where log entries occurred without compile context; to
-  fix, look an appropriate place in the stack where compile id should have been specified.
+This log contains several runs, because a restarted job appended to the same file.  Compile ids
+start over in every run, so each run has been rendered separately.
 </p>
-{unknown_stack_trie_html | format_unescaped}
+<ul>
+{{ for run in runs }}
+<li><a href="run_{run}/index.html">Run {run}</a></li>
+{{ endfor }}
+</ul>
 </div>
-{{ endif }}
-{qps | format_unescaped}
 </body>
 </html>
 "#;
@@ -202,6 +1223,17 @@ pub static TEMPLATE_FAILURES_AND_RESTARTS: &str = r#"
 </head>
 <body>
     <h1>Failures and Restarts</h1>
+    {{ if failure_groups }}
+    <h2>Failures by cause</h2>
+    <p>Failures with the same type and the same reason, once addresses, shapes and other numbers in it are masked.</p>
+    <table>
+    <tr> <th> Failure Type </th> <th> Failure Description </th> <th> Count </th> <th> Example </th> <th> First Seen </th> </tr>
+    {{ for group in failure_groups }}
+    <tr> <td><pre>{group.fail_type}</pre></td> <td><pre>{group.reason}</pre></td> <td>{group.count}</td> <td>{group.example | format_unescaped}</td> <td>{group.first_seen}</td> </tr>
+    {{ endfor }}
+    </table>
+    <h2>All failures and restarts</h2>
+    {{ endif }}
     <table>
     <tr> <th> Compile Id </th> <th> Failure Type </th> <th> Failure Description </th> <th> Failure Source (compilation failures only) </th> </tr>
     {{ for failure in failures }}
@@ -227,7 +1259,7 @@ pub static TEMPLATE_COMPILATION_METRICS: &str = r#"
     <h2>Output files:</h2>
     <ul>
         {{ for path_idx in output_files }}
-            <li><a href="{compile_id_dir}/{path_idx.url}">{path_idx.name}</a> ({path_idx.number})</li>
+            <li><a href="{compile_id_dir}/{path_idx.url}">{path_idx.name}</a> ({path_idx.number}){{ if path_idx.highlighted }} <a href="{compile_id_dir}/{path_idx.highlighted}">highlighted</a>{{ endif }}</li>
         {{ endfor }}
     </ul>
     <h2>Stack</h2>
@@ -241,6 +1273,9 @@ pub static TEMPLATE_COMPILATION_METRICS: &str = r#"
     {{ if m.code_gen_time_s }}
     <p>Code Gen Time: {m.code_gen_time_s}</p>
     {{ endif}}
+    {{ if phases }}
+    <p>By phase <abbr title="Phases nest, so they don't add up to the entire frame">[?]</abbr>:{{ for p in phases }} {p.0} {p.1}{{ if not @last }},{{ endif }}{{ endfor }}</p>
+    {{ endif }}
     <div>Dynamo Time Before Restart <abbr title="Total time spent restarting dynamo analysis">[?]</abbr>: {m.dynamo_time_before_restart_s}</div>
     <h2>Restarts and Failures</h2>
     {{ if m.fail_type }}
@@ -269,6 +1304,9 @@ pub static TEMPLATE_COMPILATION_METRICS: &str = r#"
     <p>Graph Ops: {m.graph_op_count}</p>
     <p>Graph Nodes: {m.graph_node_count}</p>
     <p>Graph Inputs: {m.graph_input_count}</p>
+    {{ if dynamic_dims }}
+    <p>Dynamic dimensions: {dynamic_dims.dynamic} of {dynamic_dims.candidates}, {dynamic_dims.specialized} <a href='#specializations'>specialized</a></p>
+    {{ endif }}
     <h2> Custom Ops </h2>
     <p> Compliant Custom Ops:</p>
     {{ for op in m.compliant_custom_ops }}
@@ -278,7 +1316,7 @@ pub static TEMPLATE_COMPILATION_METRICS: &str = r#"
     {{ for op in m.non_compliant_ops }}
     <li> <code> {op} </code> </li>
     {{ endfor }}
-    <h2>Symbolic shape specializations</h2>
+    <h2 id='specializations'>Symbolic shape specializations</h2>
     <table>
     <tr>
         <th>Sym</th> <th>Source(s)</th> <th>Value</th> <th>User stack</th> <th>Framework stack</th>
@@ -384,3 +1422,223 @@ pub static TEMPLATE_QUERY_PARAM_SCRIPT: &str = r#"
     });
     </script>
 "#;
+
+// The index's compiles by frame, see CompileList
+pub static TEMPLATE_COMPILE_LIST: &str = r#"
+<ul class="compile-list">
+{{ for frame in frames }}
+    <li class="compile-group" id="{frame.anchor}"><details><summary>Frame {frame.frame_id}{{ if frame.label }} <code>{frame.label}</code>{{ endif }}
+    <span class="compile-group-counts">({frame.num_compiles} compile(s), {frame.num_attempts} attempt(s){{ if frame.num_failed }}, {frame.num_failed} failed{{ endif }})</span></summary>
+    <ul>
+    {{ for compile in frame.compiles }}
+        <li class="compile-group"><details><summary>{compile.label} — <code>{compile.frame}</code>
+        <span class="compile-group-counts">({compile.num_attempts} attempt(s){{ if compile.num_failed }}, {compile.num_failed} failed{{ endif }})</span></summary>
+        <ul>
+        {{ for attempt in compile.attempts }}{{ call directory_entry.html with attempt }}{{ endfor }}
+        </ul>
+        </details></li>
+    {{ endfor }}
+    </ul>
+    </details></li>
+{{ endfor }}
+{{ if unknown_compile }}{{ call directory_entry.html with unknown_compile }}{{ endif }}
+</ul>
+"#;
+
+// Once the index's list of compiles is split, links into it (to index.html#<anchor>) go to the
+// page with that compile or frame, by data-frames of the links to the pages
+pub static TEMPLATE_INDEX_SHARD_REDIRECT: &str = r#"
+    <script>
+    (function() {
+        function redirect() {
+            const id = decodeURIComponent(location.hash.slice(1));
+            if (id === '' || document.getElementById(id)) return;
+            const frame = id.startsWith('frame-') ? id.slice('frame-'.length) : id.split('_')[0];
+            for (const link of document.querySelectorAll('.index-shards a[data-frames]')) {
+                if (link.dataset.frames.split(' ').includes(frame)) {
+                    location.replace(link.getAttribute('href') + location.hash);
+                    return;
+                }
+            }
+        }
+        redirect();
+        window.addEventListener('hashchange', redirect);
+    })();
+    </script>
+"#;
+
+pub static TEMPLATE_INDEX_SHARD: &str = r#"
+<html>
+<head>
+  <meta charset="UTF-8">
+</head>
+<style>
+{css | format_unescaped}
+</style>
+<body>
+<div>
+{custom_header_html | format_unescaped}
+<h2>Compiles of {label}</h2>
+<p>
+<a href="index.html">Back to the index</a>
+{{ if prev }}<span style="margin-left: 2em;"><a href="{prev}">← previous frames</a></span>{{ endif }}
+{{ if next }}<span style="margin-left: 1em;"><a href="{next}">next frames →</a></span>{{ endif }}
+</p>
+{compile_search | format_unescaped}
+{{ call compile_list.html with compiles }}
+</div>
+</body>
+</html>
+"#;
+
+// A compile (attempt) in the index, see DirectoryEntry
+pub static TEMPLATE_DIRECTORY_ENTRY: &str = r#"
+    <li data-search="{search}"{{ if severity }} data-severity="{severity}"{{ endif }}><a id="{anchor}"{{ if page }} href="{page}"{{ endif }}>{compile_id}</a> {size}
+    {{ if outcomes }}
+    <span class="attempt-outcomes">(attempts: {outcomes})</span>
+    {{ endif }}
+    {{ if span }}
+    <span class="compile-span" title="From the first to the last log entry of this compile, not measured compile time">{span}</span>
+    {{ endif }}
+    {{ for b in badges }}<span class="{b.1}">{b.0}</span> {{ endfor }}
+    {{ if files }}
+    <ul>
+        {{ for path_idx in files }}
+            <li><a href="{path_idx.url}">{path_idx.name}</a>{path_idx.size | format_bytes} {path_idx.suffix} ({path_idx.number}){{ if path_idx.highlighted }} <a href="{path_idx.highlighted}">highlighted</a>{{ endif }}</li>
+        {{ endfor }}
+    </ul>
+    {{ else }}{{ if not page }}
+    (no artifacts)
+    {{ endif }}{{ endif }}
+    </li>
+"#;
+
+// Filters the compiles of the index by their data-search (see DirectoryEntry::search) and badges;
+// hidden without JS, which leaves the list as it is
+pub static TEMPLATE_COMPILE_SEARCH: &str = r#"
+    <p class="compile-search" hidden>
+    <input type="search" placeholder="Filter compiles by id, frame, artifact or failure" size="50">
+    <span class="compile-search-count"></span>
+    </p>
+    <p class="compile-filter" hidden>
+    Show:
+    <label><input type="radio" name="compile-filter" value="" checked> all</label>
+    <label><input type="radio" name="compile-filter" value="failure"> failed</label>
+    <label><input type="radio" name="compile-filter" value="warning"> warnings only</label>
+    </p>
+    <script>
+    document.addEventListener('DOMContentLoaded', function() {
+        // Frames and their compiles start out folded, so unfold whatever a link points into
+        function unfoldTarget() {
+            const target = location.hash && document.getElementById(decodeURIComponent(location.hash.slice(1)));
+            for (let d = target && target.closest('details'); d; d = d.parentElement.closest('details')) {
+                d.open = true;
+            }
+            if (target) target.scrollIntoView();
+        }
+        unfoldTarget();
+        window.addEventListener('hashchange', unfoldTarget);
+
+        const items = Array.from(document.querySelectorAll('.compile-list li[data-search]'));
+        const groups = Array.from(document.querySelectorAll('.compile-list li.compile-group')).reverse();
+        if (items.length === 0) return;
+        const box = document.querySelector('.compile-search');
+        const input = box.querySelector('input');
+        const count = box.querySelector('.compile-search-count');
+        const filter = document.querySelector('.compile-filter');
+        box.hidden = false;
+        filter.hidden = false;
+
+        // Both the search and the filter (by the data-severity of the badges) have to match
+        function update() {
+            const query = input.value.trim().toLowerCase();
+            const severity = filter.querySelector('input:checked').value;
+            let found = 0;
+            for (const li of items) {
+                const match = (query === '' || li.dataset.search.includes(query))
+                    && (severity === '' || li.dataset.severity === severity);
+                li.hidden = !match;
+                if (match) found++;
+            }
+            // Innermost first, so a frame sees whether its compiles are still shown; while
+            // filtering, every group with a match is unfolded
+            const filtering = query !== '' || severity !== '';
+            for (const group of groups) {
+                group.hidden = !group.querySelector(':scope > details > ul > li:not([hidden])');
+                if (filtering) group.firstElementChild.open = !group.hidden;
+            }
+            count.textContent = query === '' && severity === ''
+                ? '' : `${found} of ${items.length} compiles`;
+        }
+        input.addEventListener('input', update);
+        filter.querySelectorAll('input').forEach((radio) => radio.addEventListener('change', update));
+    });
+    </script>
+"#;
+
+// A filter box for the stack tries of the index.  It only shows up once the script runs, so
+// without JS the page is just the full tries.  Every <li class='trie-node'> is a frame, with its
+// text in data-search; in each list, the nodes before the trie-branch ones are the chain leading
+// up to them.  A node stays visible if it matches, if a node above it matches, or if something
+// below it does, and branches with a match inside are unfolded.
+pub static TEMPLATE_TRIE_SEARCH: &str = r#"
+    <p class="trie-search" hidden>
+    <input type="search" placeholder="Filter frames and compile ids" size="40">
+    <span class="trie-search-count"></span>
+    </p>
+    <script>
+    document.addEventListener('DOMContentLoaded', function() {
+        const tries = document.querySelectorAll('.stack-trie > ul');
+        if (tries.length === 0) return;
+        const box = document.querySelector('.trie-search');
+        const input = box.querySelector('input');
+        const count = box.querySelector('.trie-search-count');
+        box.hidden = false;
+
+        // So that clearing the filter folds everything back the way it was
+        document.querySelectorAll('.stack-trie details').forEach((d) => {
+            d.dataset.wasOpen = d.open ? '1' : '';
+        });
+
+        // Returns how many nodes in the list (and below) match
+        function filterList(ul, query, aboveMatched) {
+            const items = Array.from(ul.children).filter((li) => li.classList.contains('trie-node'));
+            const matches = (li) => li.dataset.search.toLowerCase().includes(query);
+            const chain = items.filter((li) => !li.classList.contains('trie-branch'));
+            const branches = items.filter((li) => li.classList.contains('trie-branch'));
+            let found = 0;
+            for (const li of chain) {
+                const own = query !== '' && matches(li);
+                li.classList.toggle('trie-match', own);
+                if (own) found++;
+            }
+            const chainMatched = aboveMatched || found > 0;
+            for (const li of branches) {
+                const own = query !== '' && matches(li);
+                li.classList.toggle('trie-match', own);
+                const details = li.querySelector(':scope > details');
+                const inner = details
+                    ? filterList(details.querySelector(':scope > ul'), query, chainMatched || own)
+                    : 0;
+                li.hidden = query !== '' && !(chainMatched || own || inner > 0);
+                if (details) {
+                    details.open = query === '' ? details.dataset.wasOpen === '1'
+                        : (inner > 0 || details.dataset.wasOpen === '1');
+                }
+                found += (own ? 1 : 0) + inner;
+            }
+            for (const li of chain) {
+                li.hidden = query !== '' && !(chainMatched || found > 0);
+            }
+            return found;
+        }
+
+        input.addEventListener('input', function() {
+            const query = input.value.trim().toLowerCase();
+            let found = 0;
+            tries.forEach((ul) => { found += filterList(ul, query, false); });
+            count.textContent = query === '' ? '' : `${found} matching frame${found === 1 ? '' : 's'}`;
+        });
+    });
+    </script>
+"#;
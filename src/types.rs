@@ -1,19 +1,55 @@
 use core::hash::BuildHasherDefault;
-use fxhash::{FxHashMap, FxHasher};
-use html_escape::encode_text;
+use fxhash::{FxHashMap, FxHashSet, FxHasher};
+use html_escape::{encode_double_quoted_attribute, encode_text};
 use indexmap::IndexMap;
 use regex::Regex;
 use serde_json::Value;
 
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fmt::{self, Display, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
-use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 
-// Main function returns a list of files to save
-pub type ParseOutput = Vec<(PathBuf, String)>;
+// What a parse returns: the files of its report, and what they were made from, for callers that
+// would rather not read that back out of them
+#[derive(Debug)]
+pub struct ParseOutput {
+    // Relative to where the report goes, without what was already written there (see
+    // ParseConfig::output_dir)
+    pub files: Vec<(PathBuf, String)>,
+    // Every run that was rendered, in log order; see ParseConfig::run
+    pub runs: Vec<ParsedRun>,
+}
+
+pub struct ParsedRun {
+    // Which of the log's runs; when there's more than one, its files are in run_<n>/
+    pub run: usize,
+    pub stats: Stats,
+    // The files of each compile, as the index lists them
+    pub directory: FxIndexMap<Option<CompileId>, Vec<OutputFile>>,
+    // Of the rank that was rendered
+    pub stack_trie: StackTrieNode,
+    // The stack trie as ParseConfig::print_trie has it printed, if it was set
+    pub trie_text: Option<String>,
+}
+
+// Without the stack trie, which would be most of it
+impl fmt::Debug for ParsedRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParsedRun")
+            .field("run", &self.run)
+            .field("stats", &self.stats)
+            .field("directory", &self.directory)
+            .finish_non_exhaustive()
+    }
+}
+// NB: keyed by the exact attempt, unlike StackIndex; CompileStatuses judges a compile's attempts
+// together
 pub type CompilationMetricsIndex = FxIndexMap<Option<CompileId>, Vec<CompilationMetricsMetadata>>;
 pub type StackIndex = FxHashMap<Option<CompileId>, StackSummary>; // NB: attempt is always 0 here
 pub type SymbolicShapeSpecializationIndex =
@@ -28,557 +64,4752 @@ pub fn extract_eval_with_key_id(filename: &str) -> Option<u64> {
         .and_then(|m| m.as_str().parse::<u64>().ok())
 }
 
-pub static INTERN_TABLE: Lazy<Mutex<FxHashMap<u32, String>>> =
-    Lazy::new(|| Mutex::new(FxHashMap::default()));
+// has_payload value for a legitimately empty payload, i.e. MD5 of ""
+pub static EMPTY_PAYLOAD_MD5: &str = "d41d8cd98f00b204e9800998ecf8427e";
 
-#[derive(Default)]
+thread_local! {
+    // Filled in from `str` envelopes while parsing; reset at the start of every parse, and
+    // per-thread so that concurrent parses in one process don't see each other's entries
+    pub static INTERN_TABLE: RefCell<FxHashMap<u32, String>> = RefCell::new(FxHashMap::default());
+}
+
+// CSS classes describing how compiles went, according to their compilation metrics.  A compile
+// with restarts is judged on all of its attempts together, so the metrics index is grouped by
+// (frame_id, frame_compile_id) once up front rather than searched for every compile shown.
+pub struct CompileStatuses<'a> {
+    metrics_index: &'a CompilationMetricsIndex,
+    compiles: FxHashMap<Option<FrameKey>, &'static str>,
+}
+
+// (frame_id, frame_compile_id): what the attempts of a compile share
+type FrameKey = (Option<u32>, Option<u32>);
+
+impl<'a> CompileStatuses<'a> {
+    pub fn new(metrics_index: &'a CompilationMetricsIndex) -> Self {
+        let mut grouped: FxHashMap<_, Vec<&CompilationMetricsMetadata>> = FxHashMap::default();
+        for (cid, metrics) in metrics_index {
+            grouped
+                .entry(cid.as_ref().map(|c| (c.frame_id, c.frame_compile_id)))
+                .or_default()
+                .extend(metrics);
+        }
+        CompileStatuses {
+            metrics_index,
+            compiles: grouped
+                .into_iter()
+                .map(|(key, metrics)| (key, metrics_status(metrics.into_iter())))
+                .collect(),
+        }
+    }
+
+    // How the compile went over all of its attempts
+    pub fn compile(&self, compile_id: &Option<CompileId>) -> &'static str {
+        let key = compile_id
+            .as_ref()
+            .map(|c| (c.frame_id, c.frame_compile_id));
+        self.compiles.get(&key).copied().unwrap_or("status-missing")
+    }
+
+    // How just this one attempt went
+    pub fn attempt(&self, compile_id: &Option<CompileId>) -> &'static str {
+        metrics_status(self.metrics_index.get(compile_id).into_iter().flatten())
+    }
+}
+
+fn metrics_status<'a>(
+    metrics: impl Iterator<Item = &'a CompilationMetricsMetadata>,
+) -> &'static str {
+    let m: Vec<_> = metrics.collect();
+    if m.is_empty() {
+        "status-missing"
+    } else if m.iter().any(|n| n.fail_type.is_some()) {
+        "status-error"
+    } else if m.iter().any(|n| n.graph_op_count.unwrap_or(0) == 0) {
+        "status-empty"
+    } else if m
+        .iter()
+        .any(|n| !n.restart_reasons.as_ref().is_some_and(|o| o.is_empty()))
+    {
+        "status-break"
+    } else {
+        "status-ok"
+    }
+}
+
+// The terminal color for a compile status, after the colors of the index's legend
+fn ansi_status_color(status: &str) -> &'static str {
+    match status {
+        "status-ok" => "32",
+        "status-break" => "92",
+        "status-error" => "31",
+        "status-empty" => "2",
+        _ => "35",
+    }
+}
+
+// Cut the middle out of a long string so that it fits in `max` characters, keeping the ends
+// (for a filename, the top-level directory and the file).  Never shortens below a few
+// characters on either side, so very narrow terminals still wrap
+fn ellipsize_middle(s: &str, max: usize) -> String {
+    let len = s.chars().count();
+    if len <= max {
+        return s.to_string();
+    }
+    let keep = max.saturating_sub(1).max(12);
+    if keep + 1 >= len {
+        return s.to_string();
+    }
+    let head = keep / 2;
+    let tail = keep - head;
+    let mut out: String = s.chars().take(head).collect();
+    out.push('…');
+    out.extend(s.chars().skip(len - tail));
+    out
+}
+
+// A compile time, e.g. "214 s", with a decimal only where it matters
+pub fn fmt_seconds(s: f64) -> String {
+    if s < 1.0 {
+        format!("{:.0} ms", s * 1000.0)
+    } else if s < 10.0 {
+        format!("{s:.1} s")
+    } else {
+        format!("{s:.0} s")
+    }
+}
+
+// How many compile ids a stack trie node lists before collapsing the rest
+static MAX_TRIE_TERMINALS: usize = 5;
+
+// A compile whose stack ends at a stack trie node
+#[derive(Clone, PartialEq)]
+struct Terminal {
+    compile_id: Option<CompileId>,
+    // From the log line of its dynamo_start
+    timestamp: Option<NaiveDateTime>,
+    // From its compilation metrics, see set_compile_times
+    compile_time_s: Option<f64>,
+}
+
+// What a placeholder node in the stack trie stands in for
+#[derive(Clone, Copy)]
+enum HiddenFrames {
+    // Runs of internal frames, the shortest and the longest of them; see collapse_internal
+    Internal(usize, usize),
+    // The middle of a single-child chain; see truncate
+    Chain(usize),
+    // Everything below the depth limit, this many frames deep; see truncate
+    Subtree(usize),
+}
+
+#[derive(Default, Clone)]
 pub struct StackTrieNode {
-    terminal: Vec<Option<CompileId>>,
-    // Ordered map so that when we print we roughly print in chronological order
-    children: FxIndexMap<FrameSummary, StackTrieNode>,
+    terminal: Vec<Terminal>,
+    // When the earliest compile in this subtree happened, see update_counts
+    first_seen: Option<NaiveDateTime>,
+    // Distinct (frame_id, frame_compile_id) pairs and distinct attempts in this subtree, see
+    // update_counts
+    compiles: usize,
+    attempts: usize,
+    // Summed over the attempts in this subtree that have a compile time, and the compiles that
+    // have none for any attempt, see update_counts
+    compile_time_s: f64,
+    untimed: usize,
+    // Only on placeholder nodes, which stand in for frames that aren't shown
+    hidden: Option<HiddenFrames>,
+    // With insert_merging_lines, every line number the frame leading here was seen at, sorted;
+    // the frame itself then has line 0.  Empty otherwise
+    lines: Vec<i32>,
+    // In insertion order, so that when we print we roughly print in chronological order
+    children: TrieChildren,
+}
+
+// Most nodes of a deep trie are in single-child chains, so a lone child is kept inline instead
+// of in a map of its own
+#[derive(Default, Clone)]
+enum TrieChildren {
+    #[default]
+    Empty,
+    One(Box<(FrameSummary, StackTrieNode)>),
+    Many(FxIndexMap<FrameSummary, StackTrieNode>),
+}
+
+impl TrieChildren {
+    fn len(&self) -> usize {
+        match self {
+            TrieChildren::Empty => 0,
+            TrieChildren::One(_) => 1,
+            TrieChildren::Many(m) => m.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The child for frame, added if it isn't there yet
+    fn entry(&mut self, frame: FrameSummary) -> &mut StackTrieNode {
+        match self {
+            TrieChildren::Empty => {
+                *self = TrieChildren::One(Box::new((frame, StackTrieNode::default())));
+            }
+            TrieChildren::One(child) if child.0 != frame => {
+                let TrieChildren::One(child) = std::mem::take(self) else {
+                    unreachable!()
+                };
+                let (f, n) = *child;
+                let mut map = FxIndexMap::default();
+                map.insert(f, n);
+                *self = TrieChildren::Many(map);
+            }
+            _ => {}
+        }
+        match self {
+            TrieChildren::One(child) => &mut child.1,
+            TrieChildren::Many(m) => m.entry(frame).or_default(),
+            TrieChildren::Empty => unreachable!(),
+        }
+    }
+
+    fn insert(&mut self, frame: FrameSummary, node: StackTrieNode) {
+        *self.entry(frame) = node;
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&FrameSummary, &StackTrieNode)> {
+        let (one, many) = match self {
+            TrieChildren::Empty => (None, None),
+            TrieChildren::One(child) => (Some((&child.0, &child.1)), None),
+            TrieChildren::Many(m) => (None, Some(m.iter())),
+        };
+        one.into_iter().chain(many.into_iter().flatten())
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &FrameSummary> {
+        self.iter().map(|(f, _)| f)
+    }
+
+    fn values(&self) -> impl Iterator<Item = &StackTrieNode> {
+        self.iter().map(|(_, n)| n)
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut StackTrieNode> {
+        let (one, many) = match self {
+            TrieChildren::Empty => (None, None),
+            TrieChildren::One(child) => (Some(&mut child.1), None),
+            TrieChildren::Many(m) => (None, Some(m.values_mut())),
+        };
+        one.into_iter().chain(many.into_iter().flatten())
+    }
+
+    fn first(&self) -> Option<(&FrameSummary, &StackTrieNode)> {
+        self.iter().next()
+    }
+}
+
+impl IntoIterator for TrieChildren {
+    type Item = (FrameSummary, StackTrieNode);
+    type IntoIter = Box<dyn Iterator<Item = (FrameSummary, StackTrieNode)>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            TrieChildren::Empty => Box::new(std::iter::empty()),
+            TrieChildren::One(child) => Box::new(std::iter::once(*child)),
+            TrieChildren::Many(m) => Box::new(m.into_iter()),
+        }
+    }
 }
 
 impl StackTrieNode {
-    pub fn insert(&mut self, mut stack: StackSummary, compile_id: Option<CompileId>) {
+    pub fn insert(
+        &mut self,
+        stack: StackSummary,
+        compile_id: Option<CompileId>,
+        timestamp: Option<NaiveDateTime>,
+    ) {
+        self.insert_inner(stack, compile_id, timestamp, false);
+    }
+
+    // Like insert, but frames that only differ in their line number share a node, e.g. calls
+    // from code that a decorator or codegen laid out slightly differently in each compile
+    pub fn insert_merging_lines(
+        &mut self,
+        stack: StackSummary,
+        compile_id: Option<CompileId>,
+        timestamp: Option<NaiveDateTime>,
+    ) {
+        self.insert_inner(stack, compile_id, timestamp, true);
+    }
+
+    fn insert_inner(
+        &mut self,
+        mut stack: StackSummary,
+        compile_id: Option<CompileId>,
+        timestamp: Option<NaiveDateTime>,
+        merge_lines: bool,
+    ) {
         let mut cur = self;
         for frame in stack.drain(..) {
-            cur = cur.children.entry(frame).or_default();
+            if merge_lines {
+                cur = cur.children.entry(FrameSummary { line: 0, ..frame });
+                if let Err(i) = cur.lines.binary_search(&frame.line) {
+                    cur.lines.insert(i, frame.line);
+                }
+            } else {
+                cur = cur.children.entry(frame);
+            }
         }
-        cur.terminal.push(compile_id);
+        cur.terminal.push(Terminal {
+            compile_id,
+            timestamp,
+            compile_time_s: None,
+        });
     }
 
     pub fn insert_no_terminal(&mut self, mut stack: StackSummary) {
         let mut cur = self;
         for frame in stack.drain(..) {
-            cur = cur.children.entry(frame).or_default();
+            cur = cur.children.entry(frame);
         }
     }
 
-    pub fn is_empty(&self) -> bool {
-        return self.children.is_empty() && self.terminal.is_empty();
+    // A copy of the trie with only the compiles that satisfy keep, and only the frames leading
+    // to them
+    pub fn retain_terminals(&self, keep: &dyn Fn(&Option<CompileId>) -> bool) -> StackTrieNode {
+        let mut trie = StackTrieNode {
+            terminal: self
+                .terminal
+                .iter()
+                .filter(|t| keep(&t.compile_id))
+                .cloned()
+                .collect(),
+            lines: self.lines.clone(),
+            ..Default::default()
+        };
+        for (frame, node) in self.children.iter() {
+            let node = node.retain_terminals(keep);
+            if !node.is_empty() {
+                trie.children.insert(*frame, node);
+            }
+        }
+        trie.update_counts();
+        trie
     }
 
-    pub fn fmt(
-        &self,
-        metrics_index: Option<&CompilationMetricsIndex>,
-    ) -> Result<String, fmt::Error> {
-        let mut f = String::new();
-        write!(f, "<div class='stack-trie'>")?;
-        write!(f, "<ul>")?;
-        self.fmt_inner(&mut f, metrics_index)?;
-        write!(f, "</ul>")?;
-        write!(f, "</div>")?;
-        Ok(f)
+    // A copy of the trie with only the stacks that have a frame satisfying matches, keeping the
+    // frames above it for context and everything below it
+    pub fn retain_paths(&self, matches: &dyn Fn(&FrameSummary) -> bool) -> StackTrieNode {
+        let mut trie = StackTrieNode {
+            lines: self.lines.clone(),
+            ..Default::default()
+        };
+        for (frame, node) in self.children.iter() {
+            let node = if matches(frame) {
+                node.clone()
+            } else {
+                node.retain_paths(matches)
+            };
+            if !node.is_empty() {
+                trie.children.insert(*frame, node);
+            }
+        }
+        trie.update_counts();
+        trie
     }
 
-    pub fn fmt_inner(
+    // A copy of the trie where every run of frames satisfying is_internal is replaced by a single
+    // placeholder frame.  Runs that start at the same frame end up under the same placeholder,
+    // so if they lead to the same frame, that frame's subtrees get merged.
+    pub fn collapse_internal(&self, is_internal: &dyn Fn(&FrameSummary) -> bool) -> StackTrieNode {
+        let mut trie = self.collapse_internal_inner(is_internal);
+        trie.update_counts();
+        trie
+    }
+
+    fn collapse_internal_inner(
         &self,
-        f: &mut String,
-        mb_metrics_index: Option<&CompilationMetricsIndex>,
-    ) -> fmt::Result {
+        is_internal: &dyn Fn(&FrameSummary) -> bool,
+    ) -> StackTrieNode {
+        let mut trie = StackTrieNode {
+            terminal: self.terminal.clone(),
+            lines: self.lines.clone(),
+            ..Default::default()
+        };
         for (frame, node) in self.children.iter() {
-            let mut star = String::new();
-            for t in &node.terminal {
-                if let Some(c) = t {
-                    let ok_class = mb_metrics_index.map_or("status-missing", |metrics_index| {
-                        metrics_index.get(t).map_or("status-missing", |m| {
-                            if m.iter().any(|n| n.fail_type.is_some()) {
-                                "status-error"
-                            } else if m.iter().any(|n| n.graph_op_count.unwrap_or(0) == 0) {
-                                "status-empty"
-                            } else if m.iter().any(|n| {
-                                !n.restart_reasons.as_ref().map_or(false, |o| o.is_empty())
-                            }) {
-                                "status-break"
-                            } else {
-                                "status-ok"
-                            }
-                        })
-                    });
-                    write!(
-                        star,
-                        "<a href='#{cid}' class='{ok_class}'>{cid}</a> ",
-                        cid = c,
-                        ok_class = ok_class
-                    )?;
-                } else {
-                    write!(star, "(unknown) ")?;
-                }
+            if is_internal(frame) {
+                let placeholder = trie.children.entry(FrameSummary::placeholder());
+                node.collapse_internal_run(1, placeholder, is_internal);
+            } else {
+                trie.children
+                    .entry(*frame)
+                    .merge(node.collapse_internal_inner(is_internal));
             }
+        }
+        trie
+    }
 
-            if self.children.len() > 1 {
-                // If the node has multiple children, increase the indent and print a hyphen
-                writeln!(
-                    f,
-                    "<li><span onclick='toggleList(this)' class='marker'></span>{star}",
-                    star = star
-                )?;
-                writeln!(f, "{}<ul>", frame)?;
-                node.fmt_inner(f, mb_metrics_index)?;
-                write!(f, "</ul></li>")?;
+    // self is reached through run internal frames in a row; everything past the end of the run
+    // goes under placeholder
+    fn collapse_internal_run(
+        &self,
+        run: usize,
+        placeholder: &mut StackTrieNode,
+        is_internal: &dyn Fn(&FrameSummary) -> bool,
+    ) {
+        if !self.terminal.is_empty() || self.children.keys().all(|f| !is_internal(f)) {
+            placeholder.hidden = Some(match placeholder.hidden {
+                Some(HiddenFrames::Internal(lo, hi)) => {
+                    HiddenFrames::Internal(lo.min(run), hi.max(run))
+                }
+                _ => HiddenFrames::Internal(run, run),
+            });
+        }
+        placeholder.terminal.extend(self.terminal.iter().cloned());
+        for (frame, node) in self.children.iter() {
+            if is_internal(frame) {
+                node.collapse_internal_run(run + 1, placeholder, is_internal);
             } else {
-                // If the node has only one child, don't increase the indent and don't print a hyphen
-                writeln!(f, "<li>{star}{}</li>", frame, star = star)?;
-                node.fmt_inner(f, mb_metrics_index)?;
+                placeholder
+                    .children
+                    .entry(*frame)
+                    .merge(node.collapse_internal_inner(is_internal));
             }
         }
-        Ok(())
     }
-}
-
-#[derive(Eq, PartialEq, Hash, Deserialize, Serialize, Debug, Clone)]
-pub struct CompileId {
-    pub frame_id: u32,
-    pub frame_compile_id: u32,
-    pub attempt: u32,
-}
 
-impl fmt::Display for CompileId {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[{}/{}", self.frame_id, self.frame_compile_id)?;
-        if self.attempt != 0 {
-            write!(f, "_{}", self.attempt)?;
+    fn merge(&mut self, other: StackTrieNode) {
+        self.terminal.extend(other.terminal);
+        self.hidden = match (self.hidden, other.hidden) {
+            (Some(HiddenFrames::Internal(lo1, hi1)), Some(HiddenFrames::Internal(lo2, hi2))) => {
+                Some(HiddenFrames::Internal(lo1.min(lo2), hi1.max(hi2)))
+            }
+            (a, b) => a.or(b),
+        };
+        for line in other.lines {
+            if let Err(i) = self.lines.binary_search(&line) {
+                self.lines.insert(i, line);
+            }
+        }
+        for (frame, node) in other.children {
+            self.children.entry(frame).merge(node);
         }
-        write!(f, "]")
     }
-}
 
-#[derive(Default, Debug)]
-pub struct Stats {
-    pub ok: u64,
-    pub other_rank: u64,
-    pub fail_glog: u64,
-    pub fail_json: u64,
-    pub fail_payload_md5: u64,
-    pub fail_dynamo_guards_json: u64,
-    pub fail_parser: u64,
-    pub unknown: u64,
-}
+    // A copy of the trie cut to about max_depth frames deep.  Single-child chains carry the
+    // least information, so they are cut first, from the middle out so that their first and last
+    // frames stay; only if that isn't enough is everything below max_depth replaced by a marker.
+    pub fn truncate(&self, max_depth: usize) -> StackTrieNode {
+        let mut trie = self.truncate_inner(max_depth);
+        trie.update_counts();
+        trie
+    }
 
-#[derive(Debug, Hash, Eq, PartialEq, Deserialize, Serialize, Clone)]
-pub struct FrameSummary {
-    pub filename: u32,
-    pub line: i32,
-    pub name: String,
-    pub uninterned_filename: Option<String>,
-}
+    fn truncate_inner(&self, budget: usize) -> StackTrieNode {
+        let mut trie = StackTrieNode {
+            terminal: self.terminal.clone(),
+            hidden: self.hidden,
+            lines: self.lines.clone(),
+            ..Default::default()
+        };
+        if budget == 0 && !self.children.is_empty() {
+            // The marker keeps the compiles under it so that they are still counted
+            let marker = StackTrieNode {
+                terminal: self.all_terminals(),
+                hidden: Some(HiddenFrames::Subtree(self.depth())),
+                ..Default::default()
+            };
+            trie.children.insert(FrameSummary::placeholder(), marker);
+            return trie;
+        }
+        for (frame, node) in self.children.iter() {
+            // The chain starting at frame, up to where it branches or a compile starts
+            let mut chain = vec![(frame, node)];
+            let mut end = node;
+            while end.terminal.is_empty() && end.children.len() == 1 {
+                let (f, n) = end.children.first().unwrap();
+                chain.push((f, n));
+                end = n;
+            }
+            let available = budget.saturating_sub(end.depth());
+            // The placeholder takes a line too, and the first and last frame always stay
+            let keep = available.saturating_sub(1).max(2);
+            let mut frames: Vec<(FrameSummary, &StackTrieNode)> = Vec::new();
+            let placeholder = StackTrieNode {
+                hidden: Some(HiddenFrames::Chain(chain.len().saturating_sub(keep))),
+                ..Default::default()
+            };
+            let rows = if chain.len() > available && keep + 1 < chain.len() {
+                let bottom = keep / 2;
+                let top = keep - bottom;
+                for (f, n) in &chain[..top] {
+                    frames.push((**f, n));
+                }
+                frames.push((FrameSummary::placeholder(), &placeholder));
+                for (f, n) in &chain[chain.len() - bottom..] {
+                    frames.push((**f, n));
+                }
+                keep + 1
+            } else {
+                for (f, n) in &chain {
+                    frames.push((**f, n));
+                }
+                chain.len()
+            };
+            // Put the chain back together from the bottom up
+            let (last, _) = frames.pop().unwrap();
+            let mut cur = (last, end.truncate_inner(budget.saturating_sub(rows)));
+            for (f, orig) in frames.into_iter().rev() {
+                let mut n = StackTrieNode {
+                    hidden: orig.hidden,
+                    lines: orig.lines.clone(),
+                    ..Default::default()
+                };
+                n.children.insert(cur.0, cur.1);
+                cur = (f, n);
+            }
+            trie.children.insert(cur.0, cur.1);
+        }
+        trie
+    }
 
-pub fn simplify_filename<'a>(filename: &'a str) -> &'a str {
-    let parts: Vec<&'a str> = filename.split("#link-tree/").collect();
-    if parts.len() > 1 {
-        return parts[1];
+    // How many frames deep the trie below this node goes
+    fn depth(&self) -> usize {
+        self.children
+            .values()
+            .map(|n| n.depth() + 1)
+            .max()
+            .unwrap_or(0)
     }
-    let re = Regex::new(r"[^/]+-seed-nspid[^/]+/").unwrap();
-    if let Some(captures) = re.captures(filename) {
-        if let Some(capture) = captures.get(0) {
-            return &filename[capture.end()..];
+
+    fn all_terminals(&self) -> Vec<Terminal> {
+        let mut terminals = self.terminal.clone();
+        for node in self.children.values() {
+            terminals.extend(node.all_terminals());
         }
+        terminals
     }
-    return filename;
-}
 
-pub fn unintern_str(interned_str: u32) -> String {
-    let intern_table = INTERN_TABLE.lock().unwrap();
-    let filename = intern_table
-        .get(&interned_str)
-        .map_or("(unknown)", |s| s.as_str());
-    return filename.to_string();
-}
+    // A copy of the trie with only the frames that satisfy keep.  What was below a dropped frame
+    // moves up to its parent, merging with anything already there for the same frame, so that
+    // stacks that only differed in dropped frames become one.
+    pub fn retain_frames(&self, keep: &dyn Fn(&FrameSummary) -> bool) -> StackTrieNode {
+        let mut trie = self.retain_frames_inner(keep);
+        trie.update_counts();
+        trie
+    }
 
-impl fmt::Display for FrameSummary {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let intern_table = INTERN_TABLE.lock().unwrap();
-        let filename = if let Some(f) = &self.uninterned_filename {
-            f.as_str()
-        } else {
-            intern_table
-                .get(&self.filename)
-                .map_or("(unknown)", |s| s.as_str())
+    fn retain_frames_inner(&self, keep: &dyn Fn(&FrameSummary) -> bool) -> StackTrieNode {
+        let mut trie = StackTrieNode {
+            terminal: self.terminal.clone(),
+            lines: self.lines.clone(),
+            ..Default::default()
         };
-        if let Some(fx_id) = extract_eval_with_key_id(filename) {
-            write!(
-                f,
-                "<a href='dump_file/eval_with_key_{fx_id}.html#L{line}'>{filename}:{line}</a> in {name}",
-                fx_id = fx_id,
-                filename = encode_text(simplify_filename(filename)),
-                line = self.line,
-                name = encode_text(&self.name)
-            )?;
-        } else {
-            write!(
-                f,
-                "{}:{} in {}",
-                encode_text(simplify_filename(filename)),
-                self.line,
-                encode_text(&self.name)
-            )?;
+        for (frame, node) in self.children.iter() {
+            let node = node.retain_frames_inner(keep);
+            if keep(frame) {
+                trie.children.entry(*frame).merge(node);
+            } else {
+                trie.merge(node);
+            }
         }
-        Ok(())
+        trie
     }
-}
-
-pub type StackSummary = Vec<FrameSummary>;
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(untagged)]
-pub enum SymInt {
-    Int(i64),
-    Symbol(String),
-}
+    // Reorder the children of every node; counts must be up to date, see update_counts
+    pub fn sort_children(&mut self, order: crate::TrieOrder) {
+        if let TrieChildren::Many(m) = &mut self.children {
+            match order {
+                crate::TrieOrder::Insertion => {}
+                // Stable, so that ties stay in insertion order; nodes without a time go last
+                crate::TrieOrder::FirstSeen => {
+                    m.sort_by(|_, a, _, b| match (a.first_seen, b.first_seen) {
+                        (Some(a), Some(b)) => a.cmp(&b),
+                        (a, b) => b.is_some().cmp(&a.is_some()),
+                    })
+                }
+                crate::TrieOrder::Compiles => {
+                    m.sort_by(|_, a, _, b| (b.compiles, b.attempts).cmp(&(a.compiles, a.attempts)))
+                }
+                crate::TrieOrder::CompileTime => {
+                    m.sort_by(|_, a, _, b| b.compile_time_s.total_cmp(&a.compile_time_s))
+                }
+            }
+        }
+        for node in self.children.values_mut() {
+            node.sort_children(order);
+        }
+    }
 
-impl Default for SymInt {
-    fn default() -> Self {
-        SymInt::Int(0)
+    // Look up how long every compile took in its compilation metrics, summed over the metrics
+    // of the attempt; call before update_counts
+    pub fn set_compile_times(&mut self, metrics_index: &CompilationMetricsIndex) {
+        for t in &mut self.terminal {
+            t.compile_time_s = metrics_index
+                .get(&t.compile_id)
+                .into_iter()
+                .flatten()
+                .filter_map(|m| m.entire_frame_compile_time_s)
+                .reduce(|a, b| a + b);
+        }
+        for node in self.children.values_mut() {
+            node.set_compile_times(metrics_index);
+        }
     }
-}
 
-fn default_layout() -> String {
-    "torch.strided".to_string()
-}
+    // Count the compiles under every node; call once everything has been inserted.  Restarts
+    // are counted separately, so that a frame that restarted a lot doesn't look like many
+    // different compiles.
+    pub fn update_counts(&mut self) -> FxHashMap<CompileId, Option<f64>> {
+        let mut ids: FxHashMap<CompileId, Option<f64>> = self
+            .terminal
+            .iter()
+            .filter_map(|t| Some((t.compile_id.clone()?, t.compile_time_s)))
+            .collect();
+        self.first_seen = self.terminal.iter().filter_map(|t| t.timestamp).min();
+        for node in self.children.values_mut() {
+            ids.extend(node.update_counts());
+            self.first_seen = match (self.first_seen, node.first_seen) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        self.attempts = ids.len();
+        // Whether any attempt of each compile was timed
+        let mut timed: FxHashMap<_, bool> = FxHashMap::default();
+        for (c, time) in &ids {
+            *timed.entry((c.frame_id, c.frame_compile_id)).or_default() |= time.is_some();
+        }
+        self.compiles = timed.len();
+        self.untimed = timed.values().filter(|t| !**t).count();
+        self.compile_time_s = ids.values().flatten().sum();
+        ids
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct OptimizeDdpSplitChildMetadata {
-    pub name: String,
-}
+    // When the first compile under a branch point happened, to line the trie up with the rest of
+    // the job.  The year is only a guess (see GlogTimestamps), so leave it out
+    fn fmt_first_seen(&self) -> String {
+        self.first_seen.map_or(String::new(), |t| {
+            format!(
+                " <span class='first-seen' title='first compile at {}'>{}</span>",
+                t.format("%m/%d %H:%M:%S%.6f"),
+                t.format("%H:%M:%S")
+            )
+        })
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct EmptyMetadata {}
+    fn fmt_counts(&self) -> String {
+        let compiles = match self.compiles {
+            1 => "1 compile".to_string(),
+            compiles => format!("{compiles} compiles"),
+        };
+        let counts = if self.attempts > self.compiles {
+            format!("{compiles}, {} attempts", self.attempts)
+        } else {
+            compiles
+        };
+        // Compiles without metrics add nothing to the time, so when some are missing the time
+        // is only a lower bound, and says so
+        match self.untimed {
+            untimed if untimed == self.compiles => format!(" ({counts})"),
+            0 => format!(" ({counts}, {})", fmt_seconds(self.compile_time_s)),
+            untimed => format!(
+                " ({counts}, ≥{}, {untimed} untimed)",
+                fmt_seconds(self.compile_time_s)
+            ),
+        }
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct GraphDumpMetadata {
-    pub name: String,
-}
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty() && self.terminal.is_empty()
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct DynamoOutputGraphMetadata {
-    _sizes: Option<FxHashMap<String, Vec<SymInt>>>,
-}
+    // Number of frames whose filename refers to an intern id that was never logged
+    pub fn unresolved_frames(&self) -> u64 {
+        INTERN_TABLE.with_borrow(|intern_table| self.unresolved_frames_inner(intern_table))
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct DynamoStartMetadata {
-    pub stack: Option<StackSummary>,
-}
+    fn unresolved_frames_inner(&self, intern_table: &FxHashMap<u32, String>) -> u64 {
+        self.children
+            .iter()
+            .map(|(frame, node)| {
+                let unresolved = match frame.filename {
+                    FrameFilename::Interned(id) => !intern_table.contains_key(&id),
+                    FrameFilename::Uninterned(_) => false,
+                };
+                unresolved as u64 + node.unresolved_frames_inner(intern_table)
+            })
+            .sum()
+    }
 
-#[derive(Debug, Deserialize)]
-pub struct InductorOutputCodeMetadata {
-    pub filename: Option<PathBuf>,
-}
+    // Branch points become <details>, open down to open_depth branch points deep, so that big
+    // subtrees can be folded away
+    pub fn fmt(
+        &self,
+        metrics_index: Option<&CompilationMetricsIndex>,
+        open_depth: usize,
+        strip_rules: &StripRules,
+    ) -> Result<String, fmt::Error> {
+        self.fmt_linking_to(metrics_index, open_depth, "", strip_rules)
+    }
 
-#[derive(Debug, Deserialize)]
+    // Like fmt, for a trie that isn't on the same page as the index entries its compiles link to
+    pub fn fmt_linking_to(
+        &self,
+        metrics_index: Option<&CompilationMetricsIndex>,
+        open_depth: usize,
+        index_page: &str,
+        strip_rules: &StripRules,
+    ) -> Result<String, fmt::Error> {
+        let statuses = metrics_index.map(CompileStatuses::new);
+        let mut f = String::new();
+        write!(f, "<div class='stack-trie'>")?;
+        write!(f, "<ul>")?;
+        self.fmt_inner(
+            &mut f,
+            statuses.as_ref(),
+            0,
+            open_depth,
+            index_page,
+            strip_rules,
+        )?;
+        write!(f, "</ul>")?;
+        write!(f, "</div>")?;
+        Ok(f)
+    }
+
+    // The same tree as indented lines in a <pre>, for --plain-text
+    pub fn fmt_text(&self, strip_rules: &StripRules) -> Result<String, fmt::Error> {
+        let mut f = String::new();
+        write!(f, "<pre class='stack-trie'>")?;
+        self.fmt_text_inner(&mut f, 0, strip_rules)?;
+        write!(f, "</pre>")?;
+        Ok(f)
+    }
+
+    fn fmt_text_inner(
+        &self,
+        f: &mut String,
+        indent: usize,
+        strip_rules: &StripRules,
+    ) -> fmt::Result {
+        for (frame, node) in self.children.iter() {
+            write!(f, "{:indent$}", "", indent = indent * 2)?;
+            for group in node.terminal_groups() {
+                write!(f, "{} ", Self::fmt_terminal_group(&group))?;
+            }
+            let frame = node.fmt_frame(frame, strip_rules);
+            if self.children.len() > 1 && !node.children.is_empty() {
+                writeln!(f, "{}{}", frame, node.fmt_counts())?;
+            } else {
+                writeln!(f, "{}", frame)?;
+            }
+            let indent = if self.children.len() > 1 {
+                indent + 1
+            } else {
+                indent
+            };
+            node.fmt_text_inner(f, indent, strip_rules)?;
+        }
+        Ok(())
+    }
+
+    // The trie as a box-drawn tree for a terminal (tlparse --print-trie).  Lines longer than
+    // `width` columns get their filename shortened in the middle.  With `color`, compile ids are
+    // colored by status like in the index
+    pub fn fmt_ansi(
+        &self,
+        mb_metrics_index: Option<&CompilationMetricsIndex>,
+        width: Option<usize>,
+        color: bool,
+        strip_rules: &StripRules,
+    ) -> String {
+        let statuses = mb_metrics_index.map(CompileStatuses::new);
+        let mut f = String::new();
+        self.fmt_ansi_inner(&mut f, statuses.as_ref(), width, color, "", strip_rules);
+        f
+    }
+
+    fn fmt_ansi_inner(
+        &self,
+        f: &mut String,
+        mb_statuses: Option<&CompileStatuses>,
+        width: Option<usize>,
+        color: bool,
+        prefix: &str,
+        strip_rules: &StripRules,
+    ) {
+        let branching = self.children.len() > 1;
+        for (i, (frame, node)) in self.children.iter().enumerate() {
+            // Chains stay at the same indentation, only branch points draw the tree
+            let (connector, continuation) = match (branching, i + 1 == self.children.len()) {
+                (false, _) => ("", ""),
+                (true, false) => ("├── ", "│   "),
+                (true, true) => ("└── ", "    "),
+            };
+            let mut ids = String::new();
+            let mut ids_len = 0;
+            for group in node.terminal_groups() {
+                let label = Self::fmt_terminal_group(&group);
+                ids_len += label.chars().count() + 1;
+                match (color, group[0]) {
+                    (true, Some(_)) => {
+                        let status = mb_statuses.map_or("status-missing", |s| s.compile(group[0]));
+                        let code = ansi_status_color(status);
+                        ids.push_str(&format!("\x1b[{code}m{label}\x1b[0m "));
+                    }
+                    _ => ids.push_str(&format!("{label} ")),
+                }
+            }
+            let counts = if branching && !node.children.is_empty() {
+                node.fmt_counts()
+            } else {
+                String::new()
+            };
+            let (filename, rest) = match node.hidden {
+                None => (
+                    simplify_filename(&frame.resolved_filename(), strip_rules).to_string(),
+                    format!(":{} in {}", frame.fmt_line(&node.lines), frame.name),
+                ),
+                Some(_) => (String::new(), node.fmt_frame(frame, strip_rules)),
+            };
+            let filename = match width {
+                Some(width) => {
+                    let fixed = prefix.chars().count()
+                        + connector.chars().count()
+                        + ids_len
+                        + rest.chars().count()
+                        + counts.chars().count();
+                    ellipsize_middle(&filename, width.saturating_sub(fixed))
+                }
+                None => filename,
+            };
+            f.push_str(&format!(
+                "{prefix}{connector}{ids}{filename}{rest}{counts}\n"
+            ));
+            node.fmt_ansi_inner(
+                f,
+                mb_statuses,
+                width,
+                color,
+                &format!("{prefix}{continuation}"),
+                strip_rules,
+            );
+        }
+    }
+
+    // How the frame leading to this node is shown; a placeholder says what it hides instead
+    fn fmt_frame(&self, frame: &FrameSummary, strip_rules: &StripRules) -> String {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        match self.hidden {
+            Some(HiddenFrames::Internal(lo, hi)) => {
+                let count = if lo == hi {
+                    lo.to_string()
+                } else {
+                    format!("{lo}–{hi}")
+                };
+                format!("… {count} internal frame{} …", plural(hi))
+            }
+            Some(HiddenFrames::Chain(n)) => format!("… {n} more frame{} …", plural(n)),
+            Some(HiddenFrames::Subtree(n)) => {
+                format!("… {n} more frame{}{}", plural(n), self.fmt_counts())
+            }
+            None => frame.fmt_lines(&self.lines, strip_rules),
+        }
+    }
+
+    // What the filter box in the index matches a node against: the frame leading to it and all
+    // of its compiles, as plain text
+    fn search_text(&self, frame: &FrameSummary, strip_rules: &StripRules) -> String {
+        let mut text = match self.hidden {
+            None => format!(
+                "{}:{} in {}",
+                simplify_filename(&frame.resolved_filename(), strip_rules),
+                frame.fmt_line(&self.lines),
+                frame.name
+            ),
+            Some(_) => self.fmt_frame(frame, strip_rules),
+        };
+        for group in self.terminal_groups() {
+            text.push(' ');
+            text.push_str(&Self::fmt_terminal_group(&group));
+        }
+        text
+    }
+
+    // Terminals of the same (frame_id, frame_compile_id) are restarts of one compile, so they
+    // are grouped into one entry, in the order each compile was first seen.  Compiles without
+    // an id all go in the None group.
+    fn terminal_groups(&self) -> Vec<Vec<&Option<CompileId>>> {
+        // A depth limit marker only counts the compiles under it
+        if let Some(HiddenFrames::Subtree(_)) = self.hidden {
+            return Vec::new();
+        }
+        let mut groups: FxIndexMap<_, Vec<&Option<CompileId>>> = FxIndexMap::default();
+        for t in &self.terminal {
+            let t = &t.compile_id;
+            let key = t.as_ref().map(|c| (c.frame_id, c.frame_compile_id));
+            groups.entry(key).or_default().push(t);
+        }
+        groups
+            .into_values()
+            .map(|mut group| {
+                group.sort_by_key(|t| t.as_ref().map(|c| c.attempt));
+                // The same attempt twice is still only one attempt
+                group.dedup_by(|a, b| a.is_some() && a == b);
+                group
+            })
+            .collect()
+    }
+
+    // A group as plain text, e.g. "[0/2 ×3 attempts]"
+    fn fmt_terminal_group(group: &[&Option<CompileId>]) -> String {
+        match (group, group[0]) {
+            (_, None) if group.len() > 1 => format!("(unknown ×{})", group.len()),
+            (_, None) => "(unknown)".to_string(),
+            ([_], Some(c)) => c.to_string(),
+            (_, Some(c)) => format!("[{} ×{} attempts]", c.frame_label(), group.len()),
+        }
+    }
+
+    // The compile ids that start at this node, or the first few of them if there are many
+    fn fmt_terminals(
+        &self,
+        mb_statuses: Option<&CompileStatuses>,
+        index_page: &str,
+    ) -> Result<String, fmt::Error> {
+        let groups = self.terminal_groups();
+        let mut star = String::new();
+        for group in groups.iter().take(MAX_TRIE_TERMINALS) {
+            // Without a metrics index there is no index entry to link to, e.g. for a rank that
+            // wasn't rendered
+            let (Some(c), Some(statuses)) = (group[0], mb_statuses) else {
+                write!(star, "{} ", Self::fmt_terminal_group(group))?;
+                continue;
+            };
+            if group.len() == 1 {
+                let ok_class = statuses.compile(group[0]);
+                // Don't rely on color alone to show a failure
+                let mark = if ok_class == "status-error" {
+                    "✗ "
+                } else {
+                    ""
+                };
+                write!(
+                    star,
+                    "<a href='{index_page}#{anchor}' class='{ok_class}'>{mark}{cid}</a> ",
+                    index_page = index_page,
+                    anchor = c.as_directory_name(),
+                    cid = c,
+                    mark = mark,
+                    ok_class = ok_class
+                )?;
+                continue;
+            }
+            // Each attempt links to its own entry, and failed ones are struck through
+            write!(
+                star,
+                "<span class='attempts {}'>[{} ×{} attempts:",
+                statuses.compile(group[0]),
+                c.frame_label(),
+                group.len()
+            )?;
+            for t in group {
+                let Some(c) = t else { continue };
+                let ok_class = statuses.attempt(t);
+                let attempt = if ok_class == "status-error" {
+                    format!("<s>{}</s>", c.attempt)
+                } else {
+                    c.attempt.to_string()
+                };
+                write!(
+                    star,
+                    " <a href='{index_page}#{anchor}' class='{ok_class}'>{attempt}</a>",
+                    index_page = index_page,
+                    anchor = c.as_directory_name(),
+                    ok_class = ok_class,
+                    attempt = attempt
+                )?;
+            }
+            write!(star, "]</span> ")?;
+        }
+        if groups.len() > MAX_TRIE_TERMINALS {
+            let rest: Vec<String> = groups[MAX_TRIE_TERMINALS..]
+                .iter()
+                .map(|group| Self::fmt_terminal_group(group))
+                .collect();
+            write!(
+                star,
+                "<span title='{}'>…(+{})</span> ",
+                rest.join(" "),
+                rest.len()
+            )?;
+        }
+        Ok(star)
+    }
+
+    pub fn fmt_inner(
+        &self,
+        f: &mut String,
+        mb_statuses: Option<&CompileStatuses>,
+        depth: usize,
+        open_depth: usize,
+        index_page: &str,
+        strip_rules: &StripRules,
+    ) -> fmt::Result {
+        for (frame, node) in self.children.iter() {
+            let star = node.fmt_terminals(mb_statuses, index_page)?;
+            let search =
+                encode_double_quoted_attribute(&node.search_text(frame, strip_rules)).into_owned();
+            let frame = match node.hidden {
+                Some(HiddenFrames::Internal(..)) => format!(
+                    "<span class='internal-frames'>{}</span>",
+                    node.fmt_frame(frame, strip_rules)
+                ),
+                Some(_) => format!(
+                    "<span class='truncated-frames'>{}</span>",
+                    node.fmt_frame(frame, strip_rules)
+                ),
+                None => frame.fmt_with_source(&node.lines, strip_rules),
+            };
+            // For the filter box in the index: children of a branch point are marked, so that
+            // the rest of a list is known to be the chain leading up to them
+            let class = if self.children.len() > 1 {
+                "trie-node trie-branch"
+            } else {
+                "trie-node"
+            };
+
+            if self.children.len() > 1 && !node.children.is_empty() {
+                // If the node has multiple children, each one gets its own foldable sub-list
+                let open = if depth < open_depth { " open" } else { "" };
+                writeln!(
+                    f,
+                    "<li class='{class}' data-search=\"{search}\"><details{open}><summary>{star}{frame}{counts}{first_seen}</summary><ul>",
+                    class = class,
+                    search = search,
+                    open = open,
+                    star = star,
+                    frame = frame,
+                    counts = node.fmt_counts(),
+                    first_seen = node.fmt_first_seen()
+                )?;
+                node.fmt_inner(
+                    f,
+                    mb_statuses,
+                    depth + 1,
+                    open_depth,
+                    index_page,
+                    strip_rules,
+                )?;
+                write!(f, "</ul></details></li>")?;
+            } else {
+                // If the node has only one child (or it's a leaf), don't increase the indent
+                writeln!(
+                    f,
+                    "<li class='{class}' data-search=\"{search}\">{star}{frame}</li>",
+                    class = class,
+                    search = search,
+                    star = star,
+                    frame = frame
+                )?;
+                node.fmt_inner(f, mb_statuses, depth, open_depth, index_page, strip_rules)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// For stack_trie.json: the root only has compiles and children, every other node also has the
+// frame leading to it, with the filename as shown in the index
+struct StackTrieRoot<'a> {
+    node: &'a StackTrieNode,
+    strip_rules: &'a StripRules,
+}
+
+impl Serialize for StackTrieRoot<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("StackTrieNode", 2)?;
+        state.serialize_field("compiles", &self.node.terminal_ids())?;
+        state.serialize_field(
+            "children",
+            &self.node.serializable_children(self.strip_rules),
+        )?;
+        state.end()
+    }
+}
+
+struct StackTrieChild<'a> {
+    frame: &'a FrameSummary,
+    node: &'a StackTrieNode,
+    strip_rules: &'a StripRules,
+}
+
+impl StackTrieNode {
+    // The trie as stack_trie.json has it, with filenames shortened by strip_rules
+    pub fn serializable<'a>(&'a self, strip_rules: &'a StripRules) -> impl Serialize + 'a {
+        StackTrieRoot {
+            node: self,
+            strip_rules,
+        }
+    }
+
+    fn terminal_ids(&self) -> Vec<&Option<CompileId>> {
+        self.terminal.iter().map(|t| &t.compile_id).collect()
+    }
+
+    fn serializable_children<'a>(&'a self, strip_rules: &'a StripRules) -> Vec<StackTrieChild<'a>> {
+        self.children
+            .iter()
+            .map(|(frame, node)| StackTrieChild {
+                frame,
+                node,
+                strip_rules,
+            })
+            .collect()
+    }
+}
+
+impl Serialize for StackTrieChild<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let filename = self.frame.resolved_filename();
+        let mut state = serializer.serialize_struct("StackTrieNode", 6)?;
+        state.serialize_field("filename", simplify_filename(&filename, self.strip_rules))?;
+        // A node that merged lines has them all, and the first as its line
+        match self.node.lines.first() {
+            Some(first) => {
+                state.serialize_field("line", first)?;
+                state.serialize_field("lines", &self.node.lines)?;
+            }
+            None => state.serialize_field("line", &self.frame.line)?,
+        }
+        state.serialize_field("function", &self.frame.name)?;
+        state.serialize_field("compiles", &self.node.terminal_ids())?;
+        state.serialize_field(
+            "children",
+            &self.node.serializable_children(self.strip_rules),
+        )?;
+        state.end()
+    }
+}
+
+// Each field is individually optional so that an envelope with an incomplete id (e.g.
+// only frame_id) still keeps what it has; see Envelope::compile_id
+#[derive(Eq, PartialEq, Hash, Deserialize, Serialize, Debug, Clone)]
+pub struct CompileId {
+    pub frame_id: Option<u32>,
+    pub frame_compile_id: Option<u32>,
+    #[serde(default)]
+    pub attempt: u32,
+}
+
+impl CompileId {
+    pub fn is_partial(&self) -> bool {
+        self.frame_id.is_none() || self.frame_compile_id.is_none()
+    }
+
+    // The compile without the attempt, e.g. "0/2"; shared by all restarts of a compile
+    pub fn frame_label(&self) -> String {
+        let part = |x: Option<u32>| x.map_or("?".to_string(), |x| x.to_string());
+        format!("{}/{}", part(self.frame_id), part(self.frame_compile_id))
+    }
+
+    // A compile id as the report shows it, e.g. "[2/0]", "2/0_1" or "-/0"; the brackets are
+    // optional
+    pub fn parse(label: &str) -> Option<Self> {
+        let label = label.trim();
+        let label = label
+            .strip_prefix('[')
+            .and_then(|l| l.strip_suffix(']'))
+            .unwrap_or(label);
+        let (frame_id, rest) = label.split_once('/')?;
+        let (frame_compile_id, attempt) = rest.split_once('_').unwrap_or((rest, "0"));
+        let part = |x: &str| match x {
+            "-" | "?" => Some(None),
+            x => x.parse().ok().map(Some),
+        };
+        Some(CompileId {
+            frame_id: part(frame_id)?,
+            frame_compile_id: part(frame_compile_id)?,
+            attempt: attempt.parse().ok()?,
+        })
+    }
+
+    // Name of the output subdirectory holding this compile's artifacts
+    pub fn as_directory_name(&self) -> String {
+        let part = |x: Option<u32>| x.map_or("-".to_string(), |x| x.to_string());
+        format!(
+            "{}_{}_{}",
+            part(self.frame_id),
+            part(self.frame_compile_id),
+            self.attempt
+        )
+    }
+}
+
+// Where a compile's artifacts go, relative to the output directory; for a real compile id
+// this is also the anchor of its entry in the index, which the stack trie links to.  Envelopes
+// without a compile id get a directory named after their line instead.
+pub fn compile_directory_name(compile_id: &Option<CompileId>, lineno: usize) -> String {
+    compile_id
+        .as_ref()
+        .map_or(format!("unknown_{lineno}"), CompileId::as_directory_name)
+}
+
+impl fmt::Display for CompileId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}", self.frame_label())?;
+        if self.attempt != 0 {
+            write!(f, "_{}", self.attempt)?;
+        }
+        write!(f, "]")
+    }
+}
+
+// What stats.json has: how many log entries were rendered, and why the others weren't.  Counts
+// added in later versions are 0 when reading an older stats.json.
+#[derive(Default, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Stats {
+    pub ok: u64,
+    pub other_rank: u64,
+    pub other_run: u64,
+    pub fail_glog: u64,
+    pub fail_json: u64,
+    pub fail_payload_md5: u64,
+    pub fail_missing_payload: u64,
+    pub fail_dynamo_guards_json: u64,
+    pub fail_parser: u64,
+    pub unknown: u64,
+    pub ansi_stripped: u64,
+    pub partial_compile_id: u64,
+    pub multiple_kinds: u64,
+    pub empty_envelope: u64,
+    pub duplicate_filename: u64,
+    pub duplicate_envelope: u64,
+    pub streamed_payload: u64,
+    pub intern_conflicts: u64,
+    pub unresolved_frames: u64,
+}
+
+// A big stack trie repeats the same few function names and filenames over and over, so frames
+// keep them in FRAME_STRINGS and only carry an id
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+pub struct FrameStr(u32);
+
+#[derive(Default)]
+struct FrameStrings {
+    ids: FxHashMap<Rc<str>, u32>,
+    strings: Vec<Rc<str>>,
+}
+
+thread_local! {
+    // Reset at the start of every parse, like INTERN_TABLE
+    static FRAME_STRINGS: RefCell<FrameStrings> = RefCell::new(FrameStrings::default());
+}
+
+pub fn clear_frame_strings() {
+    FRAME_STRINGS.set(FrameStrings::default());
+}
+
+impl FrameStr {
+    pub fn new(s: &str) -> FrameStr {
+        FRAME_STRINGS.with_borrow_mut(|table| {
+            if let Some(&id) = table.ids.get(s) {
+                return FrameStr(id);
+            }
+            let id = table.strings.len() as u32;
+            let s: Rc<str> = Rc::from(s);
+            table.strings.push(s.clone());
+            table.ids.insert(s, id);
+            FrameStr(id)
+        })
+    }
+
+    pub fn resolve(self) -> Rc<str> {
+        FRAME_STRINGS.with_borrow(|table| {
+            table
+                .strings
+                .get(self.0 as usize)
+                .cloned()
+                .unwrap_or_else(|| Rc::from("(unknown)"))
+        })
+    }
+}
+
+impl fmt::Display for FrameStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.resolve())
+    }
+}
+
+impl<'de> Deserialize<'de> for FrameStr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = std::borrow::Cow::<str>::deserialize(deserializer)?;
+        Ok(FrameStr::new(&s))
+    }
+}
+
+impl Serialize for FrameStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.resolve())
+    }
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+pub enum FrameFilename {
+    // An id from the log's own intern table, which may only be filled in later in the log
+    Interned(u32),
+    // For frames that came with the filename spelled out
+    Uninterned(FrameStr),
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Deserialize, Serialize, Clone, Copy)]
+#[serde(from = "RawFrameSummary", into = "RawFrameSummary")]
+pub struct FrameSummary {
+    pub filename: FrameFilename,
+    pub line: i32,
+    pub name: FrameStr,
+}
+
+// A frame as it is in the log
+#[derive(Deserialize, Serialize)]
+struct RawFrameSummary {
+    #[serde(default)]
+    filename: u32,
+    line: i32,
+    name: FrameStr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uninterned_filename: Option<FrameStr>,
+}
+
+impl From<RawFrameSummary> for FrameSummary {
+    fn from(raw: RawFrameSummary) -> FrameSummary {
+        FrameSummary {
+            filename: match raw.uninterned_filename {
+                Some(f) => FrameFilename::Uninterned(f),
+                None => FrameFilename::Interned(raw.filename),
+            },
+            line: raw.line,
+            name: raw.name,
+        }
+    }
+}
+
+impl From<FrameSummary> for RawFrameSummary {
+    fn from(frame: FrameSummary) -> RawFrameSummary {
+        let (filename, uninterned_filename) = match frame.filename {
+            FrameFilename::Interned(id) => (id, None),
+            FrameFilename::Uninterned(f) => (u32::MAX, Some(f)),
+        };
+        RawFrameSummary {
+            filename,
+            line: frame.line,
+            name: frame.name,
+            uninterned_filename,
+        }
+    }
+}
+
+// What a compile that didn't fail is expected to have logged: one of `expected`, if it has one
+// of `when`, unless it has one of `unless`.  Each of them is an envelope kind (e.g.
+// inductor_output_code), the name of an artifact (e.g. fx_graph_cache_hit) or a field of its
+// compilation metrics that was set (e.g. inductor_compile_time_s).
+#[derive(Debug)]
+pub struct ArtifactRule {
+    pub when: Vec<String>,
+    pub expected: Vec<String>,
+    pub unless: Vec<String>,
+}
+
+// A compile that went through Inductor has AOT autograd graphs and Inductor's output code,
+// unless a cache had them (older versions of PyTorch logged every FX graph cache lookup as
+// fx_graph_cache_hash).  Going by its Inductor time leaves out compiles with other backends
+// (eager, aot_eager), which legitimately have neither.
+pub static DEFAULT_ARTIFACT_RULES: [&str; 2] = [
+    "inductor_compile_time_s => aot_forward_graph|aot_inference_graph|aot_joint_graph unless aotautograd_cache_hit",
+    "inductor_compile_time_s => inductor_output_code unless fx_graph_cache_hit|fx_graph_cache_hash|aotautograd_cache_hit",
+];
+
+impl ArtifactRule {
+    // As given to --artifact-rule: "when|... => expected|...", optionally followed by
+    // " unless skip|..."
+    pub fn parse(s: &str) -> anyhow::Result<ArtifactRule> {
+        let list = |s: &str| -> Vec<String> {
+            s.split('|')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let (when, rest) = s
+            .split_once("=>")
+            .ok_or_else(|| anyhow::anyhow!("Artifact rule {s:?} has no =>"))?;
+        let (expected, unless) = rest.split_once(" unless ").unwrap_or((rest, ""));
+        let rule = ArtifactRule {
+            when: list(when),
+            expected: list(expected),
+            unless: list(unless),
+        };
+        if rule.when.is_empty() || rule.expected.is_empty() {
+            anyhow::bail!("Artifact rule {s:?} needs artifacts on both sides of =>");
+        }
+        Ok(rule)
+    }
+
+    // Whether a compile with these artifacts is missing what the rule expects
+    pub fn is_missing(&self, artifacts: &FxHashSet<String>) -> bool {
+        let any = |kinds: &[String]| kinds.iter().any(|k| artifacts.contains(k));
+        any(&self.when) && !any(&self.expected) && !any(&self.unless)
+    }
+
+    // For the index, e.g. "no inductor_output_code recorded"
+    pub fn fmt_missing(&self) -> String {
+        format!("no {} recorded", self.expected.join(" or "))
+    }
+}
+
+// How simplify_filename shortens a path: everything up to the end of the first match is dropped
+#[derive(Clone)]
+pub enum StripRule {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl StripRule {
+    // As given to --strip-prefix: "re:" marks a regex, anything else is a plain substring
+    pub fn parse(rule: &str) -> anyhow::Result<StripRule> {
+        match rule.strip_prefix("re:") {
+            Some(re) => Ok(StripRule::Regex(Regex::new(re)?)),
+            None => Ok(StripRule::Substring(rule.to_string())),
+        }
+    }
+
+    fn strip<'a>(&self, filename: &'a str) -> Option<&'a str> {
+        let end = match self {
+            StripRule::Substring(s) => filename.find(s.as_str())? + s.len(),
+            StripRule::Regex(re) => re.find(filename)?.end(),
+        };
+        Some(&filename[end..])
+    }
+}
+
+fn default_strip_rules() -> Vec<StripRule> {
+    vec![
+        // buck's link tree
+        StripRule::Substring("#link-tree/".to_string()),
+        // container checkouts
+        StripRule::Regex(Regex::new(r"[^/]+-seed-nspid[^/]+/").unwrap()),
+        StripRule::Substring("site-packages/".to_string()),
+        StripRule::Substring("dist-packages/".to_string()),
+        // the standard library of a conda env
+        StripRule::Regex(Regex::new(r"/envs/[^/]+/lib/python[0-9.]+/").unwrap()),
+    ]
+}
+
+// Every rule simplify_filename tries, made from ParseConfig::strip_prefixes (--strip-prefix on
+// the command line) once per parse.  The default is the built-in rules alone.
+#[derive(Clone)]
+pub struct StripRules(Vec<StripRule>);
+
+impl StripRules {
+    // User rules are tried before the defaults, in the order given, and the first one that
+    // matches wins
+    pub fn new(strip_prefixes: &[String]) -> anyhow::Result<StripRules> {
+        let mut rules = strip_prefixes
+            .iter()
+            .map(|r| StripRule::parse(r))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        rules.extend(default_strip_rules());
+        Ok(StripRules(rules))
+    }
+}
+
+impl Default for StripRules {
+    fn default() -> Self {
+        StripRules(default_strip_rules())
+    }
+}
+
+pub fn simplify_filename<'a>(filename: &'a str, strip_rules: &StripRules) -> &'a str {
+    strip_rules
+        .0
+        .iter()
+        .find_map(|rule| rule.strip(filename))
+        .unwrap_or(filename)
+}
+
+// How many lines around a frame's line its source snippet shows
+static SOURCE_CONTEXT_LINES: usize = 2;
+
+thread_local! {
+    // Set at the start of every parse, see set_source_root
+    static SOURCE_ROOT: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+    // The lines of every file looked up under SOURCE_ROOT so far, None if it couldn't be read
+    static SOURCE_FILES: RefCell<FxHashMap<PathBuf, Option<Rc<Vec<String>>>>> =
+        RefCell::new(FxHashMap::default());
+}
+
+// Where the simplified filenames of frames can be found locally, for source snippets
+pub fn set_source_root(root: Option<PathBuf>) {
+    SOURCE_ROOT.set(root);
+    SOURCE_FILES.with_borrow_mut(|files| files.clear());
+}
+
+fn source_lines(path: &Path) -> Option<Rc<Vec<String>>> {
+    SOURCE_FILES.with_borrow_mut(|files| {
+        files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                let contents = std::fs::read_to_string(path).ok()?;
+                Some(Rc::new(contents.lines().map(str::to_string).collect()))
+            })
+            .clone()
+    })
+}
+
+pub fn unintern_str(interned_str: u32) -> String {
+    INTERN_TABLE.with_borrow(|intern_table| {
+        intern_table
+            .get(&interned_str)
+            .map_or("(unknown)", |s| s.as_str())
+            .to_string()
+    })
+}
+
+impl FrameSummary {
+    // Stands in for frames that aren't shown, see StackTrieNode::collapse_internal and
+    // StackTrieNode::truncate; no real frame has an empty filename
+    fn placeholder() -> FrameSummary {
+        FrameSummary::uninterned("", 0, "")
+    }
+
+    // A frame for a filename that isn't in the log's intern table
+    pub fn uninterned(filename: &str, line: i32, name: &str) -> FrameSummary {
+        FrameSummary {
+            filename: FrameFilename::Uninterned(FrameStr::new(filename)),
+            line,
+            name: FrameStr::new(name),
+        }
+    }
+
+    // Look the filename up in intern_table now, for a frame from a rank with an intern table of
+    // its own.  Ids it doesn't have are left to the parse's intern table, which has what was
+    // logged without a rank.
+    pub fn resolve_with(&mut self, intern_table: &FxHashMap<u32, String>) {
+        if let FrameFilename::Interned(id) = self.filename {
+            if let Some(filename) = intern_table.get(&id) {
+                self.filename = FrameFilename::Uninterned(FrameStr::new(filename));
+            }
+        }
+    }
+
+    // The full filename, not yet simplified
+    pub fn resolved_filename(&self) -> String {
+        match self.filename {
+            FrameFilename::Uninterned(f) => f.resolve().to_string(),
+            FrameFilename::Interned(id) => unintern_str(id),
+        }
+    }
+}
+
+impl FrameSummary {
+    // The lines around this frame's line, if its file is under the source root
+    pub fn source_snippet(&self, strip_rules: &StripRules) -> Option<String> {
+        let root = SOURCE_ROOT.with_borrow(|root| root.clone())?;
+        let filename = self.resolved_filename();
+        // Keep absolute paths that weren't simplified under the root too
+        let path = root.join(simplify_filename(&filename, strip_rules).trim_start_matches('/'));
+        let lines = source_lines(&path)?;
+        let line = usize::try_from(self.line)
+            .ok()
+            .filter(|l| (1..=lines.len()).contains(l))?;
+        let first = line.saturating_sub(SOURCE_CONTEXT_LINES).max(1);
+        let last = (line + SOURCE_CONTEXT_LINES).min(lines.len());
+        let width = last.to_string().len();
+        let snippet: Vec<String> = (first..=last)
+            .map(|l| {
+                let mark = if l == line { ">" } else { " " };
+                format!("{mark}{l:>width$} | {}", lines[l - 1])
+            })
+            .collect();
+        Some(snippet.join("\n"))
+    }
+
+    // The frame for the HTML stack trie, with its source snippet to hover over if there is one.
+    // With merged lines (see FrameSummary::fmt_lines) the snippet is around the first of them
+    pub fn fmt_with_source(&self, lines: &[i32], strip_rules: &StripRules) -> String {
+        let at = FrameSummary {
+            line: lines.first().copied().unwrap_or(self.line),
+            ..*self
+        };
+        match at.source_snippet(strip_rules) {
+            Some(snippet) => format!(
+                "<span class='source' title=\"{}\">{}</span>",
+                encode_double_quoted_attribute(&snippet),
+                self.fmt_lines(lines, strip_rules)
+            ),
+            None => self.fmt_lines(lines, strip_rules),
+        }
+    }
+
+    // The frame shown with every line in lines instead of its own, e.g. "model.py:118,121 in
+    // forward", for a stack trie node that merged frames differing only in their line (see
+    // StackTrieNode::insert_merging_lines); empty lines shows the frame as it is
+    pub fn fmt_lines(&self, lines: &[i32], strip_rules: &StripRules) -> String {
+        let mut f = String::new();
+        self.write_at(&mut f, lines, strip_rules).unwrap();
+        f
+    }
+
+    // Just the line part of fmt_lines
+    pub fn fmt_line(&self, lines: &[i32]) -> String {
+        match lines {
+            [] => self.line.to_string(),
+            _ => lines
+                .iter()
+                .map(i32::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+
+    fn write_at(&self, f: &mut dyn Write, lines: &[i32], strip_rules: &StripRules) -> fmt::Result {
+        let first = lines.first().copied().unwrap_or(self.line);
+        let line = self.fmt_line(lines);
+        let filename = self.resolved_filename();
+        let filename = filename.as_str();
+        if let Some(fx_id) = extract_eval_with_key_id(filename) {
+            write!(
+                f,
+                "<a href='dump_file/eval_with_key_{fx_id}.html#{anchor}'>{filename}:{line}</a> in {name}",
+                fx_id = fx_id,
+                anchor = crate::parsers::line_anchor(first as usize),
+                filename = encode_text(simplify_filename(filename, strip_rules)),
+                line = line,
+                name = encode_text(&self.name.resolve())
+            )?;
+        } else {
+            write!(
+                f,
+                "{}:{} in {}",
+                encode_text(simplify_filename(filename, strip_rules)),
+                line,
+                encode_text(&self.name.resolve())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// With only the built-in strip rules; a parse's own output goes through fmt_lines
+impl fmt::Display for FrameSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_at(f, &[], &StripRules::default())
+    }
+}
+
+pub type StackSummary = Vec<FrameSummary>;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SymInt {
+    Int(i64),
+    Symbol(String),
+}
+
+impl Default for SymInt {
+    fn default() -> Self {
+        SymInt::Int(0)
+    }
+}
+
+fn default_layout() -> String {
+    "torch.strided".to_string()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OptimizeDdpSplitChildMetadata {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmptyMetadata {}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GraphDumpMetadata {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DynamoOutputGraphMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _sizes: Option<FxHashMap<String, Vec<SymInt>>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DynamoStartMetadata {
+    pub stack: Option<StackSummary>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InductorOutputCodeMetadata {
+    pub filename: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct LinkMetadata {
     pub name: String,
-    pub url: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ArtifactMetadata {
+    pub name: String,
+    pub encoding: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CompilationMetricsMetadata {
+    // Other information like frame_key are already in envelope
+    pub co_name: Option<String>,
+    pub co_filename: Option<String>,
+    pub co_firstlineno: Option<i32>,
+    pub cache_size: Option<u64>,
+    pub accumulated_cache_size: Option<u64>,
+    pub guard_count: Option<u64>,
+    pub shape_env_guard_count: Option<u64>,
+    pub graph_op_count: Option<u64>,
+    pub graph_node_count: Option<u64>,
+    pub graph_input_count: Option<u64>,
+    pub start_time: Option<f64>,
+    pub entire_frame_compile_time_s: Option<f64>,
+    pub backend_compile_time_s: Option<f64>,
+    pub inductor_compile_time_s: Option<f64>,
+    pub code_gen_time_s: Option<f64>,
+    pub fail_type: Option<String>,
+    pub fail_reason: Option<String>,
+    pub fail_user_frame_filename: Option<String>,
+    pub fail_user_frame_lineno: Option<u32>,
+    pub non_compliant_ops: Option<Vec<String>>,
+    pub compliant_custom_ops: Option<Vec<String>>,
+    pub restart_reasons: Option<Vec<String>>,
+    pub dynamo_time_before_restart_s: Option<f64>,
+    // Everything newer (or older) PyTorch versions log that isn't above, which is where most
+    // per-phase timings are
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Value>,
+}
+
+// Compile phases in the order they run, each with the names its time has been logged under by
+// different PyTorch versions, preferred first.  They nest (backend compile includes aot autograd,
+// which includes inductor), so they don't add up to the whole frame's compile time.
+static COMPILE_PHASES: &[(&str, &[&str])] = &[
+    (
+        "dynamo",
+        &["dynamo_cumulative_compile_time_us", "dynamo_compile_time_s"],
+    ),
+    (
+        "backend",
+        &["backend_compile_time_s", "backend_compile_time_us"],
+    ),
+    (
+        "aot",
+        &[
+            "aot_autograd_cumulative_compile_time_us",
+            "aot_autograd_compile_time_s",
+        ],
+    ),
+    (
+        "inductor",
+        &[
+            "inductor_cumulative_compile_time_us",
+            "inductor_compile_time_s",
+        ],
+    ),
+    (
+        "codegen",
+        &[
+            "inductor_code_gen_cumulative_compile_time_us",
+            "code_gen_time_s",
+        ],
+    ),
+];
+
+// A duration in seconds, if key names one by its unit suffix; start and end times are
+// timestamps rather than durations
+fn timing_seconds(key: &str, value: &Value) -> Option<f64> {
+    if !key.contains("time") || key.starts_with("start_time") || key.starts_with("end_time") {
+        return None;
+    }
+    let v = value.as_f64()?;
+    if key.ends_with("_us") {
+        Some(v / 1e6)
+    } else if key.ends_with("_ms") {
+        Some(v / 1e3)
+    } else if key.ends_with("_s") {
+        Some(v)
+    } else {
+        None
+    }
+}
+
+impl CompilationMetricsMetadata {
+    // Time spent in each phase of the compile, under its canonical name, followed by any other
+    // timings we don't know under their own names
+    pub fn phase_times(&self) -> Vec<(String, f64)> {
+        let mut timings: BTreeMap<&str, f64> = self
+            .other
+            .iter()
+            .filter_map(|(k, v)| Some((k.as_str(), timing_seconds(k, v)?)))
+            .collect();
+        let typed = [
+            ("backend_compile_time_s", self.backend_compile_time_s),
+            ("inductor_compile_time_s", self.inductor_compile_time_s),
+            ("code_gen_time_s", self.code_gen_time_s),
+        ];
+        for (k, v) in typed {
+            if let Some(v) = v {
+                timings.insert(k, v);
+            }
+        }
+        let mut phases = Vec::new();
+        for (phase, names) in COMPILE_PHASES {
+            let mut time = None;
+            for name in *names {
+                if let Some(t) = timings.remove(name) {
+                    time = time.or(Some(t));
+                }
+            }
+            if let Some(t) = time {
+                phases.push((phase.to_string(), t));
+            }
+        }
+        phases.extend(timings.into_iter().map(|(k, t)| (k.to_string(), t)));
+        phases
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BwdCompilationMetricsMetadata {
+    pub inductor_compile_time_s: Option<f64>,
+    pub code_gen_time_s: Option<f64>,
+    pub fail_type: Option<String>,
+    pub fail_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AOTAutogradBackwardCompilationMetricsMetadata {
+    pub start_time: Option<f64>,
+    pub elapsed_time: Option<f64>, // technically redundant with envelope
+    pub fail_type: Option<String>,
+    pub fail_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SymbolicShapeSpecializationMetadata {
+    pub symbol: Option<String>,
+    pub sources: Option<Vec<String>>,
+    pub value: Option<String>,
+    pub reason: Option<String>,
+    pub stack: Option<StackSummary>,
+    pub user_stack: Option<StackSummary>,
+}
+
+// A symbol the shape env made for a dimension (or other size) of an input, i.e. one that is
+// dynamic unless it gets specialized later; so are dimensions marked with mark_dynamic
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateSymbolMetadata {
+    pub symbol: Option<String>,
+    pub val: Option<String>,
+    pub vr: Option<String>,
+    pub source: Option<String>,
+    pub user_stack: Option<StackSummary>,
+    pub stack: Option<StackSummary>,
+}
+
+// How many of the dimensions a compile could have kept dynamic it did: every symbol it
+// created, or specialized, is a candidate, and those it specialized aren't dynamic
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct DynamicDims {
+    pub candidates: usize,
+    pub dynamic: usize,
+    pub specialized: usize,
+}
+
+impl DynamicDims {
+    pub fn new(record: &CompileRecord) -> Self {
+        let specialized: FxHashSet<&String> = record.specialized_symbols.iter().collect();
+        let candidates: FxHashSet<&String> = record
+            .created_symbols
+            .iter()
+            .chain(&record.specialized_symbols)
+            .collect();
+        DynamicDims {
+            candidates: candidates.len(),
+            dynamic: candidates.len() - specialized.len(),
+            specialized: specialized.len(),
+        }
+    }
+
+    pub fn add(&mut self, other: DynamicDims) {
+        self.candidates += other.candidates;
+        self.dynamic += other.dynamic;
+        self.specialized += other.specialized;
+    }
+}
+
+// For the index: dynamic and specialized dimensions over the whole run, and per compile
+#[derive(Debug, Serialize)]
+pub struct DynamicShapesSummary {
+    pub total: DynamicDims,
+    // e.g. "82%"
+    pub specialized_percent: String,
+    // (compile id, link to its specializations, its dimensions)
+    pub compiles: Vec<(String, String, DynamicDims)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BwdCompilationMetricsContext<'e> {
+    pub m: &'e BwdCompilationMetricsMetadata,
+    pub css: &'static str,
+    pub compile_id: String,
+    pub qps: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AOTAutogradBackwardCompilationMetricsContext<'e> {
+    pub m: &'e AOTAutogradBackwardCompilationMetricsMetadata,
+    pub css: &'static str,
+    pub compile_id: String,
+    pub qps: &'static str,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OutputFile {
+    pub url: String,
+    pub name: String,
+    pub number: i32,
+    pub suffix: String,
+    // In bytes, as written; None for links to elsewhere
+    pub size: Option<u64>,
+    // Its highlighted companion page, for text artifacts, see highlighted_html (or
+    // json_tree_html)
+    pub highlighted: Option<String>,
+    // Its first lines, see artifact_preview; only for compile.html, not compile_directory.json
+    #[serde(skip)]
+    pub preview: Option<String>,
+}
+
+// A compile's artifacts in compile_directory.json, with the totals the index shows
+#[derive(Debug, Serialize)]
+pub struct CompileDirectoryEntry<'a> {
+    pub compile_id: Option<&'a CompileId>,
+    pub num_files: usize,
+    // Bytes written for it, streamed payloads included
+    pub total_size: u64,
+    // From log timestamps, see CompileSpan; not how long compiling took according to the
+    // compile itself
+    pub span_s: Option<f64>,
+    pub span_segments: usize,
+    // Its user_frame_label
+    pub frame: Option<String>,
+    // entire_frame_compile_time_s of its compilation metrics, if it has any
+    pub compile_time_s: Option<f64>,
+    pub files: &'a [OutputFile],
+    // See DataIssue
+    pub issues: &'a [DataIssue],
+}
+
+impl<'a> CompileDirectoryEntry<'a> {
+    pub fn new(
+        compile_id: Option<&'a CompileId>,
+        files: &'a [OutputFile],
+        record: Option<&'a CompileRecord>,
+        frame: Option<String>,
+        compile_time_s: Option<f64>,
+    ) -> Self {
+        let span = record.and_then(CompileRecord::span);
+        let sizes = files.iter().filter_map(|f| f.size);
+        CompileDirectoryEntry {
+            compile_id,
+            num_files: sizes.clone().count(),
+            total_size: sizes.sum(),
+            span_s: span.map(CompileSpan::duration_s),
+            span_segments: span.map_or(0, CompileSpan::segments),
+            frame,
+            compile_time_s,
+            files,
+            issues: record.map_or(&[], |r| &r.issues[..]),
+        }
+    }
+}
+
+// Where a report came from, for when its pages get copied far away from the log: the footer of
+// every page (TEMPLATE_PROVENANCE), and a block of stats.json and compile_directory.json
+#[derive(Debug, Serialize)]
+pub struct Provenance {
+    pub tlparse_version: &'static str,
+    pub input: String,
+    // None if the log couldn't be stat'ed
+    pub input_size: Option<u64>,
+    pub input_modified: Option<String>,
+    // The rank that was rendered, and how it was picked: "selected", "detected" (see
+    // detect_rank) or "first seen"
+    pub rank: Option<u32>,
+    pub rank_source: Option<&'static str>,
+    // The command line's arguments
+    pub flags: Vec<String>,
+    pub generated: String,
+}
+
+impl Provenance {
+    pub fn new(
+        input: &Path,
+        flags: &[String],
+        rank: Option<u32>,
+        rank_source: Option<&'static str>,
+    ) -> Self {
+        const FORMAT: &str = "%Y-%m-%d %H:%M:%S %:z";
+        let metadata = std::fs::metadata(input).ok();
+        Provenance {
+            tlparse_version: env!("CARGO_PKG_VERSION"),
+            input: input.display().to_string(),
+            input_size: metadata.as_ref().map(|m| m.len()),
+            input_modified: metadata
+                .and_then(|m| m.modified().ok())
+                .map(|t| DateTime::<Local>::from(t).format(FORMAT).to_string()),
+            rank,
+            rank_source,
+            flags: flags.to_vec(),
+            generated: Local::now().format(FORMAT).to_string(),
+        }
+    }
+}
+
+// compile_directory.json
+#[derive(Debug, Serialize)]
+pub struct CompileDirectoryJson<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<&'a Provenance>,
+    pub compiles: Vec<CompileDirectoryEntry<'a>>,
+}
+
+// Where a compile's time in the index summary came from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CompileTimeSource {
+    // entire_frame_compile_time_s of its compilation metrics
+    Metrics,
+    // Its CompileSpan, for compiles without metrics
+    Span,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistogramBucket {
+    pub label: String,
+    pub count: usize,
+    // Percent of the biggest bucket, for the width of its bar
+    pub width: usize,
+}
+
+// dashboard.html: the report's numbers as charts, SVG from charts; a chart is empty if the log
+// has nothing for it
+#[derive(Debug, Serialize)]
+pub struct DashboardContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    // Of CompileTimeSummary's histogram
+    pub compile_time_chart: String,
+    // How many compiles had started by each point of the log
+    pub num_compiles: usize,
+    pub compiles_chart: String,
+    pub cache_chart: String,
+    pub cache_hit_rate: String,
+    // By restart reason
+    pub num_graph_breaks: usize,
+    pub graph_breaks_chart: String,
+    // By fail type
+    pub num_failures: usize,
+    pub failures_chart: String,
+}
+
+// The compile time panel at the top of the index
+#[derive(Debug, Serialize)]
+pub struct CompileTimeSummary {
+    pub num_compiles: usize,
+    pub num_timed: usize,
+    pub untimed: usize,
+    pub total: String,
+    pub mean: String,
+    pub p50: String,
+    pub p90: String,
+    pub max: String,
+    pub source: String,
+    pub histogram: Vec<HistogramBucket>,
+}
+
+// Compile time by phase over all compiles, for the summary panel
+#[derive(Debug, Serialize)]
+pub struct PhaseBreakdown {
+    pub total: String,
+    // (phase, formatted time summed over compiles)
+    pub phases: Vec<(String, String)>,
+    // The same phases again, as the columns of rows
+    pub columns: Vec<String>,
+    pub rows: Vec<PhaseBreakdownRow>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhaseBreakdownRow {
+    pub compile_id: String,
+    // Its innermost user frame, see user_frame_label; empty for compiles without an id
+    pub frame: String,
+    pub url: String,
+    pub total: String,
+    // One per column, empty where this compile didn't log that phase
+    pub phases: Vec<String>,
+}
+
+// One compile's metrics, as PhaseBreakdown::new takes them
+pub struct CompilePhaseTimes {
+    pub compile_id: String,
+    pub frame: String,
+    // Its metrics page
+    pub url: String,
+    pub entire_frame_compile_time_s: Option<f64>,
+    pub phases: Vec<(String, f64)>,
+}
+
+impl PhaseBreakdown {
+    pub fn new(rows: Vec<CompilePhaseTimes>) -> Option<Self> {
+        // Canonical phases keep their order by coming first in every compile's phases
+        let mut totals: FxIndexMap<String, f64> = FxIndexMap::default();
+        for row in &rows {
+            for (phase, t) in &row.phases {
+                *totals.entry(phase.clone()).or_default() += t;
+            }
+        }
+        if totals.is_empty() {
+            return None;
+        }
+        let known: Vec<&str> = COMPILE_PHASES.iter().map(|(p, _)| *p).collect();
+        totals.sort_by(|a, _, b, _| {
+            let rank = |p: &str| known.iter().position(|k| *k == p).unwrap_or(known.len());
+            rank(a).cmp(&rank(b)).then(a.cmp(b))
+        });
+        let total: f64 = rows
+            .iter()
+            .filter_map(|r| r.entire_frame_compile_time_s)
+            .sum();
+        let rows = rows
+            .into_iter()
+            .map(|r| PhaseBreakdownRow {
+                compile_id: r.compile_id,
+                frame: r.frame,
+                url: r.url,
+                total: r
+                    .entire_frame_compile_time_s
+                    .map_or(String::new(), fmt_seconds),
+                phases: totals
+                    .keys()
+                    .map(|c| {
+                        r.phases
+                            .iter()
+                            .find(|(p, _)| p == c)
+                            .map_or(String::new(), |(_, t)| fmt_seconds(*t))
+                    })
+                    .collect(),
+            })
+            .collect();
+        Some(PhaseBreakdown {
+            total: fmt_seconds(total),
+            columns: totals.keys().cloned().collect(),
+            phases: totals
+                .into_iter()
+                .map(|(p, t)| (p, fmt_seconds(t)))
+                .collect(),
+            rows,
+        })
+    }
+}
+
+// Upper bounds of the histogram buckets in seconds; compile times vary over orders of
+// magnitude, so the buckets do too
+static HISTOGRAM_BOUNDS: &[f64] = &[0.1, 1.0, 10.0, 100.0, 1000.0];
+
+impl CompileTimeSummary {
+    // times has an entry for every compile that has a time at all, out of num_compiles
+    pub fn new(num_compiles: usize, times: &[(f64, CompileTimeSource)]) -> CompileTimeSummary {
+        let mut seconds: Vec<f64> = times.iter().map(|(s, _)| *s).collect();
+        seconds.sort_by(f64::total_cmp);
+        // Nearest rank, so that every percentile is a time some compile actually took
+        let percentile = |p: f64| {
+            let rank = ((p * seconds.len() as f64).ceil() as usize).max(1);
+            seconds
+                .get(rank - 1)
+                .map_or(String::new(), |s| fmt_seconds(*s))
+        };
+        let total: f64 = seconds.iter().sum();
+        let from_metrics = times
+            .iter()
+            .filter(|(_, source)| *source == CompileTimeSource::Metrics)
+            .count();
+        let source = match (from_metrics, times.len() - from_metrics) {
+            (_, 0) => "compilation metrics".to_string(),
+            (0, _) => "log timestamps (spans between a compile's first and last entry)".to_string(),
+            (m, s) => format!(
+                "compilation metrics for {m} compiles, log timestamps (spans) for the other {s}"
+            ),
+        };
+        CompileTimeSummary {
+            num_compiles,
+            num_timed: times.len(),
+            untimed: num_compiles - times.len(),
+            total: fmt_seconds(total),
+            mean: fmt_seconds(total / seconds.len().max(1) as f64),
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            max: seconds.last().map_or(String::new(), |s| fmt_seconds(*s)),
+            source,
+            histogram: Self::histogram(&seconds),
+        }
+    }
+
+    // From the bucket of the fastest compile to the bucket of the slowest, sorted seconds
+    fn histogram(seconds: &[f64]) -> Vec<HistogramBucket> {
+        let bucket = |s: f64| {
+            HISTOGRAM_BOUNDS
+                .iter()
+                .position(|b| s < *b)
+                .unwrap_or(HISTOGRAM_BOUNDS.len())
+        };
+        let (Some(first), Some(last)) = (seconds.first(), seconds.last()) else {
+            return Vec::new();
+        };
+        let mut counts = vec![0; HISTOGRAM_BOUNDS.len() + 1];
+        for s in seconds {
+            counts[bucket(*s)] += 1;
+        }
+        let biggest = counts.iter().copied().max().unwrap_or(1);
+        (bucket(*first)..=bucket(*last))
+            .map(|i| HistogramBucket {
+                label: match i {
+                    0 => format!("< {}", fmt_seconds(HISTOGRAM_BOUNDS[0])),
+                    i if i == HISTOGRAM_BOUNDS.len() => {
+                        format!("≥ {}", fmt_seconds(HISTOGRAM_BOUNDS[i - 1]))
+                    }
+                    i => format!(
+                        "{} – {}",
+                        fmt_seconds(HISTOGRAM_BOUNDS[i - 1]),
+                        fmt_seconds(HISTOGRAM_BOUNDS[i])
+                    ),
+                },
+                count: counts[i],
+                // Rounded up, so that no bucket with compiles in it looks empty
+                width: (counts[i] * 100).div_ceil(biggest),
+            })
+            .collect()
+    }
+}
+
+// Roughly how long a compile took, from the first to the last log entry with its compile id.
+// Entries more than a gap apart start a new segment, so that a compile id logged again much
+// later (a cache hit, or the same compile logged twice) doesn't stretch over everything in
+// between; the span is the segments together.
+#[derive(Debug, Default, Clone)]
+pub struct CompileSpan {
+    segments: Vec<(NaiveDateTime, NaiveDateTime)>,
+}
+
+impl CompileSpan {
+    pub fn add(&mut self, t: NaiveDateTime, gap: TimeDelta) {
+        match self.segments.last_mut() {
+            // Threads can log slightly out of order, so an entry may be a bit before the start
+            Some((start, end)) if t >= *start - gap && t <= *end + gap => {
+                *start = (*start).min(t);
+                *end = (*end).max(t);
+            }
+            _ => self.segments.push((t, t)),
+        }
+    }
+
+    pub fn duration_s(&self) -> f64 {
+        self.segments
+            .iter()
+            .map(|(start, end)| (*end - *start).num_microseconds().unwrap_or(0) as f64 / 1e6)
+            .sum()
+    }
+
+    pub fn segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    // The first log entry of the compile
+    pub fn start(&self) -> Option<NaiveDateTime> {
+        self.segments.iter().map(|(start, _)| *start).min()
+    }
+
+    // The last log entry of the compile
+    pub fn end(&self) -> Option<NaiveDateTime> {
+        self.segments.iter().map(|(_, end)| *end).max()
+    }
+
+    // For the index, e.g. "span 2.5 s" or "span 2.5 s in 2 segments"
+    pub fn fmt(&self) -> String {
+        match self.segments() {
+            1 => format!("span {}", fmt_seconds(self.duration_s())),
+            n => format!("span {} in {n} segments", fmt_seconds(self.duration_s())),
+        }
+    }
+}
+
+// How much of the log's wall clock time went to compiling: the union of every compile's span
+// segments, since compiles on different threads can overlap
+#[derive(Debug, Serialize)]
+pub struct WallClockTime {
+    pub log_span_s: f64,
+    pub compiling_s: f64,
+    // compiling_s / log_span_s, 0 for a log that is all one timestamp
+    pub fraction: f64,
+}
+
+impl WallClockTime {
+    pub fn new<'a>(
+        log_span: (NaiveDateTime, NaiveDateTime),
+        spans: impl IntoIterator<Item = &'a CompileSpan>,
+    ) -> Self {
+        let mut segments: Vec<(NaiveDateTime, NaiveDateTime)> = spans
+            .into_iter()
+            .flat_map(|s| s.segments.iter().copied())
+            .collect();
+        segments.sort();
+        let mut merged: Vec<(NaiveDateTime, NaiveDateTime)> = Vec::new();
+        for (start, end) in segments {
+            match merged.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        let seconds = |d: TimeDelta| d.num_microseconds().unwrap_or(0) as f64 / 1e6;
+        let log_span_s = seconds(log_span.1 - log_span.0);
+        let compiling_s = merged.iter().map(|(s, e)| seconds(*e - *s)).sum();
+        WallClockTime {
+            log_span_s,
+            compiling_s,
+            fraction: if log_span_s > 0.0 {
+                compiling_s / log_span_s
+            } else {
+                0.0
+            },
+        }
+    }
+
+    // For the index, e.g. "3.5 min of a 10.0 min log were spent compiling (35%)"
+    pub fn fmt(&self) -> String {
+        format!(
+            "{} of a {} log were spent compiling ({:.0}%)",
+            fmt_wall_time(self.compiling_s),
+            fmt_wall_time(self.log_span_s),
+            self.fraction * 100.0
+        )
+    }
+}
+
+// Like fmt_seconds, but in minutes past a minute, which is how long runs are thought of
+pub fn fmt_wall_time(s: f64) -> String {
+    if s < 60.0 {
+        fmt_seconds(s)
+    } else {
+        format!("{:.1} min", s / 60.0)
+    }
+}
+
+// Something that happened at a point in the log, for Timeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimelineEvent {
+    // A restart reason of compilation metrics
+    GraphBreak,
+    // The first attempt of a frame's second or later compile
+    Recompile,
+}
+
+// How many buckets Timeline aims for when picking their width itself
+static TIMELINE_BUCKETS: f64 = 20.0;
+
+// The most buckets Timeline makes however narrow they were asked to be, so that a tiny --bucket
+// over a long log doesn't make millions of them
+static MAX_TIMELINE_BUCKETS: f64 = 1000.0;
+
+// A log shorter than this many seconds is all startup, so Timeline doesn't count any of its
+// events as late
+static TIMELINE_MIN_LATE_SPAN_S: f64 = 60.0;
+
+// The widths Timeline picks from, in seconds; past the last, it goes in whole days
+static TIMELINE_BUCKET_WIDTHS: [f64; 17] = [
+    1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1800.0, 3600.0, 7200.0,
+    10800.0, 21600.0, 43200.0,
+];
+
+#[derive(Debug, Default, Serialize)]
+pub struct TimelineBucket {
+    // Seconds from the first entry of the log
+    pub start_s: f64,
+    pub graph_breaks: u64,
+    pub recompiles: u64,
+}
+
+// Graph breaks and recompiles over the log, in fixed windows: breaks that keep happening long
+// after startup usually mean data-dependent branching, and compilation that never converges
+#[derive(Debug, Serialize)]
+pub struct Timeline {
+    pub bucket_s: f64,
+    pub buckets: Vec<TimelineBucket>,
+    // Of those, how many happened in the second half of the log, if it spans at least
+    // TIMELINE_MIN_LATE_SPAN_S
+    pub late_events: u64,
+}
+
+impl Timeline {
+    // Buckets of bucket_s seconds, or of a round width that splits the log into about
+    // TIMELINE_BUCKETS if None
+    pub fn new(
+        log_span: (NaiveDateTime, NaiveDateTime),
+        events: &[(NaiveDateTime, TimelineEvent)],
+        bucket_s: Option<f64>,
+    ) -> Self {
+        let seconds = |d: TimeDelta| d.num_microseconds().unwrap_or(0) as f64 / 1e6;
+        let span_s = seconds(log_span.1 - log_span.0);
+        let bucket_s = match bucket_s {
+            Some(b) => b.max(span_s / MAX_TIMELINE_BUCKETS),
+            None => {
+                let target = span_s / TIMELINE_BUCKETS;
+                TIMELINE_BUCKET_WIDTHS
+                    .iter()
+                    .copied()
+                    .find(|&w| w >= target)
+                    .unwrap_or_else(|| (target / 86400.0).ceil() * 86400.0)
+            }
+        }
+        .max(1e-3);
+        let num_buckets = (span_s / bucket_s).floor() as usize + 1;
+        let mut buckets: Vec<TimelineBucket> = (0..num_buckets)
+            .map(|i| TimelineBucket {
+                start_s: i as f64 * bucket_s,
+                ..Default::default()
+            })
+            .collect();
+        let mut late_events = 0;
+        for (t, event) in events {
+            let offset_s = seconds(*t - log_span.0).clamp(0.0, span_s);
+            let bucket = &mut buckets[((offset_s / bucket_s) as usize).min(num_buckets - 1)];
+            match event {
+                TimelineEvent::GraphBreak => bucket.graph_breaks += 1,
+                TimelineEvent::Recompile => bucket.recompiles += 1,
+            }
+            if span_s >= TIMELINE_MIN_LATE_SPAN_S && offset_s * 2.0 > span_s {
+                late_events += 1;
+            }
+        }
+        Timeline {
+            bucket_s,
+            buckets,
+            late_events,
+        }
+    }
+
+    pub fn num_events(&self) -> u64 {
+        self.buckets
+            .iter()
+            .map(|b| b.graph_breaks + b.recompiles)
+            .sum()
+    }
+
+    // Each bucket's graph breaks and recompiles together as a bar, scaled to the busiest bucket
+    pub fn sparkline(&self) -> String {
+        static BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = self
+            .buckets
+            .iter()
+            .map(|b| b.graph_breaks + b.recompiles)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        self.buckets
+            .iter()
+            .map(|b| match b.graph_breaks + b.recompiles {
+                0 => BARS[0],
+                n => BARS[((n * 7).div_ceil(max)) as usize],
+            })
+            .collect()
+    }
+}
+
+// A bucket of timeline.html
+#[derive(Debug, Serialize)]
+pub struct TimelineRow {
+    // e.g. "30 s", from the start of the log
+    pub start: String,
+    pub graph_breaks: u64,
+    pub recompiles: u64,
+    // Of the busiest bucket, in percent
+    pub width: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    // e.g. "30 s"
+    pub bucket: String,
+    pub rows: Vec<TimelineRow>,
+}
+
+impl TimelineContext {
+    pub fn new(timeline: &Timeline, css: &'static str, custom_header_html: String) -> Self {
+        let max = timeline
+            .buckets
+            .iter()
+            .map(|b| b.graph_breaks + b.recompiles)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        TimelineContext {
+            css,
+            custom_header_html,
+            bucket: fmt_wall_time(timeline.bucket_s),
+            rows: timeline
+                .buckets
+                .iter()
+                .map(|b| TimelineRow {
+                    start: fmt_wall_time(b.start_s),
+                    graph_breaks: b.graph_breaks,
+                    recompiles: b.recompiles,
+                    width: (b.graph_breaks + b.recompiles) * 100 / max,
+                })
+                .collect(),
+        }
+    }
+}
+
+// How many compiles (frame id and frame compile id, however many attempts they took) the log
+// has, for scripts that only read stats.json; failed ones have a fail_type in their metrics or
+// failed their backward
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CompileCounts {
+    pub total: usize,
+    pub recompiles: usize,
+    pub failed: usize,
+}
+
+impl CompileCounts {
+    pub fn new<'a>(
+        compile_ids: impl Iterator<Item = &'a CompileId>,
+        metrics_index: &CompilationMetricsIndex,
+        records: &FxHashMap<CompileId, CompileRecord>,
+    ) -> Self {
+        let mut compiles: FxIndexMap<(Option<u32>, Option<u32>), bool> = FxIndexMap::default();
+        for c in compile_ids {
+            let failed = records.get(c).is_some_and(|r| r.failed_elsewhere)
+                || metrics_index
+                    .get(&Some(c.clone()))
+                    .is_some_and(|ms| ms.iter().any(|m| m.fail_type.is_some()));
+            *compiles
+                .entry((c.frame_id, c.frame_compile_id))
+                .or_default() |= failed;
+        }
+        CompileCounts {
+            total: compiles.len(),
+            recompiles: compiles.keys().filter(|(_, n)| n.unwrap_or(0) > 0).count(),
+            failed: compiles.values().filter(|f| **f).count(),
+        }
+    }
+}
+
+// stats.json: the counters printed at the end of parsing, the wall clock time and the timeline
+#[derive(Debug, Serialize)]
+pub struct StatsJson<'a> {
+    #[serde(flatten)]
+    pub stats: &'a Stats,
+    pub compiles: &'a CompileCounts,
+    // The compiles of top.html, when ParseConfig::top_compiles asks for them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_compiles: Option<&'a [TopCompile]>,
+    pub wall_clock: Option<&'a WallClockTime>,
+    pub envelope_kinds: &'a EnvelopeKinds,
+    pub timeline: Option<&'a Timeline>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<&'a Provenance>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KindCount {
+    pub kind: String,
+    pub count: u64,
+}
+
+// How many envelopes of each kind the log has, so that a missing kind (no
+// inductor_output_code because the log was captured without it) is plain to see
+#[derive(Debug, Serialize)]
+pub struct EnvelopeKinds {
+    // Every kind in ENVELOPE_KINDS, in that order, including those never seen
+    pub known: Vec<KindCount>,
+    // Envelopes with none of the known fields
+    pub unknown: u64,
+    // The fields of those envelopes, most common first, at most UNKNOWN_KINDS_LISTED
+    pub unknown_fields: Vec<KindCount>,
+}
+
+// How many of the fields of envelopes of no known kind EnvelopeKinds lists
+static UNKNOWN_KINDS_LISTED: usize = 10;
+
+impl EnvelopeKinds {
+    pub fn new(
+        known: &FxHashMap<&str, u64>,
+        unknown: u64,
+        unknown_fields: FxHashMap<String, u64>,
+    ) -> Self {
+        let mut unknown_fields: Vec<KindCount> = unknown_fields
+            .into_iter()
+            .map(|(kind, count)| KindCount { kind, count })
+            .collect();
+        unknown_fields.sort_by(|a, b| b.count.cmp(&a.count).then(a.kind.cmp(&b.kind)));
+        unknown_fields.truncate(UNKNOWN_KINDS_LISTED);
+        EnvelopeKinds {
+            known: ENVELOPE_KINDS
+                .iter()
+                .map(|kind| KindCount {
+                    kind: kind.to_string(),
+                    count: known.get(kind).copied().unwrap_or(0),
+                })
+                .collect(),
+            unknown,
+            unknown_fields,
+        }
+    }
+}
+
+// Everything the log says about one compile (one attempt of it) outside of its artifacts and
+// compilation metrics, gathered from whichever envelopes carry it, for the reports that join
+// them.  Compilation metrics stay in the CompilationMetricsIndex, which is keyed the same way.
+#[derive(Debug, Default)]
+pub struct CompileRecord {
+    // Empty if none of its entries had a timestamp, see CompileRecord::span
+    pub span: CompileSpan,
+    // A failure outside its compilation metrics, e.g. of its backward compile
+    pub failed_elsewhere: bool,
+    // The code of its guards, if they were logged and could be read
+    pub guards: Option<Vec<String>>,
+    pub cache_events: Vec<CacheEvent>,
+    // Of its create_symbol and symbolic_shape_specialization entries, see DynamicDims; a
+    // specialization without a symbol goes by its first source
+    pub created_symbols: Vec<String>,
+    pub specialized_symbols: Vec<String>,
+    // Why it was compiled, when an earlier compile of its frame was
+    pub recompile_causes: Vec<RecompileCause>,
+    // The kinds of its envelopes, the names of its artifacts and the compilation metrics fields
+    // it set, for ArtifactRule
+    pub artifacts: FxHashSet<String>,
+    // What was wrong with how its entries were logged, in the order of the log
+    pub issues: Vec<DataIssue>,
+    // With --keep-raw
+    pub raw_excerpt: RawExcerpt,
+}
+
+// How many lines of each payload --keep-raw keeps in a compile's raw.log
+pub static RAW_EXCERPT_PAYLOAD_LINES: usize = 3;
+
+// A compile's envelopes as they were in the log, for its raw.log: each header line, and the
+// first RAW_EXCERPT_PAYLOAD_LINES lines of its payload
+#[derive(Default, Debug)]
+pub struct RawExcerpt {
+    pub text: String,
+    pub entries: usize,
+    // Line numbers in the log of its first and last envelope
+    pub lines: Option<(usize, usize)>,
+}
+
+impl RawExcerpt {
+    // An envelope at lineno, with the first lines of its payload of payload_lines in all.  What
+    // was cut is noted on a line of its own, which isn't indented like payload.
+    pub fn add(&mut self, lineno: usize, header: &str, payload: &[String], payload_lines: usize) {
+        self.text.push_str(header);
+        self.text.push('\n');
+        for line in payload {
+            self.text.push_str(line);
+            self.text.push('\n');
+        }
+        if payload_lines > payload.len() {
+            self.text.push_str(&format!(
+                "[{} more payload lines of the entry at line {lineno} of the log]\n",
+                payload_lines - payload.len()
+            ));
+        }
+        self.entries += 1;
+        self.lines = Some(
+            self.lines
+                .map_or((lineno, lineno), |(first, _)| (first, lineno)),
+        );
+    }
+}
+
+// A recompile reason, with the guard that failed if it could be found among the guards of
+// the compile it names (or the one before)
+#[derive(Debug, Clone, Serialize)]
+pub struct RecompileCause {
+    pub reason: String,
+    pub failed_guard: Option<FailedGuard>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedGuard {
+    pub guard: String,
+    // (compile id, anchor in the index) of the compile it guarded
+    pub compile: (String, String),
+    // The value it saw instead, if the reason says
+    pub actual: String,
+}
+
+impl CompileRecord {
+    pub fn span(&self) -> Option<&CompileSpan> {
+        (self.span.segments() > 0).then_some(&self.span)
+    }
+
+    // What's wrong with the compile, for its entry in the index, given how it went (see
+    // AttemptOutcome) and what artifacts it's missing (see ArtifactRule::fmt_missing)
+    pub fn badges(&self, outcome: Option<AttemptOutcome>, missing_artifacts: &str) -> Vec<Badge> {
+        let mut badges = Vec::new();
+        match outcome {
+            Some(AttemptOutcome::Failed) => badges.push(Badge::Failed),
+            Some(AttemptOutcome::Restarted) => badges.push(Badge::Restarted),
+            _ => {}
+        }
+        let has = |f: fn(&DataIssue) -> bool| self.issues.iter().any(f);
+        if has(|i| matches!(i, DataIssue::Md5Mismatch { .. })) {
+            badges.push(Badge::Md5Mismatch);
+        }
+        if has(|i| matches!(i, DataIssue::TruncatedPayload { .. })) {
+            badges.push(Badge::TruncatedPayload);
+        }
+        if has(|i| matches!(i, DataIssue::DuplicateArtifact { .. })) {
+            badges.push(Badge::DuplicateArtifact);
+        }
+        if has(|i| matches!(i, DataIssue::UnknownFields { .. })) {
+            badges.push(Badge::UnknownFields);
+        }
+        if !missing_artifacts.is_empty() {
+            badges.push(Badge::MissingArtifacts(missing_artifacts.to_string()));
+        }
+        badges
+    }
+}
+
+// Something wrong with a compile, next to it in the index, see CompileRecord::badges
+#[derive(Debug, Clone, PartialEq)]
+pub enum Badge {
+    Failed,
+    Restarted,
+    Md5Mismatch,
+    TruncatedPayload,
+    DuplicateArtifact,
+    UnknownFields,
+    // e.g. "no inductor_output_code recorded"
+    MissingArtifacts(String),
+}
+
+impl Badge {
+    pub fn label(&self) -> String {
+        match self {
+            Badge::Failed => "failed".to_string(),
+            Badge::Restarted => "restarted".to_string(),
+            Badge::Md5Mismatch => "MD5 mismatch".to_string(),
+            Badge::TruncatedPayload => "truncated payload".to_string(),
+            Badge::DuplicateArtifact => "duplicate artifact".to_string(),
+            Badge::UnknownFields => "unknown fields".to_string(),
+            Badge::MissingArtifacts(missing) => missing.clone(),
+        }
+    }
+
+    // Failures are what "failed" in the index's filter shows, everything else is a warning
+    pub fn is_failure(&self) -> bool {
+        *self == Badge::Failed
+    }
+
+    // After the colors of the index's legend where there's one for it
+    pub fn class(&self) -> &'static str {
+        match self {
+            Badge::Failed => "badge status-error",
+            Badge::Restarted => "badge status-break",
+            Badge::Md5Mismatch
+            | Badge::TruncatedPayload
+            | Badge::DuplicateArtifact
+            | Badge::UnknownFields => "badge badge-warning",
+            Badge::MissingArtifacts(_) => "badge status-missing",
+        }
+    }
+}
+
+// Something wrong with how an entry of a compile was logged, which makes its artifacts suspect:
+// in the banner of its compile.html, under its badges (see CompileRecord::badges) and in
+// compile_directory.json, with what it takes to report it as a logging bug
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum DataIssue {
+    // Its payload didn't hash to the MD5 in its header (or the header's wasn't hex)
+    Md5Mismatch {
+        line: usize,
+        kind: String,
+        expected: String,
+        actual: String,
+    },
+    // Its header declared a payload that never came
+    TruncatedPayload {
+        line: usize,
+        kind: String,
+        expected: String,
+    },
+    // It wrote a file that an earlier entry already had, so it went under another name
+    DuplicateArtifact {
+        line: usize,
+        wanted: String,
+        written: String,
+    },
+    // It had fields of no known kind
+    UnknownFields {
+        line: usize,
+        kind: String,
+        fields: Vec<String>,
+    },
+}
+
+impl DataIssue {
+    pub fn fmt(&self) -> String {
+        match self {
+            DataIssue::Md5Mismatch {
+                line,
+                kind,
+                expected,
+                actual,
+            } => format!(
+                "Line {line}: the payload of {kind} has MD5 {actual}, but its header says {expected}"
+            ),
+            DataIssue::TruncatedPayload {
+                line,
+                kind,
+                expected,
+            } => format!("Line {line}: {kind} declares a payload (MD5 {expected}), but none follows"),
+            DataIssue::DuplicateArtifact {
+                line,
+                wanted,
+                written,
+            } => format!("Line {line}: {wanted} was already written, so this one is {written}"),
+            DataIssue::UnknownFields { line, kind, fields } => {
+                format!("Line {line}: {kind} has unknown fields {}", fields.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheState {
+    Hit,
+    Miss,
+    Bypass,
+}
+
+// An FX graph cache lookup, from its fx_graph_cache_{hit,miss,bypass} artifact
+#[derive(Debug)]
+pub struct CacheEvent {
+    pub state: CacheState,
+    pub key: Option<String>,
+    // time_taken_ns of a miss, or time_saved_ns of a hit: what PyTorch measured the compile at
+    pub time_s: Option<f64>,
+}
+
+impl CacheEvent {
+    pub fn parse(e: &Envelope, payload: &str) -> Option<CacheEvent> {
+        let state = match e.artifact.as_ref()?.name.as_str() {
+            "fx_graph_cache_hit" => CacheState::Hit,
+            "fx_graph_cache_miss" => CacheState::Miss,
+            "fx_graph_cache_bypass" => CacheState::Bypass,
+            _ => return None,
+        };
+        // The payload is only detail, so a lookup still counts without it
+        let detail: Value = serde_json::from_str(payload).unwrap_or(Value::Null);
+        Some(CacheEvent {
+            state,
+            key: detail["key"].as_str().map(str::to_string),
+            time_s: detail["time_taken_ns"]
+                .as_u64()
+                .or(detail["time_saved_ns"].as_u64())
+                .map(|ns| ns as f64 / 1e9),
+        })
+    }
+}
+
+// Hits, misses and bypasses of FX graph cache lookups
+#[derive(Debug, Default, Serialize)]
+pub struct CacheCounts {
+    pub hits: usize,
+    pub misses: usize,
+    pub bypasses: usize,
+    // Of all lookups, bypasses included
+    pub hit_rate: String,
+}
+
+impl CacheCounts {
+    pub fn add(&mut self, state: CacheState) {
+        match state {
+            CacheState::Hit => self.hits += 1,
+            CacheState::Miss => self.misses += 1,
+            CacheState::Bypass => self.bypasses += 1,
+        }
+        let lookups = self.hits + self.misses + self.bypasses;
+        self.hit_rate = format!("{:.0}%", self.hits as f64 * 100.0 / lookups as f64);
+    }
+}
+
+// A compile that missed the cache, in cache.html
+#[derive(Debug, Serialize)]
+pub struct CacheMiss {
+    pub compile_id: String,
+    // Of the compile in the index
+    pub anchor: String,
+    pub compile_time: String,
+    // How many attempts its compile took in all
+    pub attempts: usize,
+    pub key: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub total: CacheCounts,
+    // (frame id, its lookups)
+    pub frames: Vec<(String, CacheCounts)>,
+    // Empty when no compile that missed has a time
+    pub median_miss: String,
+    pub estimated_saved: String,
+    // The sum of what hits logged as time saved, if any did
+    pub logged_saved: String,
+    // Most expensive first
+    pub expensive_misses: Vec<CacheMiss>,
+}
+
+// What has been written into an output directory, see DirSink::totals
+#[derive(Default, Debug, Clone, Copy, Serialize)]
+pub struct OutputTotals {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+impl Display for OutputTotals {
+    // e.g. "1.9 GB across 4,112 files"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.files.to_string();
+        let mut files = String::new();
+        for (i, d) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                files.push(',');
+            }
+            files.push(d);
+        }
+        let plural = if self.files == 1 { "" } else { "s" };
+        write!(f, "{} across {files} file{plural}", fmt_bytes(self.bytes))
+    }
+}
+
+// A byte count for people, e.g. "2.3 MB"
+pub fn fmt_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["kB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit + 1 < UNITS.len() {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+// What the index shows next to a compile id, e.g. "(4 files, 2.3 MB)"; links don't count
+pub fn fmt_directory_size(files: &[OutputFile]) -> String {
+    let sizes: Vec<u64> = files.iter().filter_map(|f| f.size).collect();
+    match sizes.len() {
+        0 => String::new(),
+        1 => format!("(1 file, {})", fmt_bytes(sizes[0])),
+        n => format!("({n} files, {})", fmt_bytes(sizes.iter().sum())),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompilationMetricsContext<'e> {
+    pub m: &'e CompilationMetricsMetadata,
+    pub css: &'static str,
+    pub compile_id: String,
+    pub stack_html: String,
+    pub symbolic_shape_specializations: Vec<SymbolicShapeSpecializationContext>,
+    pub output_files: &'e Vec<OutputFile>,
+    pub compile_id_dir: &'e PathBuf,
+    pub mini_stack_html: String,
+    // (phase, formatted time)
+    pub phases: Vec<(String, String)>,
+    pub dynamic_dims: Option<DynamicDims>,
+    pub qps: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub enum FailureReason {
+    Failure((String, String, String, u32)), // (failure type, failure reason, user frame filename, user frame lineno)
+    Restart(String),                        // restart reason
+}
+impl Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureReason::Failure((
+                failure_type,
+                failure_reason,
+                user_frame_filename,
+                user_frame_lineno,
+            )) => {
+                let failure_type = encode_text(failure_type);
+                let failure_reason = encode_text(failure_reason);
+                let user_frame_filename = encode_text(user_frame_filename);
+                write!(
+                    f,
+                    "<td><pre>{failure_type}</pre></td>
+                           <td><pre>{failure_reason}</pre></td>
+                           <td><pre>{user_frame_filename}:{user_frame_lineno}</pre></td>
+                          "
+                )
+            }
+            FailureReason::Restart(restart_reason) => write!(
+                f,
+                r#"<td> RestartAnalysis </td><td><pre>{restart_reason}</pre></td><td>Not availble for restarts(yet)!</td>"#
+            ),
+        }
+    }
+}
+
+// Failure reasons usually differ in ways that don't matter for what went wrong: the addresses
+// of objects, the shapes of tensors and other numbers.  With those masked, failures with the same
+// root cause share a reason, see FailureGroup.
+pub(crate) fn normalize_fail_reason(reason: &str) -> String {
+    thread_local! {
+        static RULES: Vec<(Regex, &'static str)> = vec![
+            (Regex::new(r"0x[0-9a-fA-F]+").unwrap(), "0x…"),
+            // torch.Size([2, 3]), [s0, 4], (2, 3): lists of sizes, concrete or symbolic
+            (
+                Regex::new(r"\[\s*(?:-?\d+|s\d+)(?:\s*,\s*(?:-?\d+|s\d+))*\s*,?\s*\]").unwrap(),
+                "[…]",
+            ),
+            (
+                Regex::new(r"\(\s*(?:-?\d+|s\d+)(?:\s*,\s*(?:-?\d+|s\d+))*\s*,?\s*\)").unwrap(),
+                "(…)",
+            ),
+            (Regex::new(r"\b\d+(?:\.\d+)?\b").unwrap(), "N"),
+        ];
+    }
+    RULES.with(|rules| {
+        rules
+            .iter()
+            .fold(reason.trim().to_string(), |reason, (re, mask)| {
+                re.replace_all(&reason, *mask).into_owned()
+            })
+    })
+}
+
+// Failures with the same fail_type and normalized reason, for the breakdown on
+// failures_and_restarts.html
+#[derive(Debug, Serialize)]
+pub struct FailureGroup {
+    pub fail_type: String,
+    pub reason: String,
+    pub count: usize,
+    // A link to the first compile that failed like this, and when it did
+    pub example: String,
+    pub first_seen: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestartsAndFailuresContext {
+    // Serialized versions of (CompileId, FailureReason)
+    pub failures: Vec<(String, String)>,
+    // Most common first
+    pub failure_groups: Vec<FailureGroup>,
+    pub css: &'static str,
+    pub qps: &'static str,
+}
+
+#[derive(Debug)]
+pub enum Metadata<'e> {
+    Empty(&'e EmptyMetadata),
+    Link(&'e LinkMetadata),
+    GraphDump(&'e GraphDumpMetadata),
+    DynamoOutputGraph(&'e DynamoOutputGraphMetadata),
+    #[allow(dead_code)]
+    DynamoStart(&'e DynamoStartMetadata),
+    InductorOutputCode(&'e InductorOutputCodeMetadata),
+    OptimizeDdpSplitChild(&'e OptimizeDdpSplitChildMetadata),
+    CompilationMetrics(&'e CompilationMetricsMetadata),
+    AOTAutogradBackwardCompilationMetrics(&'e AOTAutogradBackwardCompilationMetricsMetadata),
+    BwdCompilationMetrics(&'e BwdCompilationMetricsMetadata),
+    Artifact(&'e ArtifactMetadata),
+    DumpFile(&'e DumpFileMetadata),
+    // A field tlparse doesn't know, for custom parsers; see StructuredLogParser::handles_field
+    Other(&'e Value),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DumpFileMetadata {
+    pub name: String,
+}
+
+// An envelope with none of the compile id fields has no compile id; one with only some of
+// them gets a partial CompileId rather than failing to parse
+fn deserialize_compile_id<'de, D>(deserializer: D) -> Result<Option<CompileId>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let c = CompileId::deserialize(deserializer)?;
+    if c.frame_id.is_none() && c.frame_compile_id.is_none() {
+        Ok(None)
+    } else {
+        Ok(Some(c))
+    }
+}
+
+// The union fields of Envelope, one per kind of log entry, in the order Envelope::kinds
+// takes them
+pub static ENVELOPE_KINDS: [&str; 28] = [
+    "dynamo_start",
+    "dynamo_output_graph",
+    "optimize_ddp_split_graph",
+    "optimize_ddp_split_child",
+    "compiled_autograd_graph",
+    "dynamo_guards",
+    "aot_forward_graph",
+    "aot_backward_graph",
+    "aot_inference_graph",
+    "aot_joint_graph",
+    "inductor_pre_grad_graph",
+    "inductor_post_grad_graph",
+    "dynamo_cpp_guards_str",
+    "inductor_output_code",
+    "compilation_metrics",
+    "bwd_compilation_metrics",
+    "aot_autograd_backward_compilation_metrics",
+    "graph_dump",
+    "link",
+    "symbolic_shape_specialization",
+    "create_symbol",
+    "artifact",
+    "describe_storage",
+    "describe_tensor",
+    "describe_source",
+    "dump_file",
+    "chromium_event",
+    "str",
+];
+
+// A log entry as it is in the JSON after the glog prefix, which is what tlparse reads and what
+// (with Serialize) it writes back out.  Every field is optional.  The compile id is the
+// flattened frame_id, frame_compile_id and attempt fields: compile_id is None when neither of
+// the first two is there, and attempt defaults to 0 (see CompileId).  The rest are the kinds of
+// entry, one field each (see ENVELOPE_KINDS), left out when None.  Fields tlparse doesn't know
+// are kept in _other and written back as they were; inside a kind's metadata, fields it doesn't
+// read are dropped.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Envelope {
+    // From the glog prefix rather than the JSON, see GlogTimestamps
+    #[serde(skip)]
+    pub timestamp: Option<chrono::NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<u32>,
+    #[serde(flatten, deserialize_with = "deserialize_compile_id")]
+    pub compile_id: Option<CompileId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_payload: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<StackSummary>,
+    // externally tagged union, one field per log type we recognize
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamo_start: Option<DynamoStartMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub str: Option<(String, u32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamo_output_graph: Option<DynamoOutputGraphMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optimize_ddp_split_graph: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optimize_ddp_split_child: Option<OptimizeDdpSplitChildMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compiled_autograd_graph: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamo_guards: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aot_forward_graph: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aot_backward_graph: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aot_inference_graph: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aot_joint_graph: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inductor_pre_grad_graph: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inductor_post_grad_graph: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamo_cpp_guards_str: Option<EmptyMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inductor_output_code: Option<InductorOutputCodeMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compilation_metrics: Option<CompilationMetricsMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bwd_compilation_metrics: Option<BwdCompilationMetricsMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aot_autograd_backward_compilation_metrics:
+        Option<AOTAutogradBackwardCompilationMetricsMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub graph_dump: Option<GraphDumpMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<LinkMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbolic_shape_specialization: Option<SymbolicShapeSpecializationMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create_symbol: Option<CreateSymbolMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<ArtifactMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub describe_storage: Option<StorageDesc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub describe_tensor: Option<TensorDesc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub describe_source: Option<SourceDesc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dump_file: Option<DumpFileMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chromium_event: Option<EmptyMetadata>,
+    #[serde(flatten)]
+    pub _other: FxHashMap<String, Value>,
+}
+
+impl Envelope {
+    // The union fields set on this envelope, in precedence order: if a buggy writer sets
+    // more than one, the first is the one that gets the payload.  An intern entry never
+    // carries a payload, so it loses to everything else.
+    pub fn kinds(&self) -> Vec<&'static str> {
+        // In the order of ENVELOPE_KINDS
+        let set = [
+            self.dynamo_start.is_some(),
+            self.dynamo_output_graph.is_some(),
+            self.optimize_ddp_split_graph.is_some(),
+            self.optimize_ddp_split_child.is_some(),
+            self.compiled_autograd_graph.is_some(),
+            self.dynamo_guards.is_some(),
+            self.aot_forward_graph.is_some(),
+            self.aot_backward_graph.is_some(),
+            self.aot_inference_graph.is_some(),
+            self.aot_joint_graph.is_some(),
+            self.inductor_pre_grad_graph.is_some(),
+            self.inductor_post_grad_graph.is_some(),
+            self.dynamo_cpp_guards_str.is_some(),
+            self.inductor_output_code.is_some(),
+            self.compilation_metrics.is_some(),
+            self.bwd_compilation_metrics.is_some(),
+            self.aot_autograd_backward_compilation_metrics.is_some(),
+            self.graph_dump.is_some(),
+            self.link.is_some(),
+            self.symbolic_shape_specialization.is_some(),
+            self.create_symbol.is_some(),
+            self.artifact.is_some(),
+            self.describe_storage.is_some(),
+            self.describe_tensor.is_some(),
+            self.describe_source.is_some(),
+            self.dump_file.is_some(),
+            self.chromium_event.is_some(),
+            self.str.is_some(),
+        ];
+        ENVELOPE_KINDS
+            .into_iter()
+            .zip(set)
+            .filter_map(|(name, set)| set.then_some(name))
+            .collect()
+    }
+
+    // Clear every union field except `kind`
+    pub fn retain_kind(&mut self, kind: &str) {
+        if kind != "dynamo_start" {
+            self.dynamo_start = None;
+        }
+        if kind != "dynamo_output_graph" {
+            self.dynamo_output_graph = None;
+        }
+        if kind != "optimize_ddp_split_graph" {
+            self.optimize_ddp_split_graph = None;
+        }
+        if kind != "optimize_ddp_split_child" {
+            self.optimize_ddp_split_child = None;
+        }
+        if kind != "compiled_autograd_graph" {
+            self.compiled_autograd_graph = None;
+        }
+        if kind != "dynamo_guards" {
+            self.dynamo_guards = None;
+        }
+        if kind != "aot_forward_graph" {
+            self.aot_forward_graph = None;
+        }
+        if kind != "aot_backward_graph" {
+            self.aot_backward_graph = None;
+        }
+        if kind != "aot_inference_graph" {
+            self.aot_inference_graph = None;
+        }
+        if kind != "aot_joint_graph" {
+            self.aot_joint_graph = None;
+        }
+        if kind != "inductor_pre_grad_graph" {
+            self.inductor_pre_grad_graph = None;
+        }
+        if kind != "inductor_post_grad_graph" {
+            self.inductor_post_grad_graph = None;
+        }
+        if kind != "dynamo_cpp_guards_str" {
+            self.dynamo_cpp_guards_str = None;
+        }
+        if kind != "inductor_output_code" {
+            self.inductor_output_code = None;
+        }
+        if kind != "compilation_metrics" {
+            self.compilation_metrics = None;
+        }
+        if kind != "bwd_compilation_metrics" {
+            self.bwd_compilation_metrics = None;
+        }
+        if kind != "aot_autograd_backward_compilation_metrics" {
+            self.aot_autograd_backward_compilation_metrics = None;
+        }
+        if kind != "graph_dump" {
+            self.graph_dump = None;
+        }
+        if kind != "link" {
+            self.link = None;
+        }
+        if kind != "symbolic_shape_specialization" {
+            self.symbolic_shape_specialization = None;
+        }
+        if kind != "create_symbol" {
+            self.create_symbol = None;
+        }
+        if kind != "artifact" {
+            self.artifact = None;
+        }
+        if kind != "describe_storage" {
+            self.describe_storage = None;
+        }
+        if kind != "describe_tensor" {
+            self.describe_tensor = None;
+        }
+        if kind != "describe_source" {
+            self.describe_source = None;
+        }
+        if kind != "dump_file" {
+            self.dump_file = None;
+        }
+        if kind != "chromium_event" {
+            self.chromium_event = None;
+        }
+        if kind != "str" {
+            self.str = None;
+        }
+    }
+}
+
+type MetaTensorId = u64;
+type MetaStorageId = u64;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TensorDesc {
+    id: MetaTensorId,
+    describer_id: u64,
+    ndim: u64,
+    dtype: String,
+    device: String,
+    size: Vec<SymInt>,
+    dynamo_dynamic_indices: Option<Vec<u64>>,
+    // TODO: Make layout an enum
+    #[serde(default = "default_layout")]
+    layout: String,
+    #[serde(default)]
+    is_inference: bool,
+    #[serde(default)]
+    is_leaf: bool,
+    #[serde(default)]
+    requires_grad: bool,
+    #[serde(default)]
+    is_sparse: bool,
+    #[serde(default)]
+    is_mkldnn: bool,
+    #[serde(default)]
+    is_functorch_wrapped: bool,
+    #[serde(default)]
+    is_batchedtensor: bool,
+    #[serde(default)]
+    is_legacy_batchedtensor: bool,
+    #[serde(default)]
+    is_gradtrackingtensor: bool,
+    #[serde(default)]
+    is_view: bool,
+    #[serde(default)]
+    is_nested: bool,
+    #[serde(default)]
+    is_traceable_wrapper_subclass: bool,
+    #[serde(default)]
+    is_functional: bool,
+    #[serde(default)]
+    is_conj: bool,
+    #[serde(default)]
+    is_neg: bool,
+    #[serde(default)]
+    is_parameter: bool,
+    stride: Option<Vec<SymInt>>,
+    #[serde(default)]
+    storage_offset: SymInt,
+    storage: Option<MetaStorageId>,
+    sparse_dim: Option<u64>,
+    dense_dim: Option<u64>,
+    is_coalesced: Option<bool>,
+    crow_indices: Option<MetaTensorId>,
+    col_indices: Option<MetaTensorId>,
+    ccol_indices: Option<MetaTensorId>,
+    row_indices: Option<MetaTensorId>,
+    values: Option<MetaTensorId>,
+    unwrapped: Option<MetaTensorId>,
+    bdim: Option<u64>,
+    base: Option<MetaTensorId>,
+    attrs: Option<FxHashMap<String, MetaTensorId>>,
+    creation_meta: Option<String>,
+    grad: Option<MetaTensorId>,
+    #[serde(flatten)]
+    pub _other: FxHashMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StorageDesc {
+    id: MetaStorageId,
+    describer_id: u64,
+    size: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SourceDesc {
+    describer_id: u64,
+    id: MetaTensorId,
+    source: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DynamoGuard {
+    pub code: String,
+    pub stack: Option<StackSummary>,
+    pub user_stack: Option<StackSummary>,
+}
+
+// A guard in the table of dynamo_guards.html
+#[derive(Debug, Serialize)]
+pub struct GuardRow {
+    // In the order they were logged, from 1
+    pub number: usize,
+    // See guard_kind
+    pub kind: &'static str,
+    pub code: String,
+    // The start of code, if it's too long to show whole; empty otherwise
+    pub preview: String,
+    // Its innermost user frame, or innermost frame, as file:line in function
+    pub source: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DynamoGuardsContext {
+    pub guards: Vec<GuardRow>,
+    pub num_guards: usize,
+    // (kind, how many) for the filter, most common first
+    pub kinds: Vec<(&'static str, usize)>,
+    pub guards_table: &'static str,
+    pub qps: &'static str,
+}
+
+// A compile in the index
+#[derive(Debug, Serialize)]
+pub struct DirectoryEntry {
+    pub compile_id: String,
+    pub anchor: String,
+    // Its compile.html, if it has artifacts
+    pub page: String,
+    // Only entries without a compile id, which have no compile.html, list their files here
+    pub files: Vec<OutputFile>,
+    // How many files and how big, see fmt_directory_size
+    pub size: String,
+    // If it has one, see CompileSpan::fmt
+    pub span: String,
+    // Of all its attempts, e.g. "0 restarted → 1 ok"
+    pub outcomes: String,
+    // What the index's search box matches, lowercased: the compile id, its innermost user
+    // frame, the names of its artifacts and why it failed
+    pub search: String,
+    // (label, class) of its badges, see CompileRecord::badges
+    pub badges: Vec<(String, &'static str)>,
+    // What the index's filter goes by: "failure", "warning" or nothing
+    pub severity: &'static str,
+}
+
+// A frame in the index, with its compiles by frame_compile_id; see frame_groups
+#[derive(Debug, Serialize)]
+pub struct FrameGroup {
+    // "?" for compile ids without one
+    pub frame_id: String,
+    pub anchor: String,
+    // Its innermost user frame, see user_frame_label
+    pub label: String,
+    pub num_compiles: usize,
+    pub num_attempts: usize,
+    pub num_failed: usize,
+    pub compiles: Vec<FrameCompileGroup>,
+}
+
+impl FrameGroup {
+    pub fn new(frame_id: Option<u32>, label: String, compiles: Vec<FrameCompileGroup>) -> Self {
+        // Anchored like the directories of its compiles, see CompileId::as_directory_name
+        let anchor = format!(
+            "frame-{}",
+            frame_id.map_or("-".to_string(), |f| f.to_string())
+        );
+        FrameGroup {
+            anchor,
+            frame_id: frame_id.map_or("?".to_string(), |f| f.to_string()),
+            label,
+            num_compiles: compiles.len(),
+            num_attempts: compiles.iter().map(|c| c.num_attempts).sum(),
+            num_failed: compiles.iter().map(|c| c.num_failed).sum(),
+            compiles,
+        }
+    }
+}
+
+// The index's list of compiles, or a page of it
+#[derive(Debug, Serialize)]
+pub struct CompileList {
+    pub frames: Vec<FrameGroup>,
+    // The entry for everything logged without a compile id
+    pub unknown_compile: Option<DirectoryEntry>,
+}
+
+// A page of the index's list of compiles, for logs with too many of them, see shard_frames
+#[derive(Debug, Serialize)]
+pub struct IndexShard {
+    pub url: String,
+    // Its first and last frame, e.g. "frames 0 to 41"
+    pub label: String,
+    // Space separated, as the anchors of their compiles start, for
+    // TEMPLATE_INDEX_SHARD_REDIRECT
+    pub frames: String,
+    pub num_frames: usize,
+    pub num_compiles: usize,
+    pub num_attempts: usize,
+    pub num_failed: usize,
+}
+
+impl IndexShard {
+    pub fn new(url: String, frames: &[FrameGroup]) -> Self {
+        let label = match frames {
+            [frame] => format!("frame {}", frame.frame_id),
+            [first, .., last] => format!("frames {} to {}", first.frame_id, last.frame_id),
+            [] => String::new(),
+        };
+        IndexShard {
+            url,
+            label,
+            frames: frames
+                .iter()
+                .map(|f| f.anchor.trim_start_matches("frame-"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            num_frames: frames.len(),
+            num_compiles: frames.iter().map(|f| f.num_compiles).sum(),
+            num_attempts: frames.iter().map(|f| f.num_attempts).sum(),
+            num_failed: frames.iter().map(|f| f.num_failed).sum(),
+        }
+    }
+}
+
+// index_NNN.html
+#[derive(Debug, Serialize)]
+pub struct IndexShardContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub label: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    pub compile_search: &'static str,
+    pub compiles: CompileList,
+}
+
+// A compile of a frame in the index, e.g. [7/1], with its attempts
+#[derive(Debug, Serialize)]
+pub struct FrameCompileGroup {
+    pub label: String,
+    // Its innermost user frame, see user_frame_label
+    pub frame: String,
+    pub num_attempts: usize,
+    pub num_failed: usize,
+    pub attempts: Vec<DirectoryEntry>,
+}
+
+impl FrameCompileGroup {
+    pub fn new(label: String, frame: String, attempts: Vec<DirectoryEntry>) -> Self {
+        FrameCompileGroup {
+            label,
+            frame,
+            num_attempts: attempts.len(),
+            num_failed: attempts.iter().filter(|a| a.severity == "failure").count(),
+            attempts,
+        }
+    }
+}
+
+// Groups of artifacts on compile.html, by the start of their file name; anything else is "Other"
+static ARTIFACT_CATEGORIES: [(&str, &[&str]); 4] = [
+    (
+        "Dynamo",
+        &["dynamo_", "optimize_ddp_", "compiled_autograd_graph"],
+    ),
+    ("AOT autograd", &["aot_"]),
+    (
+        "Inductor",
+        &["inductor_", "fx_graph_", "triton_", "output_code"],
+    ),
+    (
+        "Metrics",
+        &["compilation_metrics", "bwd_compilation_metrics"],
+    ),
+];
+
+// An artifact on compile.html
+#[derive(Debug, Serialize)]
+pub struct CompileArtifact {
+    // Relative to compile.html
+    pub url: String,
+    pub name: String,
+    pub number: i32,
+    pub suffix: String,
+    pub size: Option<u64>,
+    pub highlighted: String,
+    pub preview: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArtifactGroup {
+    pub category: &'static str,
+    pub artifacts: Vec<CompileArtifact>,
+}
+
+// Group a compile's files for its compile.html, in the order of ARTIFACT_CATEGORIES and each
+// group in the order they were written
+pub fn artifact_groups(files: &[OutputFile]) -> Vec<ArtifactGroup> {
+    let file_name = |file: &OutputFile| {
+        file.name
+            .rsplit('/')
+            .next()
+            .unwrap_or(&file.name)
+            .to_string()
+    };
+    let category = |file: &OutputFile| {
+        let name = file_name(file);
+        ARTIFACT_CATEGORIES
+            .iter()
+            .find(|(_, prefixes)| prefixes.iter().any(|p| name.starts_with(p)))
+            .map_or("Other", |(category, _)| category)
+    };
+    ARTIFACT_CATEGORIES
+        .iter()
+        .map(|(category, _)| *category)
+        .chain(["Other"])
+        .map(|c| ArtifactGroup {
+            category: c,
+            artifacts: files
+                .iter()
+                .filter(|f| category(f) == c)
+                .map(|f| CompileArtifact {
+                    // Files are named from the top of the output, compile.html is a level down
+                    url: match f.size {
+                        Some(_) => format!("../{}", f.url),
+                        None => f.url.clone(),
+                    },
+                    name: file_name(f),
+                    number: f.number,
+                    suffix: f.suffix.clone(),
+                    size: f.size,
+                    highlighted: f
+                        .highlighted
+                        .as_ref()
+                        .map_or(String::new(), |h| format!("../{h}")),
+                    preview: f.preview.clone(),
+                })
+                .collect(),
+        })
+        .filter(|g| !g.artifacts.is_empty())
+        .collect()
+}
+
+// The bar at the top of every page, see add_breadcrumbs
+#[derive(Debug, Serialize)]
+pub struct BreadcrumbsContext {
+    pub title: String,
+    // Relative to the page, like the links below
+    pub index: String,
+    // (compile id, link) of the compile the page belongs to, and of the ones before and after it
+    // in the log
+    pub compile: Option<(String, String)>,
+    pub prev: Option<(String, String)>,
+    pub next: Option<(String, String)>,
+    // The dark palette and its switch, see dark_mode_html
+    pub dark_mode: &'static str,
+}
+
+// compile.html: everything the report knows about one compile
+#[derive(Debug, Serialize)]
+pub struct CompileContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub compile_id: String,
+    // In the index
+    pub anchor: String,
+    // See AttemptOutcome
+    pub status: &'static str,
+    pub status_class: &'static str,
+    // Of its first and last log entries, empty without timestamps
+    pub start: String,
+    pub end: String,
+    // See CompileSpan::fmt
+    pub span: String,
+    // See measured_compile_time, e.g. "2.0 s"
+    pub compile_time: String,
+    pub compile_time_from_span: bool,
+    // Its innermost user frame
+    pub frame: String,
+    pub guard_count: String,
+    pub node_count: String,
+    pub restart_reasons: Vec<String>,
+    pub recompile_causes: Vec<RecompileCause>,
+    pub missing: String,
+    // See DataIssue::fmt
+    pub issues: Vec<String>,
+    // (compile id, diff_<a>_vs_<b>.html) of the attempt before
+    pub diff: Option<(String, String)>,
+    // (its raw.log, which lines of the log it has) with --keep-raw
+    pub raw_log: Option<(String, String)>,
+    pub groups: Vec<ArtifactGroup>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexContext {
+    pub css: &'static str,
+    // Empty but for the unknown compile if the list is split into index_shards
+    pub compiles: CompileList,
+    pub index_shards: Vec<IndexShard>,
+    pub index_shard_redirect: &'static str,
+    pub num_missing_artifacts: usize,
+    pub stack_trie_html: String,
+    // Empty if the log has no ranks
+    pub stack_trie_rank: String,
+    // (rank, stack trie html) for the ranks other than the rendered one
+    pub other_rank_stack_tries: Vec<(u32, String)>,
+    pub unknown_stack_trie_html: String,
+    pub has_unknown_stack_trie: bool,
+    pub has_condensed_stack_trie: bool,
+    pub has_compiles_by_file: bool,
+    pub num_breaks: usize,
+    pub custom_header_html: String,
+    pub has_chromium_events: bool,
+    pub intern_conflicts: Vec<(u32, String, String)>,
+    pub multiple_runs: bool,
+    // That compiled anything, if more than one; see RanksContext
+    pub num_ranks: usize,
+    pub num_divergent_rank_compiles: usize,
+    pub trie_failed_only: bool,
+    pub trie_filter: String,
+    pub internal_frames_hidden: bool,
+    pub run: usize,
+    pub compile_time_summary: Option<CompileTimeSummary>,
+    pub phase_breakdown: Option<PhaseBreakdown>,
+    pub wall_clock: Option<String>,
+    // Of Timeline, empty if there were no graph breaks or recompiles
+    pub timeline_sparkline: String,
+    pub timeline_late_events: u64,
+    pub recompile_loops: Vec<RecompileLoop>,
+    // e.g. "10.0 min", the window recompile_loops looked at
+    pub recompile_loop_window: String,
+    pub dynamic_shapes: Option<DynamicShapesSummary>,
+    pub envelope_kinds: EnvelopeKinds,
+    pub has_recompiles: bool,
+    pub has_guard_churn: bool,
+    // Guards that were added and removed again across the compiles of some frame
+    pub num_churning_guards: usize,
+    pub has_largest_artifacts: bool,
+    pub has_attempts: bool,
+    // Empty if there were no cache lookups
+    pub cache_hit_rate: String,
+    pub has_ops_histogram: bool,
+    pub has_source_files: bool,
+    pub has_top_compiles: bool,
+    pub num_eager_fallbacks: usize,
+    pub num_duplicate_graphs: usize,
+    pub frames_at_recompile_limit: usize,
+    pub cache_size_limit: usize,
+    pub trie_search: &'static str,
+    pub compile_search: &'static str,
+    pub qps: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CondensedStackTrieContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub stack_trie_html: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ByFileEntry {
+    pub filename: String,
+    pub num_compiles: usize,
+    // (compile id, anchor in the index)
+    pub compiles: Vec<(String, String)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ByFileContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub files: Vec<ByFileEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecompileEntry {
+    // Empty for compiles without a frame id
+    pub frame_id: String,
+    // The innermost frame in user code of its stack, or just the innermost frame
+    pub label: String,
+    pub num_compiles: usize,
+    pub num_attempts: usize,
+    pub at_limit: bool,
+    // (compile id, anchor in the index)
+    pub compiles: Vec<(String, String)>,
+    // (compile id, anchor in the index, why it was compiled) for the compiles that logged why
+    pub causes: Vec<(String, String, RecompileCause)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecompilesContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub cache_size_limit: usize,
+    pub frames: Vec<RecompileEntry>,
+}
+
+// How one attempt at a compile went, see AttemptOutcome::of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttemptOutcome {
+    Ok,
+    Empty,
+    Restarted,
+    Failed,
+    Missing,
+}
+
+impl AttemptOutcome {
+    // The signals can disagree, so they're taken in order: a failure anywhere wins (metrics
+    // that look fine don't make up for a backward compile that failed), then being superseded
+    // by a later attempt, and only then what the attempt's own metrics say
+    pub fn of(
+        metrics: &[CompilationMetricsMetadata],
+        failed_elsewhere: bool,
+        superseded: bool,
+    ) -> AttemptOutcome {
+        if failed_elsewhere || metrics.iter().any(|m| m.fail_type.is_some()) {
+            AttemptOutcome::Failed
+        } else if superseded {
+            AttemptOutcome::Restarted
+        } else if metrics.is_empty() {
+            AttemptOutcome::Missing
+        } else if metrics.iter().any(|m| m.graph_op_count.unwrap_or(0) == 0) {
+            AttemptOutcome::Empty
+        } else {
+            AttemptOutcome::Ok
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AttemptOutcome::Ok => "ok",
+            AttemptOutcome::Empty => "empty",
+            AttemptOutcome::Restarted => "restarted",
+            AttemptOutcome::Failed => "failed",
+            AttemptOutcome::Missing => "no metrics",
+        }
+    }
+
+    // After the colors of the index's legend
+    pub fn class(self) -> &'static str {
+        match self {
+            AttemptOutcome::Ok => "status-ok",
+            AttemptOutcome::Empty => "status-empty",
+            AttemptOutcome::Restarted => "status-break",
+            AttemptOutcome::Failed => "status-error",
+            AttemptOutcome::Missing => "status-missing",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttemptStep {
+    #[serde(skip)]
+    pub kind: AttemptOutcome,
+    pub attempt: u32,
+    // Of the attempt in the index
+    pub anchor: String,
+    pub outcome: &'static str,
+    pub class: &'static str,
+    // (compile id, diff_<a>_vs_<b>.html) of the attempt before, if they had artifacts to diff
+    pub diff: Option<(String, String)>,
+}
+
+// Every attempt at one (frame_id, frame_compile_id), in attempts.html
+#[derive(Debug, Serialize)]
+pub struct AttemptsEntry {
+    pub compile_id: String,
+    pub steps: Vec<AttemptStep>,
+    // e.g. "0 restarted → 1 restarted → 2 ok"
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttemptsContext<'a> {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub compiles: &'a [AttemptsEntry],
+}
+
+// Artifacts bigger than this many bytes aren't diffed between attempts, see ArtifactDiff
+pub static DIFF_MAX_BYTES: usize = 1024 * 1024;
+// Nor are ones that still differ in more than this many lines (of one times the other) once
+// what they start and end with in common is taken off
+pub static DIFF_MAX_CELLS: usize = 4_000_000;
+// Unchanged lines shown around each change
+static DIFF_CONTEXT_LINES: usize = 3;
+
+// A line of a diff between attempts
+#[derive(Debug, Serialize)]
+pub struct DiffLine {
+    // diff-same, diff-removed, diff-added, or diff-skip for a run of unchanged lines left out
+    pub class: &'static str,
+    // Line numbers in the earlier and later attempt's artifact, empty if it isn't in it
+    pub old: String,
+    pub new: String,
+    pub text: String,
+}
+
+impl DiffLine {
+    fn new(class: &'static str, old: Option<usize>, new: Option<usize>, text: &str) -> Self {
+        DiffLine {
+            class,
+            old: old.map_or(String::new(), |n| (n + 1).to_string()),
+            new: new.map_or(String::new(), |n| (n + 1).to_string()),
+            text: text.to_string(),
+        }
+    }
+}
+
+// A line-level diff of a and b, by their longest common subsequence, with the unchanged lines
+// away from any change left out.  None if it would take more than DIFF_MAX_CELLS.
+pub fn diff_lines(a: &str, b: &str) -> Option<Vec<DiffLine>> {
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (ma, mb) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    if ma.len().saturating_mul(mb.len()) > DIFF_MAX_CELLS {
+        return None;
+    }
+    // lcs[i][j]: the longest common subsequence of ma[i..] and mb[j..]
+    let width = mb.len() + 1;
+    let mut lcs = vec![0u32; (ma.len() + 1) * width];
+    for i in (0..ma.len()).rev() {
+        for j in (0..mb.len()).rev() {
+            lcs[i * width + j] = if ma[i] == mb[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+    // (old line, new line) of every line of the diff, None on the side it isn't on
+    let mut ops: Vec<(Option<usize>, Option<usize>)> =
+        (0..prefix).map(|i| (Some(i), Some(i))).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < ma.len() || j < mb.len() {
+        if i < ma.len() && j < mb.len() && ma[i] == mb[j] {
+            ops.push((Some(prefix + i), Some(prefix + j)));
+            i += 1;
+            j += 1;
+        } else if j == mb.len()
+            || (i < ma.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            ops.push((Some(prefix + i), None));
+            i += 1;
+        } else {
+            ops.push((None, Some(prefix + j)));
+            j += 1;
+        }
+    }
+    ops.extend((0..suffix).map(|k| (Some(a.len() - suffix + k), Some(b.len() - suffix + k))));
+    // Keep the unchanged lines within DIFF_CONTEXT_LINES of a change
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (old, new))| old.is_none() || new.is_none())
+        .map(|(k, _)| k)
+        .collect();
+    let near_change = |k: usize| {
+        let at = changed.partition_point(|&c| c + DIFF_CONTEXT_LINES < k);
+        changed
+            .get(at)
+            .is_some_and(|&c| c <= k + DIFF_CONTEXT_LINES)
+    };
+    let mut lines = Vec::new();
+    let mut skipped = 0;
+    for (k, op) in ops.into_iter().enumerate() {
+        if let (Some(_), Some(_)) = op {
+            if !near_change(k) {
+                skipped += 1;
+                continue;
+            }
+        }
+        if skipped > 0 {
+            lines.push(DiffLine::new(
+                "diff-skip",
+                None,
+                None,
+                &format!("⋯ {skipped} unchanged line(s)"),
+            ));
+            skipped = 0;
+        }
+        lines.push(match op {
+            (Some(old), Some(new)) => DiffLine::new("diff-same", Some(old), Some(new), a[old]),
+            (Some(old), None) => DiffLine::new("diff-removed", Some(old), None, a[old]),
+            (None, Some(new)) => DiffLine::new("diff-added", None, Some(new), b[new]),
+            (None, None) => unreachable!(),
+        });
+    }
+    if skipped > 0 {
+        lines.push(DiffLine::new(
+            "diff-skip",
+            None,
+            None,
+            &format!("⋯ {skipped} unchanged line(s)"),
+        ));
+    }
+    Some(lines)
+}
+
+// An artifact of two compiles (usually consecutive attempts of one), as they differ
+#[derive(Debug, Serialize)]
+pub struct ArtifactDiff {
+    // Its file name without the number, e.g. dynamo_output_graph.txt, or "guards"
+    pub name: String,
+    // "changed", "unchanged", "only in [0/0]", or why it wasn't diffed
+    pub status: String,
+    pub changed: bool,
+    pub added: usize,
+    pub removed: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+impl ArtifactDiff {
+    // a and b are the artifact in each compile, with the compile id; None if the compile
+    // doesn't have it, or Err(size) if it couldn't keep it to diff
+    pub fn new(
+        name: String,
+        a: (&str, Option<Result<&str, u64>>),
+        b: (&str, Option<Result<&str, u64>>),
+    ) -> Self {
+        let whole = |class, text: &str| -> Vec<DiffLine> {
+            text.lines()
+                .enumerate()
+                .map(|(n, line)| match class {
+                    "diff-removed" => DiffLine::new(class, Some(n), None, line),
+                    _ => DiffLine::new(class, None, Some(n), line),
+                })
+                .collect()
+        };
+        let (status, lines) = match (a.1, b.1) {
+            (Some(Ok(x)), Some(Ok(y))) if x.len() > DIFF_MAX_BYTES || y.len() > DIFF_MAX_BYTES => (
+                format!(
+                    "too large to diff ({} and {})",
+                    fmt_bytes(x.len() as u64),
+                    fmt_bytes(y.len() as u64)
+                ),
+                Vec::new(),
+            ),
+            (Some(Ok(x)), Some(Ok(y))) if x == y => ("unchanged".to_string(), Vec::new()),
+            (Some(Ok(x)), Some(Ok(y))) => match diff_lines(x, y) {
+                Some(lines) => ("changed".to_string(), lines),
+                None => (
+                    "changed, too much to diff line by line".to_string(),
+                    Vec::new(),
+                ),
+            },
+            (Some(Err(x)), _) | (_, Some(Err(x))) => (
+                format!("too large to diff ({} was streamed to disk)", fmt_bytes(x)),
+                Vec::new(),
+            ),
+            (Some(Ok(x)), None) => (format!("only in {}", a.0), whole("diff-removed", x)),
+            (None, Some(Ok(y))) => (format!("only in {}", b.0), whole("diff-added", y)),
+            (None, None) => ("in neither".to_string(), Vec::new()),
+        };
+        ArtifactDiff {
+            name,
+            changed: status != "unchanged",
+            added: lines.iter().filter(|l| l.class == "diff-added").count(),
+            removed: lines.iter().filter(|l| l.class == "diff-removed").count(),
+            status,
+            lines,
+        }
+    }
+}
+
+// diff_<a>_vs_<b>.html: what changed between two consecutive attempts of a compile, or between
+// any two compiles for `tlparse diff-compile`
+#[derive(Debug, Serialize)]
+pub struct DiffContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    // (compile id, anchor in the index) of each compile
+    pub a: (String, String),
+    pub b: (String, String),
+    // Whether graphs and guards went through normalize_fx_graph and normalize_guard first
+    pub normalized: bool,
+    pub artifacts: Vec<ArtifactDiff>,
+}
+
+impl DiffContext {
+    // The same diff as plain text, in the style of diff -u
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for d in &self.artifacts {
+            if d.lines.is_empty() {
+                text.push_str(&format!("{}: {}\n", d.name, d.status));
+                continue;
+            }
+            text.push_str(&format!(
+                "--- {} {}\n+++ {} {}\n",
+                self.a.0, d.name, self.b.0, d.name
+            ));
+            for l in &d.lines {
+                let sign = match l.class {
+                    "diff-removed" => "-",
+                    "diff-added" => "+",
+                    "diff-skip" => {
+                        text.push_str(&format!("@@ {} @@\n", l.text));
+                        continue;
+                    }
+                    _ => " ",
+                };
+                text.push_str(&format!("{sign}{}\n", l.text));
+            }
+        }
+        text
+    }
 }
 
+// What `tlparse diff-compile` made of two compiles of an output directory: diff_<a>_vs_<b>.html
+// to write into it, and the diff as text
+pub struct CompileDiff {
+    pub filename: PathBuf,
+    pub html: String,
+    pub text: String,
+}
+
+// A compile of compile_directory.json as read back, for tlparse diff and diff-compile
 #[derive(Debug, Deserialize)]
-pub struct ArtifactMetadata {
+pub struct ListedCompile {
+    pub compile_id: Option<CompileId>,
+    // Output of older versions doesn't have these
+    #[serde(default)]
+    pub frame: Option<String>,
+    #[serde(default)]
+    pub compile_time_s: Option<f64>,
+    #[serde(default)]
+    pub span_s: Option<f64>,
+    pub files: Vec<OutputFile>,
+}
+
+impl ListedCompile {
+    // By its compilation metrics if it has them, otherwise by its span
+    pub fn time_s(&self) -> Option<f64> {
+        self.compile_time_s.or(self.span_s)
+    }
+}
+
+// Seconds in tlparse diff's report are rounded to milliseconds, which is plenty for compile times
+// and keeps diff.json readable
+fn round3(x: f64) -> f64 {
+    (x * 1000.0).round() / 1000.0
+}
+
+// One of the two runs of tlparse diff
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    // As given on the command line
     pub name: String,
-    pub encoding: String,
+    pub num_compiles: usize,
+    pub num_frames: usize,
+    pub compile_time_s: f64,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct CompilationMetricsMetadata {
-    // Other information like frame_key are already in envelope
-    pub co_name: Option<String>,
-    pub co_filename: Option<String>,
-    pub co_firstlineno: Option<i32>,
-    pub cache_size: Option<u64>,
-    pub accumulated_cache_size: Option<u64>,
-    pub guard_count: Option<u64>,
-    pub shape_env_guard_count: Option<u64>,
-    pub graph_op_count: Option<u64>,
-    pub graph_node_count: Option<u64>,
-    pub graph_input_count: Option<u64>,
-    pub start_time: Option<f64>,
-    pub entire_frame_compile_time_s: Option<f64>,
-    pub backend_compile_time_s: Option<f64>,
-    pub inductor_compile_time_s: Option<f64>,
-    pub code_gen_time_s: Option<f64>,
-    pub fail_type: Option<String>,
-    pub fail_reason: Option<String>,
-    pub fail_user_frame_filename: Option<String>,
-    pub fail_user_frame_lineno: Option<u32>,
-    pub non_compliant_ops: Option<Vec<String>>,
-    pub compliant_custom_ops: Option<Vec<String>>,
-    pub restart_reasons: Option<Vec<String>>,
-    pub dynamo_time_before_restart_s: Option<f64>,
+impl RunSummary {
+    pub fn new(name: String, compiles: &[ListedCompile]) -> Self {
+        let frames: FxHashSet<Option<&String>> =
+            compiles.iter().map(|c| c.frame.as_ref()).collect();
+        RunSummary {
+            name,
+            num_compiles: compiles.len(),
+            num_frames: frames.len(),
+            compile_time_s: round3(compiles.iter().filter_map(ListedCompile::time_s).sum()),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct BwdCompilationMetricsMetadata {
-    pub inductor_compile_time_s: Option<f64>,
-    pub code_gen_time_s: Option<f64>,
-    pub fail_type: Option<String>,
-    pub fail_reason: Option<String>,
+// A compile only one of the runs has
+#[derive(Debug, Serialize)]
+pub struct RunCompile {
+    pub compile_id: String,
+    pub frame: String,
+    // Its compile.html, relative to the report
+    pub url: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct AOTAutogradBackwardCompilationMetricsMetadata {
-    pub start_time: Option<f64>,
-    pub elapsed_time: Option<f64>, // technically redundant with envelope
-    pub fail_type: Option<String>,
-    pub fail_reason: Option<String>,
+// A frame that compiled a different number of times in b than in a
+#[derive(Debug, Serialize)]
+pub struct FrameCountChange {
+    pub frame: String,
+    pub a: usize,
+    pub b: usize,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct SymbolicShapeSpecializationMetadata {
-    pub symbol: Option<String>,
-    pub sources: Option<Vec<String>>,
-    pub value: Option<String>,
-    pub reason: Option<String>,
-    pub stack: Option<StackSummary>,
-    pub user_stack: Option<StackSummary>,
+// A compile that took longer or shorter in b than in a
+#[derive(Debug, Serialize)]
+pub struct CompileTimeChange {
+    pub compile_id: String,
+    pub frame: String,
+    pub a_s: f64,
+    pub b_s: f64,
+    // Of the time in a
+    pub change_pct: f64,
+}
+
+impl CompileTimeChange {
+    pub fn new(compile_id: String, frame: String, a_s: f64, b_s: f64) -> Self {
+        CompileTimeChange {
+            compile_id,
+            frame,
+            a_s: round3(a_s),
+            b_s: round3(b_s),
+            change_pct: ((b_s - a_s) / a_s * 100.0).round(),
+        }
+    }
 }
 
+// The artifacts that differ between the two runs' compiles of the same id
 #[derive(Debug, Serialize)]
-pub struct BwdCompilationMetricsContext<'e> {
-    pub m: &'e BwdCompilationMetricsMetadata,
-    pub css: &'static str,
+pub struct ChangedArtifacts {
     pub compile_id: String,
-    pub qps: &'static str,
+    pub frame: String,
+    // Its compile.html in each run, relative to the report
+    pub a_url: String,
+    pub b_url: String,
+    // Those that didn't stay the same, see ArtifactDiff; the lines are only on the page
+    #[serde(serialize_with = "serialize_artifact_statuses")]
+    pub artifacts: Vec<ArtifactDiff>,
+}
+
+fn serialize_artifact_statuses<S: Serializer>(
+    artifacts: &[ArtifactDiff],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct Status<'a> {
+        name: &'a str,
+        status: &'a str,
+        added: usize,
+        removed: usize,
+    }
+    serializer.collect_seq(artifacts.iter().map(|d| Status {
+        name: &d.name,
+        status: &d.status,
+        added: d.added,
+        removed: d.removed,
+    }))
 }
 
+// What `tlparse diff` found comparing run b to run a, written as diff.json.  A regression is b
+// having compiles a didn't, a frame compiling more often, or a compile or the whole taking more
+// than threshold_pct longer (and at least MIN_COMPILE_TIME_CHANGE_S)
 #[derive(Debug, Serialize)]
-pub struct AOTAutogradBackwardCompilationMetricsContext<'e> {
-    pub m: &'e AOTAutogradBackwardCompilationMetricsMetadata,
+pub struct RunDiff {
+    pub regressed: bool,
+    // Each kind of regression found, for people
+    pub reasons: Vec<String>,
+    pub threshold_pct: f64,
+    pub normalized: bool,
+    pub a: RunSummary,
+    pub b: RunSummary,
+    pub only_in_a: Vec<RunCompile>,
+    pub only_in_b: Vec<RunCompile>,
+    pub frame_count_changes: Vec<FrameCountChange>,
+    // Most slowed down first
+    pub slower: Vec<CompileTimeChange>,
+    // Most sped up first
+    pub faster: Vec<CompileTimeChange>,
+    pub changed_artifacts: Vec<ChangedArtifacts>,
+}
+
+impl RunDiff {
+    // What the CLI prints
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for run in [&self.a, &self.b] {
+            text.push_str(&format!(
+                "{}: {} compile(s) of {} frame(s), {} s compiling\n",
+                run.name, run.num_compiles, run.num_frames, run.compile_time_s
+            ));
+        }
+        for (name, compiles) in [
+            (&self.a.name, &self.only_in_a),
+            (&self.b.name, &self.only_in_b),
+        ] {
+            for c in compiles {
+                text.push_str(&format!("Only in {name}: {} {}\n", c.compile_id, c.frame));
+            }
+        }
+        for f in &self.frame_count_changes {
+            text.push_str(&format!(
+                "Compiled {} -> {} time(s): {}\n",
+                f.a, f.b, f.frame
+            ));
+        }
+        for (what, changes) in [("Slower", &self.slower), ("Faster", &self.faster)] {
+            for c in changes {
+                text.push_str(&format!(
+                    "{what}: {} {} s -> {} s ({:+}%) {}\n",
+                    c.compile_id, c.a_s, c.b_s, c.change_pct, c.frame
+                ));
+            }
+        }
+        for c in &self.changed_artifacts {
+            let names: Vec<String> = c
+                .artifacts
+                .iter()
+                .map(|d| format!("{} ({})", d.name, d.status))
+                .collect();
+            text.push_str(&format!(
+                "Changed in {}: {}\n",
+                c.compile_id,
+                names.join(", ")
+            ));
+        }
+        if self.regressed {
+            text.push_str(&format!("Regressed: {}\n", self.reasons.join("; ")));
+        } else {
+            text.push_str("No regressions\n");
+        }
+        text
+    }
+}
+
+// diff.html of tlparse diff
+#[derive(Debug, Serialize)]
+pub struct RunDiffContext<'a> {
     pub css: &'static str,
+    pub custom_header_html: String,
+    #[serde(flatten)]
+    pub diff: &'a RunDiff,
+    // diff.changed_artifacts with their lines, which diff.json leaves out
+    pub artifact_lines: Vec<ChangedArtifactLines<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedArtifactLines<'a> {
+    pub compile: &'a ChangedArtifacts,
+    pub artifacts: &'a [ArtifactDiff],
+}
+
+// What tlparse diff made of two runs: the results, and the page showing them
+pub struct RunDiffReport {
+    pub diff: RunDiff,
+    pub html: String,
+}
+
+// How the guards of two compiles differ, in the order each has them
+#[derive(Debug, Serialize)]
+pub struct GuardDiff {
+    pub a: String,
+    pub b: String,
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+    pub num_shared: usize,
+}
+
+impl GuardDiff {
+    pub fn new(a: &CompileId, a_guards: &[String], b: &CompileId, b_guards: &[String]) -> Self {
+        let in_a: FxHashSet<&String> = a_guards.iter().collect();
+        let in_b: FxHashSet<&String> = b_guards.iter().collect();
+        GuardDiff {
+            a: a.to_string(),
+            b: b.to_string(),
+            only_a: a_guards
+                .iter()
+                .filter(|g| !in_b.contains(g))
+                .cloned()
+                .collect(),
+            only_b: b_guards
+                .iter()
+                .filter(|g| !in_a.contains(g))
+                .cloned()
+                .collect(),
+            num_shared: in_a.intersection(&in_b).count(),
+        }
+    }
+}
+
+// From one compile of a frame to the next, in guard_churn.html
+#[derive(Debug, Serialize)]
+pub struct GuardStep {
+    // (compile id, anchor in the index)
+    pub from: (String, String),
+    pub to: (String, String),
+    // (guard, whether it churns), in the order the compile has them
+    pub added: Vec<(String, bool)>,
+    pub removed: Vec<(String, bool)>,
+    pub num_unchanged: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuardChurnFrame {
+    // Empty for compiles without a frame id
+    pub frame_id: String,
+    // That logged guards
+    pub num_compiles: usize,
+    // (guard, how many times it was added or removed), most often first
+    pub churning: Vec<(String, usize)>,
+    pub steps: Vec<GuardStep>,
+}
+
+// What one rank logged of a compile (every attempt of one frame_id/frame_compile_id), for
+// ranks.html
+#[derive(Debug, Default)]
+pub struct RankCompile {
+    pub attempts: FxHashSet<u32>,
+    // entire_frame_compile_time_s of its compilation metrics, summed over the attempts
+    pub time_s: Option<f64>,
+}
+
+impl RankCompile {
+    // For ranks.html, e.g. "✓ 2 attempts, 1.2 s"
+    pub fn fmt(&self) -> String {
+        let mut s = "✓".to_string();
+        if self.attempts.len() > 1 {
+            write!(s, " {} attempts", self.attempts.len()).unwrap();
+            if self.time_s.is_some() {
+                s.push(',');
+            }
+        }
+        if let Some(t) = self.time_s {
+            write!(s, " {}", fmt_seconds(t)).unwrap();
+        }
+        s
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RankCompileRow {
     pub compile_id: String,
-    pub qps: &'static str,
+    // In the index, empty if the rendered rank didn't compile it
+    pub anchor: String,
+    // Per rank, in the order of RanksContext::ranks; empty if the rank didn't compile it
+    pub cells: Vec<String>,
+    // Some rank didn't compile it, or ranks made a different number of attempts
+    pub divergent: bool,
 }
 
-#[derive(Clone, Debug, Serialize)]
-pub struct OutputFile {
-    pub url: String,
-    pub name: String,
-    pub number: i32,
-    pub suffix: String,
+#[derive(Debug, Serialize)]
+pub struct RanksContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub ranks: Vec<u32>,
+    pub rendered_rank: String,
+    pub rows: Vec<RankCompileRow>,
+    pub num_divergent: usize,
 }
 
+// A frame that looks stuck recompiling, see recompile_loops
 #[derive(Debug, Serialize)]
-pub struct CompilationMetricsContext<'e> {
-    pub m: &'e CompilationMetricsMetadata,
+pub struct RecompileLoop {
+    // Empty for compiles without a frame id
+    pub frame_id: String,
+    // Its innermost user frame
+    pub frame: String,
+    // (compile id, anchor in the index) of the recompiles in the last window of the log
+    pub recompiles: Vec<(String, String)>,
+    pub num_recompiles: usize,
+    // The guard that came and went most often over those, and how often, if they logged guards
+    pub guard: Option<(String, usize)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuardChurnContext {
     pub css: &'static str,
-    pub compile_id: String,
-    pub stack_html: String,
-    pub symbolic_shape_specializations: Vec<SymbolicShapeSpecializationContext>,
-    pub output_files: &'e Vec<OutputFile>,
-    pub compile_id_dir: &'e PathBuf,
-    pub mini_stack_html: String,
-    pub qps: &'static str,
+    pub custom_header_html: String,
+    pub frames: Vec<GuardChurnFrame>,
+}
+
+// Compiles that captured the same Dynamo graph, in duplicate_graphs.html
+#[derive(Debug, Serialize)]
+pub struct DuplicateGraphGroup {
+    pub num_compiles: usize,
+    // (compile id, anchor in the index)
+    pub compiles: Vec<(String, String)>,
+    // Between the first two with guards, which is usually what made them recompile
+    pub guard_diff: Option<GuardDiff>,
+}
+
+impl DuplicateGraphGroup {
+    pub fn new(compiles: &[CompileId], records: &FxHashMap<CompileId, CompileRecord>) -> Self {
+        let mut with_guards = compiles
+            .iter()
+            .filter_map(|c| Some((c, records.get(c)?.guards.as_ref()?)));
+        let guard_diff = match (with_guards.next(), with_guards.next()) {
+            (Some((a, a_guards)), Some((b, b_guards))) => {
+                Some(GuardDiff::new(a, a_guards, b, b_guards))
+            }
+            _ => None,
+        };
+        DuplicateGraphGroup {
+            num_compiles: compiles.len(),
+            compiles: compiles
+                .iter()
+                .map(|c| (c.to_string(), c.as_directory_name()))
+                .collect(),
+            guard_diff,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
-pub enum FailureReason {
-    Failure((String, String, String, u32)), // (failure type, failure reason, user frame filename, user frame lineno)
-    Restart(String),                        // restart reason
+pub struct DuplicateGraphsContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub groups: Vec<DuplicateGraphGroup>,
 }
-impl Display for FailureReason {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+// Why a frame runs in eager in steady state, in eager_fallbacks.html.  When several apply the
+// first one is reported: past the recompile limit Dynamo doesn't even try, a skip is Dynamo
+// deciding not to compile, and a failure is it trying and giving up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EagerFallback {
+    CacheLimit,
+    Skipped,
+    Failed,
+}
+
+impl EagerFallback {
+    // For the last attempt of a frame's last compile, whose metrics are `metrics`; a frame with
+    // at least DEFAULT_CACHE_SIZE_LIMIT compiles reached the limit however its last one went
+    pub fn of(
+        metrics: &[CompilationMetricsMetadata],
+        failed_elsewhere: bool,
+        at_cache_limit: bool,
+    ) -> Option<EagerFallback> {
+        let fail_type_has = |names: &[&str]| {
+            metrics.iter().any(|m| {
+                m.fail_type
+                    .as_deref()
+                    .is_some_and(|t| names.iter().any(|n| t.contains(n)))
+            })
+        };
+        if at_cache_limit || fail_type_has(&["CacheLimitExceeded", "RecompileLimitExceeded"]) {
+            Some(EagerFallback::CacheLimit)
+        } else if fail_type_has(&["SkipFrame", "SkipCodeRecursiveException"]) {
+            Some(EagerFallback::Skipped)
+        } else if AttemptOutcome::of(metrics, failed_elsewhere, false) == AttemptOutcome::Failed {
+            Some(EagerFallback::Failed)
+        } else {
+            None
+        }
+    }
+
+    pub fn label(self) -> &'static str {
         match self {
-            FailureReason::Failure((
-                failure_type,
-                failure_reason,
-                user_frame_filename,
-                user_frame_lineno,
-            )) => {
-                let failure_type = encode_text(failure_type);
-                let failure_reason = encode_text(failure_reason);
-                let user_frame_filename = encode_text(user_frame_filename);
-                write!(
-                    f,
-                    "<td><pre>{failure_type}</pre></td>
-                           <td><pre>{failure_reason}</pre></td>
-                           <td><pre>{user_frame_filename}:{user_frame_lineno}</pre></td>
-                          "
-                )
-            }
-            FailureReason::Restart(restart_reason) => write!(
-                f,
-                r#"<td> RestartAnalysis </td><td><pre>{restart_reason}</pre></td><td>Not availble for restarts(yet)!</td>"#
-            ),
+            EagerFallback::CacheLimit => "recompile limit",
+            EagerFallback::Skipped => "skipped",
+            EagerFallback::Failed => "failed",
         }
     }
 }
 
 #[derive(Debug, Serialize)]
-pub struct RestartsAndFailuresContext {
-    // Serialized versions of (CompileId, FailureReason)
-    pub failures: Vec<(String, String)>,
+pub struct EagerFallbackEntry {
+    // Empty for compiles without a frame id
+    pub frame_id: String,
+    // The innermost frame in user code of its stack, see user_frame_label
+    pub label: String,
+    pub fallback: &'static str,
+    // The fail_reason of the compile, or why the limit applies
+    pub reason: String,
+    // (compile id, anchor in the index) of its last compile
+    pub compile: (String, String),
+}
+
+#[derive(Debug, Serialize)]
+pub struct EagerFallbacksContext {
     pub css: &'static str,
-    pub qps: &'static str,
+    pub custom_header_html: String,
+    pub cache_size_limit: usize,
+    pub frames: Vec<EagerFallbackEntry>,
 }
 
-#[derive(Debug)]
-pub enum Metadata<'e> {
-    Empty(&'e EmptyMetadata),
-    Link(&'e LinkMetadata),
-    GraphDump(&'e GraphDumpMetadata),
-    DynamoOutputGraph(&'e DynamoOutputGraphMetadata),
-    #[allow(dead_code)]
-    DynamoStart(&'e DynamoStartMetadata),
-    InductorOutputCode(&'e InductorOutputCodeMetadata),
-    OptimizeDdpSplitChild(&'e OptimizeDdpSplitChildMetadata),
-    CompilationMetrics(&'e CompilationMetricsMetadata),
-    AOTAutogradBackwardCompilationMetrics(&'e AOTAutogradBackwardCompilationMetricsMetadata),
-    BwdCompilationMetrics(&'e BwdCompilationMetricsMetadata),
-    Artifact(&'e ArtifactMetadata),
-    DumpFile(&'e DumpFileMetadata),
+// One of the most expensive compiles, in top.html
+#[derive(Debug, Serialize)]
+pub struct TopCompile {
+    pub compile_id: String,
+    // Of the compile in the index
+    pub anchor: String,
+    pub time: String,
+    // Its time is its span, as it has no compilation metrics
+    pub from_span: bool,
+    // Its innermost user frame, see user_frame_label
+    pub frame: String,
+    // From its compilation metrics; empty without
+    pub node_count: String,
+    // (url, name) of its artifacts
+    pub files: Vec<(String, String)>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct DumpFileMetadata {
+#[derive(Debug, Serialize)]
+pub struct TopCompilesContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub compiles: Vec<TopCompile>,
+}
+
+// Compiles of a file or a package, in source_files.html
+#[derive(Debug, Default, Serialize)]
+pub struct SourceGroup {
     pub name: String,
+    pub compiles: usize,
+    // Distinct frames among them
+    pub frames: usize,
+    pub compile_time_s: f64,
+    pub compile_time: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Envelope {
-    pub rank: Option<u32>,
-    #[serde(flatten)]
-    pub compile_id: Option<CompileId>,
-    #[serde(default)]
-    pub has_payload: Option<String>,
-    pub stack: Option<StackSummary>,
-    // externally tagged union, one field per log type we recognize
-    pub dynamo_start: Option<DynamoStartMetadata>,
-    pub str: Option<(String, u32)>,
-    pub dynamo_output_graph: Option<DynamoOutputGraphMetadata>,
-    pub optimize_ddp_split_graph: Option<EmptyMetadata>,
-    pub optimize_ddp_split_child: Option<OptimizeDdpSplitChildMetadata>,
-    pub compiled_autograd_graph: Option<EmptyMetadata>,
-    pub dynamo_guards: Option<EmptyMetadata>,
-    pub aot_forward_graph: Option<EmptyMetadata>,
-    pub aot_backward_graph: Option<EmptyMetadata>,
-    pub aot_inference_graph: Option<EmptyMetadata>,
-    pub aot_joint_graph: Option<EmptyMetadata>,
-    pub inductor_pre_grad_graph: Option<EmptyMetadata>,
-    pub inductor_post_grad_graph: Option<EmptyMetadata>,
-    pub dynamo_cpp_guards_str: Option<EmptyMetadata>,
-    pub inductor_output_code: Option<InductorOutputCodeMetadata>,
-    pub compilation_metrics: Option<CompilationMetricsMetadata>,
-    pub bwd_compilation_metrics: Option<BwdCompilationMetricsMetadata>,
-    pub aot_autograd_backward_compilation_metrics:
-        Option<AOTAutogradBackwardCompilationMetricsMetadata>,
-    pub graph_dump: Option<GraphDumpMetadata>,
-    pub link: Option<LinkMetadata>,
-    pub symbolic_shape_specialization: Option<SymbolicShapeSpecializationMetadata>,
-    pub artifact: Option<ArtifactMetadata>,
-    pub describe_storage: Option<StorageDesc>,
-    pub describe_tensor: Option<TensorDesc>,
-    pub describe_source: Option<SourceDesc>,
-    pub dump_file: Option<DumpFileMetadata>,
-    pub chromium_event: Option<EmptyMetadata>,
-    #[serde(flatten)]
-    pub _other: FxHashMap<String, Value>,
+#[derive(Debug, Serialize)]
+pub struct SourceFilesContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub num_without_user_frame: usize,
+    // Most compiled first
+    pub files: Vec<SourceGroup>,
+    pub packages: Vec<SourceGroup>,
 }
 
-type MetaTensorId = u64;
-type MetaStorageId = u64;
+// An operator in ops_histogram.html
+#[derive(Debug, Serialize)]
+pub struct OpCount {
+    pub op: String,
+    pub count: u64,
+    // How many compiles call it
+    pub compiles: usize,
+    // Percent of the most called operator, for the width of its bar
+    pub width: u64,
+}
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct TensorDesc {
-    id: MetaTensorId,
-    describer_id: u64,
-    ndim: u64,
-    dtype: String,
-    device: String,
-    size: Vec<SymInt>,
-    dynamo_dynamic_indices: Option<Vec<u64>>,
-    // TODO: Make layout an enum
-    #[serde(default = "default_layout")]
-    layout: String,
-    #[serde(default)]
-    is_inference: bool,
-    #[serde(default)]
-    is_leaf: bool,
-    #[serde(default)]
-    requires_grad: bool,
-    #[serde(default)]
-    is_sparse: bool,
-    #[serde(default)]
-    is_mkldnn: bool,
-    #[serde(default)]
-    is_functorch_wrapped: bool,
-    #[serde(default)]
-    is_batchedtensor: bool,
-    #[serde(default)]
-    is_legacy_batchedtensor: bool,
-    #[serde(default)]
-    is_gradtrackingtensor: bool,
-    #[serde(default)]
-    is_view: bool,
-    #[serde(default)]
-    is_nested: bool,
-    #[serde(default)]
-    is_traceable_wrapper_subclass: bool,
-    #[serde(default)]
-    is_functional: bool,
-    #[serde(default)]
-    is_conj: bool,
-    #[serde(default)]
-    is_neg: bool,
-    #[serde(default)]
-    is_parameter: bool,
-    stride: Option<Vec<SymInt>>,
-    #[serde(default)]
-    storage_offset: SymInt,
-    storage: Option<MetaStorageId>,
-    sparse_dim: Option<u64>,
-    dense_dim: Option<u64>,
-    is_coalesced: Option<bool>,
-    crow_indices: Option<MetaTensorId>,
-    col_indices: Option<MetaTensorId>,
-    ccol_indices: Option<MetaTensorId>,
-    row_indices: Option<MetaTensorId>,
-    values: Option<MetaTensorId>,
-    unwrapped: Option<MetaTensorId>,
-    bdim: Option<u64>,
-    base: Option<MetaTensorId>,
-    attrs: Option<FxHashMap<String, MetaTensorId>>,
-    creation_meta: Option<String>,
-    grad: Option<MetaTensorId>,
-    #[serde(flatten)]
-    pub _other: FxHashMap<String, Value>,
+#[derive(Debug, Serialize)]
+pub struct OpsHistogramContext {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub num_graphs: usize,
+    pub unreadable_graphs: usize,
+    pub merge_overloads: bool,
+    pub ops: Vec<OpCount>,
+    pub compiles: Vec<CompileOps>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct StorageDesc {
-    id: MetaStorageId,
-    describer_id: u64,
-    size: u64,
+#[derive(Debug, Serialize)]
+pub struct CompileOps {
+    pub compile_id: String,
+    // Of the compile in the index
+    pub anchor: String,
+    // Most called first
+    pub ops: Vec<(String, u64)>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct SourceDesc {
-    describer_id: u64,
-    id: MetaTensorId,
-    source: String,
+// A payload in largest_artifacts.html
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct LargeArtifact {
+    pub bytes: u64,
+    pub size: String,
+    pub lineno: usize,
+    pub kind: String,
+    pub compile_id: String,
+    // Of the compile in the index
+    pub anchor: String,
+    // The file it was written to, if it was
+    pub url: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct DynamoGuard {
-    pub code: String,
-    pub stack: Option<StackSummary>,
-    pub user_stack: Option<StackSummary>,
+// By size, and of two the same size the earlier one is larger, so that it's the one kept
+impl Ord for LargeArtifact {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.bytes, other.lineno).cmp(&(other.bytes, self.lineno))
+    }
+}
+
+impl PartialOrd for LargeArtifact {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 #[derive(Debug, Serialize)]
-pub struct DynamoGuardsContext {
-    pub guards: Vec<DynamoGuard>,
-    pub qps: &'static str,
+pub struct LargestArtifactsContext<'a> {
+    pub css: &'static str,
+    pub custom_header_html: String,
+    pub num_payloads: usize,
+    pub total_size: String,
+    pub artifacts: &'a [LargeArtifact],
 }
 
 #[derive(Debug, Serialize)]
-pub struct IndexContext {
+pub struct RunsIndexContext {
     pub css: &'static str,
-    pub javascript: &'static str,
-    pub directory: Vec<(String, Vec<OutputFile>)>,
-    pub stack_trie_html: String,
-    pub unknown_stack_trie_html: String,
-    pub has_unknown_stack_trie: bool,
-    pub num_breaks: usize,
     pub custom_header_html: String,
-    pub has_chromium_events: bool,
-    pub qps: &'static str,
+    pub runs: Vec<usize>,
 }
 
 #[derive(Debug, Serialize)]
@@ -589,3 +4820,289 @@ pub struct SymbolicShapeSpecializationContext {
     pub user_stack_html: String,
     pub stack_html: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_fail_reason() {
+        let cases = [
+            (
+                "Cannot access storage of <FakeTensor at 0x7f3a2b1c4d90>",
+                "Cannot access storage of <FakeTensor at 0x…>",
+            ),
+            (
+                "The size of tensor a (4) must match the size of tensor b (8) at non-singleton dimension 1",
+                "The size of tensor a (…) must match the size of tensor b (…) at non-singleton dimension N",
+            ),
+            (
+                "shape '[2, 3, 4]' is invalid for input of size 48",
+                "shape '[…]' is invalid for input of size N",
+            ),
+            (
+                "expected torch.Size([s0, 128]), got torch.Size([s1, 64])",
+                "expected torch.Size([…]), got torch.Size([…])",
+            ),
+            (
+                "Output 0 of type (3, 4) is not contiguous ",
+                "Output N of type (…) is not contiguous",
+            ),
+            // Numbers that are part of a name stay
+            ("aten.conv2d.default got s0", "aten.conv2d.default got s0"),
+            ("call_function BuiltinVariable(print) [] {}", "call_function BuiltinVariable(print) [] {}"),
+        ];
+        for (reason, normalized) in cases {
+            assert_eq!(normalize_fail_reason(reason), normalized);
+        }
+    }
+
+    // Metrics of an attempt, with just what a compile's status depends on
+    fn metrics(
+        fail_type: Option<&str>,
+        graph_op_count: u64,
+        restarts: &[&str],
+    ) -> CompilationMetricsMetadata {
+        serde_json::from_value(serde_json::json!({
+            "fail_type": fail_type,
+            "graph_op_count": graph_op_count,
+            "restart_reasons": restarts,
+        }))
+        .unwrap()
+    }
+
+    fn cid(frame_id: u32, frame_compile_id: u32, attempt: u32) -> Option<CompileId> {
+        Some(CompileId {
+            frame_id: Some(frame_id),
+            frame_compile_id: Some(frame_compile_id),
+            attempt,
+        })
+    }
+
+    #[test]
+    fn test_compile_statuses() {
+        let mut metrics_index = CompilationMetricsIndex::default();
+        // 0/0 broke, then failed, then succeeded: failed over all
+        metrics_index.insert(cid(0, 0, 0), vec![metrics(None, 3, &["graph break"])]);
+        metrics_index.insert(
+            cid(0, 0, 1),
+            vec![metrics(Some("BackendCompilerFailed"), 3, &[])],
+        );
+        metrics_index.insert(cid(0, 0, 2), vec![metrics(None, 3, &[])]);
+        // 1/0 only logged metrics for its second attempt, keyed by that attempt
+        metrics_index.insert(cid(1, 0, 1), vec![metrics(None, 3, &[])]);
+        // 0/1 broke into an empty graph: empty wins over the break
+        metrics_index.insert(cid(0, 1, 0), vec![metrics(None, 3, &["graph break"])]);
+        metrics_index.insert(cid(0, 1, 1), vec![metrics(None, 0, &[])]);
+        metrics_index.insert(None, vec![metrics(None, 3, &["graph break"])]);
+        let statuses = CompileStatuses::new(&metrics_index);
+
+        assert_eq!(statuses.compile(&cid(0, 0, 0)), "status-error");
+        assert_eq!(statuses.compile(&cid(0, 0, 2)), "status-error");
+        assert_eq!(statuses.attempt(&cid(0, 0, 0)), "status-break");
+        assert_eq!(statuses.attempt(&cid(0, 0, 1)), "status-error");
+        assert_eq!(statuses.attempt(&cid(0, 0, 2)), "status-ok");
+        assert_eq!(statuses.compile(&cid(1, 0, 0)), "status-ok");
+        assert_eq!(statuses.attempt(&cid(1, 0, 0)), "status-missing");
+        assert_eq!(statuses.attempt(&cid(1, 0, 1)), "status-ok");
+        assert_eq!(statuses.compile(&cid(0, 1, 0)), "status-empty");
+        assert_eq!(statuses.compile(&None), "status-break");
+        assert_eq!(statuses.compile(&cid(2, 0, 0)), "status-missing");
+    }
+
+    #[test]
+    fn test_trie_terminal_statuses() {
+        let mut metrics_index = CompilationMetricsIndex::default();
+        metrics_index.insert(cid(0, 0, 0), vec![metrics(None, 3, &["graph break"])]);
+        metrics_index.insert(
+            cid(0, 0, 1),
+            vec![metrics(Some("BackendCompilerFailed"), 3, &[])],
+        );
+        // An attempt that failed without logging a stack still fails its compile in the trie
+        metrics_index.insert(cid(1, 0, 0), vec![metrics(None, 3, &["graph break"])]);
+        metrics_index.insert(
+            cid(1, 0, 1),
+            vec![metrics(Some("BackendCompilerFailed"), 3, &[])],
+        );
+        let mut trie = StackTrieNode::default();
+        trie.insert(vec![], cid(0, 0, 1), None);
+        trie.insert(vec![], cid(0, 0, 0), None);
+        trie.insert(vec![], cid(1, 0, 0), None);
+        trie.insert(vec![], cid(2, 0, 0), None);
+        let statuses = CompileStatuses::new(&metrics_index);
+
+        assert_eq!(
+            trie.fmt_terminals(Some(&statuses), "").unwrap(),
+            concat!(
+                "<span class='attempts status-error'>[0/0 ×2 attempts: ",
+                "<a href='#0_0_0' class='status-break'>0</a> ",
+                "<a href='#0_0_1' class='status-error'><s>1</s></a>]</span> ",
+                "<a href='#1_0_0' class='status-error'>✗ [1/0]</a> ",
+                "<a href='#2_0_0' class='status-missing'>[2/0]</a> ",
+            )
+        );
+        // Without metrics there's nothing to link to
+        assert_eq!(
+            trie.fmt_terminals(None, "").unwrap(),
+            "[0/0 ×2 attempts] [1/0] [2/0] "
+        );
+        // Only compiles that failed in any attempt are kept
+        let failed = trie.retain_terminals(&|c| statuses.compile(c) == "status-error");
+        assert_eq!(
+            failed.fmt_terminals(None, "").unwrap(),
+            "[0/0 ×2 attempts] [1/0] "
+        );
+    }
+
+    fn frame(name: &str) -> FrameSummary {
+        FrameSummary {
+            filename: FrameFilename::Uninterned(FrameStr::new("/data/users/test/train.py")),
+            line: 1,
+            name: FrameStr::new(name),
+        }
+    }
+
+    fn stack(names: &[&str]) -> StackSummary {
+        names.iter().map(|name| frame(name)).collect()
+    }
+
+    // Each node as "name compiles/attempts", indented by depth
+    fn shape(trie: &StackTrieNode) -> Vec<String> {
+        fn walk(trie: &StackTrieNode, depth: usize, lines: &mut Vec<String>) {
+            for (frame, node) in trie.children.iter() {
+                lines.push(format!(
+                    "{:depth$}{} {}/{}",
+                    "",
+                    frame.name.resolve(),
+                    node.compiles,
+                    node.attempts,
+                    depth = depth * 2
+                ));
+                walk(node, depth + 1, lines);
+            }
+        }
+        let mut lines = Vec::new();
+        walk(trie, 0, &mut lines);
+        lines
+    }
+
+    // 0/0 restarted once; 1/0 and 2/0 compiled elsewhere in forward and main
+    fn counted_trie() -> StackTrieNode {
+        let mut trie = StackTrieNode::default();
+        trie.insert(stack(&["main", "forward", "a"]), cid(0, 0, 0), None);
+        trie.insert(stack(&["main", "forward", "a"]), cid(0, 0, 1), None);
+        trie.insert(stack(&["main", "forward", "b"]), cid(1, 0, 0), None);
+        trie.insert(stack(&["main", "other"]), cid(2, 0, 0), None);
+        // The same attempt inserted twice still counts once
+        trie.insert(stack(&["main", "other"]), cid(2, 0, 0), None);
+        trie.update_counts();
+        trie
+    }
+
+    #[test]
+    fn test_trie_counts() {
+        let trie = counted_trie();
+        assert_eq!(
+            shape(&trie),
+            [
+                "main 3/4",
+                "  forward 2/3",
+                "    a 1/2",
+                "    b 1/1",
+                "  other 1/1"
+            ]
+        );
+        let main = trie.children.iter().next().unwrap().1;
+        assert_eq!(main.fmt_counts(), " (3 compiles, 4 attempts)");
+        let other = main.children.iter().nth(1).unwrap().1;
+        assert_eq!(other.fmt_counts(), " (1 compile)");
+
+        // Time is summed over timed attempts, and only a lower bound while some are untimed
+        let mut metrics_index = CompilationMetricsIndex::default();
+        for (c, t) in [(cid(0, 0, 0), 1.0), (cid(0, 0, 1), 2.5)] {
+            let mut m = metrics(None, 3, &[]);
+            m.entire_frame_compile_time_s = Some(t);
+            metrics_index.insert(c, vec![m]);
+        }
+        let mut trie = counted_trie();
+        trie.set_compile_times(&metrics_index);
+        trie.update_counts();
+        let main = trie.children.iter().next().unwrap().1;
+        assert_eq!(
+            main.fmt_counts(),
+            format!(
+                " (3 compiles, 4 attempts, ≥{}, 2 untimed)",
+                fmt_seconds(3.5)
+            )
+        );
+        let a = main
+            .children
+            .iter()
+            .next()
+            .unwrap()
+            .1
+            .children
+            .iter()
+            .next()
+            .unwrap()
+            .1;
+        assert_eq!(
+            a.fmt_counts(),
+            format!(" (1 compile, 2 attempts, {})", fmt_seconds(3.5))
+        );
+    }
+
+    #[test]
+    fn test_trie_filters() {
+        let trie = counted_trie();
+
+        // Only the frames leading to kept compiles stay, with their counts redone
+        let kept = trie.retain_terminals(&|c| c.as_ref().is_some_and(|c| c.frame_id == Some(0)));
+        assert_eq!(shape(&kept), ["main 1/2", "  forward 1/2", "    a 1/2"]);
+        assert!(trie.retain_terminals(&|_| false).is_empty());
+
+        // A matching frame keeps what's above it and its whole subtree
+        let kept = trie.retain_paths(&|f| &*f.name.resolve() == "forward");
+        assert_eq!(
+            shape(&kept),
+            ["main 2/3", "  forward 2/3", "    a 1/2", "    b 1/1"]
+        );
+        let kept = trie.retain_paths(&|f| &*f.name.resolve() == "b");
+        assert_eq!(shape(&kept), ["main 1/1", "  forward 1/1", "    b 1/1"]);
+        assert!(trie.retain_paths(&|_| false).is_empty());
+    }
+
+    #[test]
+    fn test_strip_rules() {
+        let rules =
+            StripRules::new(&["re:^/home/[^/]+/".to_string(), "work/".to_string()]).unwrap();
+        // The first user rule that matches wins, and user rules go before the built-in ones
+        assert_eq!(
+            simplify_filename("/home/me/work/train.py", &rules),
+            "work/train.py"
+        );
+        assert_eq!(
+            simplify_filename("/opt/work/site-packages/a.py", &rules),
+            "site-packages/a.py"
+        );
+        let conda = "/home/me/.conda/envs/py310/lib/python3.10/site-packages/torch/nn/module.py";
+        assert_eq!(
+            simplify_filename(conda, &rules),
+            ".conda/envs/py310/lib/python3.10/site-packages/torch/nn/module.py"
+        );
+        // Built-in rules go in order too: site-packages before the conda env's standard library
+        assert_eq!(
+            simplify_filename(conda, &StripRules::default()),
+            "torch/nn/module.py"
+        );
+        assert_eq!(
+            simplify_filename(
+                "/home/me/.conda/envs/py310/lib/python3.10/contextlib.py",
+                &StripRules::default()
+            ),
+            "contextlib.py"
+        );
+        assert_eq!(simplify_filename("/tmp/a.py", &rules), "/tmp/a.py");
+        assert!(StripRules::new(&["re:(".to_string()]).is_err());
+    }
+}
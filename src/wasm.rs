@@ -0,0 +1,30 @@
+// What the wasm build gives JavaScript, for parsing logs in the browser without them leaving
+// the machine (see examples/wasm)
+
+use wasm_bindgen::prelude::*;
+
+use crate::{parse, ParseConfig};
+
+// Parse a whole log, in memory.  The result is a JSON object of the report's index (what
+// compile_directory.json holds) and every file of the report by its path, with / between
+// directories as links between the pages have it:
+//   {"index": {"provenance": ..., "compiles": [...]}, "files": {"index.html": "<!DOCTYPE ...", ...}}
+#[wasm_bindgen]
+pub fn parse_log(log: &[u8]) -> Result<String, JsError> {
+    let output =
+        parse(log, &ParseConfig::default()).map_err(|err| JsError::new(&format!("{err:#}")))?;
+    let mut index = serde_json::Value::Null;
+    let mut files = serde_json::Map::new();
+    for (path, contents) in output.files {
+        let name = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if name == "compile_directory.json" {
+            index = serde_json::from_str(&contents)?;
+        }
+        files.insert(name, contents.into());
+    }
+    Ok(serde_json::json!({ "index": index, "files": files }).to_string())
+}
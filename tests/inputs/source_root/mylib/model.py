@@ -0,0 +1,6 @@
+def first():
+    return forward()
+
+def forward(x):
+    y = x.sin()
+    return y.cos()
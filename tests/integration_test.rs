@@ -1,11 +1,38 @@
 use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
-use tlparse;
+use std::process::Command;
 
 fn prefix_exists(map: &HashMap<PathBuf, String>, prefix: &str) -> bool {
     map.keys()
-        .any(|key| key.to_str().map_or(false, |s| s.starts_with(prefix)))
+        .any(|key| key.to_str().is_some_and(|s| s.starts_with(prefix)))
+}
+
+// The stack trie of a page, up to the end of its list
+fn stack_trie_html(page: &str) -> &str {
+    let start = page.find("<div class='stack-trie'>").unwrap();
+    let end = start + page[start..].find("</div>").unwrap();
+    &page[start..end]
+}
+
+// A fresh, empty scratch directory for tests that run the CLI
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tlparse_test_{}_{}", name, std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run_cli(args: &[&OsStr]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_tlparse"))
+        .args(args)
+        .arg("--no-browser")
+        .output()
+        .unwrap()
 }
 
 #[test]
@@ -29,7 +56,7 @@ fn test_parse_simple() {
     };
     let output = tlparse::parse_path(&path, config);
     assert!(output.is_ok());
-    let map: HashMap<PathBuf, String> = output.unwrap().into_iter().collect();
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
     // Check all files are present
     for prefix in expected_files {
         assert!(
@@ -65,7 +92,7 @@ fn test_parse_compilation_metrics() {
     };
     let output = tlparse::parse_path(&path, config);
     assert!(output.is_ok());
-    let map: HashMap<PathBuf, String> = output.unwrap().into_iter().collect();
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
     // Check all files are present
     for prefix in expected_files {
         assert!(
@@ -94,7 +121,7 @@ fn test_parse_compilation_failures() {
     };
     let output = tlparse::parse_path(&path, config);
     assert!(output.is_ok());
-    let map: HashMap<PathBuf, String> = output.unwrap().into_iter().collect();
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
     // Check all files are present
     for prefix in expected_files {
         assert!(
@@ -118,7 +145,7 @@ fn test_parse_artifact() {
     };
     let output = tlparse::parse_path(&path, config);
     assert!(output.is_ok());
-    let map: HashMap<PathBuf, String> = output.unwrap().into_iter().collect();
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
     // Check all files are present
     for prefix in expected_files {
         assert!(
@@ -142,7 +169,7 @@ fn test_parse_chromium_event() {
     };
     let output = tlparse::parse_path(&path, config);
     assert!(output.is_ok());
-    let map: HashMap<PathBuf, String> = output.unwrap().into_iter().collect();
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
     // Check all files are present
     for prefix in expected_files {
         assert!(
@@ -168,7 +195,7 @@ fn test_cache_hit_miss() {
     };
     let output = tlparse::parse_path(&path, config);
     assert!(output.is_ok());
-    let map: HashMap<PathBuf, String> = output.unwrap().into_iter().collect();
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
     // Check all files are present
     for prefix in expected_files {
         assert!(
@@ -178,3 +205,4139 @@ fn test_cache_hit_miss() {
         );
     }
 }
+
+#[test]
+fn test_parse_ansi_colored() {
+    let expected_files = [
+        "0_0_0/aot_forward_graph",
+        "0_0_0/dynamo_output_graph",
+        "index.html",
+        "0_0_0/inductor_output_code",
+        "0_0_0/dynamo_guards",
+    ];
+    // ansi_colored.log is simple.log with the glog prefixes wrapped in color codes, and a
+    // color reset in front of some payload continuation lines
+    let path = Path::new("tests/inputs/ansi_colored.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    for prefix in expected_files {
+        assert!(
+            prefix_exists(&map, prefix),
+            "{} not found in output",
+            prefix
+        );
+    }
+
+    // Stripping must recover exactly what the uncolored log produces
+    let plain_path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let plain_config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    let plain: HashMap<PathBuf, String> = tlparse::parse_path(&plain_path, plain_config)
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    for (filename, content) in &plain {
+        // stats.json counts the lines that were stripped
+        if filename == Path::new("raw.log") || filename == Path::new("stats.json") {
+            continue;
+        }
+        assert_eq!(map.get(filename), Some(content), "{:?} differs", filename);
+    }
+}
+
+#[test]
+fn test_parse_partial_compile_id() {
+    let expected_files = [
+        "0_0_0/dynamo_output_graph",
+        "2_-_0/dynamo_output_graph",
+        "3_0_0/aot_forward_graph",
+        "index.html",
+    ];
+    // An envelope with only frame_id keeps the partial id instead of losing it, and an
+    // unexpected extra id field doesn't stop the rest of the envelope from parsing
+    let path = Path::new("tests/inputs/partial_compile_id.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        strict: true,
+        strict_compile_id: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    for prefix in expected_files {
+        assert!(
+            prefix_exists(&map, prefix),
+            "{} not found in output",
+            prefix
+        );
+    }
+    assert!(map[Path::new("index.html")].contains("[2/?]"));
+}
+
+#[test]
+fn test_parse_multiple_kinds() {
+    let path = Path::new("tests/inputs/multiple_kinds.log").to_path_buf();
+    // An envelope setting more than one artifact field is a writer bug
+    let strict_config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(tlparse::parse_path(&path, strict_config).is_err());
+
+    let config = tlparse::ParseConfig::default();
+    let output = tlparse::parse_path(&path, config);
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    // The graph wins over the intern entry, which is dropped
+    assert!(prefix_exists(&map, "0_0_0/dynamo_output_graph"));
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("real_file.py"));
+    assert!(!index.contains("bogus_file.py"));
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    assert_eq!(stats["multiple_kinds"], 1);
+}
+
+#[test]
+fn test_parse_empty_envelope() {
+    // Envelopes with a recognized prefix but no artifact field parse fine and write nothing
+    let path = Path::new("tests/inputs/multiple_kinds.log").to_path_buf();
+    let config = tlparse::ParseConfig::default();
+    let output = tlparse::parse_path(&path, config);
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(!prefix_exists(&map, "4_0_0/"));
+    // `{}`, and the envelope with only a compile id; counted apart from multiple kinds
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    assert_eq!(stats["empty_envelope"], 2);
+    assert_eq!(stats["multiple_kinds"], 1);
+}
+
+#[test]
+fn test_parse_missing_payload() {
+    let path = Path::new("tests/inputs/missing_payload.log").to_path_buf();
+    let strict_config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(tlparse::parse_path(&path, strict_config).is_err());
+
+    let config = tlparse::ParseConfig::default();
+    let output = tlparse::parse_path(&path, config);
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    // has_payload but no payload lines: a stub instead of an empty artifact
+    assert!(prefix_exists(
+        &map,
+        "0_0_0/dynamo_output_graph_missing_payload"
+    ));
+    assert!(!prefix_exists(&map, "0_0_0/dynamo_output_graph_0"));
+    // MD5 of the empty string: a legitimately empty artifact
+    let empty = map
+        .iter()
+        .find(|(k, _)| k.starts_with("1_0_0") && k.extension().is_some_and(|e| e == "txt"))
+        .expect("1_0_0 artifact not found");
+    assert!(empty.0.to_string_lossy().contains("dynamo_output_graph"));
+    assert!(empty.1.is_empty());
+    // Normal payload
+    assert!(prefix_exists(&map, "2_0_0/dynamo_output_graph"));
+}
+
+#[test]
+fn test_deterministic_output() {
+    // Parsing the same log twice must give byte-identical reports, in the same order
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let first = tlparse::parse_path(&path, tlparse::ParseConfig::default()).unwrap();
+    let second = tlparse::parse_path(&path, tlparse::ParseConfig::default()).unwrap();
+    assert_eq!(first.files, second.files);
+
+    // Compiles are listed in the order they first appear in the log
+    let map: HashMap<PathBuf, String> = first.files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    let positions: Vec<usize> = ["id=\"0_0_0\"", "id=\"1_0_0\"", "id=\"2_0_0\""]
+        .iter()
+        .map(|id| {
+            index
+                .find(id)
+                .unwrap_or_else(|| panic!("{} not in index", id))
+        })
+        .collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_duplicate_filenames() {
+    // The same dump_file logged twice must not clobber the first copy
+    let path = Path::new("tests/inputs/duplicate_dump_file.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    assert!(output.is_ok());
+    let output = output.unwrap().files;
+    let filenames: Vec<&PathBuf> = output.iter().map(|(f, _)| f).collect();
+    assert!(filenames.contains(&&PathBuf::from("dump_file/eval_with_key_3.html")));
+    assert!(filenames.contains(&&PathBuf::from("dump_file/eval_with_key_3_1.html")));
+    let map: HashMap<PathBuf, String> = output.into_iter().collect();
+    assert!(map[Path::new("0_0_0/compile.html")].contains("(duplicate)"));
+}
+
+#[test]
+fn test_overwrite_requires_marker() {
+    let dir = scratch_dir("overwrite");
+    let log = Path::new("tests/inputs/simple.log");
+    let out = dir.join("out");
+
+    // First run creates the directory and its marker, so overwriting it is fine
+    let first = run_cli(&[log.as_os_str(), OsStr::new("-o"), out.as_os_str()]);
+    assert!(first.status.success());
+    assert!(out.join(".tlparse").is_file());
+    let again = run_cli(&[
+        log.as_os_str(),
+        OsStr::new("-o"),
+        out.as_os_str(),
+        OsStr::new("--overwrite"),
+    ]);
+    assert!(again.status.success());
+
+    // Without the marker, --overwrite refuses unless --force is given
+    let precious = dir.join("precious");
+    fs::create_dir(&precious).unwrap();
+    fs::write(precious.join("important.txt"), "keep me").unwrap();
+    let refused = run_cli(&[
+        log.as_os_str(),
+        OsStr::new("-o"),
+        precious.as_os_str(),
+        OsStr::new("--overwrite"),
+    ]);
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("not created by tlparse"));
+    assert!(precious.join("important.txt").is_file());
+    let forced = run_cli(&[
+        log.as_os_str(),
+        OsStr::new("-o"),
+        precious.as_os_str(),
+        OsStr::new("--overwrite"),
+        OsStr::new("--force"),
+    ]);
+    assert!(forced.status.success());
+    assert!(!precious.join("important.txt").exists());
+
+    // An existing regular file is reported, not deleted
+    let file = dir.join("file");
+    fs::write(&file, "not a directory").unwrap();
+    let not_dir = run_cli(&[
+        log.as_os_str(),
+        OsStr::new("-o"),
+        file.as_os_str(),
+        OsStr::new("--overwrite"),
+        OsStr::new("--force"),
+    ]);
+    assert!(!not_dir.status.success());
+    assert!(String::from_utf8_lossy(&not_dir.stderr).contains("is not a directory"));
+    assert!(file.is_file());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_rank_detection_skips_rankless_lines() {
+    // The first envelopes have no rank; they must not make us drop every ranked line
+    let path = Path::new("tests/inputs/rank_none_first.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(prefix_exists(&map, "0_0_0/dynamo_output_graph"));
+    assert!(prefix_exists(&map, "1_0_0/dynamo_output_graph"));
+    assert!(!prefix_exists(&map, "2_0_0/dynamo_output_graph"));
+    assert!(prefix_exists(&map, "3_0_0/dynamo_output_graph"));
+    let result = run_cli(&[path.as_os_str(), OsStr::new("--dry-run")]);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("Detected rank: 0\n"), "{stderr}");
+
+    // An explicit rank overrides detection, so there's nothing detected to say
+    let config = tlparse::ParseConfig {
+        rank: Some(1),
+        ..Default::default()
+    };
+    let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    assert!(prefix_exists(&map, "0_0_0/dynamo_output_graph"));
+    assert!(!prefix_exists(&map, "1_0_0/dynamo_output_graph"));
+    assert!(prefix_exists(&map, "2_0_0/dynamo_output_graph"));
+    let result = run_cli(&[
+        path.as_os_str(),
+        OsStr::new("--dry-run"),
+        OsStr::new("--rank"),
+        OsStr::new("1"),
+    ]);
+    assert!(!String::from_utf8_lossy(&result.stderr).contains("Detected rank"));
+}
+
+fn assert_no_empty_dirs(dir: &Path) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            assert!(
+                fs::read_dir(&path).unwrap().next().is_some(),
+                "{} is empty",
+                path.display()
+            );
+            assert_no_empty_dirs(&path);
+        }
+    }
+}
+
+#[test]
+fn test_no_empty_compile_dirs() {
+    let dir = scratch_dir("empty_dirs");
+    let out = dir.join("out");
+    let log = Path::new("tests/inputs/multiple_kinds.log");
+    let result = run_cli(&[log.as_os_str(), OsStr::new("-o"), out.as_os_str()]);
+    assert!(result.status.success());
+    assert_no_empty_dirs(&out);
+    // [1/0] only has a dynamo_start, so it is listed without any artifacts
+    assert!(!out.join("1_0_0").exists());
+    let index = fs::read_to_string(out.join("index.html")).unwrap();
+    assert!(index.contains("(no artifacts)"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_intern_conflicts() {
+    let path = Path::new("tests/inputs/intern_conflict.log").to_path_buf();
+    let strict_config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(tlparse::parse_path(&path, strict_config).is_err());
+
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // The first assignment wins, and the conflict is reported exactly once (re-sending
+    // the original string is not a conflict)
+    assert!(index.contains("first.py:3 in f"));
+    assert!(!index.contains("second.py:3 in f"));
+    assert!(index.contains("Intern table conflicts"));
+    assert_eq!(
+        index
+            .matches("<code>/data/users/test/second.py</code>")
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn test_late_intern_strings() {
+    // The intern entries are logged after the stack and compilation metrics that use them
+    let path = Path::new("tests/inputs/late_intern.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("late_main.py:3 in main"));
+    assert!(index.contains("late_model.py:12 in f"));
+    let metrics = &map[Path::new("0_0_0/compilation_metrics_0.html")];
+    assert!(metrics.contains("late_main.py:3 in main"));
+    assert!(!metrics.contains("(unknown)"));
+}
+
+#[test]
+fn test_bad_input_paths() {
+    let dir = scratch_dir("bad_input");
+    let out = dir.join("out");
+    let stderr = |o: &std::process::Output| String::from_utf8_lossy(&o.stderr).into_owned();
+
+    let missing = run_cli(&[
+        dir.join("typo.log").as_os_str(),
+        OsStr::new("-o"),
+        out.as_os_str(),
+    ]);
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(stderr(&missing).contains("typo.log: no such file"));
+    assert!(!stderr(&missing).contains("panicked"));
+    // Bad inputs are rejected before the output directory is created
+    assert!(!out.exists());
+
+    // A directory lists the trace files it contains
+    let logs = dir.join("logs");
+    fs::create_dir(&logs).unwrap();
+    fs::write(logs.join("dedicated_log_torch_trace_rank_0.log"), "").unwrap();
+    fs::write(logs.join("notes.txt"), "").unwrap();
+    let directory = run_cli(&[logs.as_os_str(), OsStr::new("-o"), out.as_os_str()]);
+    assert_eq!(directory.status.code(), Some(1));
+    let msg = stderr(&directory);
+    assert!(msg.contains("is a directory"));
+    assert!(msg.contains("--latest"));
+    assert!(msg.contains("dedicated_log_torch_trace_rank_0.log"));
+    assert!(!msg.contains("notes.txt"));
+
+    // Permissions aren't enforced for root, so only check this when they are
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let unreadable = dir.join("unreadable.log");
+        fs::write(&unreadable, "").unwrap();
+        fs::set_permissions(&unreadable, fs::Permissions::from_mode(0o000)).unwrap();
+        if fs::File::open(&unreadable).is_err() {
+            let denied = run_cli(&[unreadable.as_os_str(), OsStr::new("-o"), out.as_os_str()]);
+            assert_eq!(denied.status.code(), Some(1));
+            assert!(stderr(&denied).contains("permission denied"));
+        }
+    }
+
+    let empty = dir.join("empty.log");
+    fs::write(&empty, "").unwrap();
+    let parsed = run_cli(&[empty.as_os_str(), OsStr::new("-o"), out.as_os_str()]);
+    assert!(parsed.status.success());
+    assert!(stderr(&parsed).contains("no log lines were read"));
+    assert!(out.join("index.html").is_file());
+}
+
+#[test]
+fn test_concatenated_runs() {
+    // A restarted job appended a second run, whose compile ids start again from 0/0
+    let path = Path::new("tests/inputs/two_runs.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("run_0/index.html"));
+    assert!(index.contains("run_1/index.html"));
+    let first = &map[Path::new("run_0/index.html")];
+    let second = &map[Path::new("run_1/index.html")];
+    assert!(first.contains("first_run.py:5 in train"));
+    assert!(!first.contains("second_run.py"));
+    assert!(second.contains("second_run.py:5 in train"));
+    assert!(first.contains("this is\n<strong>run 0</strong>"));
+    assert!(second.contains("this is\n<strong>run 1</strong>"));
+    // Interns restarting from 0 are a new run, not a conflict
+    assert!(!second.contains("Intern table conflicts"));
+    assert!(map[Path::new("run_1/0_0_0/dynamo_output_graph_0.txt")].contains("second_run"));
+
+    let config = tlparse::ParseConfig {
+        run: Some(1),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("index.html")].contains("second_run.py:5 in train"));
+    assert!(map[Path::new("0_0_0/dynamo_output_graph_0.txt")].contains("second_run"));
+
+    let config = tlparse::ParseConfig {
+        run: Some(2),
+        ..Default::default()
+    };
+    assert!(tlparse::parse_path(&path, config).is_err());
+}
+
+#[test]
+fn test_dedupe() {
+    // Every envelope in this log was written twice
+    let path = Path::new("tests/inputs/teed.log").to_path_buf();
+    let count_graphs = |output: Vec<(PathBuf, String)>| {
+        output
+            .iter()
+            .filter(|(p, _)| p.to_string_lossy().contains("dynamo_output_graph"))
+            // Not their highlighted companions
+            .filter(|(p, _)| p.extension().is_some_and(|e| e == "txt"))
+            .count()
+    };
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    assert_eq!(count_graphs(output.unwrap().files), 2);
+
+    let config = tlparse::ParseConfig {
+        dedupe: true,
+        strict: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    assert!(output.is_ok());
+    let output = output.unwrap().files;
+    let map: HashMap<PathBuf, String> = output.clone().into_iter().collect();
+    assert!(map[Path::new("index.html")].contains("teed.py:4 in f"));
+    assert_eq!(count_graphs(output), 1);
+}
+
+#[test]
+fn test_glog_timestamps() {
+    use chrono::NaiveDate;
+    let ymd = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+    // 23:59 -> 00:00 moves on to the next day
+    let mut timestamps = tlparse::GlogTimestamps::new(2024);
+    let before = timestamps.resolve(4, 1, 23, 59, 59, 999999).unwrap();
+    let after = timestamps.resolve(4, 2, 0, 0, 0, 1).unwrap();
+    assert_eq!(
+        before,
+        ymd(2024, 4, 1)
+            .and_hms_micro_opt(23, 59, 59, 999999)
+            .unwrap()
+    );
+    assert_eq!(
+        after,
+        ymd(2024, 4, 2).and_hms_micro_opt(0, 0, 0, 1).unwrap()
+    );
+    assert!(after > before);
+
+    // Dec 31 -> Jan 1 moves on to the next year, and stays there
+    let mut timestamps = tlparse::GlogTimestamps::new(2023);
+    let before = timestamps.resolve(12, 31, 23, 59, 59, 0).unwrap();
+    let after = timestamps.resolve(1, 1, 0, 0, 1, 0).unwrap();
+    let later = timestamps.resolve(1, 1, 0, 0, 2, 0).unwrap();
+    assert_eq!(before.date(), ymd(2023, 12, 31));
+    assert_eq!(after.date(), ymd(2024, 1, 1));
+    assert_eq!(later.date(), ymd(2024, 1, 1));
+
+    // Threads interleaving slightly out of order is not a rollover
+    let straggler = timestamps.resolve(12, 31, 23, 59, 58, 0).unwrap();
+    assert_eq!(straggler.date(), ymd(2023, 12, 31));
+
+    assert!(timestamps.resolve(2, 30, 0, 0, 0, 0).is_none());
+
+    // A leap day in a year without one goes to the nearest leap year, and what follows with it
+    let mut timestamps = tlparse::GlogTimestamps::new(2025);
+    let before = timestamps.resolve(2, 28, 23, 59, 59, 0).unwrap();
+    let leap_day = timestamps.resolve(2, 29, 12, 0, 0, 0).unwrap();
+    let after = timestamps.resolve(3, 1, 0, 0, 0, 0).unwrap();
+    assert_eq!(before.date(), ymd(2025, 2, 28));
+    assert_eq!(leap_day.date(), ymd(2024, 2, 29));
+    assert_eq!(after.date(), ymd(2024, 3, 1));
+    let mut timestamps = tlparse::GlogTimestamps::new(2027);
+    assert_eq!(
+        timestamps.resolve(2, 29, 0, 0, 0, 0).unwrap().date(),
+        ymd(2028, 2, 29)
+    );
+    // Between two as near, the earlier one
+    let mut timestamps = tlparse::GlogTimestamps::new(2026);
+    assert_eq!(
+        timestamps.resolve(2, 29, 0, 0, 0, 0).unwrap().date(),
+        ymd(2024, 2, 29)
+    );
+}
+
+// Writes a log with one entry of kind whose payload is `lines` lines of `line`
+fn write_big_payload_log(path: &Path, kind: &str, line: &str, lines: usize) {
+    use md5::{Digest, Md5};
+    use std::io::Write;
+    let mut hasher = Md5::new();
+    for i in 0..lines {
+        if i > 0 {
+            hasher.update("\n");
+        }
+        hasher.update(line);
+    }
+    let md5: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let mut f = std::io::BufWriter::new(fs::File::create(path).unwrap());
+    writeln!(
+        f,
+        "V0401 08:54:21.000002 140424060892160 torch/_logging/structured.py:19] {{\"{kind}\": {{}}, \"frame_id\": 0, \"frame_compile_id\": 0, \"attempt\": 0, \"has_payload\": \"{md5}\"}}"
+    )
+    .unwrap();
+    for _ in 0..lines {
+        writeln!(f, "\t{line}").unwrap();
+    }
+}
+
+#[test]
+fn test_stream_large_payload() {
+    let dir = scratch_dir("stream_payload");
+    let log = dir.join("big.log");
+    write_big_payload_log(
+        &log,
+        "dynamo_output_graph",
+        "x = torch.ops.aten.add.Tensor(y, z)",
+        100,
+    );
+    let out = dir.join("out");
+    fs::create_dir(&out).unwrap();
+    let config = tlparse::ParseConfig {
+        strict: true,
+        output_dir: Some(out.clone()),
+        stream_payload_threshold: Some(1000),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&log, config);
+    assert!(output.is_ok());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    // The payload went straight to disk, byte for byte, and is linked from the index
+    assert!(!map
+        .keys()
+        .any(|p| p.starts_with("0_0_0") && !p.ends_with("compile.html")));
+    let streamed = fs::read_to_string(out.join("0_0_0/dynamo_output_graph_0.txt")).unwrap();
+    assert_eq!(
+        streamed,
+        vec!["x = torch.ops.aten.add.Tensor(y, z)"; 100].join("\n")
+    );
+    assert!(map[Path::new("index.html")].contains("0_0_0/compile.html"));
+    assert!(map[Path::new("0_0_0/compile.html")].contains("../0_0_0/dynamo_output_graph_0.txt"));
+    // Its size is counted as it's written
+    assert!(map[Path::new("compile_directory.json")].contains("\"size\": 3599"));
+    assert!(out.join("raw.log").is_file());
+    assert!(!map.contains_key(Path::new("raw.log")));
+}
+
+#[test]
+fn test_stream_large_payload_unknown_kind() {
+    let dir = scratch_dir("stream_payload_unknown");
+    let log = dir.join("big.log");
+    write_big_payload_log(&log, "some_new_artifact", "x = 1", 300);
+    let out = dir.join("out");
+    fs::create_dir(&out).unwrap();
+    let config = tlparse::ParseConfig {
+        strict: true,
+        output_dir: Some(out.clone()),
+        stream_payload_threshold: Some(1000),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&log, config).unwrap();
+    let map: HashMap<PathBuf, String> = output.files.into_iter().collect();
+    // Written out all the same, and listed like any other streamed payload
+    let streamed = fs::read_to_string(out.join("0_0_0/unknown_0.txt")).unwrap();
+    assert_eq!(streamed, vec!["x = 1"; 300].join("\n"));
+    assert!(map[Path::new("0_0_0/compile.html")].contains("../0_0_0/unknown_0.txt"));
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    assert_eq!(stats["streamed_payload"], 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_stream_large_payload_memory() {
+    // Peak memory is per process, and the other tests run in threads of this one, so the parse
+    // runs on its own in a child: this test again, told what to parse through the environment
+    fn peak_rss_kb() -> u64 {
+        let status = fs::read_to_string("/proc/self/status").unwrap();
+        let line = status.lines().find(|l| l.starts_with("VmHWM:")).unwrap();
+        line.split_whitespace().nth(1).unwrap().parse().unwrap()
+    }
+    if let Some(log) = std::env::var_os("TLPARSE_TEST_MEMORY_LOG") {
+        let log = PathBuf::from(log);
+        let before = peak_rss_kb();
+        let config = tlparse::ParseConfig {
+            strict: true,
+            output_dir: Some(log.with_file_name("out")),
+            stream_payload_threshold: Some(1024 * 1024),
+            ..Default::default()
+        };
+        assert!(tlparse::parse_path(&log, config).is_ok());
+        println!("peak memory grew by {} KiB", peak_rss_kb() - before);
+        return;
+    }
+
+    let dir = scratch_dir("stream_payload_memory");
+    let log = dir.join("huge.log");
+    // 128 MiB of payload
+    write_big_payload_log(&log, "dynamo_output_graph", &"x".repeat(1023), 128 * 1024);
+    let out = dir.join("out");
+    fs::create_dir(&out).unwrap();
+    let child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "test_stream_large_payload_memory", "--nocapture"])
+        .env("TLPARSE_TEST_MEMORY_LOG", &log)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&child.stdout);
+    assert!(child.status.success(), "{stdout}");
+    let grown: u64 = stdout
+        .lines()
+        .find_map(|l| l.split_once("peak memory grew by ")?.1.strip_suffix(" KiB"))
+        .unwrap_or_else(|| panic!("{stdout}"))
+        .parse()
+        .unwrap();
+    assert!(grown < 16 * 1024, "peak memory grew by {grown} KiB");
+    assert_eq!(
+        fs::metadata(out.join("0_0_0/dynamo_output_graph_0.txt"))
+            .unwrap()
+            .len(),
+        128 * 1024 * 1024 - 1
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_space_indented_payloads() {
+    let expected = "class GraphModule(torch.nn.Module):\n    def forward(self, L_x_: \"f32[3]\"):\n        add = L_x_ + 1\n        return (add,)";
+    // One log indents payloads with four spaces throughout, the other alternates with tabs
+    for log in ["space_indented.log", "mixed_indent.log"] {
+        let path = Path::new("tests/inputs").join(log);
+        let config = tlparse::ParseConfig {
+            strict: true,
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&path, config);
+        assert!(output.is_ok(), "{log} failed to parse");
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        assert_eq!(map[Path::new("0_0_0/dynamo_output_graph_0.txt")], expected);
+    }
+
+    // Only tabs when spaces are turned off
+    let config = tlparse::ParseConfig {
+        strict: true,
+        payload_space_indent: Some(0),
+        ..Default::default()
+    };
+    let path = Path::new("tests/inputs/space_indented.log").to_path_buf();
+    assert!(tlparse::parse_path(&path, config).is_err());
+}
+
+#[test]
+fn test_strip_prefixes() {
+    let path = Path::new("tests/inputs/strip_prefix.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        show_internal_frames: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("torch/nn/modules/module.py:1 in fn0"));
+    assert!(!index.contains("site-packages/torch"));
+    assert!(index.contains("contextlib.py:2 in fn1"));
+    assert!(!index.contains("python3.10/contextlib.py"));
+    // Built-in rules go in order, so the container path wins over site-packages
+    assert!(index.contains("lib/site-packages/caffe2/test.py:3 in fn2"));
+    assert!(index.contains("mylib/layers.py:4 in fn3"));
+    assert!(!index.contains("venv/lib"));
+    assert!(index.contains("/home/me/work/train.py:5 in fn4"));
+
+    // User rules come before the built-in ones
+    let config = tlparse::ParseConfig {
+        strip_prefixes: vec!["re:^/home/[^/]+/work/".to_string()],
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("venv/lib/python3.10/site-packages/mylib/layers.py:4 in fn3"));
+    assert!(!index.contains("/home/me/work/train.py"));
+    assert!(index.contains("train.py:5 in fn4"));
+    assert!(index.contains("contextlib.py:2 in fn1"));
+    // And everywhere else filenames are shown
+    assert!(map[Path::new("stack_trie.json")].contains(r#""filename":"train.py""#));
+
+    let config = tlparse::ParseConfig {
+        strip_prefixes: vec!["re:(".to_string()],
+        ..Default::default()
+    };
+    assert!(tlparse::parse_path(&path, config).is_err());
+}
+
+#[test]
+fn test_trie_terminals() {
+    let path = Path::new("tests/inputs/trie_terminals.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    let trie = stack_trie_html(index);
+    // Eight compiles share the first leaf, so only the first five are listed
+    let expected = concat!(
+        "<div class='stack-trie'><ul>",
+        "<li class='trie-node' data-search=\"/data/users/test/loop.py:1 in main\">",
+        "/data/users/test/loop.py:1 in main</li>\n",
+        // The filter box can find the compiles that aren't listed too
+        "<li class='trie-node trie-branch' data-search=\"/data/users/test/loop.py:7 in step ",
+        "[0/0] [0/1] [0/2] [0/3] [0/4] [0/5] [0/6] [0/7]\">",
+        "<a href='#0_0_0' class='status-missing'>[0/0]</a> ",
+        "<a href='#0_1_0' class='status-missing'>[0/1]</a> ",
+        "<a href='#0_2_0' class='status-missing'>[0/2]</a> ",
+        "<a href='#0_3_0' class='status-missing'>[0/3]</a> ",
+        "<a href='#0_4_0' class='status-missing'>[0/4]</a> ",
+        "<span title='[0/5] [0/6] [0/7]'>…(+3)</span> ",
+        "/data/users/test/loop.py:7 in step</li>\n",
+        "<li class='trie-node trie-branch' data-search=\"/data/users/test/loop.py:12 in eval [1/0]\">",
+        "<a href='#1_0_0' class='status-missing'>[1/0]</a> ",
+        "/data/users/test/loop.py:12 in eval</li>\n",
+        "</ul>",
+    );
+    assert_eq!(trie, expected);
+    // Every terminal links to its compile's entry in the index
+    for anchor in ["0_0_0", "0_4_0", "1_0_0"] {
+        assert!(index.contains(&format!("<a id=\"{anchor}\">")));
+    }
+}
+
+#[test]
+fn test_trie_folding() {
+    let path = Path::new("tests/inputs/trie_fold.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        trie_open_depth: Some(1),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    let trie = stack_trie_html(index);
+    // Only branch points fold, and only the first level starts out open
+    assert_eq!(trie.matches("<details").count(), 2);
+    assert_eq!(trie.matches("</details>").count(), 2);
+    assert_eq!(trie.matches("<details open>").count(), 1);
+    // Branch points say how many compiles are under them, counting the restart of [0/0] as an
+    // attempt rather than another compile
+    assert!(trie.contains(concat!(
+        "<summary>/data/users/test/&lt;fold&gt;.py:2 in &lt;lambda&gt; (3 compiles, 4 attempts) ",
+        "<span class='first-seen' title='first compile at 04/01 08:54:21.000002'>08:54:21</span></summary>"
+    )));
+    assert!(trie.contains(concat!(
+        "<details><summary>/data/users/test/&lt;fold&gt;.py:3 in a1 (2 compiles, 3 attempts) ",
+        "<span class='first-seen' title='first compile at 04/01 08:54:21.000002'>08:54:21</span></summary>"
+    )));
+    // Angle brackets in file and function names are escaped
+    for raw in ["<module>", "<lambda>", "<genexpr>", "<fold>"] {
+        assert!(!trie.contains(raw));
+    }
+
+    let config = tlparse::ParseConfig {
+        plain_text: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains(concat!(
+        "<pre class='stack-trie'>/data/users/test/&lt;fold&gt;.py:1 in &lt;module&gt;\n",
+        "/data/users/test/&lt;fold&gt;.py:2 in &lt;lambda&gt; (3 compiles, 4 attempts)\n",
+        "  /data/users/test/&lt;fold&gt;.py:3 in a1 (2 compiles, 3 attempts)\n",
+        "    [0/0 ×2 attempts] /data/users/test/&lt;fold&gt;.py:6 in b1\n",
+        "    [1/0] /data/users/test/&lt;fold&gt;.py:7 in b2\n",
+        "  [2/0] /data/users/test/&lt;fold&gt;.py:4 in a2\n",
+        "[3/0] /data/users/test/&lt;fold&gt;.py:5 in &lt;genexpr&gt;\n",
+        "</pre>",
+    )));
+}
+
+#[test]
+fn test_trie_compile_outcomes() {
+    let path = Path::new("tests/inputs/compile_outcomes.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("<a href='#0_0_0' class='status-ok'>[0/0]</a>"));
+    assert!(index.contains("<a href='#1_0_0' class='status-error'>✗ [1/0]</a>"));
+
+    let config = tlparse::ParseConfig {
+        trie_failed_only: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    let trie = stack_trie_html(index);
+    assert!(trie.contains("outcomes.py:1 in main"));
+    assert!(trie.contains("outcomes.py:11 in breaks"));
+    assert!(!trie.contains("in works"));
+    assert!(index.contains("Only compiles that failed are shown."));
+}
+
+#[test]
+fn test_trie_filter() {
+    let path = Path::new("tests/inputs/trie_fold.log").to_path_buf();
+    let trie_for = |filter: &str| {
+        let config = tlparse::ParseConfig {
+            trie_filter: Some(filter.to_string()),
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&path, config);
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        let index = &map[Path::new("index.html")];
+        stack_trie_html(index).to_string()
+    };
+
+    // Matching a function keeps its ancestors and everything below it
+    let trie = trie_for("^a1$");
+    assert!(trie.contains("in &lt;module&gt;"));
+    assert!(trie.contains("in &lt;lambda&gt;"));
+    assert!(trie.contains("in b1"));
+    assert!(trie.contains("in b2"));
+    assert!(!trie.contains("in a2"));
+    assert!(!trie.contains("in &lt;genexpr&gt;"));
+
+    // Leaves match too, and siblings that don't are pruned
+    let trie = trie_for("genexpr");
+    assert!(trie.contains("in &lt;genexpr&gt;"));
+    assert!(!trie.contains("in &lt;lambda&gt;"));
+
+    // Filenames are matched after simplification
+    let trie = trie_for("^/data/users/test/<fold>\\.py$");
+    assert!(trie.contains("in a2"));
+    let trie = trie_for("^nothing$");
+    assert!(!trie.contains("<li "));
+
+    let config = tlparse::ParseConfig {
+        trie_filter: Some("(".to_string()),
+        ..Default::default()
+    };
+    assert!(tlparse::parse_path(&path, config).is_err());
+}
+
+#[test]
+fn test_internal_frames() {
+    let path = Path::new("tests/inputs/internal_frames.log").to_path_buf();
+    let trie_for = |config: tlparse::ParseConfig| {
+        let output = tlparse::parse_path(&path, config);
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        let index = &map[Path::new("index.html")];
+        stack_trie_html(index).to_string()
+    };
+
+    // Both ways into forward are hidden, and what's below forward is merged
+    let trie = trie_for(tlparse::ParseConfig::default());
+    assert!(trie.contains("<span class='internal-frames'>… 1–2 internal frames …</span>"));
+    assert!(!trie.contains("module.py"));
+    assert!(!trie.contains("eval_frame.py"));
+    assert_eq!(trie.matches("model.py:30 in forward<").count(), 1);
+    assert!(trie.contains("model.py:31 in inner"));
+
+    let trie = trie_for(tlparse::ParseConfig {
+        show_internal_frames: true,
+        ..Default::default()
+    });
+    assert!(trie.contains("torch/nn/modules/module.py:20 in _call_impl"));
+    assert!(trie.contains("torch/_dynamo/eval_frame.py:40 in _fn"));
+    assert_eq!(trie.matches("model.py:30 in forward<").count(), 2);
+
+    // Extra prefixes are collapsed too
+    let trie = trie_for(tlparse::ParseConfig {
+        internal_frame_prefixes: vec!["/data/users/test/".to_string()],
+        ..Default::default()
+    });
+    assert!(trie.contains("… 3–5 internal frames …"));
+    assert!(!trie.contains("model.py"));
+}
+
+#[test]
+fn test_stack_trie_json() {
+    let path = Path::new("tests/inputs/trie_fold.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let trie: serde_json::Value = serde_json::from_str(&map[Path::new("stack_trie.json")]).unwrap();
+
+    fn collect(node: &serde_json::Value, ids: &mut Vec<(u64, u64, u64)>) {
+        for c in node["compiles"].as_array().unwrap() {
+            ids.push((
+                c["frame_id"].as_u64().unwrap(),
+                c["frame_compile_id"].as_u64().unwrap(),
+                c["attempt"].as_u64().unwrap(),
+            ));
+        }
+        for child in node["children"].as_array().unwrap() {
+            collect(child, ids);
+        }
+    }
+    let mut ids = Vec::new();
+    collect(&trie, &mut ids);
+    ids.sort();
+    assert_eq!(
+        ids,
+        vec![(0, 0, 0), (0, 0, 1), (1, 0, 0), (2, 0, 0), (3, 0, 0)]
+    );
+
+    let top = &trie["children"][0];
+    assert_eq!(top["filename"], "/data/users/test/<fold>.py");
+    assert_eq!(top["line"], 1);
+    assert_eq!(top["function"], "<module>");
+    assert_eq!(top["children"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_per_rank_stack_tries() {
+    let path = Path::new("tests/inputs/ranks.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("the trie below is for rank 0"));
+
+    let tries: Vec<&str> = index.split("<div class='stack-trie'>").skip(1).collect();
+    assert_eq!(tries.len(), 3);
+    // The rendered rank links to its compiles
+    assert!(tries[0].contains("in stage0"));
+    assert!(tries[0].contains("in only_rank0"));
+    assert!(!tries[0].contains("in stage1"));
+    assert!(tries[0].contains("<a href='#0_0_0'"));
+    // Other ranks come in order, with their compiles unlinked
+    assert!(index.contains("<summary>Rank 1</summary>"));
+    assert!(tries[1].contains("in stage1"));
+    assert!(tries[1].contains("in shared"));
+    assert!(!tries[1].contains("in stage0"));
+    assert!(!tries[1].contains("<a href"));
+    assert!(index.contains("<summary>Rank 2</summary>"));
+    assert!(tries[2].contains("in shared"));
+    assert!(!tries[2].contains("in stage1"));
+}
+
+#[test]
+fn test_trie_restart_attempts() {
+    let path = Path::new("tests/inputs/restarts.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    let trie = stack_trie_html(index);
+    // Attempts of one compile are a single entry, each linking to itself, with the failed one
+    // struck through; a compile with only a later attempt, and compiles in between, stay apart
+    let expected = concat!(
+        "<div class='stack-trie'><ul>",
+        "<li class='trie-node' data-search=\"/data/users/test/restarts.py:1 in main\">",
+        "/data/users/test/restarts.py:1 in main</li>\n",
+        "<li class='trie-node trie-branch' data-search=\"/data/users/test/restarts.py:2 in restarts ",
+        "[0/0 ×3 attempts] [1/0_1]\">",
+        "<span class='attempts status-error'>[0/0 ×3 attempts: ",
+        "<a href='#0_0_0' class='status-break'>0</a> ",
+        "<a href='#0_0_1' class='status-error'><s>1</s></a> ",
+        "<a href='#0_0_2' class='status-ok'>2</a>]</span> ",
+        "<a href='#1_0_1' class='status-ok'>[1/0_1]</a> ",
+        "/data/users/test/restarts.py:2 in restarts</li>\n",
+        "<li class='trie-node trie-branch' data-search=\"/data/users/test/restarts.py:3 in interleaved ",
+        "[2/0 ×2 attempts] [3/0]\">",
+        "<span class='attempts status-break'>[2/0 ×2 attempts: ",
+        "<a href='#2_0_0' class='status-break'>0</a> ",
+        "<a href='#2_0_1' class='status-ok'>1</a>]</span> ",
+        "<a href='#3_0_0' class='status-ok'>[3/0]</a> ",
+        "/data/users/test/restarts.py:3 in interleaved</li>\n",
+        "<li class='trie-node trie-branch' data-search=\"/data/users/test/restarts.py:4 in no_id (unknown ×2)\">",
+        "(unknown ×2) /data/users/test/restarts.py:4 in no_id</li>\n",
+        "</ul>",
+    );
+    assert_eq!(trie, expected);
+
+    // A compile counts as failed if any of its attempts did
+    let config = tlparse::ParseConfig {
+        trie_failed_only: true,
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("[0/0 ×3 attempts:"));
+    let trie = stack_trie_html(index);
+    assert!(!trie.contains("in interleaved"));
+}
+
+#[test]
+fn test_trie_max_depth() {
+    let path = Path::new("tests/inputs/deep_stack.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // The 60 frame chain is cut in the middle, keeping both ends and the leaves below it
+    assert!(index.contains("<span class='truncated-frames'>… 22 more frames …</span>"));
+    for kept in [
+        "in d0<",
+        "in d18<",
+        "in d41<",
+        "in d59<",
+        "in leaf_a<",
+        "in leaf_b<",
+    ] {
+        assert!(index.contains(kept), "{kept}");
+    }
+    for cut in ["in d19<", "in d40<"] {
+        assert!(!index.contains(cut), "{cut}");
+    }
+    // Only the rendering is cut
+    let json = &map[Path::new("stack_trie.json")];
+    assert!(json.contains("\"d19\""));
+    assert!(json.contains("\"d40\""));
+
+    let config = tlparse::ParseConfig {
+        trie_max_depth: Some(0),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("in d19<"));
+    assert!(!index.contains("more frames"));
+
+    // When cutting chains isn't enough, whatever is below the limit becomes one marker
+    let path = Path::new("tests/inputs/trie_fold.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        trie_max_depth: Some(1),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains(concat!(
+        "<li class='trie-node' data-search=\"/data/users/test/&lt;fold&gt;.py:1 in &lt;module&gt;\">",
+        "/data/users/test/&lt;fold&gt;.py:1 in &lt;module&gt;</li>\n",
+        "<li class='trie-node' data-search=\"… 3 more frames (4 compiles, 5 attempts)\">",
+        "<span class='truncated-frames'>… 3 more frames (4 compiles, 5 attempts)</span></li>\n",
+        "</ul>"
+    )));
+}
+
+#[test]
+fn test_source_snippets() {
+    let path = Path::new("tests/inputs/source_snippets.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        source_root: Some(PathBuf::from("tests/inputs/source_root")),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // Two lines either side, cut short at the start of the file
+    assert!(index.contains(concat!(
+        "<span class='source' title=\" 3 | \n 4 | def forward(x):\n&gt;5 |     y = x.sin()\n",
+        " 6 |     return y.cos()\">mylib/model.py:5 in forward</span>"
+    )));
+    assert!(index.contains("<span class='source' title=\"&gt;1 | def first():\n"));
+    // Lines past the end and files that don't exist just don't get a snippet
+    assert!(index.contains(
+        "<a href='#1_0_0' class='status-missing'>[1/0]</a> mylib/model.py:99 in past_the_end</li>"
+    ));
+    assert!(index.contains(
+        "<a href='#2_0_0' class='status-missing'>[2/0]</a> mylib/missing.py:1 in elsewhere</li>"
+    ));
+
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(!map[Path::new("index.html")].contains("class='source'"));
+}
+
+#[test]
+fn test_condensed_stack_trie() {
+    let path = Path::new("tests/inputs/condensed.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("index.html")].contains("<a href=\"stack_trie_condensed.html\">"));
+    let page = &map[Path::new("stack_trie_condensed.html")];
+    let trie = stack_trie_html(page);
+    // The two stacks through forward only differed in torch and standard library frames, so
+    // they merge; a user package in site-packages stays, and a compile whose frames were all
+    // dropped below main moves up to it.  Compiles link back to the index.
+    let expected = concat!(
+        "<div class='stack-trie'><ul>",
+        "<li class='trie-node' data-search=\"/data/users/test/model.py:1 in main [3/0]\">",
+        "<a href='index.html#3_0_0' class='status-missing'>[3/0]</a> /data/users/test/model.py:1 in main</li>\n",
+        "<li class='trie-node trie-branch' data-search=\"/data/users/test/model.py:2 in forward\">",
+        "<details open><summary>/data/users/test/model.py:2 in forward (2 compiles) ",
+        "<span class='first-seen' title='first compile at 04/01 08:54:21.000008'>08:54:21</span></summary><ul>\n",
+        "<li class='trie-node' data-search=\"/data/users/test/model.py:3 in block [0/0] [1/0]\">",
+        "<a href='index.html#0_0_0' class='status-missing'>[0/0]</a> ",
+        "<a href='index.html#1_0_0' class='status-missing'>[1/0]</a> /data/users/test/model.py:3 in block</li>\n",
+        "</ul></details></li>",
+        "<li class='trie-node trie-branch' data-search=\"mylib/helpers.py:50 in helper [2/0]\">",
+        "<a href='index.html#2_0_0' class='status-missing'>[2/0]</a> mylib/helpers.py:50 in helper</li>\n",
+        "</ul>",
+    );
+    assert_eq!(trie, expected);
+
+    // The full trie still has everything
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("contextlib.py:20 in inner"));
+    assert!(index.contains("[4/0]"));
+}
+
+#[test]
+fn test_by_file() {
+    let path = Path::new("tests/inputs/condensed.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("index.html")].contains("<a href=\"by_file.html\">"));
+    let page = &map[Path::new("by_file.html")];
+    let rows: Vec<&str> = page
+        .lines()
+        .filter(|l| l.starts_with("<tr> <td>"))
+        .collect();
+    // Most compiles first, then by name
+    assert_eq!(rows.len(), 7);
+    assert!(rows[0].starts_with("<tr> <td><code>/data/users/test/model.py</code></td> <td>4</td>"));
+    assert!(rows[1].contains(concat!(
+        "<code>torch/_dynamo/eval_frame.py</code></td> <td>2</td> ",
+        "<td><a href=\"index.html#1_0_0\">[1/0]</a> <a href=\"index.html#4_0_0\">[4/0]</a> </td>"
+    )));
+    assert!(rows[2].contains("<code>/env/lib/python3.10/contextlib.py</code>"));
+    assert!(rows[6].contains("<code>torch/utils/checkpoint.py</code>"));
+
+    // Restarts are listed, but only the compile is counted
+    let path = Path::new("tests/inputs/restarts.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let page = &map[Path::new("by_file.html")];
+    assert!(page.contains("<code>/data/users/test/restarts.py</code></td> <td>4</td>"));
+    assert!(page.contains("<a href=\"index.html#0_0_2\">[0/0_2]</a>"));
+}
+
+#[test]
+fn test_trie_order() {
+    let path = Path::new("tests/inputs/trie_order.log").to_path_buf();
+    let order_of = |trie_order| {
+        let config = tlparse::ParseConfig {
+            trie_order,
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&path, config);
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        let index = map[Path::new("index.html")].clone();
+        let mut found: Vec<(usize, &str)> = ["in a (", "in b (", "in c ("]
+            .into_iter()
+            .map(|f| (index.find(f).unwrap(), f))
+            .collect();
+        found.sort();
+        (found.into_iter().map(|(_, f)| f).collect::<Vec<_>>(), index)
+    };
+    let (order, index) = order_of(tlparse::TrieOrder::Insertion);
+    assert_eq!(order, ["in a (", "in b (", "in c ("]);
+    // Branches show when their earliest compile started, not their first line in the log
+    assert!(index.contains(
+        "in b (2 compiles) <span class='first-seen' title='first compile at 04/01 08:54:21.000000'>08:54:21</span>"
+    ));
+    assert_eq!(
+        order_of(tlparse::TrieOrder::FirstSeen).0,
+        ["in b (", "in c (", "in a ("]
+    );
+    // Ties keep log order
+    assert_eq!(
+        order_of(tlparse::TrieOrder::Compiles).0,
+        ["in b (", "in a (", "in c ("]
+    );
+}
+
+#[test]
+fn test_print_trie() {
+    let dir = scratch_dir("print_trie");
+    let log = Path::new("tests/inputs/trie_fold.log");
+    let out = dir.join("out");
+    let output = run_cli(&[
+        log.as_os_str(),
+        OsStr::new("-o"),
+        out.as_os_str(),
+        OsStr::new("--print-trie"),
+    ]);
+    assert!(output.status.success());
+    // Not a terminal, so no colors and no width limit
+    let expected = concat!(
+        "/data/users/test/<fold>.py:1 in <module>\n",
+        "├── /data/users/test/<fold>.py:2 in <lambda> (3 compiles, 4 attempts)\n",
+        "│   ├── /data/users/test/<fold>.py:3 in a1 (2 compiles, 3 attempts)\n",
+        "│   │   ├── [0/0 ×2 attempts] /data/users/test/<fold>.py:6 in b1\n",
+        "│   │   └── [1/0] /data/users/test/<fold>.py:7 in b2\n",
+        "│   └── [2/0] /data/users/test/<fold>.py:4 in a2\n",
+        "└── [3/0] /data/users/test/<fold>.py:5 in <genexpr>\n",
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_trie_merge_lines() {
+    let path = Path::new("tests/inputs/trie_lines.log").to_path_buf();
+    let render = |trie_merge_lines| {
+        let config = tlparse::ParseConfig {
+            trie_merge_lines,
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&path, config);
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        (
+            map[Path::new("index.html")].clone(),
+            map[Path::new("stack_trie.json")].clone(),
+        )
+    };
+    // By default the two call sites are siblings
+    let (index, _) = render(false);
+    assert!(index.contains("/data/users/test/model.py:121 in forward (2 compiles)"));
+    assert!(index.contains("/data/users/test/model.py:118 in forward (1 compile)"));
+    // Merged, there is a single chain down to the leaf, with the lines in order
+    let (index, json) = render(true);
+    assert!(index.contains("/data/users/test/model.py:118,121 in forward"));
+    assert!(!index.contains("model.py:121 in forward"));
+    assert!(!index.contains("(1 compile)"));
+    assert!(json.contains(r#""line":118,"lines":[118,121],"function":"forward""#));
+}
+
+#[test]
+fn test_trie_compile_time() {
+    let path = Path::new("tests/inputs/trie_time.log").to_path_buf();
+    let render = |trie_order| {
+        let config = tlparse::ParseConfig {
+            trie_order,
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&path, config);
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        map[Path::new("index.html")].clone()
+    };
+    let index = render(tlparse::TrieOrder::Insertion);
+    assert!(index.contains("in b (2 compiles, 214 s)"));
+    // [3/0] has no compilation metrics, so a's time is only a lower bound
+    assert!(index.contains("in a (2 compiles, ≥2.5 s, 1 untimed)"));
+    let index = render(tlparse::TrieOrder::CompileTime);
+    assert!(index.find("in b (").unwrap() < index.find("in a (").unwrap());
+
+    // --trie-sort time is still by first compile; compile time is its own order
+    let dir = scratch_dir("trie_sort");
+    let out = dir.join("out");
+    let index_for = |log: &str, sort: &str| {
+        let result = run_cli(&[
+            OsStr::new(log),
+            OsStr::new("-o"),
+            out.as_os_str(),
+            OsStr::new("--overwrite"),
+            OsStr::new("--trie-sort"),
+            OsStr::new(sort),
+        ]);
+        assert!(result.status.success());
+        fs::read_to_string(out.join("index.html")).unwrap()
+    };
+    let index = index_for("tests/inputs/trie_order.log", "time");
+    assert!(index.find("in b (").unwrap() < index.find("in c (").unwrap());
+    assert!(index.find("in c (").unwrap() < index.find("in a (").unwrap());
+    let index = index_for("tests/inputs/trie_time.log", "compile-time");
+    assert!(index.find("in b (").unwrap() < index.find("in a (").unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_trie_search_box() {
+    let path = Path::new("tests/inputs/trie_fold.log").to_path_buf();
+    let index_for = |plain_text| {
+        let config = tlparse::ParseConfig {
+            plain_text,
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&path, config);
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        map[Path::new("index.html")].clone()
+    };
+    // Hidden until the script shows it, so that it's not a dead input without JS
+    let index = index_for(false);
+    assert!(index.contains("<p class=\"trie-search\" hidden>"));
+    assert!(index.contains(
+        "<li class='trie-node trie-branch' data-search=\"/data/users/test/&lt;fold&gt;.py:4 in a2 [2/0]\">"
+    ));
+    // The plain text trie has no nodes to filter, which the script checks for itself
+    assert!(!index_for(true).contains("class='trie-node"));
+}
+
+#[test]
+fn test_artifact_sizes() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let size = map[Path::new("0_0_0/dynamo_output_graph_0.txt")].len() as u64;
+    let index = &map[Path::new("index.html")];
+    assert!(map[Path::new("0_0_0/compile.html")].contains(&format!(
+        "dynamo_output_graph_0.txt</a> <span class='artifact-size'>{size} B</span>"
+    )));
+    // Neither compile.html nor the highlighted companions of text artifacts are artifacts
+    let artifacts: Vec<(&PathBuf, &String)> = map
+        .iter()
+        .filter(|(p, _)| {
+            p.starts_with("0_0_0")
+                && !p.ends_with("compile.html")
+                && !(p.extension().is_some_and(|e| e == "html")
+                    && map.contains_key(&p.with_extension("txt")))
+        })
+        .collect();
+    let total: u64 = artifacts.iter().map(|(_, out)| out.len() as u64).sum();
+    let files = artifacts.len();
+    assert!(index.contains(&format!(
+        "<a id=\"0_0_0\" href=\"0_0_0/compile.html\">[0/0]</a> ({files} files, {:.1} kB)",
+        total as f64 / 1000.0
+    )));
+    let json: serde_json::Value =
+        serde_json::from_str(&map[Path::new("compile_directory.json")]).unwrap();
+    assert_eq!(json["compiles"][0]["num_files"], files);
+    assert_eq!(json["compiles"][0]["total_size"], total);
+}
+
+#[test]
+fn test_compile_spans() {
+    let path = Path::new("tests/inputs/compile_spans.log").to_path_buf();
+    let render = |compile_span_gap_s| {
+        let config = tlparse::ParseConfig {
+            compile_span_gap_s,
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&path, config);
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        let json: serde_json::Value =
+            serde_json::from_str(&map[Path::new("compile_directory.json")]).unwrap();
+        (map[Path::new("index.html")].clone(), json)
+    };
+    // The metrics logged again ten minutes later are a segment of their own
+    let (index, json) = render(None);
+    assert!(index.contains(">span 2.5 s in 2 segments</span>"));
+    assert!(index.contains(">span 0 ms</span>"));
+    assert_eq!(json["compiles"][0]["span_s"], 2.5);
+    assert_eq!(json["compiles"][0]["span_segments"], 2);
+    let (index, json) = render(Some(3600.0));
+    assert!(index.contains(">span 600 s</span>"));
+    assert_eq!(json["compiles"][0]["span_segments"], 1);
+}
+
+#[test]
+fn test_compile_time_summary() {
+    let path = Path::new("tests/inputs/trie_time.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // [3/0] has no metrics, so its time is its span
+    assert!(index.contains("Compiles: <strong>4</strong>."));
+    assert!(index.contains("mean 54 s, p50 2.5 s, p90 200 s,\nmax 200 s"));
+    assert!(index.contains(
+        "(from compilation metrics for 3 compiles, log timestamps (spans) for the other 1)"
+    ));
+    let start = index.find("<table class=\"histogram\">").unwrap();
+    let histogram = &index[start..start + index[start..].find("</table>").unwrap()];
+    let buckets: Vec<&str> = histogram.split("<tr>").skip(1).collect();
+    assert_eq!(buckets.len(), 5);
+    assert!(buckets[0].contains("<td>&lt; 100 ms</td> <td>1</td>"));
+    assert!(buckets[1].contains("<td>100 ms – 1.0 s</td> <td>0</td>"));
+    assert!(buckets[4].contains("<td>100 s – 1000 s</td> <td>1</td>"));
+
+    // Without any compiles there is nothing to sum up
+    let dir = scratch_dir("compile_time_summary");
+    let log = dir.join("no_compiles.log");
+    fs::write(
+        &log,
+        "V0401 08:54:21.000001 1 torch/_logging/structured.py:19] {\"str\": [\"/a.py\", 0]}\n",
+    )
+    .unwrap();
+    let output = tlparse::parse_path(&log, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(!map[Path::new("index.html")].contains("<h2>Summary</h2>"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_failure_groups() {
+    let path = Path::new("tests/inputs/failure_groups.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let page = &map[Path::new("failures_and_restarts.html")];
+    // The two shape mismatches only differ in shapes and addresses, and come first
+    let expected = concat!(
+        "<tr> <td><pre>RuntimeError</pre></td> ",
+        "<td><pre>shape mismatch: torch.Size([…]) vs torch.Size([…]) for &lt;Tensor at 0x…&gt;</pre></td> ",
+        "<td>2</td> <td><a href='0_0_0/compilation_metrics_0.html'>[0/0]</a> </td> <td>04/01 08:54:21</td> </tr>",
+    );
+    assert!(page.contains(expected));
+    let unsupported = page.find("<tr> <td><pre>Unsupported</pre></td>").unwrap();
+    assert!(page.find(expected).unwrap() < unsupported);
+    assert!(page[unsupported..].contains("<td>1</td>"));
+}
+
+#[test]
+fn test_recompiles() {
+    let path = Path::new("tests/inputs/recompiles.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let recompiles = &map[Path::new("recompiles.html")];
+    // Frame 0 was compiled 8 times, once with a restart, and comes first
+    let rows: Vec<&str> = recompiles
+        .lines()
+        .filter(|l| l.starts_with("<tr> <td>"))
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].starts_with("<tr> <td>0</td> <td><code>model.py:30 in forward</code></td>"));
+    assert!(rows[0].contains("<span class=\"status-error\">⚠ 8</span></td> <td>9</td>"));
+    assert!(rows[0].contains("<a href=\"index.html#0_3_1\">[0/3_1]</a>"));
+    assert!(rows[1].contains("<td>1</td> <td>1</td>"));
+    assert!(!rows[1].contains("⚠"));
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("⚠ 1 frame(s) reached the recompile limit"));
+    assert!(index.contains("<a href=\"recompiles.html\">"));
+}
+
+#[test]
+fn test_compile_phases() {
+    let path = Path::new("tests/inputs/compile_phases.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // Microseconds and seconds fields land in the same phases, and the timing nobody mapped
+    // is kept under its own name
+    assert!(index.contains(
+        "total <strong>12 s</strong> — dynamo 9.0 s, backend 8.0 s, aot 5.5 s, inductor 6.5 s, codegen 500 ms, triton_compile_time_us 2.5 s"
+    ));
+    assert!(!index.contains("start_time_us"));
+    assert!(index.contains("<th>dynamo</th> <th>backend</th> <th>aot</th> <th>inductor</th> <th>codegen</th> <th>triton_compile_time_us</th>"));
+    assert!(index.contains("<td><a href=\"0_1_0/compilation_metrics_1.html\">[0/1]</a> — <code>model.py:30 in forward</code></td> <td>3.0 s</td> <td></td> <td>2.0 s</td> <td></td> <td>1.5 s</td> <td>500 ms</td> <td></td>"));
+    let metrics = &map[Path::new("0_0_0/compilation_metrics_0.html")];
+    assert!(metrics.contains(": dynamo 9.0 s, backend 6.0 s, aot 5.5 s, inductor 5.0 s, triton_compile_time_us 2.5 s</p>"));
+}
+
+#[test]
+fn test_wall_clock_time() {
+    let log = generated_log(&wall_clock_config());
+    let output = tlparse::parse(&log[..], &tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    // [0/0] from 1 to 11 s and [1/0] from 5 to 15 s overlap, so 14 s of compiling, not 20, and
+    // 10 s more of [2/0], which ends the log
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("Wall clock: 24 s of a 40 s log were spent compiling (60%)"));
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    assert_eq!(stats["wall_clock"]["log_span_s"], 40.0);
+    assert_eq!(stats["wall_clock"]["compiling_s"], 24.0);
+    assert_eq!(stats["wall_clock"]["fraction"], 0.6);
+    assert_eq!(stats["ok"], 9);
+}
+
+#[test]
+fn test_largest_artifacts() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let largest = &map[Path::new("largest_artifacts.html")];
+    assert!(largest.contains("The log has 5 payloads, 16.4 kB in all"));
+    let rows: Vec<&str> = largest
+        .lines()
+        .filter(|l| l.starts_with("<tr> <td>"))
+        .collect();
+    assert_eq!(rows.len(), 5);
+    // Largest first, linked to where it was written
+    assert!(rows[0].starts_with("<tr> <td>7.1 kB</td> <td><a href=\"0_0_0/inductor_output_code_"));
+    assert!(rows[0].ends_with("<td><a href=\"index.html#0_0_0\">[0/0]</a></td> <td>75</td> </tr>"));
+    assert!(rows[4].starts_with("<tr> <td>705 B</td>"));
+    assert!(map[Path::new("index.html")].contains("<a href=\"largest_artifacts.html\">"));
+}
+
+#[test]
+fn test_attempt_outcomes() {
+    let render = |log: &str| {
+        let path = Path::new(log).to_path_buf();
+        let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        map
+    };
+    let map = render("tests/inputs/restarts.log");
+    let attempts = &map[Path::new("attempts.html")];
+    assert!(attempts.contains("<tr> <td>[0/0]</td> <td><a href=\"index.html#0_0_0\" class=\"status-break\">0 restarted</a> → <a href=\"index.html#0_0_1\" class=\"status-error\">1 failed</a> → <a href=\"index.html#0_0_2\" class=\"status-ok\">2 ok</a></td> </tr>"));
+    assert!(attempts.contains("<tr> <td>[3/0]</td> <td><a href=\"index.html#3_0_0\" class=\"status-ok\">0 ok</a></td> </tr>"));
+    // Only compiles with several attempts get them in the index, on every attempt
+    let index = &map[Path::new("index.html")];
+    assert_eq!(
+        index
+            .matches("(attempts: 0 restarted → 1 failed → 2 ok)")
+            .count(),
+        3
+    );
+    assert!(!index.contains("(attempts: 0 ok)"));
+    // Metrics without a failure don't outweigh a backward compile that failed
+    let map = render("tests/inputs/attempt_outcomes.log");
+    assert!(map[Path::new("index.html")].contains("(attempts: 0 restarted → 1 failed)"));
+}
+
+#[test]
+fn test_ops_histogram() {
+    let path = Path::new("tests/inputs/ops_histogram.log").to_path_buf();
+    let render = |ops_merge_overloads| {
+        let config = tlparse::ParseConfig {
+            ops_merge_overloads,
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&path, config);
+        let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+        map
+    };
+    let map = render(false);
+    // Method calls are on graph values, submodule calls aren't counted, and the graph that
+    // isn't one is skipped
+    assert_eq!(
+        map[Path::new("ops_histogram.csv")],
+        "op,count,compiles\n\
+         aten.add.Tensor,2,2\n\
+         Tensor.cos,1,1\n\
+         Tensor.sin,1,1\n\
+         aten.add.Scalar,1,1\n\
+         aten.sin.default,1,1\n\
+         operator.getitem,1,1\n\
+         torch.add,1,1\n"
+    );
+    assert!(map[Path::new("ops_by_compile.csv")]
+        .ends_with("[0/0],torch.add,1\n[1/0],aten.add.Tensor,1\n"));
+    let html = &map[Path::new("ops_histogram.html")];
+    assert!(html.contains("in the 3 FX graphs"));
+    assert!(html.contains("1 graphs couldn't be read"));
+    assert!(map[Path::new("index.html")].contains("<a href=\"ops_histogram.html\">"));
+    let map = render(true);
+    assert!(map[Path::new("ops_histogram.csv")].starts_with("op,count,compiles\naten.add,3,2\n"));
+}
+
+#[test]
+fn test_duplicate_graphs() {
+    let path = Path::new("tests/inputs/duplicate_graphs.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let duplicates = &map[Path::new("duplicate_graphs.html")];
+    // Node names, placeholder names, comments and addresses differ, but [0/2] does a mul
+    assert!(duplicates.contains("<h3>3 compiles</h3>\n<p><a href=\"index.html#0_0_0\">[0/0]</a> <a href=\"index.html#0_1_0\">[0/1]</a> <a href=\"index.html#0_3_0\">[0/3]</a> </p>"));
+    assert!(!duplicates.contains("[0/2]"));
+    assert!(duplicates.contains("2 guards are the same in [0/0] and [0/1]."));
+    assert!(duplicates.contains(
+        "<p>Only in [0/0]:</p>\n<ul>\n<li><code>L[&#39;x&#39;].size()[0] == 2</code></li>"
+    ));
+    assert!(duplicates.contains(
+        "<p>Only in [0/1]:</p>\n<ul>\n<li><code>L[&#39;x&#39;].size()[0] == 3</code></li>"
+    ));
+    assert!(map[Path::new("index.html")].contains(
+        "1 groups of compiles captured the same graph; see <a href=\"duplicate_graphs.html\">"
+    ));
+}
+
+#[test]
+fn test_cache_dashboard() {
+    let path = Path::new("tests/inputs/cache_hit_miss.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let cache = &map[Path::new("cache.html")];
+    assert!(cache.contains("1 hits, 2 misses and 0 bypasses make a hit rate\nof 33%."));
+    assert!(cache.contains("<tr> <td>1</td> <td>1</td> <td>2</td> <td>0</td> <td>33%</td> </tr>"));
+    // What the hit logged is 6372999079 ns
+    assert!(cache.contains("The hits themselves logged saving 6.4 s."));
+    assert!(cache.contains("<code>f4lkea5y7lzhlshohvr3aqpd7bchdflfs7j5wn7mrurponawoutk</code>"));
+    assert!(map[Path::new("index.html")].contains("33% of FX graph cache lookups hit"));
+    // No lookups, no dashboard
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(!map.contains_key(Path::new("cache.html")));
+}
+
+#[test]
+fn test_guard_churn() {
+    let path = Path::new("tests/inputs/guard_churn.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let churn = &map[Path::new("guard_churn.html")];
+    // [0/1] has the guards of [0/0] reordered, double quoted and with extra whitespace
+    assert!(churn.contains("[0/1]</a>: 2 unchanged, the same guards</p>"));
+    assert!(churn.contains(
+        "<li><code class=\"guard-churning\">L[&#39;self&#39;].training == True</code> (changed 3 times)</li>"
+    ));
+    assert!(churn.contains(
+        "[0/3]</a>: 2 unchanged</p>\n\n<pre class=\"guard-removed guard-churning\">- L[&#39;self&#39;].training == True</pre>"
+    ));
+    // A guard logged twice is one guard
+    assert!(
+        churn.contains("[0/4]</a>: 2 unchanged</p>\n<pre class=\"guard-added guard-churning\">")
+    );
+    // Frame 1 only compiled once
+    assert!(!churn.contains("Frame 1"));
+    assert!(map[Path::new("index.html")].contains("1 guard(s) kept appearing and disappearing"));
+}
+
+#[test]
+fn test_recompile_reasons() {
+    let path = Path::new("tests/inputs/recompile_reasons.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let recompiles = &map[Path::new("recompiles.html")];
+    // Described in words, with the compile named
+    assert!(recompiles.contains("compile <a href=\"index.html#0_1_0\">[0/1]</a> happened because guard <code>L[&#39;x&#39;].size()[0] == 32</code> from <a href=\"index.html#0_0_0\">[0/0]</a> failed (actual: 48)"));
+    // The guard's code, differently quoted, from the compile before
+    assert!(recompiles.contains("compile <a href=\"index.html#0_2_0\">[0/2]</a> happened because guard <code>L[&#39;x&#39;].size()[0] == 48</code> from <a href=\"index.html#0_1_0\">[0/1]</a> failed\n"));
+    // Nothing to find it in
+    assert!(recompiles.contains("compile <a href=\"index.html#0_3_0\">[0/3]</a> happened because: <code>0/2: the guard about L[&#39;y&#39;] went wrong somehow</code>"));
+}
+
+#[test]
+fn test_compiles_by_source() {
+    let path = Path::new("tests/inputs/source_files.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    // Two compiles of frame 0, the second attempted twice; torch frames aren't user code
+    assert_eq!(
+        map[Path::new("source_files.csv")],
+        "file,package,compiles,frames,compile_time_s\n\
+         teama/models/enc.py,teama,2,1,0.000\n\
+         (no user frame),(no user frame),1,1,0.000\n\
+         teamb/dec.py,teamb,1,1,0.000\n"
+    );
+    assert_eq!(
+        map[Path::new("source_packages.csv")],
+        "package,compiles,frames,compile_time_s\n\
+         teama,2,1,0.000\n\
+         (no user frame),1,1,0.000\n\
+         teamb,1,1,0.000\n"
+    );
+    let html = &map[Path::new("source_files.html")];
+    assert!(html.contains("1 compile(s) had no frame in user code"));
+    assert!(map[Path::new("index.html")].contains("<a href=\"source_files.html\">"));
+}
+
+#[test]
+fn test_top_compiles() {
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        top_compiles: Some(2),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let top = &map[Path::new("top.html")];
+    let first = top.find("[0/0_1]</a> — <code>/data/users/jjwu/a/pytorch/torch/_dynamo/eval_frame.py:410 in _fn</code></td> <td>12 ms</td>").unwrap();
+    let second = top.find("[1/0_1]</a> — <code>/data/users/jjwu/a/pytorch/test/dynamo/test_misc.py:9551 in fn</code></td> <td>7 ms</td>").unwrap();
+    assert!(first < second);
+    assert!(!top.contains("[2/0]"));
+    assert!(top.contains("in fn</code></td> <td>7 ms</td> <td>3</td> <td><a href=\"1_0_1/dynamo_output_graph_3.txt\">"));
+    // For the CLI to print, only when asked for
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    let ids: Vec<_> = stats["top_compiles"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["compile_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["[0/0_1]", "[1/0_1]"]);
+    let result = run_cli(&[
+        OsStr::new("tests/inputs/comp_metrics.log"),
+        OsStr::new("--dry-run"),
+        OsStr::new("--top"),
+        OsStr::new("2"),
+    ]);
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert!(
+        stdout.starts_with("Top compiles:\n     12 ms  [0/0_1] — "),
+        "{stdout}"
+    );
+    // Without metrics, by span
+    let log = generated_log(&tlparse::GenConfig {
+        metrics: false,
+        ..wall_clock_config()
+    });
+    let output = tlparse::parse(&log[..], &tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("top.html")].contains(" <em>span</em></td>"));
+    assert!(!map[Path::new("stats.json")].contains("top_compiles"));
+}
+
+#[test]
+fn test_envelope_kinds() {
+    let path = Path::new("tests/inputs/envelope_kinds.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("<tr> <td><code>dynamo_start</code></td> <td>2</td> </tr>"));
+    assert!(index.contains("<tr> <td><code>str</code></td> <td>1</td> </tr>"));
+    // Kinds that never showed up are listed too
+    assert!(index.contains(
+        "<tr class=\"kind-missing\"> <td><code>inductor_output_code</code></td> <td>0</td> </tr>"
+    ));
+    assert!(index.contains("3 entries were of no known kind, with the fields <code>fancy_new_thing</code> (2), <code>other_new_thing</code> (1)."));
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    let kinds = &stats["envelope_kinds"];
+    assert_eq!(kinds["known"][0]["kind"], "dynamo_start");
+    assert_eq!(kinds["known"][0]["count"], 2);
+    assert_eq!(kinds["unknown"], 3);
+    assert_eq!(kinds["unknown_fields"][0]["kind"], "fancy_new_thing");
+}
+
+#[test]
+fn test_output_totals() {
+    let dir = scratch_dir("output_totals");
+    let log = dir.join("big.log");
+    write_big_payload_log(
+        &log,
+        "dynamo_output_graph",
+        "x = torch.ops.aten.add.Tensor(y, z)",
+        100,
+    );
+    let out = dir.join("out");
+    fs::create_dir(&out).unwrap();
+    let config = tlparse::ParseConfig {
+        output_dir: Some(out.clone()),
+        stream_payload_threshold: Some(1000),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&log, config).unwrap();
+    // The streamed payload and raw.log are already written; dotfiles aren't output
+    fs::write(out.join(".tlparse"), "").unwrap();
+    let raw_size = fs::metadata(&log).unwrap().len();
+    let mut sink = tlparse::DirSink::new(&out);
+    sink.count_existing().unwrap();
+    assert_eq!(sink.totals().files, 2);
+    assert_eq!(sink.totals().bytes, 3599 + raw_size);
+    let expected: u64 = 3599
+        + raw_size
+        + output
+            .files
+            .iter()
+            .map(|(_, c)| c.len() as u64)
+            .sum::<u64>();
+    let num_files = output.files.len() as u64 + 2;
+    tlparse::write_output(output, &mut sink).unwrap();
+    assert_eq!(sink.totals().files, num_files);
+    assert_eq!(sink.totals().bytes, expected);
+    // Each sink counts its own
+    assert_eq!(tlparse::DirSink::new(&out).totals().files, 0);
+    assert_eq!(
+        format!(
+            "{}",
+            tlparse::OutputTotals {
+                files: 4112,
+                bytes: 1_900_000_000
+            }
+        ),
+        "1.9 GB across 4,112 files"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_eager_fallbacks() {
+    let path = Path::new("tests/inputs/eager_fallbacks.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let fallbacks = &map[Path::new("eager_fallbacks.html")];
+    // The recompile limit comes before the skip of its last compile
+    assert!(fallbacks.contains("<td>recompile limit</td> <td><a href=\"index.html#0_7_0\">[0/7]</a></td> <td><pre>compiled 8 times, the limit is 8</pre></td>"));
+    assert!(fallbacks.contains("<td>skipped</td> <td><a href=\"index.html#1_0_0\">[1/0]</a></td> <td><pre>SkipFrame: no content in function call</pre></td>"));
+    assert!(fallbacks.contains("<td>failed</td> <td><a href=\"index.html#2_0_0\">[2/0]</a></td> <td><pre>Unsupported: call_function UserDefinedObjectVariable</pre></td>"));
+    // Compiled fine the second time
+    assert!(!fallbacks.contains("[3/"));
+    // Dynamo said so itself
+    assert!(fallbacks
+        .contains("<td>recompile limit</td> <td><a href=\"index.html#4_0_0\">[4/0]</a></td>"));
+    assert!(map[Path::new("index.html")].contains("4 frame(s) run in eager</span>"));
+}
+
+#[test]
+fn test_dynamic_shapes() {
+    let path = Path::new("tests/inputs/dynamic_shapes.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // s0 and s1 of [0/0], of which s1 was specialized, and s0 of [1/0]
+    assert!(index.contains(
+        "Dynamic shapes: 1 of 3 candidate dimensions were specialized (33%), 2 stayed dynamic"
+    ));
+    assert!(index.contains("<tr> <td><a href=\"0_0_0/compilation_metrics_0.html#specializations\">[0/0]</a></td> <td>1</td> <td>1</td> </tr>"));
+    assert!(index.contains("<tr> <td><a href=\"1_0_0/compilation_metrics_1.html#specializations\">[1/0]</a></td> <td>1</td> <td>0</td> </tr>"));
+    // [2/0] was static
+    assert!(!index.contains("[2/0]</a></td> <td>0</td>"));
+    assert!(index.contains("<tr> <td><code>create_symbol</code></td> <td>3</td> </tr>"));
+    let metrics = &map[Path::new("0_0_0/compilation_metrics_0.html")];
+    assert!(metrics
+        .contains("Dynamic dimensions: 1 of 2, 1 <a href='#specializations'>specialized</a>"));
+    assert!(!map[Path::new("2_0_0/compilation_metrics_2.html")].contains("Dynamic dimensions"));
+}
+
+#[test]
+fn test_timeline() {
+    let path = Path::new("tests/inputs/timeline.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    // Ten minutes in windows of 30 s: the two graph breaks and two recompiles at startup, and
+    // one recompile nine minutes in
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("class=\"sparkline\">█▄▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▄▁▁</a>"));
+    assert!(index.contains("1 happened in the second half of the log</span>"));
+    let timeline = &map[Path::new("timeline.html")];
+    assert!(timeline.contains("<tr> <td>30 s</td> <td>0</td> <td>1</td> <td><div class=\"histogram-bar\" style=\"width: 33%\"></div></td> </tr>"));
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    assert_eq!(stats["timeline"]["bucket_s"], 30.0);
+    assert_eq!(stats["timeline"]["buckets"].as_array().unwrap().len(), 20);
+    assert_eq!(stats["timeline"]["buckets"][0]["graph_breaks"], 2);
+    assert_eq!(stats["timeline"]["late_events"], 1);
+
+    let config = tlparse::ParseConfig {
+        timeline_bucket_s: Some(120.0),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    assert_eq!(stats["timeline"]["buckets"].as_array().unwrap().len(), 5);
+    assert_eq!(stats["timeline"]["buckets"][0]["recompiles"], 2);
+
+    // A log all at startup has no timeline
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(!map[Path::new("index.html")].contains("second half of the log"));
+}
+
+#[test]
+fn test_recompile_loops() {
+    let index = |gen: &tlparse::GenConfig, config: tlparse::ParseConfig| {
+        let output = tlparse::parse(&generated_log(gen)[..], &config).unwrap();
+        let (_, index) = output
+            .files
+            .into_iter()
+            .find(|(p, _)| p == Path::new("index.html"))
+            .unwrap();
+        index
+    };
+    // Frame 0 flips between two sets of guards in the last ten minutes of a half-hour log
+    let looping = tlparse::GenConfig {
+        frames: 1,
+        recompiles: 5,
+        artifacts: vec!["dynamo_guards".to_string()],
+        payload_lines: 3,
+        compile_times: vec![0.0, 1260.0, 1380.0, 1500.0, 1620.0, 1740.0],
+        guard_variants: 2,
+        ..Default::default()
+    };
+    let page = index(&looping, tlparse::ParseConfig::default());
+    assert!(page.contains("<h2>Recompile loops</h2>"));
+    assert!(page.contains("<li><span class=\"status-error\">⚠ Frame 0</span> <code>/workspace/model.py:20 in forward_0</code>: 5 recompiles (<a href='#0_1_0'>[0/1]</a> <a href='#0_2_0'>[0/2]</a> <a href='#0_3_0'>[0/3]</a> <a href='#0_4_0'>[0/4]</a> <a href='#0_5_0'>[0/5]</a>), <code>L[&#39;x&#39;].size()[0] == 9</code> came and went 4 times</li>"));
+    let config = tlparse::ParseConfig {
+        recompile_loop_min_compiles: Some(6),
+        ..Default::default()
+    };
+    assert!(!index(&looping, config).contains("Recompile loops"));
+    // Recompiling as late, but with the same guards every time
+    let same_guards = tlparse::GenConfig {
+        guard_variants: 1,
+        ..looping
+    };
+    assert!(!index(&same_guards, tlparse::ParseConfig::default()).contains("Recompile loops"));
+
+    // The same flipping, but all while warming up
+    let warmup = tlparse::GenConfig {
+        frames: 1,
+        recompiles: 5,
+        artifacts: vec!["dynamo_guards".to_string()],
+        payload_lines: 3,
+        compile_times: vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0],
+        guard_variants: 2,
+        log_s: Some(300.0),
+        ..Default::default()
+    };
+    assert!(!index(&warmup, tlparse::ParseConfig::default()).contains("Recompile loops"));
+    let config = tlparse::ParseConfig {
+        recompile_loop_window_s: Some(1.0),
+        ..Default::default()
+    };
+    assert!(!index(&warmup, config).contains("Recompile loops"));
+}
+
+#[test]
+fn test_missing_artifacts() {
+    let path = Path::new("tests/inputs/missing_artifacts.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    let note = |compile: &str| {
+        let entry = &index[index.find(&format!("<a id=\"{compile}\"")).unwrap()..];
+        let entry = &entry[..entry.find("</li>").unwrap()];
+        entry
+            .split("<span class=\"badge status-missing\">")
+            .nth(1)
+            .map(|s| s[..s.find("</span>").unwrap()].to_string())
+    };
+    assert!(index.contains("2 compile(s) are missing artifacts</span>"));
+    assert_eq!(note("0_0_0"), None);
+    assert_eq!(note("1_0_0").unwrap(), "no inductor_output_code recorded");
+    // The eager backend never gets to AOT autograd
+    assert_eq!(note("2_0_0"), None);
+    assert_eq!(note("3_0_0").unwrap(), "no aot_forward_graph or aot_inference_graph or aot_joint_graph recorded, no inductor_output_code recorded");
+    // AOT autograd's cache had it
+    assert_eq!(note("4_0_0"), None);
+    // Failed, so nothing is expected of it
+    assert_eq!(note("5_0_0"), None);
+
+    let config = tlparse::ParseConfig {
+        artifact_rules: Some(vec!["dynamo_output_graph => aot_forward_graph".to_string()]),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("index.html")].contains("3 compile(s) are missing artifacts</span>"));
+
+    let config = tlparse::ParseConfig {
+        artifact_rules: Some(vec![]),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config);
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(!map[Path::new("index.html")].contains("missing artifacts"));
+
+    let config = tlparse::ParseConfig {
+        artifact_rules: Some(vec!["inductor_output_code".to_string()]),
+        ..Default::default()
+    };
+    assert!(tlparse::parse_path(&path, config).is_err());
+}
+
+#[test]
+fn test_compiles_per_rank() {
+    let path = Path::new("tests/inputs/rank_divergence.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("index.html")]
+        .contains("<span class=\"status-error\">the ranks disagree on 2 compile(s)</span>"));
+    let ranks = &map[Path::new("ranks.html")];
+    assert!(ranks.contains("<tr> <td><a href=\"index.html#0_0_0\">[0/0]</a></td> <td>✓ 2.0 s</td> <td>✓ 2.5 s</td> </tr>"));
+    // Only rank 1 recompiled, so there's nothing of it to link to
+    assert!(ranks
+        .contains("<tr class=\"rank-divergent\"> <td>[0/1]</td> <td></td> <td>✓ 1.0 s</td> </tr>"));
+    assert!(ranks.contains("<tr class=\"rank-divergent\"> <td><a href=\"index.html#1_0_0\">[1/0]</a></td> <td>✓ 2 attempts, 1.0 s</td> <td>✓ 500 ms</td> </tr>"));
+
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(!map.contains_key(Path::new("ranks.html")));
+}
+
+#[test]
+fn test_compile_pages() {
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // The index links to the compile's page instead of listing its files
+    assert!(index.contains("<a id=\"0_0_1\" href=\"0_0_1/compile.html\">[0/0_1]</a>"));
+    assert!(!index.contains("0_0_1/dynamo_output_graph"));
+    let page = &map[Path::new("0_0_1/compile.html")];
+    assert!(page.contains("<span class=\"status-ok\">ok</span>"));
+    assert!(page.contains("<th>Compile time</th> <td>12 ms</td>"));
+    assert!(page.contains("<th>Guards</th> <td>9</td>"));
+    assert!(page.contains("<th>Graph nodes</th> <td>3</td>"));
+    assert!(page.contains("eval_frame.py:410 in _fn</code>"));
+    assert!(page.contains("skip function graph_break"));
+    // Artifacts are grouped, and linked relative to the page
+    let dynamo = page.find("<h4>Dynamo</h4>").unwrap();
+    let metrics = page.find("<h4>Metrics</h4>").unwrap();
+    assert!(dynamo < metrics);
+    assert!(page[dynamo..metrics].contains("href=\"../0_0_1/dynamo_output_graph_"));
+    assert!(page[metrics..].contains("href=\"../0_0_1/compilation_metrics_"));
+}
+
+#[test]
+fn test_highlighted_artifacts() {
+    let dir = scratch_dir("highlighted_artifacts");
+    let log = dir.join("graph.log");
+    write_big_payload_log(
+        &log,
+        "dynamo_output_graph",
+        "lt = torch.ops.aten.lt.Tensor(a, b)  # a < b </pre>",
+        3,
+    );
+    let render = |highlight_max_bytes, plain_text| {
+        let config = tlparse::ParseConfig {
+            highlight_max_bytes,
+            plain_text,
+            ..Default::default()
+        };
+        let output = tlparse::parse_path(&log, config).unwrap();
+        output
+            .files
+            .into_iter()
+            .collect::<HashMap<PathBuf, String>>()
+    };
+    let map = render(None, false);
+    let page = &map[Path::new("0_0_0/dynamo_output_graph_0.html")];
+    // Every line numbered and anchored, the payload escaped
+    assert!(page.contains("<span class=\"line\" id=\"L3\"><a class=\"lineno\" href=\"#L3\">3</a>"));
+    assert!(!page.contains("id=\"L4\""));
+    assert!(page.contains("<span style="));
+    assert!(page.contains("&lt;/pre&gt;"));
+    assert!(!page.contains("a < b"));
+    assert!(map[Path::new("0_0_0/compile.html")]
+        .contains("<a href=\"../0_0_0/dynamo_output_graph_0.html\">highlighted</a>"));
+    // Too big to highlight: still numbered, but plain
+    let map = render(Some(10), false);
+    let page = &map[Path::new("0_0_0/dynamo_output_graph_0.html")];
+    assert!(page.contains("id=\"L3\""));
+    assert!(!page.contains("<span style="));
+    assert!(page.contains("a &lt; b &lt;/pre&gt;"));
+    // No HTML at all in plain text mode
+    let map = render(None, true);
+    assert!(!map.contains_key(Path::new("0_0_0/dynamo_output_graph_0.html")));
+}
+
+#[test]
+fn test_breadcrumbs() {
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let bar = |page: &str| {
+        let html = &map[Path::new(page)];
+        let bar = &html[html.find("<div class=\"breadcrumbs\"").unwrap()..];
+        bar[..bar.find("</div>").unwrap()].to_string()
+    };
+    // Every HTML page has one
+    for (p, html) in &map {
+        if p.extension().is_some_and(|e| e == "html") {
+            assert!(html.contains("<div class=\"breadcrumbs\""), "{p:?}");
+        }
+    }
+    let index = bar("index.html");
+    assert!(index.contains("<a href=\"index.html\">tlparse report</a>"));
+    assert!(!index.contains("→"));
+    // Links from a compile's directory go a level up; 1_0_0 has no artifacts, so no page
+    let graph = bar("1_0_1/dynamo_output_graph_3.html");
+    assert!(graph.contains("<a href=\"../index.html\">tlparse report</a>"));
+    assert!(graph.contains("<a href=\"../1_0_1/compile.html\">[1/0_1]</a>"));
+    assert!(graph.contains("<a href=\"../index.html#1_0_0\">← [1/0]</a>"));
+    assert!(graph.contains("<a href=\"../2_0_0/compile.html\">[2/0] →</a>"));
+    // The sizes in the index are of the pages as written, bar included
+    let json: serde_json::Value =
+        serde_json::from_str(&map[Path::new("compile_directory.json")]).unwrap();
+    let guards = json["compiles"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|c| c["files"].as_array().unwrap())
+        .find(|f| f["url"] == "1_0_1/dynamo_guards_4.html")
+        .unwrap();
+    assert_eq!(
+        guards["size"],
+        map[Path::new("1_0_1/dynamo_guards_4.html")].len()
+    );
+}
+
+#[test]
+fn test_compile_search() {
+    let path = Path::new("tests/inputs/comp_failure.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // Only shown once the script runs, so the list is all there without JS
+    assert!(index.contains("<p class=\"compile-search\" hidden>"));
+    assert!(index.contains("<ul class=\"compile-list\">"));
+    let start = index.find("<li data-search=\"[0/0] ").unwrap();
+    let search = &index[start..start + index[start..].find("\">").unwrap()];
+    // Lowercased, for a case-insensitive match
+    assert!(search.contains("eval_frame.py:410 in _fn"));
+    assert!(search.contains("dynamo_output_graph_0.txt"));
+    assert!(search.contains("backendcompilerfailed"));
+    assert!(search.contains("broken_backend"));
+    assert!(!search.contains("BackendCompilerFailed"));
+}
+
+#[test]
+fn test_compile_badges() {
+    let path = Path::new("tests/inputs/badges.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    let entry = |compile: &str| {
+        let entry = &index[..index.find(&format!("<a id=\"{compile}\"")).unwrap()];
+        let entry = &index[entry.rfind("<li ").unwrap()..];
+        entry[..entry.find("</li>").unwrap()].to_string()
+    };
+    let clean = entry("0_0_0");
+    assert!(!clean.contains("data-severity"));
+    assert!(!clean.contains("class=\"badge"));
+    let restarted = entry("1_0_0");
+    assert!(restarted.contains("data-severity=\"warning\""));
+    assert!(restarted.contains("<span class=\"badge status-break\">restarted</span>"));
+    let failed = entry("1_0_1");
+    assert!(failed.contains("data-severity=\"failure\""));
+    assert!(failed.contains("<span class=\"badge status-error\">failed</span>"));
+    assert!(entry("2_0_0").contains("<span class=\"badge badge-warning\">MD5 mismatch</span>"));
+    assert!(entry("3_0_0").contains("<span class=\"badge badge-warning\">truncated payload</span>"));
+    // The filter only shows up with JS
+    assert!(index.contains("<p class=\"compile-filter\" hidden>"));
+}
+
+#[test]
+fn test_guards_table() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let page = &map[Path::new("0_0_0/dynamo_guards_4.html")];
+    // One option per type, most common first
+    assert!(page.contains("<option value=\"\">all 6 guards</option>\n<option value=\"ID_MATCH\">ID_MATCH (2)</option>"));
+    assert!(page.contains("<tr data-kind=\"HASATTR\"> <td>1</td> <td>HASATTR</td> <td><code>hasattr(L[&#39;x&#39;], &#39;_dynamo_dynamic_indices&#39;) == False</code></td> <td></td> </tr>"));
+    // Where the guard was made, and long guards fold until clicked
+    assert!(page.contains("<td><code>/data/users/jjwu/a/pytorch/torch/_dynamo/output_graph.py:467 in init_ambient_guards</code></td>"));
+    assert!(page.contains("<tr data-kind=\"TENSOR_MATCH\"> <td>6</td> <td>TENSOR_MATCH</td> <td><details><summary><code>check_tensor(L[&#39;x&#39;], Tensor,"));
+    assert!(page.contains("device=0…</code></summary>"));
+    assert!(page.contains("<p class=\"guard-filter\" hidden>"));
+}
+
+#[test]
+fn test_attempt_diff() {
+    let path = Path::new("tests/inputs/attempt_diff.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let page = &map[Path::new("diff_0_0_0_vs_0_0_1.html")];
+    assert!(page.contains("dynamo_output_graph.txt</a>: changed (+2 −2)"));
+    assert!(page.contains("guards</a>: changed (+1 −1)"));
+    assert!(page.contains("aot_forward_graph.txt</a>: only in [0/0_1] (+2 −0)"));
+    assert!(page.contains(
+        "<tr class=\"diff-removed\"><td class=\"lineno\">9</td><td class=\"lineno\"></td><td><pre>        s = t.exp()</pre></td></tr>"
+    ));
+    assert!(page.contains(
+        "<tr class=\"diff-added\"><td class=\"lineno\"></td><td class=\"lineno\">9</td><td><pre>        s = t.tanh()</pre></td></tr>"
+    ));
+    // Unchanged lines away from the changes are left out
+    assert!(page.contains("⋯ 5 unchanged line(s)"));
+    assert!(!page.contains("y = x.sin()"));
+    assert!(
+        map[Path::new("attempts.html")].contains("(<a href=\"diff_0_0_0_vs_0_0_1.html\">diff</a>)")
+    );
+    assert!(map[Path::new("0_0_1/compile.html")]
+        .contains("<a href='../diff_0_0_0_vs_0_0_1.html'>what changed since [0/0]</a>"));
+    assert!(!map[Path::new("0_0_0/compile.html")].contains("what changed since"));
+}
+
+#[test]
+fn test_diff_compile() {
+    let dir = scratch_dir("diff_compile");
+    let out = dir.join("out");
+    let parsed = run_cli(&[
+        OsStr::new("tests/inputs/compile_diff.log"),
+        OsStr::new("-o"),
+        out.as_os_str(),
+    ]);
+    assert!(parsed.status.success());
+    let diff = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_tlparse"))
+            .arg("diff-compile")
+            .arg(&out)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let normalized = diff(&["2/0", "[2/1]"]);
+    assert!(normalized.status.success());
+    let text = String::from_utf8_lossy(&normalized.stdout);
+    // Renamed nodes and requoted guards aren't changes once normalized
+    assert!(text.contains("--- [2/0] dynamo_output_graph.txt\n+++ [2/1] dynamo_output_graph.txt\n"));
+    assert!(text.contains("         n2 = n1.sin()\n"));
+    assert!(text.contains("+        n4 = n3 * 2\n"));
+    assert!(text.contains(
+        " ___check_type_id(L['x'], 94)\n-___check_tensor_size(L['x'], 8)\n+2 <= L['x'].size()[0]\n"
+    ));
+    let page = fs::read_to_string(out.join("diff_2_0_0_vs_2_1_0.html")).unwrap();
+    assert!(page.contains("dynamo_output_graph.txt</a>: changed (+3 −2)"));
+    assert!(page.contains("Graphs and guards are normalized first"));
+    let raw = diff(&["2/0", "2/1", "--no-normalize"]);
+    let text = String::from_utf8_lossy(&raw.stdout);
+    assert!(text.contains("-        sin = l_x_.sin()\n"));
+    assert!(text.contains("+        sin_1 = l_x_.sin()\n"));
+    assert!(text.contains("+___check_type_id(L[\"x\"],  94)\n"));
+    let missing = diff(&["2/0", "2/5"]);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("No compile [2/5]"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_dashboard() {
+    let path = Path::new("tests/inputs/cache_hit_miss.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("index.html")].contains("<a href=\"dashboard.html\">Dashboard</a>"));
+    let dashboard = &map[Path::new("dashboard.html")];
+    // Inline SVG, nothing to fetch
+    assert_eq!(dashboard.matches("<svg class=\"chart\"").count(), 3);
+    assert!(!dashboard.contains("<script src"));
+    assert!(dashboard.contains("<title>100 ms – 1.0 s: 1</title>"));
+    assert!(dashboard.contains("How many of the 2 compile(s) had started"));
+    assert!(dashboard.contains("<title>hits: 1</title>"));
+    assert!(dashboard.contains("<title>misses: 2</title>"));
+    assert!(dashboard.contains("No graph breaks."));
+    assert!(dashboard.contains("No failures."));
+
+    let path = Path::new("tests/inputs/comp_failure.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let dashboard = &map[Path::new("dashboard.html")];
+    assert!(dashboard.contains("1 failure(s), by type"));
+    assert!(dashboard
+        .contains("<title>&lt;class 'torch._dynamo.exc.BackendCompilerFailed'&gt;: 1</title>"));
+    assert!(dashboard.contains("No compile looked up the FX graph cache."));
+}
+
+#[test]
+fn test_keep_raw() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        keep_raw: true,
+        ..Default::default()
+    };
+    let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    let raw = &map[Path::new("0_0_0/raw.log")];
+    let log = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    // Headers as they were, the start of their payloads, and what was left out
+    assert!(raw.starts_with(&format!("{}\n", lines[11])));
+    assert!(raw.contains(&format!(
+        "{}\n{}\n{}\n{}\n",
+        lines[12], lines[13], lines[14], lines[15]
+    )));
+    assert!(raw.contains("[11 more payload lines of the entry at line 13 of the log]\n"));
+    assert!(raw.lines().count() < 8 * 5);
+    assert_eq!(raw.lines().filter(|l| l.starts_with('V')).count(), 8);
+    assert!(map[Path::new("0_0_0/compile.html")]
+        .contains("<a href='raw.log'>raw.log</a> (8 entries, lines 12 to 258 of the log)"));
+
+    // Not without --keep-raw
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default()).unwrap();
+    assert!(!output
+        .files
+        .iter()
+        .any(|(p, _)| p.ends_with("raw.log") && p.parent() != Some(Path::new(""))));
+}
+
+#[test]
+fn test_dark_mode() {
+    let path = Path::new("tests/inputs/comp_failure.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    // Every page, whatever its own stylesheet, follows the system setting and has the switch
+    for (path, html) in map
+        .iter()
+        .filter(|(p, _)| p.extension().is_some_and(|e| e == "html"))
+    {
+        assert!(
+            html.contains("@media (prefers-color-scheme: dark) {"),
+            "{}",
+            path.display()
+        );
+        assert!(html.contains(":root:not(.light-mode) body { background-color: #1e1e1e;"));
+        assert!(html.contains(":root.dark-mode .diff-added { background-color: #1f3d1f; }"));
+        assert!(html.contains(
+            "<label class=\"dark-mode-toggle\" style=\"float: right;\" hidden><input type=\"checkbox\"> Dark mode</label>"
+        ));
+        assert!(html.contains("localStorage.setItem(\"tlparse-dark-mode\""));
+    }
+    let index = &map[Path::new("index.html")];
+    assert_eq!(index.matches("prefers-color-scheme: dark)").count(), 2);
+}
+
+#[test]
+fn test_provenance() {
+    let path = Path::new("tests/inputs/comp_failure.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        rank: Some(0),
+        provenance: Some(vec!["--rank".to_string(), "0".to_string()]),
+        ..Default::default()
+    };
+    let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    let size = fs::metadata(&path).unwrap().len();
+    let footer =
+        format!("from <code>tests/inputs/comp_failure.log</code> ({size} bytes, modified ");
+    for (p, html) in map
+        .iter()
+        .filter(|(p, _)| p.extension().is_some_and(|e| e == "html"))
+    {
+        assert!(html.contains(&footer), "{}", p.display());
+        assert!(html.contains(", rank 0 (selected), with <code>--rank 0</code>\n</div>\n"));
+        assert!(html.contains(&format!(
+            "Generated by tlparse {}",
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+    assert!(map[Path::new("index.html")].contains("</code>\n</div>\n</body>"));
+    for json in ["stats.json", "compile_directory.json"] {
+        let json: serde_json::Value = serde_json::from_str(&map[Path::new(json)]).unwrap();
+        let provenance = &json["provenance"];
+        assert_eq!(provenance["tlparse_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(provenance["input_size"], size);
+        assert_eq!(provenance["rank"], 0);
+        assert_eq!(provenance["rank_source"], "selected");
+        assert_eq!(provenance["flags"], serde_json::json!(["--rank", "0"]));
+    }
+
+    // Off by default, so that reports only change when their logs do
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default()).unwrap();
+    assert!(output
+        .files
+        .iter()
+        .all(|(_, out)| !out.contains("class=\"provenance\"") && !out.contains("\"provenance\":")));
+}
+
+#[test]
+fn test_index_grouped_by_frame() {
+    let path = Path::new("tests/inputs/recompiles.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    // Frames in the order they first compiled, labeled by their innermost user frame
+    let frame_1 = index
+        .find("<li class=\"compile-group\" id=\"frame-1\"><details><summary>Frame 1 <code>train.py:9 in step</code>")
+        .unwrap();
+    let frame_0 = index
+        .find("<li class=\"compile-group\" id=\"frame-0\"><details><summary>Frame 0 <code>model.py:30 in forward</code>\n    <span class=\"compile-group-counts\">(8 compile(s), 9 attempt(s))</span></summary>")
+        .unwrap();
+    assert!(frame_1 < frame_0);
+    // Each compile of a frame holds its attempts, all folded
+    let compile = index
+        .find("<li class=\"compile-group\"><details><summary>[0/3] — <code>model.py:30 in forward</code>\n        <span class=\"compile-group-counts\">(2 attempt(s))</span></summary>")
+        .unwrap();
+    let attempts: Vec<usize> = ["id=\"0_3_0\"", "id=\"0_3_1\"", "[0/4]"]
+        .iter()
+        .map(|s| index[compile..].find(s).unwrap())
+        .collect();
+    assert!(attempts.windows(2).all(|w| w[0] < w[1]));
+    assert!(!index.contains("<details open><summary>Frame"));
+    assert!(!index.contains("<details open><summary>[0/"));
+    // Compiles that logged no stack say so
+    let path = Path::new("tests/inputs/duplicate_graphs.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(
+        map[Path::new("index.html")].contains("<details><summary>[0/0] — <code>(no stack)</code>")
+    );
+}
+
+#[test]
+fn test_index_shards() {
+    let path = Path::new("tests/inputs/recompiles.log").to_path_buf();
+    let render = |index_shard_size| {
+        let config = tlparse::ParseConfig {
+            index_shard_size,
+            ..Default::default()
+        };
+        let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+            .unwrap()
+            .files
+            .into_iter()
+            .collect();
+        map
+    };
+    // Small enough for one page
+    let map = render(None);
+    assert!(map[Path::new("index.html")].contains("<a id=\"0_3_1\""));
+    assert!(!map.contains_key(Path::new("index_000.html")));
+
+    // Frames stay whole, so frame 0's 9 attempts get a page of their own
+    let map = render(Some(4));
+    let index = &map[Path::new("index.html")];
+    assert!(!index.contains("<a id=\"0_3_1\""));
+    assert!(index.contains("<li><a href=\"index_000.html\" data-frames=\"1\">frame 1</a> <span class=\"compile-group-counts\">(1 frame(s), 1 compile(s), 1 attempt(s))</span></li>"));
+    assert!(index.contains("<li><a href=\"index_001.html\" data-frames=\"0\">frame 0</a> <span class=\"compile-group-counts\">(1 frame(s), 8 compile(s), 9 attempt(s))</span></li>"));
+    assert!(index.contains("location.replace(link.getAttribute('href') + location.hash)"));
+    let first = &map[Path::new("index_000.html")];
+    assert!(first.contains("<h2>Compiles of frame 1</h2>"));
+    assert!(first.contains("<a id=\"1_0_0\""));
+    assert!(first.contains("<a href=\"index_001.html\">next frames →</a>"));
+    let second = &map[Path::new("index_001.html")];
+    assert!(second.contains("<a id=\"0_3_1\""));
+    assert!(second.contains("<a href=\"index_000.html\">← previous frames</a>"));
+    assert!(!second.contains("next frames"));
+    assert!(!map.contains_key(Path::new("index_002.html")));
+    // compile_directory.json still has everything
+    let json: serde_json::Value =
+        serde_json::from_str(&map[Path::new("compile_directory.json")]).unwrap();
+    assert_eq!(json["compiles"].as_array().unwrap().len(), 10);
+
+    // Or never split
+    let map = render(Some(0));
+    assert!(map[Path::new("index.html")].contains("<a id=\"0_3_1\""));
+}
+
+#[test]
+fn test_artifact_previews() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let render = |no_previews| {
+        let config = tlparse::ParseConfig {
+            no_previews,
+            ..Default::default()
+        };
+        let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+            .unwrap()
+            .files
+            .into_iter()
+            .collect();
+        map
+    };
+    let map = render(false);
+    let page = &map[Path::new("0_0_0/compile.html")];
+    // The first lines of text artifacts, escaped, and nothing of pages
+    let graph = map[Path::new("0_0_0/dynamo_output_graph_0.txt")]
+        .lines()
+        .take(5)
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert!(page.contains(&format!(
+        "<details class=\"artifact-preview\"><summary>preview</summary><pre>{}\n…</pre></details>",
+        graph.trim_end()
+    )));
+    assert!(page.contains("<pre>class &lt;lambda&gt;(torch.nn.Module):\n"));
+    let guards = page.find("dynamo_guards_4.html</a>").unwrap();
+    assert!(page[guards..].starts_with(&format!(
+        "dynamo_guards_4.html</a> <span class='artifact-size'>{:.1} kB</span>  (4)\n</li>",
+        map[Path::new("0_0_0/dynamo_guards_4.html")].len() as f64 / 1000.0
+    )));
+    assert!(!map[Path::new("compile_directory.json")].contains("preview"));
+
+    let map = render(true);
+    assert!(!map[Path::new("0_0_0/compile.html")].contains("<details class=\"artifact-preview\">"));
+}
+
+#[test]
+fn test_json_artifacts() {
+    let path = Path::new("tests/inputs/json_artifacts.log").to_path_buf();
+    let render = |config: tlparse::ParseConfig| {
+        let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+            .unwrap()
+            .files
+            .into_iter()
+            .collect();
+        map
+    };
+    let map = render(tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    });
+    // A one-line JSON string payload comes out pretty-printed, as a tree too
+    let pretty = &map[Path::new("0_0_0/provenance_map_0.json")];
+    assert!(pretty.starts_with("{\n  \"nodes\": {\n"));
+    let tree = &map[Path::new("0_0_0/provenance_map_0.html")];
+    assert!(tree.contains("<ul class=\"json-tree\"><li><details open><summary><span class=\"json-count\">{ 2 key(s) }</span></summary>"));
+    assert!(tree.contains("<li><details><summary><span class=\"json-key\">add_key_to_find</span>: <span class=\"json-count\">[ 4 item(s) ]</span></summary>"));
+    assert!(tree.contains("<li><span class=\"json-key\">source</span>: <span class=\"json-string\">\"model.py:3\"</span></li>"));
+    assert!(tree.contains("<span class=\"json-literal\">null</span>"));
+    assert!(map[Path::new("0_0_0/compile.html")]
+        .contains("<a href=\"../0_0_0/provenance_map_0.html\">highlighted</a>"));
+    assert!(map.contains_key(Path::new("0_0_0/cache_key_3.html")));
+    // What only looks like JSON, or says it is but doesn't parse, is left as it was
+    assert_eq!(
+        map[Path::new("0_0_0/not_json_1.txt")],
+        "{this starts like JSON but isn't\n}"
+    );
+    assert_eq!(
+        map[Path::new("0_0_0/broken_json_2.txt")],
+        "{\"truncated\": [1, 2"
+    );
+    assert!(!map.contains_key(Path::new("0_0_0/not_json_1.json")));
+
+    let map = render(tlparse::ParseConfig {
+        json_keep_raw: true,
+        ..Default::default()
+    });
+    assert_eq!(
+        map[Path::new("0_0_0/provenance_map_raw_1.txt")],
+        fs::read_to_string(&path).unwrap().lines().nth(3).unwrap()[1..]
+    );
+    assert!(map.contains_key(Path::new("0_0_0/provenance_map_0.json")));
+}
+
+#[test]
+fn test_line_anchors() {
+    // Output code and dump_file sources number and anchor their lines like the FX graphs
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    let (_, code) = map
+        .iter()
+        .find(|(f, _)| {
+            let f = f.to_string_lossy();
+            f.contains("inductor_output_code") && f.ends_with(".html")
+        })
+        .unwrap();
+    assert!(code.contains("<span class=\"line\" id=\"L1\"><a class=\"lineno\" href=\"#L1\">1</a>"));
+
+    let path = Path::new("tests/inputs/duplicate_dump_file.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    let source = &map[Path::new("dump_file/eval_with_key_3.html")];
+    assert!(
+        source.contains("<span class=\"line\" id=\"L2\"><a class=\"lineno\" href=\"#L2\">2</a>")
+    );
+}
+
+#[test]
+fn test_data_issues() {
+    let path = Path::new("tests/inputs/data_issues.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    // Everything needed to report it, on the compile's page
+    let compile = &map[Path::new("0_0_0/compile.html")];
+    assert!(compile.contains("<div class=\"data-issues\">"));
+    assert!(compile.contains("<li>Line 1: the payload of dynamo_output_graph has MD5 7505265f6a12c108ea0b68a8f9c69b62, but its header says 00000000000000000000000000000000</li>"));
+    assert!(
+        compile.contains("<li>Line 4: optimize_ddp_split_child has unknown fields new_field</li>")
+    );
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("<span class=\"badge badge-warning\">MD5 mismatch</span>"));
+    assert!(index.contains("<span class=\"badge badge-warning\">unknown fields</span>"));
+    let json: serde_json::Value =
+        serde_json::from_str(&map[Path::new("compile_directory.json")]).unwrap();
+    assert_eq!(
+        json["compiles"][0]["issues"][0],
+        serde_json::json!({
+            "issue": "md5_mismatch",
+            "line": 1,
+            "kind": "dynamo_output_graph",
+            "expected": "00000000000000000000000000000000",
+            "actual": "7505265f6a12c108ea0b68a8f9c69b62",
+        })
+    );
+
+    // Truncated payloads and artifacts written under another name
+    let path = Path::new("tests/inputs/missing_payload.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("0_0_0/compile.html")].contains("<li>Line 1: dynamo_output_graph declares a payload (MD5 f2c5ec1e46ebc0d04b2c78ef646fdd12), but none follows</li>"));
+    assert!(!map[Path::new("1_0_0/compile.html")].contains("data-issues\""));
+    let path = Path::new("tests/inputs/duplicate_dump_file.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map[Path::new("0_0_0/compile.html")].contains("<li>Line 4: dump_file/eval_with_key_3.html was already written, so this one is dump_file/eval_with_key_3_1.html</li>"));
+    assert!(map[Path::new("index.html")]
+        .contains("<span class=\"badge badge-warning\">duplicate artifact</span>"));
+}
+
+#[test]
+fn test_parse_reader() {
+    // A log parsed from memory comes out the same as from its file
+    let log: &[u8] = include_bytes!("inputs/simple.log");
+    let config = tlparse::ParseConfig {
+        strict: true,
+        ..Default::default()
+    };
+    let from_reader = tlparse::parse(log, &config).unwrap();
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let from_path = tlparse::parse_path(&path, config).unwrap();
+    assert_eq!(
+        from_reader.files.iter().map(|(f, _)| f).collect::<Vec<_>>(),
+        from_path.files.iter().map(|(f, _)| f).collect::<Vec<_>>()
+    );
+    let map: HashMap<PathBuf, String> = from_reader.files.into_iter().collect();
+    assert_eq!(map[Path::new("raw.log")].as_bytes(), log);
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    assert_eq!(stats["ok"], 8);
+}
+
+// Counts the lines of every Dynamo graph, to test custom parsers with
+struct GraphLinesParser;
+impl tlparse::StructuredLogParser for GraphLinesParser {
+    fn name(&self) -> &'static str {
+        "graph_lines"
+    }
+    fn get_metadata<'e>(&self, e: &'e tlparse::Envelope) -> Option<tlparse::Metadata<'e>> {
+        e.dynamo_output_graph
+            .as_ref()
+            .map(tlparse::Metadata::DynamoOutputGraph)
+    }
+    fn parse<'e>(
+        &self,
+        _lineno: usize,
+        _metadata: tlparse::Metadata<'e>,
+        _rank: Option<u32>,
+        compile_id: &Option<tlparse::CompileId>,
+        payload: &str,
+    ) -> anyhow::Result<tlparse::ParserResults> {
+        let dir = compile_id.as_ref().unwrap().as_directory_name();
+        Ok(vec![tlparse::ParserOutput::File(
+            PathBuf::from(dir).join("graph_lines.txt"),
+            payload.lines().count().to_string(),
+        )])
+    }
+}
+
+// Fails on everything it's given, one way or the other
+struct BrokenParser {
+    panics: bool,
+}
+impl tlparse::StructuredLogParser for BrokenParser {
+    fn name(&self) -> &'static str {
+        "broken"
+    }
+    fn get_metadata<'e>(&self, e: &'e tlparse::Envelope) -> Option<tlparse::Metadata<'e>> {
+        e.dynamo_output_graph
+            .as_ref()
+            .map(tlparse::Metadata::DynamoOutputGraph)
+    }
+    fn parse<'e>(
+        &self,
+        lineno: usize,
+        _metadata: tlparse::Metadata<'e>,
+        _rank: Option<u32>,
+        _compile_id: &Option<tlparse::CompileId>,
+        _payload: &str,
+    ) -> anyhow::Result<tlparse::ParserResults> {
+        if self.panics {
+            panic!("no good on line {lineno}");
+        }
+        anyhow::bail!("no good on line {lineno}")
+    }
+}
+
+#[test]
+fn test_custom_parsers() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        custom_parsers: vec![
+            Box::new(BrokenParser { panics: true }),
+            Box::new(GraphLinesParser),
+            Box::new(BrokenParser { panics: false }),
+        ],
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config).unwrap();
+    let map: HashMap<PathBuf, String> = output.files.into_iter().collect();
+    // Broken parsers around it don't stop it, or the built-in ones
+    let (_, lines) = map
+        .iter()
+        .find(|(f, _)| {
+            let f = f.to_string_lossy();
+            f.starts_with("0_0_0/graph_lines/graph_lines_") && f.ends_with(".txt")
+        })
+        .unwrap();
+    assert_eq!(lines, "14");
+    assert!(prefix_exists(&map, "0_0_0/dynamo_output_graph"));
+    assert!(map[Path::new("0_0_0/compile.html")].contains("graph_lines_"));
+    let stats: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    assert_eq!(stats["fail_parser"], 2);
+}
+
+#[test]
+fn test_custom_envelope_fields() {
+    let path = Path::new("tests/inputs/custom_envelope.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        custom_parsers: vec![Box::new(tlparse::EnvelopeFieldParser {
+            field: "acme_checkpoint".to_string(),
+            filename: "checkpoint.json".to_string(),
+        })],
+        ..Default::default()
+    };
+    let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    // Without a payload, the field's value; in the parser's own directory
+    let (_, checkpoint) = map
+        .iter()
+        .find(|(f, _)| {
+            let f = f.to_string_lossy();
+            f.starts_with("0_0_0/custom_artifact/checkpoint_") && f.ends_with(".json")
+        })
+        .unwrap();
+    assert_eq!(checkpoint, "{\n  \"step\": 100\n}");
+    // Only the field nothing handles is unknown
+    let compile = &map[Path::new("0_0_0/compile.html")];
+    assert!(compile.contains("<li>Line 2: entry has unknown fields acme_shard_plan</li>"));
+    assert!(!compile.contains("unknown fields acme_checkpoint"));
+
+    // From the command line
+    let dir = scratch_dir("custom_artifact");
+    let out = dir.join("out");
+    let output = run_cli(&[
+        path.as_os_str(),
+        OsStr::new("-o"),
+        out.as_os_str(),
+        OsStr::new("--custom-artifact"),
+        OsStr::new("acme_shard_plan=shard_plan.txt"),
+    ]);
+    assert!(output.status.success());
+    let written: Vec<String> = fs::read_dir(out.join("0_0_0/custom_artifact"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    let plan = written
+        .iter()
+        .find(|f| f.starts_with("shard_plan_") && f.ends_with(".txt"))
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(out.join("0_0_0/custom_artifact").join(plan)).unwrap(),
+        "shard 0: layers 0-11\nshard 1: layers 12-23"
+    );
+    let bad = run_cli(&[
+        path.as_os_str(),
+        OsStr::new("-o"),
+        dir.join("bad").as_os_str(),
+        OsStr::new("--custom-artifact"),
+        OsStr::new("acme_shard_plan"),
+    ]);
+    assert!(!bad.status.success());
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("expected FIELD=FILENAME"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// The JSON of every log entry of a fixture, without its glog prefix
+fn envelope_lines(log: &str) -> Vec<String> {
+    fs::read_to_string(log)
+        .unwrap()
+        .lines()
+        .filter(|l| !l.starts_with('\t'))
+        .filter_map(|l| l.find("] {").map(|i| l[i + 2..].to_string()))
+        .collect()
+}
+
+#[test]
+fn test_envelope_serde() {
+    // What's read is written back the same way, and reads back to the same
+    for log in [
+        "tests/inputs/simple.log",
+        "tests/inputs/comp_metrics.log",
+        "tests/inputs/custom_envelope.log",
+    ] {
+        for line in envelope_lines(log) {
+            let e: tlparse::Envelope = serde_json::from_str(&line).unwrap();
+            let json = serde_json::to_value(&e).unwrap();
+            let again: tlparse::Envelope = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&again).unwrap(), json, "{line}");
+        }
+    }
+    // Field names are part of the format
+    let e: tlparse::Envelope = serde_json::from_str(
+        r#"{"artifact": {"name": "fx_graph_cache_hit", "encoding": "json"}, "frame_id": 3, "frame_compile_id": 1, "rank": 2, "has_payload": "abc", "acme_field": [1]}"#,
+    )
+    .unwrap();
+    let c = e.compile_id.as_ref().unwrap();
+    assert_eq!(
+        (c.frame_id, c.frame_compile_id, c.attempt),
+        (Some(3), Some(1), 0)
+    );
+    assert_eq!(
+        serde_json::to_value(&e).unwrap(),
+        serde_json::json!({
+            "artifact": {"name": "fx_graph_cache_hit", "encoding": "json"},
+            "frame_id": 3,
+            "frame_compile_id": 1,
+            "attempt": 0,
+            "rank": 2,
+            "has_payload": "abc",
+            "acme_field": [1],
+        })
+    );
+    // Without frame_id or frame_compile_id there's no compile id
+    let e: tlparse::Envelope = serde_json::from_str(r#"{"str": ["a.py", 0]}"#).unwrap();
+    assert!(e.compile_id.is_none());
+    assert_eq!(
+        serde_json::to_value(&e).unwrap(),
+        serde_json::json!({"str": ["a.py", 0]})
+    );
+}
+
+#[test]
+fn test_stats_serde() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let json: serde_json::Value = serde_json::from_str(&map[Path::new("stats.json")]).unwrap();
+    let stats: tlparse::Stats = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(stats.ok, 8);
+    // stats.json has more sections around the counts, which are written back as read
+    let counts = serde_json::to_value(&stats).unwrap();
+    for (k, v) in counts.as_object().unwrap() {
+        assert_eq!(&json[k], v, "{k}");
+    }
+    // Counts a stats.json doesn't have yet are 0
+    let old: tlparse::Stats = serde_json::from_str(r#"{"ok": 3, "fail_json": 1}"#).unwrap();
+    assert_eq!((old.ok, old.fail_json, old.unresolved_frames), (3, 1, 0));
+}
+
+#[test]
+fn test_output_sinks() {
+    // The same pages through both sinks: byte for byte, and nothing else on disk
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let parse = || tlparse::parse_path(&path, tlparse::ParseConfig::default()).unwrap();
+    let mut memory = tlparse::MemorySink::default();
+    tlparse::write_output(parse(), &mut memory).unwrap();
+    let out = scratch_dir("output_sinks");
+    tlparse::write_output(parse(), &mut tlparse::DirSink::new(&out)).unwrap();
+
+    assert!(memory.index().unwrap().contains("<html"));
+    let mut on_disk = Vec::new();
+    let mut dirs = vec![out.clone()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                on_disk.push(path.strip_prefix(&out).unwrap().to_path_buf());
+            }
+        }
+    }
+    assert_eq!(on_disk.len(), memory.files.len());
+    for (path, contents) in &memory.files {
+        assert_eq!(
+            &fs::read(out.join(path)).unwrap(),
+            contents,
+            "{}",
+            path.display()
+        );
+    }
+    let totals = memory.totals();
+    assert_eq!(totals.files, memory.files.len() as u64);
+}
+
+#[test]
+fn test_dry_run() {
+    let dir = scratch_dir("dry_run");
+    let out = dir.join("out");
+    let result = run_cli(&[
+        OsStr::new("tests/inputs/simple.log"),
+        OsStr::new("-o"),
+        out.as_os_str(),
+        OsStr::new("--dry-run"),
+    ]);
+    assert!(result.status.success());
+    assert!(!out.exists());
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    let index = out.join("index.html");
+    assert!(stdout
+        .lines()
+        .any(|l| l.ends_with(&format!("\t{}", index.display()))));
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .contains("Would write"));
+}
+
+#[test]
+fn test_envelopes_iterator() {
+    let log = fs::read_to_string("tests/inputs/simple.log").unwrap();
+    let entries: Vec<tlparse::LogEntry> = tlparse::envelopes(log.as_bytes())
+        .unwrap()
+        .collect::<anyhow::Result<_>>()
+        .unwrap();
+    assert_eq!(
+        entries.len(),
+        envelope_lines("tests/inputs/simple.log").len()
+    );
+    let graph = entries
+        .iter()
+        .find(|e| e.envelope.dynamo_output_graph.is_some())
+        .unwrap();
+    assert_eq!(graph.line, 13);
+    assert_eq!(
+        graph.envelope.compile_id.as_ref().unwrap().to_string(),
+        "[0/0]"
+    );
+    assert!(graph.envelope.timestamp.is_some());
+    let payload = graph.payload.as_deref().unwrap();
+    assert_eq!(
+        format!("{:x}", <md5::Md5 as md5::Digest>::digest(payload)),
+        "74cabbbada68afbad8a921c47aa2b317"
+    );
+
+    // A broken entry is reported and reading goes on
+    let log = "V0401 08:54:21.882000 1 a.py:1] {\"frame_id\": \n\
+               V0401 08:54:21.882000 1 a.py:1] {\"str\": [\"a.py\", 0]}\n";
+    let entries: Vec<_> = tlparse::envelopes(log.as_bytes()).unwrap().collect();
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0]
+        .as_ref()
+        .unwrap_err()
+        .to_string()
+        .starts_with("line 1"));
+    assert!(entries[1].as_ref().unwrap().payload.is_none());
+}
+
+#[test]
+fn test_custom_header_html() {
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let header = "<div id='acme-header'>ACME</div>";
+    let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, tlparse::ParseConfig::default())
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    assert!(!map.values().any(|page| page.contains("acme-header")));
+
+    let config = tlparse::ParseConfig {
+        custom_header_html: header.to_string(),
+        ..Default::default()
+    };
+    let map: HashMap<PathBuf, String> = tlparse::parse_path(&path, config)
+        .unwrap()
+        .files
+        .into_iter()
+        .collect();
+    // Unescaped, on the index and the pages under it
+    assert!(map[Path::new("index.html")].contains(header));
+    assert!(map[Path::new("0_0_0/compile.html")].contains(header));
+}
+
+#[test]
+fn test_strict_compile_id() {
+    // chromium_events.log has entries outside of any compile
+    let path = Path::new("tests/inputs/chromium_events.log").to_path_buf();
+    assert!(tlparse::parse_path(&path, tlparse::ParseConfig::default()).is_ok());
+    let config = tlparse::ParseConfig {
+        strict_compile_id: true,
+        ..Default::default()
+    };
+    let err = tlparse::parse_path(&path, config).unwrap_err();
+    assert_eq!(err.to_string(), "Some log entries did not have compile id");
+
+    // Every entry of simple.log that isn't an intern string has a compile id
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        strict_compile_id: true,
+        ..Default::default()
+    };
+    assert!(tlparse::parse_path(&path, config).is_ok());
+}
+
+#[test]
+fn test_verbose() {
+    let dir = scratch_dir("verbose");
+    let log = Path::new("tests/inputs/custom_envelope.log");
+    let run = |extra: &[&str]| {
+        let out = dir.join("out");
+        let mut args = vec![
+            log.as_os_str(),
+            OsStr::new("-o"),
+            out.as_os_str(),
+            OsStr::new("--overwrite"),
+        ];
+        args.extend(extra.iter().map(OsStr::new));
+        let result = run_cli(&args);
+        assert!(result.status.success());
+        String::from_utf8(result.stderr).unwrap()
+    };
+    assert!(!run(&[]).contains("Unknown field acme_shard_plan"));
+    assert!(run(&["--verbose"]).contains("Unknown field acme_shard_plan"));
+}
+
+// Status line, headers (lowercased names) and body of a GET of path
+#[cfg(feature = "serve")]
+fn http_get(
+    addr: std::net::SocketAddr,
+    path: &str,
+    headers: &str,
+) -> (String, HashMap<String, String>, Vec<u8>) {
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\n{headers}\r\n"
+    )
+    .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8(response[..end].to_vec()).unwrap();
+    let mut lines = head.lines();
+    let status = lines.next().unwrap().to_string();
+    let headers = lines
+        .filter_map(|l| l.split_once(": "))
+        .map(|(k, v)| (k.to_ascii_lowercase(), v.to_string()))
+        .collect();
+    (status, headers, response[end + 4..].to_vec())
+}
+
+#[cfg(feature = "serve")]
+#[test]
+fn test_serve_dir() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    let out = scratch_dir("serve");
+    let path = Path::new("tests/inputs/simple.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default()).unwrap();
+    tlparse::write_output(output, &mut tlparse::DirSink::new(&out)).unwrap();
+    let big = "x = torch.ops.aten.add.Tensor(y, z)\n".repeat(1000);
+    fs::write(out.join("0_0_0/big.txt"), &big).unwrap();
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+    let dir = out.clone();
+    let server = std::thread::spawn(move || tlparse::serve_dir(&dir, listener, &SHUTDOWN));
+
+    let (status, headers, body) = http_get(addr, "/", "");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(headers["content-type"], "text/html; charset=utf-8");
+    assert_eq!(body, fs::read(out.join("index.html")).unwrap());
+    let (_, headers, _) = http_get(addr, "/stats.json?x=1", "");
+    assert_eq!(headers["content-type"], "application/json");
+
+    // Big text is gzipped, only for clients that take it
+    let (_, headers, body) = http_get(addr, "/0_0_0/big.txt", "Accept-Encoding: gzip, br\r\n");
+    assert_eq!(headers["content-encoding"], "gzip");
+    let mut unzipped = String::new();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&body[..]), &mut unzipped)
+        .unwrap();
+    assert_eq!(unzipped, big);
+    let (_, headers, body) = http_get(addr, "/0_0_0/big.txt", "");
+    assert!(!headers.contains_key("content-encoding"));
+    assert_eq!(headers["content-length"], big.len().to_string());
+    assert_eq!(body, big.as_bytes());
+
+    // Nothing outside the directory
+    for path in [
+        "/nope.html",
+        "/../Cargo.toml",
+        "/%2e%2e/Cargo.toml",
+        "/0_0_0/../../x",
+    ] {
+        assert_eq!(
+            http_get(addr, path, "").0,
+            "HTTP/1.1 404 Not Found",
+            "{path}"
+        );
+    }
+
+    SHUTDOWN.store(true, Ordering::Relaxed);
+    assert!(server.join().unwrap().is_ok());
+}
+
+#[test]
+fn test_diff_runs() {
+    let dir = scratch_dir("diff_runs");
+    let run = |log: &str, sub: &str| {
+        let out = dir.join(sub);
+        fs::create_dir(&out).unwrap();
+        let path = Path::new(log).to_path_buf();
+        let output = tlparse::parse_path(&path, tlparse::ParseConfig::default()).unwrap();
+        tlparse::write_output(output, &mut tlparse::DirSink::new(&out)).unwrap();
+        tlparse::DiffRun {
+            dir: out,
+            name: sub.to_string(),
+            href: format!("{sub}/"),
+        }
+    };
+    // comp_metrics_after.log is comp_metrics.log with [1/0_1]'s graph changed, [2/0] taking
+    // 0.9 s and frame 2 compiled again as [2/1]
+    let a = run("tests/inputs/comp_metrics.log", "before");
+    let b = run("tests/inputs/comp_metrics_after.log", "after");
+    let report = tlparse::diff_runs(&a, &b, true, 10.0).unwrap();
+    let diff = &report.diff;
+    assert!(diff.regressed);
+    assert_eq!(diff.reasons.len(), 4, "{:?}", diff.reasons);
+    assert!(diff.only_in_a.is_empty());
+    assert_eq!(diff.only_in_b.len(), 1);
+    assert_eq!(diff.only_in_b[0].compile_id, "[2/1]");
+    assert_eq!(diff.only_in_b[0].url, "after/2_1_0/compile.html");
+    assert_eq!(diff.frame_count_changes.len(), 1);
+    assert_eq!(
+        (diff.frame_count_changes[0].a, diff.frame_count_changes[0].b),
+        (1, 2)
+    );
+    assert!(diff.frame_count_changes[0]
+        .frame
+        .ends_with("in torch_dynamo_resume_in_fn_at_9551"));
+    assert_eq!(diff.slower.len(), 1);
+    assert_eq!(diff.slower[0].compile_id, "[2/0]");
+    assert_eq!(diff.slower[0].b_s, 0.9);
+    assert!(diff.faster.is_empty());
+    assert_eq!(diff.changed_artifacts.len(), 1);
+    let changed = &diff.changed_artifacts[0];
+    assert_eq!(changed.compile_id, "[1/0_1]");
+    assert_eq!(changed.artifacts.len(), 1);
+    assert_eq!(changed.artifacts[0].name, "dynamo_output_graph.txt");
+    assert_eq!(
+        (changed.artifacts[0].added, changed.artifacts[0].removed),
+        (1, 1)
+    );
+    assert!(report.html.contains("n2 = n1.tan()"));
+    // diff.json has what changed, not the lines
+    let json = serde_json::to_value(diff).unwrap();
+    assert_eq!(
+        json["changed_artifacts"][0]["artifacts"][0],
+        serde_json::json!({"name": "dynamo_output_graph.txt", "status": "changed", "added": 1, "removed": 1})
+    );
+
+    // The other way around, only what's gone and what got faster, which isn't a regression
+    let report = tlparse::diff_runs(&b, &a, true, 10.0).unwrap();
+    assert!(!report.diff.regressed, "{:?}", report.diff.reasons);
+    assert_eq!(report.diff.only_in_a.len(), 1);
+    assert_eq!(report.diff.faster.len(), 1);
+    assert!(report.html.contains("No regressions"));
+}
+
+#[test]
+fn test_diff_cli() {
+    let dir = scratch_dir("diff_cli");
+    let before = Path::new("tests/inputs/comp_metrics.log");
+    let after = Path::new("tests/inputs/comp_metrics_after.log");
+    let out = dir.join("out");
+    let diff = |a: &Path, b: &Path, extra: &[&str]| {
+        let mut args = vec![
+            OsStr::new("diff"),
+            a.as_os_str(),
+            b.as_os_str(),
+            OsStr::new("-o"),
+            out.as_os_str(),
+            OsStr::new("--overwrite"),
+        ];
+        args.extend(extra.iter().map(OsStr::new));
+        Command::new(env!("CARGO_BIN_EXE_tlparse"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    // Logs are parsed into the output directory, and regressions only fail the command when asked
+    assert!(diff(before, after, &[]).status.success());
+    assert!(out.join("a/index.html").is_file() && out.join("b/index.html").is_file());
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out.join("diff.json")).unwrap()).unwrap();
+    assert_eq!(json["regressed"], true);
+    let result = diff(before, after, &["--fail-on-regression"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8(result.stdout)
+        .unwrap()
+        .contains("Slower: [2/0] 0.003 s -> 0.9 s (+26418%)"));
+    assert!(diff(before, before, &["--fail-on-regression"])
+        .status
+        .success());
+
+    // An output directory is used as it is
+    let parsed = dir.join("parsed");
+    assert!(
+        run_cli(&[before.as_os_str(), OsStr::new("-o"), parsed.as_os_str()])
+            .status
+            .success()
+    );
+    assert!(
+        diff(&parsed, after, &["--fail-on-regression"])
+            .status
+            .code()
+            == Some(1)
+    );
+    assert!(!out.join("a").exists());
+    let page = fs::read_to_string(out.join("diff.html")).unwrap();
+    let href = format!(
+        "{}/1_0_1/compile.html",
+        parsed.canonicalize().unwrap().display()
+    );
+    assert!(page.contains(&href));
+}
+
+#[test]
+fn test_find_artifact() {
+    let find = |log: &str, compile_id: &str, name: &str| {
+        let reader = std::io::BufReader::new(fs::File::open(log).unwrap());
+        tlparse::find_artifact(reader, compile_id, name).map_err(|e| e.to_string())
+    };
+    // By kind, with or without brackets and extension
+    let graph = find(
+        "tests/inputs/comp_metrics.log",
+        "1/0_1",
+        "dynamo_output_graph",
+    )
+    .unwrap();
+    assert!(graph.starts_with("class GraphModule(torch.nn.Module):"));
+    assert_eq!(
+        find(
+            "tests/inputs/comp_metrics.log",
+            "[1/0_1]",
+            "dynamo_output_graph.txt"
+        ),
+        Ok(graph)
+    );
+    // By an artifact's name
+    assert_eq!(
+        find("tests/inputs/json_artifacts.log", "0/0", "not_json"),
+        Ok("{this starts like JSON but isn't\n}".to_string())
+    );
+    assert_eq!(
+        find("tests/inputs/comp_metrics.log", "1/0_1", "inductor_output_code"),
+        Err("[1/0_1] has no artifact inductor_output_code; it has: dynamo_output_graph, dynamo_guards".to_string())
+    );
+    assert_eq!(
+        find("tests/inputs/comp_metrics.log", "1/0", "dynamo_guards"),
+        Err("No compile [1/0]; the log has artifacts for: [0/0_1], [1/0_1], [2/0]".to_string())
+    );
+
+    // A name the compile logged again is numbered from its second
+    let log = fs::read_to_string("tests/inputs/json_artifacts.log").unwrap();
+    let twice = format!("{log}{log}");
+    let find = |name: &str| {
+        tlparse::find_artifact(twice.as_bytes(), "[0/0]", name).map_err(|e| e.to_string())
+    };
+    assert_eq!(find("not_json (2)"), find("not_json"));
+    assert!(find("not_json (3)").unwrap_err().ends_with(
+        "it has: provenance_map, not_json, broken_json, cache_key, provenance_map (2), not_json (2), broken_json (2), cache_key (2)"
+    ));
+}
+
+#[test]
+fn test_cat() {
+    let dir = scratch_dir("cat");
+    let log = Path::new("tests/inputs/json_artifacts.log")
+        .canonicalize()
+        .unwrap();
+    let cat = |name: &str| {
+        Command::new(env!("CARGO_BIN_EXE_tlparse"))
+            .current_dir(&dir)
+            .args([
+                OsStr::new("cat"),
+                log.as_os_str(),
+                OsStr::new("0/0"),
+                OsStr::new(name),
+            ])
+            .output()
+            .unwrap()
+    };
+    let result = cat("not_json");
+    assert!(result.status.success());
+    assert_eq!(result.stdout, b"{this starts like JSON but isn't\n}\n");
+    let result = cat("output_code");
+    assert!(!result.status.success());
+    assert!(result.stdout.is_empty());
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .contains("it has: provenance_map, not_json, broken_json, cache_key"));
+    // Nothing is written
+    assert!(fs::read_dir(&dir).unwrap().next().is_none());
+}
+
+#[test]
+fn test_grep_log() {
+    let log = fs::read("tests/inputs/comp_metrics.log").unwrap();
+    let grep = |log: &[u8], pattern: &str, artifact: Option<&str>| {
+        let mut found = Vec::new();
+        tlparse::grep_log(log, pattern, artifact, |m| {
+            found.push(m.to_string());
+            true
+        })
+        .unwrap();
+        found
+    };
+    assert_eq!(
+        grep(&log, r"\.sin\(\)", None),
+        [
+            "[1/0_1] dynamo_output_graph:5:         # File: /data/users/jjwu/a/pytorch/test/dynamo/test_misc.py:9552 in torch_dynamo_resume_in_fn_at_9551, code: x.sin()",
+            "[1/0_1] dynamo_output_graph:6:         sin = l_x_.sin();  l_x_ = None",
+        ]
+    );
+    assert_eq!(grep(&log, "L_x_", None).len(), 6);
+    assert_eq!(grep(&log, "L_x_", Some("dynamo_guards")).len(), 0);
+    assert_eq!(grep(&log, "CURRENT_DEVICE", Some("dynamo_guards")).len(), 3);
+    assert!(tlparse::grep_log(&log[..], "(", None, |_| true).is_err());
+
+    // Stopping early, and bytes that aren't UTF-8
+    let mut stopped = Vec::new();
+    tlparse::grep_log(&log[..], "L_x_", None, |m| {
+        stopped.push(m.line);
+        stopped.len() < 3
+    })
+    .unwrap();
+    assert_eq!(stopped, [2, 3, 2]);
+    let binary = String::from_utf8(log)
+        .unwrap()
+        .replacen("sin = l_x_.sin()", "sin = \u{1}SIN", 1)
+        .into_bytes();
+    let binary: Vec<u8> = binary
+        .iter()
+        .flat_map(|&b| if b == 1 { vec![0xff, 0xfe] } else { vec![b] })
+        .collect();
+    assert_eq!(
+        grep(&binary, "SIN", None),
+        ["[1/0_1] dynamo_output_graph:6:         sin = \u{fffd}\u{fffd}SIN;  l_x_ = None"]
+    );
+}
+
+#[test]
+fn test_grep_cli() {
+    let log = Path::new("tests/inputs/comp_metrics.log");
+    let grep = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_tlparse"))
+            .arg("grep")
+            .args(args)
+            .arg(log)
+            .output()
+            .unwrap();
+        (
+            output.status.success(),
+            String::from_utf8(output.stdout).unwrap(),
+        )
+    };
+    assert_eq!(
+        grep(&["-l", "L_x_"]),
+        (true, "[0/0_1]\n[1/0_1]\n[2/0]\n".to_string())
+    );
+    let (ok, out) = grep(&["--artifact", "dynamo_output_graph", "sin ="]);
+    assert!(ok);
+    assert_eq!(
+        out,
+        "[1/0_1] dynamo_output_graph:6:         sin = l_x_.sin();  l_x_ = None\n"
+    );
+    assert_eq!(
+        grep(&["--artifact", "dynamo_guards", "sin ="]),
+        (false, String::new())
+    );
+}
+
+#[test]
+fn test_extract_compile() {
+    let log = fs::read("tests/inputs/comp_metrics.log").unwrap();
+    let config = tlparse::ParseConfig::default();
+    let extract = |label: &str, all_attempts: bool| {
+        let compile_id = tlparse::CompileId::parse(label).unwrap();
+        tlparse::extract_compile(&log[..], &compile_id, all_attempts, &config)
+    };
+    let names = |output: &[(PathBuf, String)]| {
+        output
+            .iter()
+            .map(|(p, _)| p.to_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    let output = extract("1/0", true).unwrap();
+    // Only its attempts are listed
+    assert_eq!(
+        output.runs[0]
+            .directory
+            .keys()
+            .map(|c| c.as_ref().unwrap().to_string())
+            .collect::<Vec<_>>(),
+        ["[1/0]", "[1/0_1]"]
+    );
+    let output = output.files;
+    assert_eq!(
+        names(&output),
+        [
+            "1_0_1/dynamo_output_graph_0.html",
+            "1_0_1/dynamo_output_graph_0.txt",
+            "1_0_1/dynamo_guards_1.html",
+            "1_0_1/compilation_metrics_2.html",
+            "diff_1_0_0_vs_1_0_1.html",
+            "1_0_1/compile.html",
+        ]
+    );
+    // The same artifacts as a full parse has, stacks included
+    let full = tlparse::parse_path(
+        &Path::new("tests/inputs/comp_metrics.log").to_path_buf(),
+        tlparse::ParseConfig::default(),
+    )
+    .unwrap()
+    .files;
+    let contents = |output: &[(PathBuf, String)], name: &str| {
+        output
+            .iter()
+            .find(|(p, _)| p.to_str() == Some(name))
+            .unwrap()
+            .1
+            .clone()
+    };
+    assert_eq!(
+        contents(&output, "1_0_1/dynamo_output_graph_0.txt"),
+        contents(&full, "1_0_1/dynamo_output_graph_3.txt")
+    );
+    assert!(contents(&output, "1_0_1/compile.html").contains("test_misc.py"));
+
+    // One attempt leaves out the diff between them
+    let output = extract("1/0_1", false).unwrap().files;
+    assert!(names(&output).iter().all(|n| n.starts_with("1_0_1/")));
+    assert_eq!(
+        extract("1/0_0", false).unwrap_err().to_string(),
+        "No compile [1/0] in the log"
+    );
+    assert_eq!(
+        extract("7/0", true).unwrap_err().to_string(),
+        "No compile [7/0] in the log"
+    );
+}
+
+#[test]
+fn test_extract_cli() {
+    let dir = scratch_dir("extract_cli");
+    let log = Path::new("tests/inputs/comp_metrics.log")
+        .canonicalize()
+        .unwrap();
+    let extract = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_tlparse"))
+            .current_dir(&dir)
+            .arg("extract")
+            .arg(&log)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    assert!(extract(&["--compile-id", "2/0"]).status.success());
+    assert!(dir.join("tl_2_0/2_0_0/compile.html").is_file());
+    assert!(!dir.join("tl_2_0/index.html").exists());
+    assert!(extract(&["--compile-id", "1/0", "--attempt", "1"])
+        .status
+        .success());
+    assert!(dir.join("tl_1_0_1/1_0_1/compile.html").is_file());
+    // Like any other output directory, it's only replaced with --overwrite
+    assert!(!extract(&["--compile-id", "2/0"]).status.success());
+    assert!(extract(&["--compile-id", "2/0", "--overwrite"])
+        .status
+        .success());
+
+    // A full output is never written into, not even with --overwrite
+    let full = dir.join("full");
+    assert!(
+        run_cli(&[log.as_os_str(), OsStr::new("-o"), full.as_os_str()])
+            .status
+            .success()
+    );
+    let result = extract(&["--compile-id", "2/0", "-o", "full", "--overwrite"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .contains("holds a full tlparse output"));
+    assert!(full.join("index.html").is_file());
+    assert!(!extract(&["--compile-id", "2/x"]).status.success());
+}
+
+// Three compiles, the first two overlapping: [0/0] from 1 to 11 s, [1/0] from 5 to 15 s, and
+// [2/0] from 31 to 41 s
+fn wall_clock_config() -> tlparse::GenConfig {
+    tlparse::GenConfig {
+        recompiles: 0,
+        artifacts: vec!["dynamo_output_graph".to_string()],
+        compile_times: vec![1.0, 5.0, 31.0],
+        compile_s: 10.0,
+        ..Default::default()
+    }
+}
+
+fn generated_log(config: &tlparse::GenConfig) -> Vec<u8> {
+    let mut log = Vec::new();
+    tlparse::generate_log(config, &mut log).unwrap();
+    log
+}
+
+fn generated_stats(log: &[u8], config: tlparse::ParseConfig) -> tlparse::Stats {
+    let output = tlparse::parse(log, &config).unwrap();
+    let stats = &output
+        .files
+        .iter()
+        .find(|(p, _)| p == Path::new("stats.json"))
+        .unwrap()
+        .1;
+    serde_json::from_str(stats).unwrap()
+}
+
+#[test]
+fn test_generated_log() {
+    // Every entry but the three interned filenames is counted ok, payload or not
+    let config = tlparse::GenConfig {
+        frames: 4,
+        recompiles: 2,
+        artifacts: vec!["dynamo_output_graph".to_string(), "my_artifact".to_string()],
+        payload_lines: 50,
+        ..Default::default()
+    };
+    let log = generated_log(&config);
+    let strict = tlparse::ParseConfig {
+        strict: true,
+        strict_compile_id: true,
+        ..Default::default()
+    };
+    let stats = generated_stats(&log, strict);
+    assert_eq!(stats.ok, 4 * 3 * 4);
+    assert_eq!(stats.fail_payload_md5 + stats.fail_glog, 0);
+    let graph = tlparse::find_artifact(&log[..], "3/2", "dynamo_output_graph").unwrap();
+    assert_eq!(graph.lines().count(), 50);
+    assert!(tlparse::find_artifact(&log[..], "3/2", "my_artifact").is_ok());
+    // The same config is the same log
+    assert_eq!(generated_log(&config), log);
+
+    // Exactly the corruption asked for
+    let config = tlparse::GenConfig {
+        bad_md5: 2,
+        truncated_payloads: 3,
+        junk_lines: 4,
+        seed: 7,
+        ..Default::default()
+    };
+    let corrupted = generated_log(&config);
+    let stats = generated_stats(&corrupted, tlparse::ParseConfig::default());
+    assert_eq!(
+        (
+            stats.fail_payload_md5,
+            stats.fail_missing_payload,
+            stats.fail_glog
+        ),
+        (2, 3, 4)
+    );
+    assert_eq!(stats.ok, 3 * 2 * 5);
+    let reseeded = generated_log(&tlparse::GenConfig { seed: 8, ..config });
+    // Elsewhere, but as much of it
+    assert_ne!(reseeded, corrupted);
+    let reseeded = generated_stats(&reseeded, tlparse::ParseConfig::default());
+    assert_eq!(
+        serde_json::to_value(reseeded).unwrap(),
+        serde_json::to_value(stats).unwrap()
+    );
+    assert!(tlparse::generate_log(
+        &tlparse::GenConfig {
+            bad_md5: 100,
+            ..Default::default()
+        },
+        &mut Vec::new()
+    )
+    .is_err());
+
+    // Ranks take turns, and one of them is rendered
+    let log = generated_log(&tlparse::GenConfig {
+        ranks: 3,
+        ..Default::default()
+    });
+    let stats = generated_stats(&log, tlparse::ParseConfig::default());
+    assert_eq!((stats.ok, stats.other_rank), (30, 60));
+    let heads: Vec<&str> = std::str::from_utf8(&log)
+        .unwrap()
+        .lines()
+        .filter(|l| !l.starts_with('\t'))
+        .take(3)
+        .collect();
+    for (rank, head) in heads.iter().enumerate() {
+        assert!(head.contains(&format!(" {} torch/", 1000 + rank)), "{head}");
+        assert!(head.contains(&format!("\"rank\":{rank}")), "{head}");
+    }
+    let timestamps: Vec<&str> = std::str::from_utf8(&log)
+        .unwrap()
+        .lines()
+        .filter(|l| !l.starts_with('\t'))
+        .map(|l| &l[1..21])
+        .collect();
+    assert!(timestamps.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_gen_cli() {
+    let dir = scratch_dir("gen_cli");
+    let log = dir.join("gen.log");
+    let result = Command::new(env!("CARGO_BIN_EXE_tlparse"))
+        .args(["gen", "--frames", "2", "--recompiles", "0", "--artifacts"])
+        .arg("aot_forward_graph,inductor_output_code")
+        .args(["--bad-md5", "1", "-o"])
+        .arg(&log)
+        .output()
+        .unwrap();
+    assert!(result.status.success());
+    let out = dir.join("out");
+    assert!(
+        run_cli(&[log.as_os_str(), OsStr::new("-o"), out.as_os_str()])
+            .status
+            .success()
+    );
+    let stats: tlparse::Stats =
+        serde_json::from_str(&fs::read_to_string(out.join("stats.json")).unwrap()).unwrap();
+    assert_eq!((stats.ok, stats.fail_payload_md5), (8, 1));
+    assert!(out.join("1_0_0/compile.html").is_file());
+}
+
+// A log of these envelopes (JSON objects without has_payload) and their payloads, with the MD5s
+// right
+fn log_with_payloads(entries: &[(&str, &str)]) -> String {
+    let mut log = String::new();
+    for (i, (envelope, payload)) in entries.iter().enumerate() {
+        let md5 = <md5::Md5 as md5::Digest>::digest(payload);
+        let envelope = format!(
+            "{}, \"has_payload\": \"{md5:x}\"}}",
+            envelope.strip_suffix('}').unwrap()
+        );
+        log.push_str(&format!(
+            "V1014 09:00:{i:02}.000000 1000 torch/_dynamo/output_graph.py:1300] {envelope}\n"
+        ));
+        for line in payload.lines() {
+            log.push_str(&format!("\t{line}\n"));
+        }
+    }
+    log
+}
+
+#[test]
+fn test_normalize_artifacts() {
+    let graph = |n: usize, user: &str| {
+        format!(
+            "class GraphModule(torch.nn.Module):\n    def forward(self, L_x_: \"f32[4]\"):\n        open_1 = builtins.open('/tmp/torchinductor_{user}/ab/c.py')\n        add = L_x_ + 0xFF\n        return (add, self.__compiled_fn_{n})"
+        )
+    };
+    let code = |n: usize| {
+        format!(
+            "triton_poi_fused_add_{n}.run(buf0, stream=<Stream cuda_stream=0x55d1c0ffee{n:02}>)"
+        )
+    };
+    let (graph_0, graph_1, code_0, code_1) = (graph(3, "alice"), graph(9, "bob"), code(3), code(9));
+    let log = log_with_payloads(&[
+        (
+            r#"{"dynamo_output_graph": {}, "frame_id": 0, "frame_compile_id": 0, "attempt": 0}"#,
+            &graph_0,
+        ),
+        (
+            r#"{"inductor_output_code": {"filename": "a.py"}, "frame_id": 0, "frame_compile_id": 0, "attempt": 0}"#,
+            &code_0,
+        ),
+        (
+            r#"{"dynamo_output_graph": {}, "frame_id": 1, "frame_compile_id": 0, "attempt": 0}"#,
+            &graph_1,
+        ),
+        (
+            r#"{"inductor_output_code": {"filename": "a.py"}, "frame_id": 1, "frame_compile_id": 0, "attempt": 0}"#,
+            &code_1,
+        ),
+    ]);
+    let file = |output: &[(PathBuf, String)], name: &str| {
+        output
+            .iter()
+            .find(|(p, _)| p == Path::new(name))
+            .unwrap()
+            .1
+            .clone()
+    };
+    // The two graphs only differ in noise, so they're duplicates whether or not it's written
+    for normalize_artifacts in [false, true] {
+        let config = tlparse::ParseConfig {
+            strict: true,
+            normalize_artifacts,
+            ..Default::default()
+        };
+        let output = tlparse::parse(log.as_bytes(), &config).unwrap().files;
+        assert!(
+            file(&output, "index.html").contains("1 groups of compiles captured the same graph")
+        );
+        let graph = file(&output, "0_0_0/dynamo_output_graph_0.txt");
+        // Output code is only written as its page
+        let code = file(&output, "1_0_0/inductor_output_code_a_3.html");
+        if normalize_artifacts {
+            assert_eq!(graph, file(&output, "1_0_0/dynamo_output_graph_2.txt"));
+            assert!(graph.contains("builtins.open('/tmp/…')"));
+            // Hex constants in the code itself stay
+            assert!(graph.contains("L_x_ + 0xFF"));
+            assert!(code.contains("triton_poi_fused_add_0"));
+            assert!(code.contains("0x…") && !code.contains("0x55d1c0ffee"));
+        } else {
+            assert_eq!(graph, graph_0);
+            assert!(code.contains("triton_poi_fused_add_9"));
+            assert!(code.contains("0x55d1c0ffee09"));
+        }
+    }
+}
+
+#[test]
+fn test_post_hook() {
+    let dir = scratch_dir("post_hook");
+    let out = dir.join("out");
+    let record = dir.join("hook.txt");
+    let hook = format!(
+        "echo \"$1 $2 $TLPARSE_COMPILES $TLPARSE_FAILED_COMPILES $TLPARSE_OK_ENTRIES\" > {}; exit 3",
+        record.display()
+    );
+    let run = |extra: &[&str]| {
+        let mut args = vec![
+            OsStr::new("tests/inputs/comp_failure.log"),
+            OsStr::new("-o"),
+            out.as_os_str(),
+            OsStr::new("--overwrite"),
+            OsStr::new("--post-hook"),
+            OsStr::new(&hook),
+        ];
+        args.extend(extra.iter().map(OsStr::new));
+        run_cli(&args)
+    };
+
+    // A failing hook is reported, but is only tlparse's failure when asked
+    let result = run(&[]);
+    assert!(result.status.success());
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .contains("Post hook failed (exit status: 3)"));
+    assert_eq!(
+        fs::read_to_string(&record).unwrap().trim(),
+        format!(
+            "{} {} 1 1 3",
+            out.display(),
+            out.join("stats.json").display()
+        )
+    );
+    let stats: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out.join("stats.json")).unwrap()).unwrap();
+    assert_eq!(
+        stats["compiles"],
+        serde_json::json!({"total": 1, "recompiles": 0, "failed": 1})
+    );
+    assert!(!run(&["--fail-on-hook-error"]).status.success());
+
+    // Nor does it run when parsing fails
+    fs::remove_file(&record).unwrap();
+    let result = run_cli(&[
+        dir.join("missing.log").as_os_str(),
+        OsStr::new("-o"),
+        dir.join("out2").as_os_str(),
+        OsStr::new("--post-hook"),
+        OsStr::new(&hook),
+    ]);
+    assert!(!result.status.success());
+    assert!(!record.exists());
+}
+
+#[test]
+fn test_report_to_stdout() {
+    // Nothing on disk, and only the page on stdout
+    let dir = scratch_dir("report_to_stdout");
+    let result = Command::new(env!("CARGO_BIN_EXE_tlparse"))
+        .arg(fs::canonicalize("tests/inputs/comp_failure.log").unwrap())
+        .args(["-o", "-"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(result.status.success());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    let report = String::from_utf8(result.stdout).unwrap();
+    assert!(report.starts_with("<!DOCTYPE html>"));
+    assert!(report.trim_end().ends_with("</html>"));
+    assert!(report.contains(r#""index.html":"#));
+    assert!(report.contains(r#""0_0_0/compilation_metrics_1.html":"#));
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .contains("to stdout as one page"));
+
+    let result = run_cli(&[
+        OsStr::new("tests/inputs/comp_failure.log"),
+        OsStr::new("-o"),
+        OsStr::new("-"),
+        OsStr::new("--post-hook"),
+        OsStr::new("true"),
+    ]);
+    assert!(!result.status.success());
+}
+
+#[test]
+fn test_report_to_stdout_with_top() {
+    // What --top prints goes to stderr, so that stdout is only the page
+    let result = run_cli(&[
+        OsStr::new("tests/inputs/simple.log"),
+        OsStr::new("-o"),
+        OsStr::new("-"),
+        OsStr::new("--top"),
+        OsStr::new("3"),
+    ]);
+    assert!(result.status.success());
+    assert!(String::from_utf8(result.stdout)
+        .unwrap()
+        .starts_with("<!DOCTYPE html>"));
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .contains("Top compiles:\n"));
+
+    let result = run_cli(&[
+        OsStr::new("tests/inputs/simple.log"),
+        OsStr::new("-o"),
+        OsStr::new("-"),
+        OsStr::new("--print-trie"),
+    ]);
+    assert!(!result.status.success());
+    assert!(result.stdout.is_empty());
+    assert!(String::from_utf8(result.stderr)
+        .unwrap()
+        .contains("--print-trie prints to stdout, which -o - writes the report to"));
+}
+
+#[test]
+fn test_progress_json() {
+    let dir = scratch_dir("progress_json");
+    let log = dir.join("junk.log");
+    fs::write(
+        &log,
+        generated_log(&tlparse::GenConfig {
+            junk_lines: 2,
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    let result = run_cli(&[
+        log.as_os_str(),
+        OsStr::new("-o"),
+        dir.join("out").as_os_str(),
+        OsStr::new("--progress-json"),
+    ]);
+    assert!(result.status.success());
+    // Nothing but events on stderr
+    let events: Vec<serde_json::Value> = String::from_utf8(result.stderr)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let of_type = |t: &str| events.iter().filter(|e| e["type"] == t).collect::<Vec<_>>();
+    assert_eq!(events[0]["type"], "start");
+    let size = fs::metadata(&log).unwrap().len();
+    assert_eq!(events[0]["total"], size);
+    let errors = of_type("error");
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Failed to parse glog prefix"));
+    let finish = of_type("finish");
+    assert_eq!(finish.len(), 1);
+    assert_eq!(
+        (
+            &finish[0]["bytes"],
+            &finish[0]["fail_glog"],
+            &finish[0]["ok"]
+        ),
+        (&size.into(), &2.into(), &30.into())
+    );
+    // Throttled, rather than one per line of the log
+    assert!(!of_type("progress").is_empty() && of_type("progress").len() < 10);
+    assert!(events.last().unwrap()["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Wrote "));
+
+    // Not being able to open the report is an event too
+    let result = Command::new(env!("CARGO_BIN_EXE_tlparse"))
+        .arg(&log)
+        .arg("-o")
+        .arg(dir.join("out"))
+        .args([
+            "--overwrite",
+            "--progress-json",
+            "--open-with",
+            "no-such-opener",
+        ])
+        .output()
+        .unwrap();
+    assert!(result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    let last: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(last["type"], "error");
+    let message = last["message"].as_str().unwrap();
+    assert!(message.contains("not found") && message.contains("the report is at"));
+    assert!(stderr
+        .lines()
+        .all(|l| serde_json::from_str::<serde_json::Value>(l).is_ok()));
+}
+
+#[test]
+fn test_open_with() {
+    let dir = scratch_dir("open_with");
+    let out = dir.join("it's out");
+    let opened = dir.join("opened.txt");
+    let index = || fs::canonicalize(out.join("index.html")).unwrap();
+    let tlparse = |browser: &str, open_with: Option<&str>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_tlparse"));
+        command
+            .arg("tests/inputs/simple.log")
+            .arg("-o")
+            .arg(&out)
+            .arg("--overwrite")
+            .env("BROWSER", browser);
+        if let Some(open_with) = open_with {
+            command.args(["--open-with", open_with]);
+        }
+        command.output().unwrap()
+    };
+    let record = format!("echo {{}} > '{}'", opened.display());
+
+    assert!(tlparse("false", Some(&record)).status.success());
+    assert_eq!(
+        fs::read_to_string(&opened).unwrap().trim_end(),
+        index().display().to_string()
+    );
+
+    // BROWSER's commands are tried in turn
+    fs::remove_file(&opened).unwrap();
+    let separator = if cfg!(windows) { ';' } else { ':' };
+    let browser = format!("false{separator}echo %s > '{}'", opened.display());
+    assert!(tlparse(&browser, None).status.success());
+    assert_eq!(
+        fs::read_to_string(&opened).unwrap().trim_end(),
+        index().display().to_string()
+    );
+
+    // Failing to open the report isn't failing
+    let result = tlparse("false", Some("exit 1"));
+    assert!(result.status.success());
+    assert!(String::from_utf8(result.stderr).unwrap().contains(&format!(
+        "--open-with exit 1 failed: exit status: 1; the report is at {}",
+        index().display()
+    )));
+}
+
+#[test]
+fn test_post_hook_per_run() {
+    // With several runs there's no top-level stats.json, so the hook runs for each run_N
+    let dir = scratch_dir("post_hook_per_run");
+    let out = dir.join("out");
+    let record = dir.join("hook.txt");
+    let hook = format!("echo \"$1 $2 $TLPARSE_RUN\" >> {}", record.display());
+    let result = run_cli(&[
+        OsStr::new("tests/inputs/two_runs.log"),
+        OsStr::new("-o"),
+        out.as_os_str(),
+        OsStr::new("--post-hook"),
+        OsStr::new(&hook),
+    ]);
+    assert!(result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(stderr.contains("Post hook succeeded for run_0"));
+    assert!(stderr.contains("Post hook succeeded for run_1"));
+    let expected: Vec<String> = (0..2)
+        .map(|run| {
+            let report = out.join(format!("run_{run}"));
+            format!(
+                "{} {} {run}",
+                report.display(),
+                report.join("stats.json").display()
+            )
+        })
+        .collect();
+    assert_eq!(
+        fs::read_to_string(&record)
+            .unwrap()
+            .lines()
+            .collect::<Vec<_>>(),
+        expected
+    );
+}
+
+#[test]
+fn test_per_rank_intern_tables() {
+    // Both ranks intern id 0, each to a file of its own; what was interned without a rank is
+    // everyone's
+    let path = Path::new("tests/inputs/rank_intern.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    let index = &map[Path::new("index.html")];
+    let tries: Vec<&str> = index.split("<div class='stack-trie'>").skip(1).collect();
+    assert_eq!(tries.len(), 2);
+    assert!(tries[0].contains("/data/users/test/rank0.py:1 in main"));
+    assert!(!tries[0].contains("rank1.py"));
+    assert!(tries[0].contains("/data/users/test/shared.py:2 in helper"));
+    assert!(tries[1].contains("/data/users/test/rank1.py:1 in main"));
+    assert!(!tries[1].contains("rank0.py"));
+    assert!(tries[1].contains("/data/users/test/shared.py:2 in helper"));
+    // Different ranks giving the same id different files isn't a conflict
+    assert!(!index.contains("Intern table conflicts"));
+}
+
+#[test]
+fn test_multi_rank_single_run() {
+    // Both ranks write to one file and intern from 0, so rank 1's ids go back to 0 after rank 0's
+    // got to 1; that's not a restart
+    let path = Path::new("tests/inputs/multi_rank_interns.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(!map.keys().any(|p| p.starts_with("run_0")));
+    let index = &map[Path::new("index.html")];
+    assert!(index.contains("/data/users/test/train.py:5 in train"));
+    assert!(!index.contains("several runs"));
+
+    // The whole job restarting is, though, even though only the first rank's ids go backwards
+    // before the second rank starts over too
+    let log = fs::read(&path).unwrap().repeat(2);
+    let output = tlparse::parse(&log[..], &tlparse::ParseConfig::default());
+    let map: HashMap<PathBuf, String> = output.unwrap().files.into_iter().collect();
+    assert!(map.contains_key(Path::new("run_0/index.html")));
+    assert!(map.contains_key(Path::new("run_1/index.html")));
+    assert!(!map.contains_key(Path::new("run_2/index.html")));
+}
+
+#[test]
+fn test_concatenated_runs_streamed() {
+    // The first run's payloads are written out before it's known that another run follows
+    let dir = scratch_dir("concatenated_runs_streamed");
+    let config = tlparse::ParseConfig {
+        output_dir: Some(dir.clone()),
+        stream_payload_threshold: Some(1),
+        ..Default::default()
+    };
+    let path = Path::new("tests/inputs/two_runs.log").to_path_buf();
+    tlparse::parse_path(&path, config).unwrap();
+    let first = fs::read_to_string(dir.join("run_0/0_0_0/dynamo_output_graph_0.txt")).unwrap();
+    assert!(first.contains("first_run"));
+    let second = fs::read_to_string(dir.join("run_1/0_0_0/dynamo_output_graph_0.txt")).unwrap();
+    assert!(second.contains("second_run"));
+    assert!(!dir.join("0_0_0").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_parse_output_typed() {
+    // The same numbers, compiles and stacks as the JSON files have
+    let path = Path::new("tests/inputs/comp_metrics.log").to_path_buf();
+    let output = tlparse::parse_path(&path, tlparse::ParseConfig::default()).unwrap();
+    assert_eq!(output.runs.len(), 1);
+    let run = &output.runs[0];
+    let map: HashMap<&Path, &String> = output.files.iter().map(|(p, c)| (p.as_path(), c)).collect();
+    let stats: tlparse::Stats = serde_json::from_str(map[Path::new("stats.json")]).unwrap();
+    assert_eq!(run.stats.ok, stats.ok);
+    assert!(run.stats.ok > 0);
+    let listed: serde_json::Value =
+        serde_json::from_str(map[Path::new("compile_directory.json")]).unwrap();
+    for c in run.directory.keys().flatten() {
+        assert!(listed["compiles"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|l| l["compile_id"] == serde_json::to_value(c).unwrap()));
+    }
+    assert!(!run.stack_trie.is_empty());
+}
+
+#[test]
+fn test_raw_log_copy() {
+    let path = Path::new("tests/inputs/two_runs.log").to_path_buf();
+    let log = fs::read(&path).unwrap();
+    let has_raw =
+        |output: &tlparse::ParseOutput| output.files.iter().any(|(p, _)| p == Path::new("raw.log"));
+    let config = tlparse::ParseConfig {
+        no_raw_log: true,
+        ..Default::default()
+    };
+    assert!(!has_raw(&tlparse::parse_path(&path, config).unwrap()));
+    let config = tlparse::ParseConfig {
+        no_raw_log: true,
+        ..Default::default()
+    };
+    assert!(!has_raw(&tlparse::parse(&log[..], &config).unwrap()));
+
+    // A reader is copied as it's read, all of it even when only the first run is parsed
+    let dir = scratch_dir("raw_log_copy");
+    let config = tlparse::ParseConfig {
+        output_dir: Some(dir.clone()),
+        run: Some(0),
+        ..Default::default()
+    };
+    let output = tlparse::parse(&log[..], &config).unwrap();
+    assert!(!has_raw(&output));
+    assert_eq!(fs::read(dir.join("raw.log")).unwrap(), log);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_print_trie_runs() {
+    // Printed by the CLI, not the library, each run's under its directory
+    let path = Path::new("tests/inputs/two_runs.log").to_path_buf();
+    let config = tlparse::ParseConfig {
+        print_trie: Some(tlparse::TerminalStyle::default()),
+        ..Default::default()
+    };
+    let output = tlparse::parse_path(&path, config).unwrap();
+    let texts: Vec<&str> = output
+        .runs
+        .iter()
+        .map(|r| r.trie_text.as_deref().unwrap())
+        .collect();
+    assert_eq!(texts.len(), 2);
+    assert!(texts[0].contains("first_run.py:5 in train"));
+    assert!(texts[1].contains("second_run.py:5 in train"));
+
+    let dir = scratch_dir("print_trie_runs");
+    let out = dir.join("out");
+    let printed = run_cli(&[
+        path.as_os_str(),
+        OsStr::new("-o"),
+        out.as_os_str(),
+        OsStr::new("--print-trie"),
+    ]);
+    assert!(printed.status.success());
+    let stdout = String::from_utf8(printed.stdout).unwrap();
+    assert_eq!(stdout, format!("run_0:\n{}run_1:\n{}", texts[0], texts[1]));
+    fs::remove_dir_all(&dir).unwrap();
+}